@@ -6,13 +6,15 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
+use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Debug, Formatter};
 
 use miette::{bail, ensure, miette, Result};
 use rand::prelude::*;
 
-use crate::data::value::DataValue;
+use crate::data::functions::decimal_cmp;
+use crate::data::value::{DataValue, Decimal, Num};
 
 pub(crate) struct Aggregation {
     pub(crate) name: &'static str,
@@ -496,12 +498,70 @@ impl NormalAggrObj for AggrStdDev {
     }
 }
 
+/// Exact accumulator for decimals. Integers are accumulated exactly alongside decimals,
+/// but the accumulator is only used if decimals are encountered and floats are not.
+pub(crate) struct DecimalAccum {
+    value: Decimal,
+    seen_decimal: bool,
+    seen_float: bool,
+}
+
+impl DecimalAccum {
+    fn new(init: i64) -> Self {
+        Self {
+            value: Decimal::from(init),
+            seen_decimal: false,
+            seen_float: false,
+        }
+    }
+    fn update(
+        &mut self,
+        value: &DataValue,
+        name: &str,
+        op: impl Fn(&Decimal, &Decimal) -> Option<Decimal>,
+    ) -> Result<()> {
+        let d = match value {
+            DataValue::Decimal(d) => {
+                self.seen_decimal = true;
+                *d
+            }
+            DataValue::Num(Num::Int(i)) => Decimal::from(*i),
+            _ => {
+                self.seen_float = true;
+                return Ok(());
+            }
+        };
+        if !self.seen_float {
+            self.value = op(&self.value, &d)
+                .ok_or_else(|| miette!("decimal overflow when computing '{}'", name))?;
+        }
+        Ok(())
+    }
+    fn get(&self) -> Option<Decimal> {
+        if self.seen_decimal && !self.seen_float {
+            Some(self.value)
+        } else {
+            None
+        }
+    }
+}
+
 define_aggr!(AGGR_MEAN, false);
 
-#[derive(Default)]
 pub(crate) struct AggrMean {
     count: i64,
     sum: f64,
+    exact: DecimalAccum,
+}
+
+impl Default for AggrMean {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            sum: 0.,
+            exact: DecimalAccum::new(0),
+        }
+    }
 }
 
 impl NormalAggrObj for AggrMean {
@@ -511,21 +571,39 @@ impl NormalAggrObj for AggrMean {
                 self.sum += n.get_float();
                 self.count += 1;
             }
+            DataValue::Decimal(d) => {
+                self.sum += d.to_f64();
+                self.count += 1;
+            }
             v => bail!("cannot compute 'mean': encountered value {:?}", v),
         }
-        Ok(())
+        self.exact.update(value, "mean", Decimal::checked_add)
     }
 
     fn get(&self) -> Result<DataValue> {
+        if let Some(sum) = self.exact.get() {
+            if let Some(mean) = sum.checked_div(&Decimal::from(self.count)) {
+                return Ok(DataValue::Decimal(mean));
+            }
+        }
         Ok(DataValue::from(self.sum / (self.count as f64)))
     }
 }
 
 define_aggr!(AGGR_SUM, false);
 
-#[derive(Default)]
 pub(crate) struct AggrSum {
     sum: f64,
+    exact: DecimalAccum,
+}
+
+impl Default for AggrSum {
+    fn default() -> Self {
+        Self {
+            sum: 0.,
+            exact: DecimalAccum::new(0),
+        }
+    }
 }
 
 impl NormalAggrObj for AggrSum {
@@ -534,13 +612,19 @@ impl NormalAggrObj for AggrSum {
             DataValue::Num(n) => {
                 self.sum += n.get_float();
             }
+            DataValue::Decimal(d) => {
+                self.sum += d.to_f64();
+            }
             v => bail!("cannot compute 'sum': encountered value {:?}", v),
         }
-        Ok(())
+        self.exact.update(value, "sum", Decimal::checked_add)
     }
 
     fn get(&self) -> Result<DataValue> {
-        Ok(DataValue::from(self.sum))
+        Ok(match self.exact.get() {
+            Some(d) => DataValue::Decimal(d),
+            None => DataValue::from(self.sum),
+        })
     }
 }
 
@@ -548,11 +632,15 @@ define_aggr!(AGGR_PRODUCT, false);
 
 pub(crate) struct AggrProduct {
     product: f64,
+    exact: DecimalAccum,
 }
 
 impl Default for AggrProduct {
     fn default() -> Self {
-        Self { product: 1.0 }
+        Self {
+            product: 1.0,
+            exact: DecimalAccum::new(1),
+        }
     }
 }
 
@@ -562,14 +650,35 @@ impl NormalAggrObj for AggrProduct {
             DataValue::Num(n) => {
                 self.product *= n.get_float();
             }
+            DataValue::Decimal(d) => {
+                self.product *= d.to_f64();
+            }
             v => bail!("cannot compute 'product': encountered value {:?}", v),
         }
-        Ok(())
+        self.exact.update(value, "product", Decimal::checked_mul)
     }
 
     fn get(&self) -> Result<DataValue> {
-        Ok(DataValue::from(self.product))
+        Ok(match self.exact.get() {
+            Some(d) => DataValue::Decimal(d),
+            None => DataValue::from(self.product),
+        })
+    }
+}
+
+/// Compares two values for `min` and `max`, exactly if any of them is a decimal.
+/// Returns `None` if they are not comparable, as is the case for `NaN`.
+fn numeric_cmp(left: &DataValue, right: &DataValue, op: &str) -> Result<Option<Ordering>> {
+    if let Some(ord) = decimal_cmp(left, right) {
+        return Ok(Some(ord));
     }
+    let f1 = left
+        .get_float()
+        .ok_or_else(|| miette!("'{}' applied to non-numerical values", op))?;
+    let f2 = right
+        .get_float()
+        .ok_or_else(|| miette!("'{}' applied to non-numerical values", op))?;
+    Ok(f1.partial_cmp(&f2))
 }
 
 define_aggr!(AGGR_MIN, true);
@@ -595,14 +704,7 @@ impl NormalAggrObj for AggrMin {
             self.found = value.clone();
             return Ok(());
         }
-        let f1 = self
-            .found
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-        let f2 = value
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-        if f1 > f2 {
+        if numeric_cmp(&self.found, value, "min")? == Some(Ordering::Greater) {
             self.found = value.clone();
         }
         Ok(())
@@ -628,19 +730,11 @@ impl MeetAggrObj for MeetAggrMin {
             *left = right.clone();
             return Ok(true);
         }
-        let f1 = left
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-        let f2 = right
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-
-        Ok(if f1 > f2 {
+        let replace = numeric_cmp(left, right, "min")? == Some(Ordering::Greater);
+        if replace {
             *left = right.clone();
-            true
-        } else {
-            false
-        })
+        }
+        Ok(replace)
     }
 }
 
@@ -667,14 +761,7 @@ impl NormalAggrObj for AggrMax {
             self.found = value.clone();
             return Ok(());
         }
-        let f1 = self
-            .found
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-        let f2 = value
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-        if f1 < f2 {
+        if numeric_cmp(&self.found, value, "max")? == Some(Ordering::Less) {
             self.found = value.clone();
         }
         Ok(())
@@ -700,19 +787,11 @@ impl MeetAggrObj for MeetAggrMax {
             *left = right.clone();
             return Ok(true);
         }
-        let f1 = left
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-        let f2 = right
-            .get_float()
-            .ok_or_else(|| miette!("'min' applied to non-numerical values"))?;
-
-        Ok(if f1 < f2 {
+        let replace = numeric_cmp(left, right, "max")? == Some(Ordering::Less);
+        if replace {
             *left = right.clone();
-            true
-        } else {
-            false
-        })
+        }
+        Ok(replace)
     }
 }
 
@@ -799,6 +799,8 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "remove_json_path" => &OP_REMOVE_JSON_PATH,
         "parse_json" => &OP_PARSE_JSON,
         "dump_json" => &OP_DUMP_JSON,
+        "json_query" => &OP_JSON_QUERY,
+        "json_contains" => &OP_JSON_CONTAINS,
         "json_object" => &OP_JSON_OBJECT,
         "is_json" => &OP_IS_JSON,
         "json_to_scalar" => &OP_JSON_TO_SCALAR,
@@ -859,10 +861,20 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "trim_end" => &OP_TRIM_END,
         "starts_with" => &OP_STARTS_WITH,
         "ends_with" => &OP_ENDS_WITH,
+        "split" => &OP_SPLIT,
+        "join" => &OP_JOIN,
+        "pad_start" => &OP_PAD_START,
+        "pad_end" => &OP_PAD_END,
+        "replace" => &OP_REPLACE,
+        "levenshtein" => &OP_LEVENSHTEIN,
+        "jaro_winkler" => &OP_JARO_WINKLER,
+        "sha256" => &OP_SHA256,
+        "xxhash64" => &OP_XXHASH64,
         "is_null" => &OP_IS_NULL,
         "is_int" => &OP_IS_INT,
         "is_float" => &OP_IS_FLOAT,
         "is_num" => &OP_IS_NUM,
+        "is_decimal" => &OP_IS_DECIMAL,
         "is_string" => &OP_IS_STRING,
         "is_list" => &OP_IS_LIST,
         "is_bytes" => &OP_IS_BYTES,
@@ -880,6 +892,12 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "unicode_normalize" => &OP_UNICODE_NORMALIZE,
         "haversine" => &OP_HAVERSINE,
         "haversine_deg_input" => &OP_HAVERSINE_DEG_INPUT,
+        "st_point" => &OP_ST_POINT,
+        "st_bbox" => &OP_ST_BBOX,
+        "st_contains" => &OP_ST_CONTAINS,
+        "st_intersects" => &OP_ST_INTERSECTS,
+        "st_distance" => &OP_ST_DISTANCE,
+        "st_dwithin" => &OP_ST_DWITHIN,
         "deg_to_rad" => &OP_DEG_TO_RAD,
         "rad_to_deg" => &OP_RAD_TO_DEG,
         "get" => &OP_GET,
@@ -903,6 +921,7 @@ pub(crate) fn get_op(name: &str) -> Option<&'static Op> {
         "windows" => &OP_WINDOWS,
         "to_int" => &OP_TO_INT,
         "to_float" => &OP_TO_FLOAT,
+        "to_decimal" => &OP_TO_DECIMAL,
         "to_string" => &OP_TO_STRING,
         "l2_dist" => &OP_L2_DIST,
         "l2_normalize" => &OP_L2_NORMALIZE,
@@ -6,8 +6,9 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::cmp::Reverse;
+use std::cmp::{Ordering, Reverse};
 use std::collections::BTreeSet;
+use std::hash::Hasher;
 use std::mem;
 use std::ops::{Div, Rem};
 use std::str::FromStr;
@@ -23,15 +24,19 @@ use miette::{bail, ensure, miette, IntoDiagnostic, Result};
 use num_traits::FloatConst;
 use rand::prelude::*;
 use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
 use smartstring::SmartString;
+use twox_hash::XxHash64;
 use unicode_normalization::UnicodeNormalization;
 use uuid::v1::Timestamp;
 
 use crate::data::expr::Op;
+use crate::data::geo::Geometry;
 use crate::data::json::JsonValue;
+use crate::data::json_path::{json_contains, JsonPath};
 use crate::data::relation::VecElementType;
 use crate::data::value::{
-    DataValue, JsonData, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs, Vector,
+    DataValue, Decimal, JsonData, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs, Vector,
 };
 
 macro_rules! define_op {
@@ -52,6 +57,9 @@ fn ensure_same_value_type(a: &DataValue, b: &DataValue) -> Result<()> {
         (Null, Null)
             | (Bool(_), Bool(_))
             | (Num(_), Num(_))
+            | (Decimal(_), Decimal(_))
+            | (Decimal(_), Num(_))
+            | (Num(_), Decimal(_))
             | (Str(_), Str(_))
             | (Bytes(_), Bytes(_))
             | (Regex(_), Regex(_))
@@ -68,6 +76,26 @@ fn ensure_same_value_type(a: &DataValue, b: &DataValue) -> Result<()> {
     Ok(())
 }
 
+/// Interpret the operands of a binary arithmetic operation as decimals,
+/// if one of them is a decimal and the other one is exact.
+fn decimal_operands(a: &DataValue, b: &DataValue) -> Option<(Decimal, Decimal)> {
+    match (a, b) {
+        (DataValue::Decimal(l), DataValue::Decimal(r)) => Some((*l, *r)),
+        (DataValue::Decimal(l), DataValue::Num(Num::Int(r))) => Some((*l, Decimal::from(*r))),
+        (DataValue::Num(Num::Int(l)), DataValue::Decimal(r)) => Some((Decimal::from(*l), *r)),
+        _ => None,
+    }
+}
+
+/// Compare numbers by value, where at least one of them is a decimal
+pub(crate) fn decimal_cmp(a: &DataValue, b: &DataValue) -> Option<Ordering> {
+    match (a, b) {
+        (DataValue::Decimal(l), DataValue::Num(Num::Float(r))) => l.to_f64().partial_cmp(r),
+        (DataValue::Num(Num::Float(l)), DataValue::Decimal(r)) => l.partial_cmp(&r.to_f64()),
+        _ => decimal_operands(a, b).map(|(l, r)| l.cmp_value(&r)),
+    }
+}
+
 define_op!(OP_LIST, 0, true);
 pub(crate) fn op_list(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::List(args.to_vec()))
@@ -259,6 +287,9 @@ fn to_json(d: &DataValue) -> JsonValue {
         DataValue::Validity(vld) => {
             json!([vld.timestamp.0, vld.is_assert.0])
         }
+        DataValue::Decimal(d) => {
+            json!(d.to_string())
+        }
         DataValue::Bot => {
             json!(null)
         }
@@ -284,6 +315,29 @@ pub(crate) fn op_dump_json(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+define_op!(OP_JSON_QUERY, 2, false);
+pub(crate) fn op_json_query(args: &[DataValue]) -> Result<DataValue> {
+    let doc = to_json(&args[0]);
+    let path = args[1]
+        .get_str()
+        .ok_or_else(|| miette!("json_query requires a string as the path"))?;
+    let path = JsonPath::parse(path)?;
+    Ok(DataValue::List(
+        path.select(&doc)
+            .into_iter()
+            .map(|v| json2val(v.clone()))
+            .collect_vec(),
+    ))
+}
+
+define_op!(OP_JSON_CONTAINS, 2, false);
+pub(crate) fn op_json_contains(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(json_contains(
+        &to_json(&args[0]),
+        &to_json(&args[1]),
+    )))
+}
+
 define_op!(OP_COALESCE, 0, true);
 pub(crate) fn op_coalesce(args: &[DataValue]) -> Result<DataValue> {
     for val in args {
@@ -299,6 +353,9 @@ pub(crate) fn op_eq(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(f)), DataValue::Num(Num::Int(i)))
         | (DataValue::Num(Num::Int(i)), DataValue::Num(Num::Float(f))) => *i as f64 == *f,
+        (DataValue::Decimal(_), _) | (_, DataValue::Decimal(_)) => {
+            decimal_cmp(&args[0], &args[1]) == Some(Ordering::Equal)
+        }
         (a, b) => a == b,
     }))
 }
@@ -335,6 +392,9 @@ pub(crate) fn op_neq(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(f)), DataValue::Num(Num::Int(i)))
         | (DataValue::Num(Num::Int(i)), DataValue::Num(Num::Float(f))) => *i as f64 != *f,
+        (DataValue::Decimal(_), _) | (_, DataValue::Decimal(_)) => {
+            decimal_cmp(&args[0], &args[1]) != Some(Ordering::Equal)
+        }
         (a, b) => a != b,
     }))
 }
@@ -345,6 +405,9 @@ pub(crate) fn op_gt(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l > *r as f64,
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => *l as f64 > *r,
+        (DataValue::Decimal(_), _) | (_, DataValue::Decimal(_)) => {
+            decimal_cmp(&args[0], &args[1]) == Some(Ordering::Greater)
+        }
         (a, b) => a > b,
     }))
 }
@@ -355,6 +418,12 @@ pub(crate) fn op_ge(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l >= *r as f64,
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => *l as f64 >= *r,
+        (DataValue::Decimal(_), _) | (_, DataValue::Decimal(_)) => {
+            matches!(
+                decimal_cmp(&args[0], &args[1]),
+                Some(Ordering::Greater | Ordering::Equal)
+            )
+        }
         (a, b) => a >= b,
     }))
 }
@@ -365,6 +434,9 @@ pub(crate) fn op_lt(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l < (*r as f64),
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => (*l as f64) < *r,
+        (DataValue::Decimal(_), _) | (_, DataValue::Decimal(_)) => {
+            decimal_cmp(&args[0], &args[1]) == Some(Ordering::Less)
+        }
         (a, b) => a < b,
     }))
 }
@@ -375,6 +447,12 @@ pub(crate) fn op_le(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Float(l)), DataValue::Num(Num::Int(r))) => *l <= (*r as f64),
         (DataValue::Num(Num::Int(l)), DataValue::Num(Num::Float(r))) => (*l as f64) <= *r,
+        (DataValue::Decimal(_), _) | (_, DataValue::Decimal(_)) => {
+            matches!(
+                decimal_cmp(&args[0], &args[1]),
+                Some(Ordering::Less | Ordering::Equal)
+            )
+        }
         (a, b) => a <= b,
     }))
 }
@@ -388,6 +466,7 @@ pub(crate) fn op_add(args: &[DataValue]) -> Result<DataValue> {
             DataValue::Num(Num::Int(i)) => i_accum += i,
             DataValue::Num(Num::Float(f)) => f_accum += f,
             DataValue::Vec(_) => return add_vecs(args),
+            DataValue::Decimal(_) => return add_decimals(args),
             _ => bail!("addition requires numbers"),
         }
     }
@@ -398,6 +477,33 @@ pub(crate) fn op_add(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+fn add_decimals(args: &[DataValue]) -> Result<DataValue> {
+    if args
+        .iter()
+        .any(|arg| matches!(arg, DataValue::Num(Num::Float(_))))
+    {
+        let mut accum = 0.0f64;
+        for arg in args {
+            accum += arg
+                .get_float()
+                .ok_or_else(|| miette!("addition requires numbers"))?;
+        }
+        return Ok(DataValue::from(accum));
+    }
+    let mut accum = Decimal::from(0);
+    for arg in args {
+        let d = match arg {
+            DataValue::Decimal(d) => *d,
+            DataValue::Num(Num::Int(i)) => Decimal::from(*i),
+            _ => bail!("addition requires numbers"),
+        };
+        accum = accum
+            .checked_add(&d)
+            .ok_or_else(|| miette!("decimal overflow in addition"))?;
+    }
+    Ok(DataValue::Decimal(accum))
+}
+
 fn add_vecs(args: &[DataValue]) -> Result<DataValue> {
     if args.len() == 1 {
         return Ok(args[0].clone());
@@ -455,8 +561,13 @@ pub(crate) fn op_max(args: &[DataValue]) -> Result<DataValue> {
     let res = args
         .iter()
         .try_fold(None, |accum, nxt| match (accum, nxt) {
-            (None, d @ DataValue::Num(_)) => Ok(Some(d.clone())),
+            (None, d @ (DataValue::Num(_) | DataValue::Decimal(_))) => Ok(Some(d.clone())),
             (Some(DataValue::Num(a)), DataValue::Num(b)) => Ok(Some(DataValue::Num(a.max(*b)))),
+            (Some(a), b) => match decimal_cmp(&a, b) {
+                Some(Ordering::Less) => Ok(Some(b.clone())),
+                Some(_) => Ok(Some(a)),
+                None => bail!("'max can only be applied to numbers'"),
+            },
             _ => bail!("'max can only be applied to numbers'"),
         })?;
     match res {
@@ -470,8 +581,13 @@ pub(crate) fn op_min(args: &[DataValue]) -> Result<DataValue> {
     let res = args
         .iter()
         .try_fold(None, |accum, nxt| match (accum, nxt) {
-            (None, d @ DataValue::Num(_)) => Ok(Some(d.clone())),
+            (None, d @ (DataValue::Num(_) | DataValue::Decimal(_))) => Ok(Some(d.clone())),
             (Some(DataValue::Num(a)), DataValue::Num(b)) => Ok(Some(DataValue::Num(a.min(*b)))),
+            (Some(a), b) => match decimal_cmp(&a, b) {
+                Some(Ordering::Greater) => Ok(Some(b.clone())),
+                Some(_) => Ok(Some(a)),
+                None => bail!("'min' can only be applied to numbers"),
+            },
             _ => bail!("'min' can only be applied to numbers"),
         })?;
     match res {
@@ -482,6 +598,12 @@ pub(crate) fn op_min(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_SUB, 2, false);
 pub(crate) fn op_sub(args: &[DataValue]) -> Result<DataValue> {
+    if let Some((a, b)) = decimal_operands(&args[0], &args[1]) {
+        return Ok(DataValue::Decimal(
+            a.checked_sub(&b)
+                .ok_or_else(|| miette!("decimal overflow in subtraction"))?,
+        ));
+    }
     Ok(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Int(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Int(*a - *b))
@@ -495,6 +617,12 @@ pub(crate) fn op_sub(args: &[DataValue]) -> Result<DataValue> {
         (DataValue::Num(Num::Float(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Float(a - (*b as f64)))
         }
+        (DataValue::Decimal(a), DataValue::Num(Num::Float(b))) => {
+            DataValue::Num(Num::Float(a.to_f64() - b))
+        }
+        (DataValue::Num(Num::Float(a)), DataValue::Decimal(b)) => {
+            DataValue::Num(Num::Float(a - b.to_f64()))
+        }
         (DataValue::Vec(a), DataValue::Vec(b)) => match (a, b) {
             (Vector::F32(a), Vector::F32(b)) => DataValue::Vec(Vector::F32(a - b)),
             (Vector::F64(a), Vector::F64(b)) => DataValue::Vec(Vector::F64(a - b)),
@@ -550,6 +678,7 @@ pub(crate) fn op_mul(args: &[DataValue]) -> Result<DataValue> {
             DataValue::Num(Num::Int(i)) => i_accum *= i,
             DataValue::Num(Num::Float(f)) => f_accum *= f,
             DataValue::Vec(_) => return mul_vecs(args),
+            DataValue::Decimal(_) => return mul_decimals(args),
             _ => bail!("multiplication requires numbers"),
         }
     }
@@ -560,6 +689,33 @@ pub(crate) fn op_mul(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+fn mul_decimals(args: &[DataValue]) -> Result<DataValue> {
+    if args
+        .iter()
+        .any(|arg| matches!(arg, DataValue::Num(Num::Float(_))))
+    {
+        let mut accum = 1.0f64;
+        for arg in args {
+            accum *= arg
+                .get_float()
+                .ok_or_else(|| miette!("multiplication requires numbers"))?;
+        }
+        return Ok(DataValue::from(accum));
+    }
+    let mut accum = Decimal::from(1);
+    for arg in args {
+        let d = match arg {
+            DataValue::Decimal(d) => *d,
+            DataValue::Num(Num::Int(i)) => Decimal::from(*i),
+            _ => bail!("multiplication requires numbers"),
+        };
+        accum = accum
+            .checked_mul(&d)
+            .ok_or_else(|| miette!("decimal overflow in multiplication"))?;
+    }
+    Ok(DataValue::Decimal(accum))
+}
+
 fn mul_vecs(args: &[DataValue]) -> Result<DataValue> {
     if args.len() == 1 {
         return Ok(args[0].clone());
@@ -614,6 +770,13 @@ fn mul_vecs(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_DIV, 2, false);
 pub(crate) fn op_div(args: &[DataValue]) -> Result<DataValue> {
+    if let Some((a, b)) = decimal_operands(&args[0], &args[1]) {
+        ensure!(!b.is_zero(), "decimal division by zero");
+        return Ok(DataValue::Decimal(
+            a.checked_div(&b)
+                .ok_or_else(|| miette!("decimal overflow in division"))?,
+        ));
+    }
     Ok(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Int(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Float((*a as f64) / (*b as f64)))
@@ -627,6 +790,12 @@ pub(crate) fn op_div(args: &[DataValue]) -> Result<DataValue> {
         (DataValue::Num(Num::Float(a)), DataValue::Num(Num::Int(b))) => {
             DataValue::Num(Num::Float(a / (*b as f64)))
         }
+        (DataValue::Decimal(a), DataValue::Num(Num::Float(b))) => {
+            DataValue::Num(Num::Float(a.to_f64() / b))
+        }
+        (DataValue::Num(Num::Float(a)), DataValue::Decimal(b)) => {
+            DataValue::Num(Num::Float(a / b.to_f64()))
+        }
         (DataValue::Vec(a), DataValue::Vec(b)) => match (a, b) {
             (Vector::F32(a), Vector::F32(b)) => DataValue::Vec(Vector::F32(a / b)),
             (Vector::F64(a), Vector::F64(b)) => DataValue::Vec(Vector::F64(a / b)),
@@ -672,6 +841,10 @@ pub(crate) fn op_minus(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(-(*i))),
         DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(-(*f))),
+        DataValue::Decimal(d) => DataValue::Decimal(
+            d.checked_neg()
+                .ok_or_else(|| miette!("decimal overflow in negation"))?,
+        ),
         DataValue::Vec(Vector::F64(v)) => DataValue::Vec(Vector::F64(0. - v)),
         DataValue::Vec(Vector::F32(v)) => DataValue::Vec(Vector::F32(0. - v)),
         _ => bail!("minus can only be applied to numbers"),
@@ -683,6 +856,16 @@ pub(crate) fn op_abs(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(i.abs())),
         DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.abs())),
+        DataValue::Decimal(d) => {
+            if d.signum() < 0 {
+                DataValue::Decimal(
+                    d.checked_neg()
+                        .ok_or_else(|| miette!("decimal overflow in 'abs'"))?,
+                )
+            } else {
+                DataValue::Decimal(*d)
+            }
+        }
         DataValue::Vec(Vector::F64(v)) => DataValue::Vec(Vector::F64(v.mapv(|x| x.abs()))),
         DataValue::Vec(Vector::F32(v)) => DataValue::Vec(Vector::F32(v.mapv(|x| x.abs()))),
         _ => bail!("'abs' requires numbers"),
@@ -693,6 +876,7 @@ define_op!(OP_SIGNUM, 1, false);
 pub(crate) fn op_signum(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(i.signum())),
+        DataValue::Decimal(d) => DataValue::from(d.signum()),
         DataValue::Num(Num::Float(f)) => {
             if f.signum() < 0. {
                 DataValue::from(-1)
@@ -713,6 +897,7 @@ pub(crate) fn op_floor(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(*i)),
         DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.floor())),
+        DataValue::Decimal(d) => DataValue::Decimal(d.floor()),
         _ => bail!("'floor' requires numbers"),
     })
 }
@@ -722,6 +907,7 @@ pub(crate) fn op_ceil(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(*i)),
         DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.ceil())),
+        DataValue::Decimal(d) => DataValue::Decimal(d.ceil()),
         _ => bail!("'ceil' requires numbers"),
     })
 }
@@ -731,6 +917,7 @@ pub(crate) fn op_round(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(Num::Int(i)) => DataValue::Num(Num::Int(*i)),
         DataValue::Num(Num::Float(f)) => DataValue::Num(Num::Float(f.round())),
+        DataValue::Decimal(d) => DataValue::Decimal(d.rescale(0).unwrap()),
         _ => bail!("'round' requires numbers"),
     })
 }
@@ -1068,6 +1255,13 @@ pub(crate) fn op_pow(args: &[DataValue]) -> Result<DataValue> {
 
 define_op!(OP_MOD, 2, false);
 pub(crate) fn op_mod(args: &[DataValue]) -> Result<DataValue> {
+    if let Some((a, b)) = decimal_operands(&args[0], &args[1]) {
+        ensure!(!b.is_zero(), "'mod' requires non-zero divisor");
+        return Ok(DataValue::Decimal(
+            a.checked_rem(&b)
+                .ok_or_else(|| miette!("decimal overflow in 'mod'"))?,
+        ));
+    }
     Ok(match (&args[0], &args[1]) {
         (DataValue::Num(Num::Int(a)), DataValue::Num(Num::Int(b))) => {
             if *b == 0 {
@@ -1377,6 +1571,210 @@ pub(crate) fn op_ends_with(args: &[DataValue]) -> Result<DataValue> {
     }
 }
 
+define_op!(OP_SPLIT, 2, false);
+pub(crate) fn op_split(args: &[DataValue]) -> Result<DataValue> {
+    match (&args[0], &args[1]) {
+        (DataValue::Str(s), DataValue::Str(sep)) => {
+            ensure!(
+                !sep.is_empty(),
+                "'split' requires a non-empty separator, use 'chars' to split into characters"
+            );
+            Ok(DataValue::List(
+                s.split(sep as &str).map(DataValue::from).collect(),
+            ))
+        }
+        _ => bail!("'split' requires strings"),
+    }
+}
+
+define_op!(OP_JOIN, 2, false);
+pub(crate) fn op_join(args: &[DataValue]) -> Result<DataValue> {
+    let list = args[0]
+        .get_slice()
+        .ok_or_else(|| miette!("first argument to 'join' must be a list"))?;
+    let sep = args[1]
+        .get_str()
+        .ok_or_else(|| miette!("second argument to 'join' must be a string"))?;
+    let mut ret = String::new();
+    for (i, el) in list.iter().enumerate() {
+        if i > 0 {
+            ret.push_str(sep);
+        }
+        match el {
+            DataValue::Str(s) => ret.push_str(s),
+            v => bail!("'join' requires a list of strings, got {}", v),
+        }
+    }
+    Ok(DataValue::from(ret))
+}
+
+fn pad_impl(args: &[DataValue], name: &str, at_start: bool) -> Result<DataValue> {
+    let s = args[0]
+        .get_str()
+        .ok_or_else(|| miette!("'{}' requires a string as first argument", name))?;
+    let target_len = args[1]
+        .get_non_neg_int()
+        .ok_or_else(|| miette!("'{}' requires a non-negative length", name))?
+        as usize;
+    let fill = match args.get(2) {
+        None => " ",
+        Some(DataValue::Str(f)) => f as &str,
+        Some(v) => bail!("'{}' requires a string as padding, got {}", name, v),
+    };
+    let cur_len = s.chars().count();
+    if cur_len >= target_len || fill.is_empty() {
+        return Ok(DataValue::from(s));
+    }
+    let padding: String = fill.chars().cycle().take(target_len - cur_len).collect();
+    Ok(DataValue::from(if at_start {
+        padding + s
+    } else {
+        s.to_string() + padding.as_str()
+    }))
+}
+
+define_op!(OP_PAD_START, 2, true);
+pub(crate) fn op_pad_start(args: &[DataValue]) -> Result<DataValue> {
+    ensure!(args.len() <= 3, "'pad_start' takes at most three arguments");
+    pad_impl(args, "pad_start", true)
+}
+
+define_op!(OP_PAD_END, 2, true);
+pub(crate) fn op_pad_end(args: &[DataValue]) -> Result<DataValue> {
+    ensure!(args.len() <= 3, "'pad_end' takes at most three arguments");
+    pad_impl(args, "pad_end", false)
+}
+
+define_op!(OP_REPLACE, 3, false);
+pub(crate) fn op_replace(args: &[DataValue]) -> Result<DataValue> {
+    match (&args[0], &args[1], &args[2]) {
+        (DataValue::Str(s), DataValue::Str(from), DataValue::Str(to)) => {
+            if from.is_empty() {
+                Ok(DataValue::Str(s.clone()))
+            } else {
+                Ok(DataValue::from(s.replace(from as &str, to)))
+            }
+        }
+        _ => bail!("'replace' requires strings"),
+    }
+}
+
+define_op!(OP_LEVENSHTEIN, 2, false);
+pub(crate) fn op_levenshtein(args: &[DataValue]) -> Result<DataValue> {
+    match (&args[0], &args[1]) {
+        (DataValue::Str(a), DataValue::Str(b)) => {
+            let a = a.chars().collect_vec();
+            let b = b.chars().collect_vec();
+            let mut prev = (0..=b.len()).collect_vec();
+            let mut cur = vec![0; b.len() + 1];
+            for (i, ca) in a.iter().enumerate() {
+                cur[0] = i + 1;
+                for (j, cb) in b.iter().enumerate() {
+                    let cost = usize::from(ca != cb);
+                    cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+                }
+                mem::swap(&mut prev, &mut cur);
+            }
+            Ok(DataValue::from(prev[b.len()] as i64))
+        }
+        _ => bail!("'levenshtein' requires strings"),
+    }
+}
+
+fn jaro_similarity(a: &[char], b: &[char]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.;
+    }
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0usize;
+    for (i, ca) in a.iter().enumerate() {
+        let lo = i.saturating_sub(window);
+        let hi = (i + window + 1).min(b.len());
+        for j in lo..hi {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.;
+    }
+    let mut transpositions = 0usize;
+    let mut k = 0;
+    for (i, ca) in a.iter().enumerate() {
+        if !a_matched[i] {
+            continue;
+        }
+        while !b_matched[k] {
+            k += 1;
+        }
+        if *ca != b[k] {
+            transpositions += 1;
+        }
+        k += 1;
+    }
+    let m = matches as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - (transpositions / 2) as f64) / m) / 3.
+}
+
+define_op!(OP_JARO_WINKLER, 2, false);
+pub(crate) fn op_jaro_winkler(args: &[DataValue]) -> Result<DataValue> {
+    match (&args[0], &args[1]) {
+        (DataValue::Str(a), DataValue::Str(b)) => {
+            let a = a.chars().collect_vec();
+            let b = b.chars().collect_vec();
+            let jaro = jaro_similarity(&a, &b);
+            let prefix = a
+                .iter()
+                .zip(b.iter())
+                .take(4)
+                .take_while(|(x, y)| x == y)
+                .count();
+            Ok(DataValue::from(jaro + prefix as f64 * 0.1 * (1. - jaro)))
+        }
+        _ => bail!("'jaro_winkler' requires strings"),
+    }
+}
+
+fn hash_input<'a>(arg: &'a DataValue, name: &str) -> Result<&'a [u8]> {
+    match arg {
+        DataValue::Str(s) => Ok(s.as_bytes()),
+        DataValue::Bytes(b) => Ok(b),
+        v => bail!("'{}' requires a string or bytes, got {}", name, v),
+    }
+}
+
+define_op!(OP_SHA256, 1, false);
+pub(crate) fn op_sha256(args: &[DataValue]) -> Result<DataValue> {
+    let data = hash_input(&args[0], "sha256")?;
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    Ok(DataValue::Bytes(hasher.finalize().to_vec()))
+}
+
+define_op!(OP_XXHASH64, 1, true);
+pub(crate) fn op_xxhash64(args: &[DataValue]) -> Result<DataValue> {
+    ensure!(args.len() <= 2, "'xxhash64' takes at most two arguments");
+    let data = hash_input(&args[0], "xxhash64")?;
+    let seed = match args.get(1) {
+        None => 0,
+        Some(v) => v
+            .get_int()
+            .ok_or_else(|| miette!("'xxhash64' requires an integer seed"))?,
+    };
+    let mut hasher = XxHash64::with_seed(seed as u64);
+    hasher.write(data);
+    Ok(DataValue::from(hasher.finish() as i64))
+}
+
 define_op!(OP_REGEX, 1, false);
 pub(crate) fn op_regex(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
@@ -1476,14 +1874,19 @@ define_op!(OP_IS_NUM, 1, false);
 pub(crate) fn op_is_num(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(matches!(
         args[0],
-        DataValue::Num(Num::Int(_)) | DataValue::Num(Num::Float(_))
+        DataValue::Num(Num::Int(_)) | DataValue::Num(Num::Float(_)) | DataValue::Decimal(_)
     )))
 }
 
+define_op!(OP_IS_DECIMAL, 1, false);
+pub(crate) fn op_is_decimal(args: &[DataValue]) -> Result<DataValue> {
+    Ok(DataValue::from(matches!(args[0], DataValue::Decimal(_))))
+}
+
 define_op!(OP_IS_FINITE, 1, false);
 pub(crate) fn op_is_finite(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(match &args[0] {
-        DataValue::Num(Num::Int(_)) => true,
+        DataValue::Num(Num::Int(_)) | DataValue::Decimal(_) => true,
         DataValue::Num(Num::Float(f)) => f.is_finite(),
         _ => false,
     }))
@@ -1638,6 +2041,61 @@ pub(crate) fn op_haversine_deg_input(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::from(ret))
 }
 
+define_op!(OP_ST_POINT, 2, false);
+pub(crate) fn op_st_point(args: &[DataValue]) -> Result<DataValue> {
+    let x = args[0]
+        .get_float()
+        .ok_or_else(|| miette!("'st_point' requires numbers"))?;
+    let y = args[1]
+        .get_float()
+        .ok_or_else(|| miette!("'st_point' requires numbers"))?;
+    Ok(DataValue::Json(JsonData(json!({
+        "type": "Point",
+        "coordinates": [x, y]
+    }))))
+}
+
+define_op!(OP_ST_BBOX, 1, false);
+pub(crate) fn op_st_bbox(args: &[DataValue]) -> Result<DataValue> {
+    let bbox = Geometry::from_value(&args[0])?.bbox();
+    Ok(if bbox.is_empty() {
+        DataValue::Null
+    } else {
+        bbox.to_value()
+    })
+}
+
+define_op!(OP_ST_CONTAINS, 2, false);
+pub(crate) fn op_st_contains(args: &[DataValue]) -> Result<DataValue> {
+    let a = Geometry::from_value(&args[0])?;
+    let b = Geometry::from_value(&args[1])?;
+    Ok(DataValue::from(a.contains(&b)))
+}
+
+define_op!(OP_ST_INTERSECTS, 2, false);
+pub(crate) fn op_st_intersects(args: &[DataValue]) -> Result<DataValue> {
+    let a = Geometry::from_value(&args[0])?;
+    let b = Geometry::from_value(&args[1])?;
+    Ok(DataValue::from(a.intersects(&b)))
+}
+
+define_op!(OP_ST_DISTANCE, 2, false);
+pub(crate) fn op_st_distance(args: &[DataValue]) -> Result<DataValue> {
+    let a = Geometry::from_value(&args[0])?;
+    let b = Geometry::from_value(&args[1])?;
+    Ok(DataValue::from(a.distance(&b)))
+}
+
+define_op!(OP_ST_DWITHIN, 3, false);
+pub(crate) fn op_st_dwithin(args: &[DataValue]) -> Result<DataValue> {
+    let a = Geometry::from_value(&args[0])?;
+    let b = Geometry::from_value(&args[1])?;
+    let d = args[2]
+        .get_float()
+        .ok_or_else(|| miette!("'st_dwithin' requires a number as distance"))?;
+    Ok(DataValue::from(a.distance(&b) <= d))
+}
+
 define_op!(OP_DEG_TO_RAD, 1, false);
 pub(crate) fn op_deg_to_rad(args: &[DataValue]) -> Result<DataValue> {
     let x = args[0]
@@ -1930,6 +2388,7 @@ pub(crate) fn op_to_bool(args: &[DataValue]) -> Result<DataValue> {
         DataValue::Set(s) => !s.is_empty(),
         DataValue::Vec(_) => true,
         DataValue::Validity(vld) => vld.is_assert.0,
+        DataValue::Decimal(d) => !d.is_zero(),
         DataValue::Bot => false,
         DataValue::Json(json) => match &json.0 {
             Value::Null => false,
@@ -1956,6 +2415,7 @@ pub(crate) fn op_to_unity(args: &[DataValue]) -> Result<DataValue> {
         DataValue::Set(s) => i64::from(!s.is_empty()),
         DataValue::Vec(_) => 1,
         DataValue::Validity(vld) => i64::from(vld.is_assert.0),
+        DataValue::Decimal(d) => i64::from(!d.is_zero()),
         DataValue::Bot => 0,
         DataValue::Json(json) => match &json.0 {
             Value::Null => 0,
@@ -1987,6 +2447,14 @@ pub(crate) fn op_to_int(args: &[DataValue]) -> Result<DataValue> {
                 .into()
         }
         DataValue::Validity(vld) => DataValue::Num(Num::Int(vld.timestamp.0 .0)),
+        DataValue::Decimal(d) => {
+            let truncated = if d.signum() < 0 { d.ceil() } else { d.floor() };
+            DataValue::from(
+                truncated
+                    .get_int()
+                    .ok_or_else(|| miette!("The decimal is too large for an int"))?,
+            )
+        }
         v => bail!("'to_int' does not recognize {:?}", v),
     })
 }
@@ -1995,6 +2463,7 @@ define_op!(OP_TO_FLOAT, 1, false);
 pub(crate) fn op_to_float(args: &[DataValue]) -> Result<DataValue> {
     Ok(match &args[0] {
         DataValue::Num(n) => n.get_float().into(),
+        DataValue::Decimal(d) => d.to_f64().into(),
         DataValue::Null => DataValue::from(0.0),
         DataValue::Bool(b) => DataValue::from(if *b { 1.0 } else { 0.0 }),
         DataValue::Str(t) => match t as &str {
@@ -2011,6 +2480,32 @@ pub(crate) fn op_to_float(args: &[DataValue]) -> Result<DataValue> {
     })
 }
 
+define_op!(OP_TO_DECIMAL, 1, true);
+pub(crate) fn op_to_decimal(args: &[DataValue]) -> Result<DataValue> {
+    ensure!(args.len() <= 2, "'to_decimal' takes at most two arguments");
+    let d = match &args[0] {
+        DataValue::Decimal(d) => *d,
+        DataValue::Num(Num::Int(i)) => Decimal::from(*i),
+        DataValue::Num(Num::Float(f)) => Decimal::from_f64(*f)
+            .ok_or_else(|| miette!("The float {} cannot be represented as a decimal", f))?,
+        DataValue::Str(s) => Decimal::parse(s)
+            .ok_or_else(|| miette!("The string cannot be interpreted as decimal"))?,
+        v => bail!("'to_decimal' does not recognize {:?}", v),
+    };
+    Ok(DataValue::Decimal(match args.get(1) {
+        None => d,
+        Some(scale) => {
+            let scale = scale
+                .get_non_neg_int()
+                .ok_or_else(|| miette!("'to_decimal' requires a non-negative integer scale"))?;
+            u32::try_from(scale)
+                .ok()
+                .and_then(|scale| d.rescale(scale))
+                .ok_or_else(|| miette!("cannot rescale {} to {} decimal places", d, scale))?
+        }
+    }))
+}
+
 define_op!(OP_TO_STRING, 1, false);
 pub(crate) fn op_to_string(args: &[DataValue]) -> Result<DataValue> {
     Ok(DataValue::Str(val2str(&args[0]).into()))
@@ -2020,6 +2515,7 @@ fn val2str(arg: &DataValue) -> String {
     match arg {
         DataValue::Str(s) => s.to_string(),
         DataValue::Json(JsonData(JsonValue::String(s))) => s.clone(),
+        DataValue::Decimal(d) => d.to_string(),
         v => {
             let jv = to_json(v);
             jv.to_string()
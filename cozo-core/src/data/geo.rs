@@ -0,0 +1,366 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Planar geometry used by the `st_*` functions and the spatial index.
+//!
+//! Geometries are represented as GeoJSON stored in `Json` values. A list of two numbers
+//! is also accepted as a point. All computations are done in the plane, so distances
+//! are in the same unit as the coordinates.
+
+use miette::{bail, miette, Result};
+
+use crate::data::json::JsonValue;
+use crate::data::value::DataValue;
+
+pub(crate) type Point = (f64, f64);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct BBox {
+    pub(crate) min_x: f64,
+    pub(crate) min_y: f64,
+    pub(crate) max_x: f64,
+    pub(crate) max_y: f64,
+}
+
+impl BBox {
+    pub(crate) fn empty() -> Self {
+        Self {
+            min_x: f64::INFINITY,
+            min_y: f64::INFINITY,
+            max_x: f64::NEG_INFINITY,
+            max_y: f64::NEG_INFINITY,
+        }
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        self.min_x > self.max_x || self.min_y > self.max_y
+    }
+    fn extend_point(&mut self, p: Point) {
+        self.min_x = self.min_x.min(p.0);
+        self.min_y = self.min_y.min(p.1);
+        self.max_x = self.max_x.max(p.0);
+        self.max_y = self.max_y.max(p.1);
+    }
+    pub(crate) fn union(&self, other: &Self) -> Self {
+        Self {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+    pub(crate) fn expand(&self, by: f64) -> Self {
+        Self {
+            min_x: self.min_x - by,
+            min_y: self.min_y - by,
+            max_x: self.max_x + by,
+            max_y: self.max_y + by,
+        }
+    }
+    pub(crate) fn intersects(&self, other: &Self) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+    }
+    pub(crate) fn contains(&self, other: &Self) -> bool {
+        self.min_x <= other.min_x
+            && self.min_y <= other.min_y
+            && self.max_x >= other.max_x
+            && self.max_y >= other.max_y
+    }
+    pub(crate) fn area(&self) -> f64 {
+        if self.is_empty() {
+            0.
+        } else {
+            (self.max_x - self.min_x) * (self.max_y - self.min_y)
+        }
+    }
+    pub(crate) fn enlargement(&self, other: &Self) -> f64 {
+        self.union(other).area() - self.area()
+    }
+    pub(crate) fn to_value(self) -> DataValue {
+        DataValue::List(vec![
+            DataValue::from(self.min_x),
+            DataValue::from(self.min_y),
+            DataValue::from(self.max_x),
+            DataValue::from(self.max_y),
+        ])
+    }
+    pub(crate) fn from_value(v: &DataValue) -> Option<Self> {
+        match v.get_slice()? {
+            [min_x, min_y, max_x, max_y] => Some(Self {
+                min_x: min_x.get_float()?,
+                min_y: min_y.get_float()?,
+                max_x: max_x.get_float()?,
+                max_y: max_y.get_float()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// A geometry flattened into its isolated points, polylines and polygons.
+/// The first ring of each polygon is the exterior, the rest are holes.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct Geometry {
+    points: Vec<Point>,
+    lines: Vec<Vec<Point>>,
+    polygons: Vec<Vec<Vec<Point>>>,
+}
+
+fn json_point(v: &JsonValue) -> Result<Point> {
+    match v.as_array().map(|a| a.as_slice()) {
+        Some([x, y, ..]) => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => Ok((x, y)),
+            _ => bail!("GeoJSON coordinates must be numbers, got {}", v),
+        },
+        _ => bail!("Invalid GeoJSON position {}", v),
+    }
+}
+
+fn json_points(v: &JsonValue) -> Result<Vec<Point>> {
+    v.as_array()
+        .ok_or_else(|| miette!("Invalid GeoJSON coordinates {}", v))?
+        .iter()
+        .map(json_point)
+        .collect()
+}
+
+fn json_rings(v: &JsonValue) -> Result<Vec<Vec<Point>>> {
+    v.as_array()
+        .ok_or_else(|| miette!("Invalid GeoJSON polygon {}", v))?
+        .iter()
+        .map(json_points)
+        .collect()
+}
+
+fn json_array(v: &JsonValue) -> Result<&Vec<JsonValue>> {
+    v.as_array()
+        .ok_or_else(|| miette!("Invalid GeoJSON coordinates {}", v))
+}
+
+fn orient(a: Point, b: Point, c: Point) -> f64 {
+    (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0)
+}
+
+fn within_span(a: Point, b: Point, p: Point) -> bool {
+    p.0 >= a.0.min(b.0) && p.0 <= a.0.max(b.0) && p.1 >= a.1.min(b.1) && p.1 <= a.1.max(b.1)
+}
+
+fn segments_intersect(p1: Point, p2: Point, q1: Point, q2: Point) -> bool {
+    let o1 = orient(p1, p2, q1);
+    let o2 = orient(p1, p2, q2);
+    let o3 = orient(q1, q2, p1);
+    let o4 = orient(q1, q2, p2);
+    if o1 * o2 < 0. && o3 * o4 < 0. {
+        return true;
+    }
+    (o1 == 0. && within_span(p1, p2, q1))
+        || (o2 == 0. && within_span(p1, p2, q2))
+        || (o3 == 0. && within_span(q1, q2, p1))
+        || (o4 == 0. && within_span(q1, q2, p2))
+}
+
+fn segments_cross(p1: Point, p2: Point, q1: Point, q2: Point) -> bool {
+    orient(p1, p2, q1) * orient(p1, p2, q2) < 0. && orient(q1, q2, p1) * orient(q1, q2, p2) < 0.
+}
+
+fn point_segment_distance(p: Point, a: Point, b: Point) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    let t = if len_sq == 0. {
+        0.
+    } else {
+        (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0., 1.)
+    };
+    let (cx, cy) = (a.0 + t * dx, a.1 + t * dy);
+    ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt()
+}
+
+fn segment_distance(p1: Point, p2: Point, q1: Point, q2: Point) -> f64 {
+    if segments_intersect(p1, p2, q1, q2) {
+        return 0.;
+    }
+    point_segment_distance(p1, q1, q2)
+        .min(point_segment_distance(p2, q1, q2))
+        .min(point_segment_distance(q1, p1, p2))
+        .min(point_segment_distance(q2, p1, p2))
+}
+
+fn ring_edges(ring: &[Point]) -> impl Iterator<Item = (Point, Point)> + '_ {
+    ring.iter()
+        .copied()
+        .zip(ring.iter().copied().cycle().skip(1))
+}
+
+/// Even-odd test, so holes are excluded automatically. Points on the boundary may go either way.
+fn point_in_rings(p: Point, rings: &[Vec<Point>]) -> bool {
+    let mut inside = false;
+    for ring in rings {
+        for (a, b) in ring_edges(ring) {
+            if (a.1 > p.1) != (b.1 > p.1) && p.0 < (b.0 - a.0) * (p.1 - a.1) / (b.1 - a.1) + a.0 {
+                inside = !inside;
+            }
+        }
+    }
+    inside
+}
+
+impl Geometry {
+    pub(crate) fn from_value(v: &DataValue) -> Result<Self> {
+        match v {
+            DataValue::Json(j) => Self::from_geojson(&j.0),
+            DataValue::Str(s) => {
+                let j: JsonValue = serde_json::from_str(s)
+                    .map_err(|_| miette!("Cannot interpret string as GeoJSON: {}", s))?;
+                Self::from_geojson(&j)
+            }
+            DataValue::List(l) => match l.as_slice() {
+                [x, y] => match (x.get_float(), y.get_float()) {
+                    (Some(x), Some(y)) => Ok(Self {
+                        points: vec![(x, y)],
+                        ..Default::default()
+                    }),
+                    _ => bail!("A point must consist of two numbers, got {}", v),
+                },
+                _ => bail!("A point must consist of two numbers, got {}", v),
+            },
+            v => bail!("Cannot interpret {} as a geometry", v),
+        }
+    }
+    fn from_geojson(v: &JsonValue) -> Result<Self> {
+        let kind = v
+            .get("type")
+            .and_then(|t| t.as_str())
+            .ok_or_else(|| miette!("GeoJSON object must have a 'type' field: {}", v))?;
+        if kind == "Feature" {
+            let geometry = v
+                .get("geometry")
+                .ok_or_else(|| miette!("GeoJSON feature must have a 'geometry' field"))?;
+            return Self::from_geojson(geometry);
+        }
+        let coords = v
+            .get("coordinates")
+            .ok_or_else(|| miette!("GeoJSON geometry must have a 'coordinates' field: {}", v))?;
+        let mut ret = Self::default();
+        match kind {
+            "Point" => ret.points.push(json_point(coords)?),
+            "MultiPoint" => ret.points = json_points(coords)?,
+            "LineString" => ret.lines.push(json_points(coords)?),
+            "MultiLineString" => {
+                for line in json_array(coords)? {
+                    ret.lines.push(json_points(line)?);
+                }
+            }
+            "Polygon" => ret.polygons.push(json_rings(coords)?),
+            "MultiPolygon" => {
+                for poly in json_array(coords)? {
+                    ret.polygons.push(json_rings(poly)?);
+                }
+            }
+            k => bail!("Unsupported GeoJSON geometry type '{}'", k),
+        }
+        Ok(ret)
+    }
+    pub(crate) fn bbox(&self) -> BBox {
+        let mut ret = BBox::empty();
+        for p in self.vertices() {
+            ret.extend_point(p);
+        }
+        ret
+    }
+    fn vertices(&self) -> impl Iterator<Item = Point> + '_ {
+        self.points
+            .iter()
+            .chain(self.lines.iter().flatten())
+            .chain(self.polygons.iter().flatten().flatten())
+            .copied()
+    }
+    /// Segments of lines and polygon boundaries, with isolated points as degenerate segments.
+    fn segments(&self) -> Vec<(Point, Point)> {
+        let mut ret = self.points.iter().map(|p| (*p, *p)).collect::<Vec<_>>();
+        for line in &self.lines {
+            match line.as_slice() {
+                [p] => ret.push((*p, *p)),
+                l => ret.extend(l.windows(2).map(|w| (w[0], w[1]))),
+            }
+        }
+        for ring in self.polygons.iter().flatten() {
+            ret.extend(ring_edges(ring));
+        }
+        ret
+    }
+    fn interior_contains(&self, p: Point) -> bool {
+        self.polygons.iter().any(|rings| point_in_rings(p, rings))
+    }
+    fn covers_point(&self, p: Point) -> bool {
+        self.interior_contains(p)
+            || self
+                .segments()
+                .into_iter()
+                .any(|(a, b)| orient(a, b, p) == 0. && within_span(a, b, p))
+    }
+    pub(crate) fn intersects(&self, other: &Self) -> bool {
+        if !self.bbox().intersects(&other.bbox()) {
+            return false;
+        }
+        if other.vertices().any(|p| self.interior_contains(p))
+            || self.vertices().any(|p| other.interior_contains(p))
+        {
+            return true;
+        }
+        let other_segments = other.segments();
+        self.segments().into_iter().any(|(p1, p2)| {
+            other_segments
+                .iter()
+                .any(|(q1, q2)| segments_intersect(p1, p2, *q1, *q2))
+        })
+    }
+    /// Whether `other` lies entirely within `self`, boundary included.
+    pub(crate) fn contains(&self, other: &Self) -> bool {
+        if other.vertices().next().is_none() || !self.bbox().contains(&other.bbox()) {
+            return false;
+        }
+        if !other.vertices().all(|p| self.covers_point(p)) {
+            return false;
+        }
+        let own_segments = self.segments();
+        for (q1, q2) in other.segments() {
+            if !self.covers_point(((q1.0 + q2.0) / 2., (q1.1 + q2.1) / 2.)) {
+                return false;
+            }
+            if own_segments
+                .iter()
+                .any(|(p1, p2)| segments_cross(*p1, *p2, q1, q2))
+            {
+                return false;
+            }
+        }
+        // a hole of `self` must not be swallowed by a polygon of `other`
+        !self.vertices().any(|p| {
+            other.interior_contains(p)
+                && !other
+                    .segments()
+                    .into_iter()
+                    .any(|(a, b)| orient(a, b, p) == 0. && within_span(a, b, p))
+        })
+    }
+    pub(crate) fn distance(&self, other: &Self) -> f64 {
+        if self.intersects(other) {
+            return 0.;
+        }
+        let other_segments = other.segments();
+        let mut ret = f64::INFINITY;
+        for (p1, p2) in self.segments() {
+            for (q1, q2) in other_segments.iter() {
+                ret = ret.min(segment_distance(p1, p2, *q1, *q2));
+            }
+        }
+        ret
+    }
+}
@@ -96,6 +96,7 @@ impl From<DataValue> for JsonValue {
                 json!([v.timestamp.0, v.is_assert])
             }
             DataValue::Json(j) => j.0,
+            DataValue::Decimal(d) => JsonValue::String(d.to_string()),
         }
     }
 }
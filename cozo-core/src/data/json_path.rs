@@ -0,0 +1,335 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! JSONPath selection, JSON containment, and the flattening of JSON documents into
+//! `(path, scalar)` pairs used by the JSON inverted index.
+//!
+//! The supported JSONPath subset consists of the root `$`, member access `.key` and
+//! `['key']`, array indices `[n]` (negative indices count from the end), slices
+//! `[start:end]`, wildcards `.*` and `[*]`, and recursive descent `..`.
+
+use std::collections::BTreeSet;
+
+use miette::{bail, Result};
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::json::JsonValue;
+use crate::data::value::DataValue;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    Key(String),
+    Index(i64),
+    Slice(Option<i64>, Option<i64>),
+    Wildcard,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Step {
+    recursive: bool,
+    selector: Selector,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct JsonPath {
+    steps: Vec<Step>,
+}
+
+impl JsonPath {
+    pub(crate) fn parse(path: &str) -> Result<Self> {
+        let chars = path.trim().chars().collect::<Vec<_>>();
+        if chars.first() != Some(&'$') {
+            bail!("JSON path '{}' must start with '$'", path);
+        }
+        let mut steps = vec![];
+        let mut pos = 1;
+        while pos < chars.len() {
+            match chars[pos] {
+                '.' => {
+                    pos += 1;
+                    let recursive = chars.get(pos) == Some(&'.');
+                    if recursive {
+                        pos += 1;
+                    }
+                    match chars.get(pos) {
+                        Some('[') => {
+                            let (selector, next) = parse_bracket(path, &chars, pos)?;
+                            steps.push(Step {
+                                recursive,
+                                selector,
+                            });
+                            pos = next;
+                        }
+                        Some('*') => {
+                            steps.push(Step {
+                                recursive,
+                                selector: Selector::Wildcard,
+                            });
+                            pos += 1;
+                        }
+                        _ => {
+                            let start = pos;
+                            while pos < chars.len() && chars[pos] != '.' && chars[pos] != '[' {
+                                pos += 1;
+                            }
+                            if start == pos {
+                                bail!("Empty member name in JSON path '{}'", path);
+                            }
+                            steps.push(Step {
+                                recursive,
+                                selector: Selector::Key(chars[start..pos].iter().collect()),
+                            });
+                        }
+                    }
+                }
+                '[' => {
+                    let (selector, next) = parse_bracket(path, &chars, pos)?;
+                    steps.push(Step {
+                        recursive: false,
+                        selector,
+                    });
+                    pos = next;
+                }
+                c => bail!("Unexpected character '{}' in JSON path '{}'", c, path),
+            }
+        }
+        Ok(Self { steps })
+    }
+
+    pub(crate) fn select<'a>(&self, doc: &'a JsonValue) -> Vec<&'a JsonValue> {
+        let mut current = vec![doc];
+        for step in &self.steps {
+            if step.recursive {
+                let mut expanded = vec![];
+                for v in current {
+                    collect_descendants(v, &mut expanded);
+                }
+                current = expanded;
+            }
+            let mut next = vec![];
+            for v in current {
+                apply_selector(&step.selector, v, &mut next);
+            }
+            current = next;
+        }
+        current
+    }
+}
+
+/// Parses a bracketed selector starting at the `[` at `pos`,
+/// returning the selector and the position after the closing `]`.
+fn parse_bracket(path: &str, chars: &[char], mut pos: usize) -> Result<(Selector, usize)> {
+    pos += 1;
+    let selector = match chars.get(pos) {
+        Some('*') => {
+            pos += 1;
+            Selector::Wildcard
+        }
+        Some(q @ ('\'' | '"')) => {
+            let quote = *q;
+            pos += 1;
+            let mut key = String::new();
+            loop {
+                match chars.get(pos) {
+                    None => bail!("Unterminated string in JSON path '{}'", path),
+                    Some('\\') => {
+                        match chars.get(pos + 1) {
+                            Some(c) => key.push(*c),
+                            None => bail!("Unterminated string in JSON path '{}'", path),
+                        }
+                        pos += 2;
+                    }
+                    Some(c) if *c == quote => {
+                        pos += 1;
+                        break;
+                    }
+                    Some(c) => {
+                        key.push(*c);
+                        pos += 1;
+                    }
+                }
+            }
+            Selector::Key(key)
+        }
+        _ => {
+            let start = pos;
+            while pos < chars.len() && chars[pos] != ']' {
+                pos += 1;
+            }
+            let content = chars[start..pos].iter().collect::<String>();
+            let parse_int = |s: &str| -> Result<Option<i64>> {
+                let s = s.trim();
+                if s.is_empty() {
+                    return Ok(None);
+                }
+                match s.parse::<i64>() {
+                    Ok(i) => Ok(Some(i)),
+                    Err(_) => bail!("Bad index '{}' in JSON path '{}'", s, path),
+                }
+            };
+            match content.split_once(':') {
+                Some((from, to)) => Selector::Slice(parse_int(from)?, parse_int(to)?),
+                None => match parse_int(&content)? {
+                    Some(i) => Selector::Index(i),
+                    None => bail!("Empty brackets in JSON path '{}'", path),
+                },
+            }
+        }
+    };
+    if chars.get(pos) != Some(&']') {
+        bail!("Expected ']' in JSON path '{}'", path);
+    }
+    Ok((selector, pos + 1))
+}
+
+fn collect_descendants<'a>(v: &'a JsonValue, out: &mut Vec<&'a JsonValue>) {
+    out.push(v);
+    match v {
+        JsonValue::Array(arr) => {
+            for el in arr {
+                collect_descendants(el, out);
+            }
+        }
+        JsonValue::Object(obj) => {
+            for el in obj.values() {
+                collect_descendants(el, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn resolve_index(i: i64, len: usize) -> Option<usize> {
+    let i = if i < 0 { len as i64 + i } else { i };
+    if i < 0 || i >= len as i64 {
+        None
+    } else {
+        Some(i as usize)
+    }
+}
+
+fn apply_selector<'a>(selector: &Selector, v: &'a JsonValue, out: &mut Vec<&'a JsonValue>) {
+    match (selector, v) {
+        (Selector::Key(k), JsonValue::Object(obj)) => {
+            if let Some(el) = obj.get(k) {
+                out.push(el);
+            }
+        }
+        (Selector::Index(i), JsonValue::Array(arr)) => {
+            if let Some(i) = resolve_index(*i, arr.len()) {
+                out.push(&arr[i]);
+            }
+        }
+        (Selector::Slice(from, to), JsonValue::Array(arr)) => {
+            let len = arr.len() as i64;
+            let clamp = |i: i64| {
+                let i = if i < 0 { len + i } else { i };
+                i.clamp(0, len) as usize
+            };
+            let from = clamp(from.unwrap_or(0));
+            let to = clamp(to.unwrap_or(len));
+            if from < to {
+                out.extend(arr[from..to].iter());
+            }
+        }
+        (Selector::Wildcard, JsonValue::Array(arr)) => out.extend(arr.iter()),
+        (Selector::Wildcard, JsonValue::Object(obj)) => out.extend(obj.values()),
+        _ => {}
+    }
+}
+
+/// Converts a scalar JSON value into the value stored in the inverted index.
+/// Numbers with an integral value are normalized to integers so that `1` and `1.0` agree.
+fn scalar_key(v: &JsonValue) -> Option<DataValue> {
+    Some(match v {
+        JsonValue::Null => DataValue::Null,
+        JsonValue::Bool(b) => DataValue::Bool(*b),
+        JsonValue::Number(n) => match n.as_i64() {
+            Some(i) => DataValue::from(i),
+            None => {
+                let f = n.as_f64()?;
+                if f.fract() == 0.0 && f.abs() < i64::MAX as f64 {
+                    DataValue::from(f as i64)
+                } else {
+                    DataValue::from(f)
+                }
+            }
+        },
+        JsonValue::String(s) => DataValue::Str(SmartString::from(s.as_str())),
+        JsonValue::Array(_) | JsonValue::Object(_) => return None,
+    })
+}
+
+/// Tests whether `doc` contains `pattern`: objects must contain every key of the pattern
+/// with a contained value, every element of a pattern array must be contained by some
+/// element of the document array, and scalars must be equal.
+pub(crate) fn json_contains(doc: &JsonValue, pattern: &JsonValue) -> bool {
+    match (doc, pattern) {
+        (JsonValue::Object(d), JsonValue::Object(p)) => p
+            .iter()
+            .all(|(k, pv)| d.get(k).is_some_and(|dv| json_contains(dv, pv))),
+        (JsonValue::Array(d), JsonValue::Array(p)) => {
+            p.iter().all(|pv| d.iter().any(|dv| json_contains(dv, pv)))
+        }
+        (JsonValue::Array(_) | JsonValue::Object(_), _)
+        | (_, JsonValue::Array(_) | JsonValue::Object(_)) => false,
+        (d, p) => scalar_key(d) == scalar_key(p),
+    }
+}
+
+fn push_key(path: &str, key: &str) -> String {
+    let mut chars = key.chars();
+    let is_ident = match chars.next() {
+        Some(c) => {
+            (c.is_alphabetic() || c == '_') && chars.all(|c| c.is_alphanumeric() || c == '_')
+        }
+        None => false,
+    };
+    if is_ident {
+        format!("{path}.{key}")
+    } else {
+        let escaped = key.replace('\\', "\\\\").replace('\'', "\\'");
+        format!("{path}['{escaped}']")
+    }
+}
+
+fn flatten_into(
+    v: &JsonValue,
+    path: String,
+    out: &mut BTreeSet<(SmartString<LazyCompact>, DataValue)>,
+) {
+    match v {
+        JsonValue::Object(obj) => {
+            for (k, el) in obj {
+                flatten_into(el, push_key(&path, k), out);
+            }
+        }
+        JsonValue::Array(arr) => {
+            let path = format!("{path}[*]");
+            for el in arr {
+                flatten_into(el, path.clone(), out);
+            }
+        }
+        v => {
+            if let Some(key) = scalar_key(v) {
+                out.insert((SmartString::from(path), key));
+            }
+        }
+    }
+}
+
+/// Flattens a document into the set of `(path, scalar)` pairs at its leaves.
+/// Array positions are collapsed to `[*]`, so that if a document contains a pattern,
+/// every pair of the pattern is also a pair of the document.
+pub(crate) fn json_index_entries(
+    doc: &JsonValue,
+) -> BTreeSet<(SmartString<LazyCompact>, DataValue)> {
+    let mut out = BTreeSet::new();
+    flatten_into(doc, "$".to_string(), &mut out);
+    out
+}
@@ -6,7 +6,7 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::cmp::Reverse;
+use std::cmp::{Ordering, Reverse};
 use std::collections::BTreeSet;
 use std::io::Write;
 use std::str::FromStr;
@@ -15,7 +15,7 @@ use byteorder::{BigEndian, ByteOrder, WriteBytesExt};
 use regex::Regex;
 
 use crate::data::value::{
-    DataValue, JsonData, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs, Vector,
+    DataValue, Decimal, JsonData, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs, Vector,
 };
 
 const INIT_TAG: u8 = 0x00;
@@ -37,10 +37,16 @@ const BOT_TAG: u8 = 0xFF;
 const VEC_F32: u8 = 0x01;
 const VEC_F64: u8 = 0x02;
 
+const IS_DECIMAL: u8 = 0b01000000;
+const IS_DECIMAL_ABOVE_INTS: u8 = 0b00001000;
+const IS_DECIMAL_BELOW_INTS: u8 = 0b00000010;
 const IS_FLOAT: u8 = 0b00010000;
 const IS_APPROX_INT: u8 = 0b00000100;
 const IS_EXACT_INT: u8 = 0b00000000;
 const EXACT_INT_BOUND: i64 = 0x20_0000_0000_0000;
+// follows an approximate int to mark a decimal just above it,
+// the same byte as `BOT_TAG` so that bounds ending at the int exclude it
+const DECIMAL_AFTER_INT: u8 = 0xFF;
 
 pub(crate) trait MemCmpEncoder: Write {
     fn encode_datavalue(&mut self, v: &DataValue) {
@@ -121,9 +127,35 @@ pub(crate) trait MemCmpEncoder: Write {
                 self.write_u64::<BigEndian>(ts_flipped).unwrap();
                 self.write_u8(!vld.is_assert.0 as u8).unwrap();
             }
+            DataValue::Decimal(d) => {
+                self.write_u8(NUM_TAG).unwrap();
+                self.encode_decimal(d);
+            }
             DataValue::Bot => self.write_u8(BOT_TAG).unwrap(),
         }
     }
+    // decimals are encoded as numbers, so that the two are ordered together,
+    // see `Decimal::num_position` for where they are placed
+    fn encode_decimal(&mut self, d: &Decimal) {
+        let (bucket, position) = d.num_position();
+        self.write_u64::<BigEndian>(order_encode_f64(bucket))
+            .unwrap();
+        match position {
+            DecimalPosition::BelowInts => self.write_u8(IS_DECIMAL_BELOW_INTS).unwrap(),
+            DecimalPosition::AfterInt(i) => {
+                self.write_u8(IS_APPROX_INT).unwrap();
+                self.write_u64::<BigEndian>(order_encode_i64(i)).unwrap();
+                self.write_u8(DECIMAL_AFTER_INT).unwrap();
+            }
+            DecimalPosition::AboveInts => self.write_u8(IS_DECIMAL_ABOVE_INTS).unwrap(),
+            DecimalPosition::AboveFloat => self.write_u8(IS_DECIMAL).unwrap(),
+        }
+        let (int_part, frac_part) = d.split_parts();
+        self.write_u128::<BigEndian>(int_part as u128 ^ SIGN_MARK_128)
+            .unwrap();
+        self.write_u128::<BigEndian>(frac_part).unwrap();
+        self.write_u8(d.scale() as u8).unwrap();
+    }
     fn encode_num(&mut self, v: Num) {
         let f = v.get_float();
         let u = order_encode_f64(f);
@@ -192,6 +224,7 @@ pub fn decode_bytes(data: &[u8]) -> (Vec<u8>, &[u8]) {
 }
 
 const SIGN_MARK: u64 = 0x8000000000000000;
+const SIGN_MARK_128: u128 = 1 << 127;
 
 fn order_encode_i64(v: i64) -> u64 {
     v as u64 ^ SIGN_MARK
@@ -254,6 +287,59 @@ impl Num {
     }
 }
 
+/// Where a decimal goes among the numbers that have the same float value as its bucket
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub(crate) enum DecimalPosition {
+    /// Before all ints, which are all larger
+    BelowInts,
+    /// Right after the int that is its integer part
+    AfterInt(i64),
+    /// After all ints, which are all smaller, but before the float
+    AboveInts,
+    /// After the int and the float that are equal to the bucket, both smaller or equal
+    AboveFloat,
+}
+
+impl Decimal {
+    /// The float value that a decimal shares with the numbers it is ordered among,
+    /// and its place within them.
+    ///
+    /// Ints and floats are ordered by their value as a float first, ints before floats if equal,
+    /// so a decimal needs its own bucket to compare exactly with ints. Where ints convert to
+    /// floats exactly, this is the largest float not above the decimal, after which comes the
+    /// decimal. Where they do not, it is the float of the integer part of the decimal,
+    /// among whose ints the decimal goes by value.
+    pub(crate) fn num_position(&self) -> (f64, DecimalPosition) {
+        let (int_part, _) = self.split_parts();
+        if int_part > -(EXACT_INT_BOUND as i128) && int_part < EXACT_INT_BOUND as i128 {
+            let f = self.to_f64();
+            let bucket = if self.cmp_f64(f) == Ordering::Less {
+                f.next_down()
+            } else {
+                f
+            };
+            return (bucket, DecimalPosition::AboveFloat);
+        }
+        let position = match i64::try_from(int_part) {
+            Ok(i) => DecimalPosition::AfterInt(i),
+            Err(_) if int_part < 0 => DecimalPosition::BelowInts,
+            Err(_) => DecimalPosition::AboveInts,
+        };
+        (int_part as f64, position)
+    }
+    pub(crate) fn decode_from_key(bs: &[u8]) -> (Self, &[u8]) {
+        let (int_bytes, remaining) = bs.split_at(16);
+        let int_part = (BigEndian::read_u128(int_bytes) ^ SIGN_MARK_128) as i128;
+        let (frac_bytes, remaining) = remaining.split_at(16);
+        let frac_part = BigEndian::read_u128(frac_bytes);
+        let (scale, remaining) = remaining.split_first().unwrap();
+        (
+            Decimal::from_parts(int_part, frac_part, *scale as u32),
+            remaining,
+        )
+    }
+}
+
 impl DataValue {
     pub(crate) fn decode_from_key(bs: &[u8]) -> (Self, &[u8]) {
         let (tag, remaining) = bs.split_first().unwrap();
@@ -261,6 +347,22 @@ impl DataValue {
             NULL_TAG => (DataValue::Null, remaining),
             FALSE_TAG => (DataValue::from(false), remaining),
             TRUE_TAG => (DataValue::from(true), remaining),
+            NUM_TAG
+                if matches!(
+                    remaining[8],
+                    IS_DECIMAL | IS_DECIMAL_ABOVE_INTS | IS_DECIMAL_BELOW_INTS
+                ) =>
+            {
+                let (d, remaining) = Decimal::decode_from_key(&remaining[9..]);
+                (DataValue::Decimal(d), remaining)
+            }
+            NUM_TAG
+                if remaining[8] == IS_APPROX_INT
+                    && remaining.get(17) == Some(&DECIMAL_AFTER_INT) =>
+            {
+                let (d, remaining) = Decimal::decode_from_key(&remaining[18..]);
+                (DataValue::Decimal(d), remaining)
+            }
             NUM_TAG => {
                 let (n, remaining) = Num::decode_from_key(remaining);
                 (DataValue::Num(n), remaining)
@@ -27,7 +27,9 @@ use crate::parse::SourceSpan;
 use crate::query::compile::ContainedRuleMultiplicity;
 use crate::query::logical::{Disjunction, NamedFieldNotFound};
 use crate::runtime::hnsw::HnswIndexManifest;
+use crate::runtime::json_index::{JsonIndexManifest, JsonIndexSearch};
 use crate::runtime::minhash_lsh::{LshSearch, MinHashLshIndexManifest};
+use crate::runtime::spatial::{SpatialIndexManifest, SpatialSearch};
 use crate::runtime::relation::{
     AccessLevel, InputRelationHandle, InsufficientAccessLevel, RelationHandle,
 };
@@ -257,6 +259,8 @@ pub(crate) struct FixedRuleApply {
     pub(crate) arity: usize,
     pub(crate) span: SourceSpan,
     pub(crate) fixed_impl: Arc<Box<dyn FixedRule>>,
+    /// Additional outputs bound in the query, mapped to the rules they are bound to
+    pub(crate) named_outputs: BTreeMap<SmartString<LazyCompact>, Symbol>,
 }
 
 impl FixedRuleApply {
@@ -284,6 +288,7 @@ pub(crate) struct MagicFixedRuleApply {
     pub(crate) span: SourceSpan,
     pub(crate) arity: usize,
     pub(crate) fixed_impl: Arc<Box<dyn FixedRule>>,
+    pub(crate) named_outputs: BTreeMap<SmartString<LazyCompact>, Symbol>,
 }
 
 #[derive(Error, Diagnostic, Debug)]
@@ -385,6 +390,11 @@ pub(crate) enum FixedRuleArg {
         valid_at: Option<ValidityTs>,
         span: SourceSpan,
     },
+    Graph {
+        name: Symbol,
+        bindings: Vec<Symbol>,
+        span: SourceSpan,
+    },
 }
 
 impl Debug for FixedRuleArg {
@@ -412,6 +422,10 @@ impl Display for FixedRuleArg {
                 }
                 sf.finish()?;
             }
+            FixedRuleArg::Graph { name, bindings, .. } => {
+                write!(f, "@{name}")?;
+                f.debug_list().entries(bindings).finish()?;
+            }
         }
         Ok(())
     }
@@ -430,6 +444,11 @@ pub(crate) enum MagicFixedRuleRuleArg {
         valid_at: Option<ValidityTs>,
         span: SourceSpan,
     },
+    Graph {
+        name: Symbol,
+        bindings: Vec<Symbol>,
+        span: SourceSpan,
+    },
 }
 
 impl MagicFixedRuleRuleArg {
@@ -437,20 +456,23 @@ impl MagicFixedRuleRuleArg {
     pub(crate) fn bindings(&self) -> &[Symbol] {
         match self {
             MagicFixedRuleRuleArg::InMem { bindings, .. }
-            | MagicFixedRuleRuleArg::Stored { bindings, .. } => bindings,
+            | MagicFixedRuleRuleArg::Stored { bindings, .. }
+            | MagicFixedRuleRuleArg::Graph { bindings, .. } => bindings,
         }
     }
     #[allow(dead_code)]
     pub(crate) fn span(&self) -> SourceSpan {
         match self {
             MagicFixedRuleRuleArg::InMem { span, .. }
-            | MagicFixedRuleRuleArg::Stored { span, .. } => *span,
+            | MagicFixedRuleRuleArg::Stored { span, .. }
+            | MagicFixedRuleRuleArg::Graph { span, .. } => *span,
         }
     }
     pub(crate) fn get_binding_map(&self, starting: usize) -> BTreeMap<Symbol, usize> {
         let bindings = match self {
             MagicFixedRuleRuleArg::InMem { bindings, .. }
-            | MagicFixedRuleRuleArg::Stored { bindings, .. } => bindings,
+            | MagicFixedRuleRuleArg::Stored { bindings, .. }
+            | MagicFixedRuleRuleArg::Graph { bindings, .. } => bindings,
         };
         bindings
             .iter()
@@ -1168,6 +1190,301 @@ impl SearchInput {
 
         Ok(Disjunction::conj(conj))
     }
+    fn normalize_spatial(
+        mut self,
+        base_handle: RelationHandle,
+        idx_handle: RelationHandle,
+        manifest: SpatialIndexManifest,
+        gen: &mut TempSymbGen,
+    ) -> Result<Disjunction> {
+        let mut conj = Vec::with_capacity(self.bindings.len() + 8);
+        let mut bindings = Vec::with_capacity(self.bindings.len());
+        let mut seen_variables = BTreeSet::new();
+
+        for col in base_handle
+            .metadata
+            .keys
+            .iter()
+            .chain(base_handle.metadata.non_keys.iter())
+        {
+            if let Some(arg) = self.bindings.remove(&col.name) {
+                match arg {
+                    Expr::Binding { var, .. } => {
+                        if var.is_ignored_symbol() {
+                            bindings.push(gen.next_ignored(var.span));
+                        } else if seen_variables.insert(var.clone()) {
+                            bindings.push(var);
+                        } else {
+                            let span = var.span;
+                            let dup = gen.next(span);
+                            let unif = NormalFormAtom::Unification(Unification {
+                                binding: dup.clone(),
+                                expr: Expr::Binding {
+                                    var,
+                                    tuple_pos: None,
+                                },
+                                one_many_unif: false,
+                                span,
+                            });
+                            conj.push(unif);
+                            bindings.push(dup);
+                        }
+                    }
+                    expr => {
+                        let span = expr.span();
+                        let kw = gen.next(span);
+                        bindings.push(kw.clone());
+                        let unif = NormalFormAtom::Unification(Unification {
+                            binding: kw,
+                            expr,
+                            one_many_unif: false,
+                            span,
+                        });
+                        conj.push(unif)
+                    }
+                }
+            } else {
+                bindings.push(gen.next_ignored(self.span));
+            }
+        }
+
+        if let Some((name, _)) = self.bindings.pop_first() {
+            bail!(NamedFieldNotFound(
+                self.relation.name.to_string(),
+                name.to_string(),
+                self.span
+            ));
+        }
+
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Field `{0}` is required for spatial search")]
+        #[diagnostic(code(parser::spatial_query_required))]
+        struct SpatialRequiredMissing(String, #[label] SourceSpan);
+
+        let query = match self
+            .parameters
+            .remove("query")
+            .ok_or_else(|| miette!(SpatialRequiredMissing("query".to_string(), self.span)))?
+        {
+            Expr::Binding { var, .. } => var,
+            expr => {
+                let span = expr.span();
+                let kw = gen.next(span);
+                let unif = NormalFormAtom::Unification(Unification {
+                    binding: kw.clone(),
+                    expr,
+                    one_many_unif: false,
+                    span,
+                });
+                conj.push(unif);
+                kw
+            }
+        };
+
+        let radius = match self.parameters.remove("radius") {
+            Some(expr) => {
+                let r = expr.eval_to_const()?;
+                let r = r
+                    .get_float()
+                    .ok_or(ExpectedNonNegFloatForSpatialRadius(self.span))?;
+
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("Expected non-negative float for `radius`")]
+                #[diagnostic(code(parser::expected_float_for_spatial_radius))]
+                struct ExpectedNonNegFloatForSpatialRadius(#[label] SourceSpan);
+
+                ensure!(r >= 0.0, ExpectedNonNegFloatForSpatialRadius(self.span));
+                r
+            }
+            None => 0.,
+        };
+
+        let k = match self.parameters.remove("k") {
+            None => None,
+            Some(k_expr) => {
+                let k = k_expr.eval_to_const()?;
+                let k = k.get_int().ok_or(ExpectedPosIntForSpatialK(self.span))?;
+
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("Expected positive integer for `k`")]
+                #[diagnostic(code(parser::expected_int_for_spatial_k))]
+                struct ExpectedPosIntForSpatialK(#[label] SourceSpan);
+
+                ensure!(k > 0, ExpectedPosIntForSpatialK(self.span));
+                Some(k as usize)
+            }
+        };
+
+        let filter = self.parameters.remove("filter");
+
+        let bind_distance = match self.parameters.remove("bind_distance") {
+            None => None,
+            Some(Expr::Binding { var, .. }) => Some(var),
+            Some(expr) => {
+                let span = expr.span();
+                let kw = gen.next(span);
+                let unif = NormalFormAtom::Unification(Unification {
+                    binding: kw.clone(),
+                    expr,
+                    one_many_unif: false,
+                    span,
+                });
+                conj.push(unif);
+                Some(kw)
+            }
+        };
+
+        if !self.parameters.is_empty() {
+            bail!(
+                "Unknown parameters for spatial search: {:?}",
+                self.parameters.keys()
+            );
+        }
+
+        conj.push(NormalFormAtom::SpatialSearch(SpatialSearch {
+            base_handle,
+            idx_handle,
+            manifest,
+            bindings,
+            query,
+            radius,
+            k,
+            bind_distance,
+            filter,
+            span: self.span,
+        }));
+
+        Ok(Disjunction::conj(conj))
+    }
+    fn normalize_json_index(
+        mut self,
+        base_handle: RelationHandle,
+        idx_handle: RelationHandle,
+        manifest: JsonIndexManifest,
+        gen: &mut TempSymbGen,
+    ) -> Result<Disjunction> {
+        let mut conj = Vec::with_capacity(self.bindings.len() + 8);
+        let mut bindings = Vec::with_capacity(self.bindings.len());
+        let mut seen_variables = BTreeSet::new();
+
+        for col in base_handle
+            .metadata
+            .keys
+            .iter()
+            .chain(base_handle.metadata.non_keys.iter())
+        {
+            if let Some(arg) = self.bindings.remove(&col.name) {
+                match arg {
+                    Expr::Binding { var, .. } => {
+                        if var.is_ignored_symbol() {
+                            bindings.push(gen.next_ignored(var.span));
+                        } else if seen_variables.insert(var.clone()) {
+                            bindings.push(var);
+                        } else {
+                            let span = var.span;
+                            let dup = gen.next(span);
+                            let unif = NormalFormAtom::Unification(Unification {
+                                binding: dup.clone(),
+                                expr: Expr::Binding {
+                                    var,
+                                    tuple_pos: None,
+                                },
+                                one_many_unif: false,
+                                span,
+                            });
+                            conj.push(unif);
+                            bindings.push(dup);
+                        }
+                    }
+                    expr => {
+                        let span = expr.span();
+                        let kw = gen.next(span);
+                        bindings.push(kw.clone());
+                        let unif = NormalFormAtom::Unification(Unification {
+                            binding: kw,
+                            expr,
+                            one_many_unif: false,
+                            span,
+                        });
+                        conj.push(unif)
+                    }
+                }
+            } else {
+                bindings.push(gen.next_ignored(self.span));
+            }
+        }
+
+        if let Some((name, _)) = self.bindings.pop_first() {
+            bail!(NamedFieldNotFound(
+                self.relation.name.to_string(),
+                name.to_string(),
+                self.span
+            ));
+        }
+
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Field `{0}` is required for JSON index search")]
+        #[diagnostic(code(parser::json_index_query_required))]
+        struct JsonIndexRequiredMissing(String, #[label] SourceSpan);
+
+        let query = match self
+            .parameters
+            .remove("query")
+            .ok_or_else(|| miette!(JsonIndexRequiredMissing("query".to_string(), self.span)))?
+        {
+            Expr::Binding { var, .. } => var,
+            expr => {
+                let span = expr.span();
+                let kw = gen.next(span);
+                let unif = NormalFormAtom::Unification(Unification {
+                    binding: kw.clone(),
+                    expr,
+                    one_many_unif: false,
+                    span,
+                });
+                conj.push(unif);
+                kw
+            }
+        };
+
+        let k = match self.parameters.remove("k") {
+            None => None,
+            Some(k_expr) => {
+                let k = k_expr.eval_to_const()?;
+                let k = k.get_int().ok_or(ExpectedPosIntForJsonIndexK(self.span))?;
+
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("Expected positive integer for `k`")]
+                #[diagnostic(code(parser::expected_int_for_json_index_k))]
+                struct ExpectedPosIntForJsonIndexK(#[label] SourceSpan);
+
+                ensure!(k > 0, ExpectedPosIntForJsonIndexK(self.span));
+                Some(k as usize)
+            }
+        };
+
+        let filter = self.parameters.remove("filter");
+
+        if !self.parameters.is_empty() {
+            bail!(
+                "Unknown parameters for JSON index search: {:?}",
+                self.parameters.keys()
+            );
+        }
+
+        conj.push(NormalFormAtom::JsonIndexSearch(JsonIndexSearch {
+            base_handle,
+            idx_handle,
+            manifest,
+            bindings,
+            query,
+            k,
+            filter,
+            span: self.span,
+        }));
+
+        Ok(Disjunction::conj(conj))
+    }
     fn normalize_fts(
         mut self,
         base_handle: RelationHandle,
@@ -1587,6 +1904,16 @@ impl SearchInput {
         {
             return self.normalize_lsh(base_handle, idx_handle, manifest, gen);
         }
+        if let Some((idx_handle, _, manifest)) =
+            base_handle.spatial_indices.get(&self.index.name).cloned()
+        {
+            return self.normalize_spatial(base_handle, idx_handle, manifest, gen);
+        }
+        if let Some((idx_handle, manifest)) =
+            base_handle.json_indices.get(&self.index.name).cloned()
+        {
+            return self.normalize_json_index(base_handle, idx_handle, manifest, gen);
+        }
         #[derive(Debug, Error, Diagnostic)]
         #[error("Index {name} not found on relation {relation}")]
         #[diagnostic(code(eval::hnsw_index_not_found))]
@@ -1727,6 +2054,8 @@ pub(crate) enum NormalFormAtom {
     HnswSearch(HnswSearch),
     FtsSearch(FtsSearch),
     LshSearch(LshSearch),
+    SpatialSearch(SpatialSearch),
+    JsonIndexSearch(JsonIndexSearch),
 }
 
 #[derive(Debug, Clone)]
@@ -1740,6 +2069,8 @@ pub(crate) enum MagicAtom {
     HnswSearch(HnswSearch),
     FtsSearch(FtsSearch),
     LshSearch(LshSearch),
+    SpatialSearch(SpatialSearch),
+    JsonIndexSearch(JsonIndexSearch),
 }
 
 #[derive(Clone, Debug)]
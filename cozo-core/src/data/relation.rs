@@ -21,7 +21,7 @@ use smartstring::{LazyCompact, SmartString};
 use thiserror::Error;
 
 use crate::data::expr::Expr;
-use crate::data::value::{DataValue, JsonData, UuidWrapper, Validity, ValidityTs, Vector};
+use crate::data::value::{DataValue, Decimal, JsonData, UuidWrapper, Validity, ValidityTs, Vector};
 use crate::Num;
 
 #[derive(Debug, Clone, Eq, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -72,6 +72,12 @@ impl Display for NullableColType {
             ColType::Json => {
                 f.write_str("Json")?;
             }
+            ColType::Decimal(scale) => {
+                f.write_str("Decimal")?;
+                if let Some(s) = scale {
+                    write!(f, "({s})")?;
+                }
+            }
         }
         if self.nullable {
             f.write_str("?")?;
@@ -100,6 +106,7 @@ pub enum ColType {
     Tuple(Vec<NullableColType>),
     Validity,
     Json,
+    Decimal(Option<u32>),
 }
 
 #[derive(
@@ -448,10 +455,26 @@ impl NullableColType {
                 DataValue::Validity(vld) => {
                     json!([vld.timestamp.0, vld.is_assert.0])
                 }
+                DataValue::Decimal(d) => {
+                    json!(d.to_string())
+                }
                 DataValue::Bot => {
                     json!(null)
                 }
             })),
+            ColType::Decimal(scale) => {
+                let d = match &data {
+                    DataValue::Decimal(d) => *d,
+                    DataValue::Num(Num::Int(i)) => Decimal::from(*i),
+                    DataValue::Num(Num::Float(f)) => Decimal::from_f64(*f).ok_or_else(make_err)?,
+                    DataValue::Str(s) => Decimal::parse(s).ok_or_else(make_err)?,
+                    _ => bail!(make_err()),
+                };
+                DataValue::Decimal(match scale {
+                    None => d,
+                    Some(s) => d.rescale(*s).ok_or_else(make_err)?,
+                })
+            }
         })
     }
 }
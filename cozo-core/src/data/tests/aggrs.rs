@@ -10,7 +10,7 @@ use approx::AbsDiffEq;
 use itertools::Itertools;
 
 use crate::data::aggr::parse_aggr;
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, Decimal};
 
 #[test]
 fn test_and() {
@@ -299,6 +299,37 @@ fn test_sum() {
     assert_eq!(sum_aggr.get().unwrap(), DataValue::from(15.));
 }
 
+#[test]
+fn test_decimal_sum_product_mean() {
+    let dec = |s: &str| DataValue::Decimal(Decimal::parse(s).unwrap());
+
+    let mut aggr = parse_aggr("sum").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    let mut sum_aggr = aggr.normal_op.unwrap();
+    for _ in 0..4 {
+        sum_aggr.set(&dec("0.25")).unwrap();
+    }
+    sum_aggr.set(&DataValue::from(1)).unwrap();
+    assert_eq!(sum_aggr.get().unwrap(), dec("2.00"));
+    sum_aggr.set(&DataValue::from(0.5)).unwrap();
+    assert_eq!(sum_aggr.get().unwrap(), DataValue::from(2.5));
+
+    let mut aggr = parse_aggr("product").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    let mut product_aggr = aggr.normal_op.unwrap();
+    product_aggr.set(&dec("1.1")).unwrap();
+    product_aggr.set(&dec("1.1")).unwrap();
+    product_aggr.set(&DataValue::from(2)).unwrap();
+    assert_eq!(product_aggr.get().unwrap(), dec("2.42"));
+
+    let mut aggr = parse_aggr("mean").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    let mut mean_aggr = aggr.normal_op.unwrap();
+    mean_aggr.set(&dec("1.00")).unwrap();
+    mean_aggr.set(&dec("2.00")).unwrap();
+    assert_eq!(mean_aggr.get().unwrap(), dec("1.50"));
+}
+
 #[test]
 fn test_product() {
     let mut aggr = parse_aggr("product").unwrap().clone();
@@ -361,6 +392,62 @@ fn test_max() {
     assert_eq!(v, DataValue::from(10));
 }
 
+#[test]
+fn test_decimal_min_max() {
+    let dec = |s: &str| DataValue::Decimal(Decimal::parse(s).unwrap());
+    let values = [
+        dec("1.0000000000000000000000000002"),
+        dec("1.0000000000000000000000000001"),
+        dec("1.0000000000000000000000000003"),
+        dec("0.0000000000000000000000000001"),
+        dec("0.0000000000000000000000000002"),
+    ];
+
+    let mut aggr = parse_aggr("min").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    aggr.meet_init(&[]).unwrap();
+    let mut min_aggr = aggr.normal_op.unwrap();
+    for val in &values[..3] {
+        min_aggr.set(val).unwrap();
+    }
+    assert_eq!(min_aggr.get().unwrap(), values[1]);
+    min_aggr.set(&DataValue::from(1)).unwrap();
+    assert_eq!(min_aggr.get().unwrap(), DataValue::from(1));
+    for val in &values[3..] {
+        min_aggr.set(val).unwrap();
+    }
+    assert_eq!(min_aggr.get().unwrap(), values[3]);
+
+    let m_min_aggr = aggr.meet_op.unwrap();
+    let mut v = values[0].clone();
+    assert!(m_min_aggr.update(&mut v, &values[1]).unwrap());
+    assert!(!m_min_aggr.update(&mut v, &values[2]).unwrap());
+    assert_eq!(v, values[1]);
+    assert!(m_min_aggr.update(&mut v, &values[3]).unwrap());
+    assert!(!m_min_aggr.update(&mut v, &values[4]).unwrap());
+    assert_eq!(v, values[3]);
+
+    let mut aggr = parse_aggr("max").unwrap().clone();
+    aggr.normal_init(&[]).unwrap();
+    aggr.meet_init(&[]).unwrap();
+    let mut max_aggr = aggr.normal_op.unwrap();
+    for val in &values {
+        max_aggr.set(val).unwrap();
+    }
+    assert_eq!(max_aggr.get().unwrap(), values[2]);
+    max_aggr.set(&DataValue::from(1)).unwrap();
+    assert_eq!(max_aggr.get().unwrap(), values[2]);
+
+    let m_max_aggr = aggr.meet_op.unwrap();
+    let mut v = values[3].clone();
+    assert!(m_max_aggr.update(&mut v, &values[4]).unwrap());
+    assert_eq!(v, values[4]);
+    assert!(m_max_aggr.update(&mut v, &values[1]).unwrap());
+    assert!(m_max_aggr.update(&mut v, &values[0]).unwrap());
+    assert!(!m_max_aggr.update(&mut v, &values[1]).unwrap());
+    assert_eq!(v, values[0]);
+}
+
 #[test]
 fn test_choice_rand() {
     let mut aggr = parse_aggr("choice_rand").unwrap().clone();
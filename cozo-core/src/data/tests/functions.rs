@@ -12,7 +12,7 @@ use regex::Regex;
 use serde_json::json;
 
 use crate::data::functions::*;
-use crate::data::value::{DataValue, RegexWrapper};
+use crate::data::value::{DataValue, Decimal, JsonData, RegexWrapper};
 use crate::DbInstance;
 
 #[test]
@@ -1487,3 +1487,330 @@ fn test_range() {
         .into_json();
     assert_eq!(res["rows"][0][0], json!([15, 13, 11, 9, 7, 5]));
 }
+
+#[test]
+fn test_decimal() {
+    let dec = |s: &str| DataValue::Decimal(Decimal::parse(s).unwrap());
+    assert_eq!(
+        op_to_decimal(&[DataValue::from("1.10")]).unwrap(),
+        dec("1.10")
+    );
+    assert_eq!(
+        op_to_decimal(&[DataValue::from(0.1), DataValue::from(3)]).unwrap(),
+        dec("0.100")
+    );
+    assert_eq!(
+        op_to_decimal(&[DataValue::from("2.345"), DataValue::from(2)]).unwrap(),
+        dec("2.35")
+    );
+    assert_eq!(
+        op_to_decimal(&[DataValue::from("-2.345"), DataValue::from(2)]).unwrap(),
+        dec("-2.35")
+    );
+    assert!(op_to_decimal(&[DataValue::from("abc")]).is_err());
+
+    assert_eq!(
+        op_add(&[dec("0.1"), dec("0.2"), DataValue::from(1)]).unwrap(),
+        dec("1.3")
+    );
+    assert_eq!(
+        op_add(&[dec("0.1"), DataValue::from(0.5)]).unwrap(),
+        DataValue::from(0.6)
+    );
+    assert_eq!(op_sub(&[dec("1.00"), dec("0.01")]).unwrap(), dec("0.99"));
+    assert_eq!(op_mul(&[dec("1.5"), dec("1.5")]).unwrap(), dec("2.25"));
+    assert_eq!(
+        op_mul(&[dec("19.99"), DataValue::from(3)]).unwrap(),
+        dec("59.97")
+    );
+    assert_eq!(op_div(&[dec("10.00"), dec("4")]).unwrap(), dec("2.50"));
+    assert_eq!(
+        op_div(&[dec("1"), DataValue::from(3)]).unwrap(),
+        dec("0.33333333")
+    );
+    assert!(op_div(&[dec("1"), DataValue::from(0)]).is_err());
+    assert_eq!(op_mod(&[dec("7.5"), dec("2")]).unwrap(), dec("1.5"));
+    assert_eq!(op_minus(&[dec("1.5")]).unwrap(), dec("-1.5"));
+    assert_eq!(op_abs(&[dec("-1.5")]).unwrap(), dec("1.5"));
+    assert_eq!(op_floor(&[dec("-1.5")]).unwrap(), dec("-2"));
+    assert_eq!(op_ceil(&[dec("-1.5")]).unwrap(), dec("-1"));
+    assert_eq!(op_round(&[dec("2.5")]).unwrap(), dec("3"));
+
+    assert_eq!(
+        op_eq(&[dec("1.5"), dec("1.50")]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_eq(&[dec("2.0"), DataValue::from(2)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_lt(&[dec("0.1"), DataValue::from(0.2)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_ge(&[dec("-0.1"), dec("-0.10")]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_max(&[dec("0.1"), DataValue::from(0), dec("0.25")]).unwrap(),
+        dec("0.25")
+    );
+
+    assert_eq!(
+        op_to_string(&[dec("-0.05")]).unwrap(),
+        DataValue::from("-0.05")
+    );
+    assert_eq!(op_to_int(&[dec("-2.7")]).unwrap(), DataValue::from(-2));
+    assert_eq!(op_to_float(&[dec("2.5")]).unwrap(), DataValue::from(2.5));
+    assert_eq!(
+        op_json(&[dec("0.10")]).unwrap(),
+        DataValue::Json(crate::JsonData(json!("0.10")))
+    );
+    assert_eq!(op_is_decimal(&[dec("1")]).unwrap(), DataValue::from(true));
+    assert_eq!(op_is_num(&[dec("1")]).unwrap(), DataValue::from(true));
+}
+
+#[test]
+fn test_string_utils() {
+    assert_eq!(
+        op_split(&[DataValue::from("a,b,,c"), DataValue::from(",")]).unwrap(),
+        DataValue::List(vec![
+            DataValue::from("a"),
+            DataValue::from("b"),
+            DataValue::from(""),
+            DataValue::from("c")
+        ])
+    );
+    assert!(op_split(&[DataValue::from("abc"), DataValue::from("")]).is_err());
+    assert_eq!(
+        op_join(&[
+            DataValue::List(vec![DataValue::from("a"), DataValue::from("b")]),
+            DataValue::from("--")
+        ])
+        .unwrap(),
+        DataValue::from("a--b")
+    );
+    assert!(op_join(&[
+        DataValue::List(vec![DataValue::from(1)]),
+        DataValue::from(",")
+    ])
+    .is_err());
+
+    assert_eq!(
+        op_pad_start(&[
+            DataValue::from("7"),
+            DataValue::from(3),
+            DataValue::from("0")
+        ])
+        .unwrap(),
+        DataValue::from("007")
+    );
+    assert_eq!(
+        op_pad_end(&[
+            DataValue::from("ab"),
+            DataValue::from(7),
+            DataValue::from("xy")
+        ])
+        .unwrap(),
+        DataValue::from("abxyxyx")
+    );
+    assert_eq!(
+        op_pad_start(&[DataValue::from("abc"), DataValue::from(2)]).unwrap(),
+        DataValue::from("abc")
+    );
+    assert_eq!(
+        op_pad_end(&[DataValue::from("é"), DataValue::from(2)]).unwrap(),
+        DataValue::from("é ")
+    );
+
+    assert_eq!(
+        op_replace(&[
+            DataValue::from("a.b.c"),
+            DataValue::from("."),
+            DataValue::from("::")
+        ])
+        .unwrap(),
+        DataValue::from("a::b::c")
+    );
+
+    assert_eq!(
+        op_levenshtein(&[DataValue::from("kitten"), DataValue::from("sitting")]).unwrap(),
+        DataValue::from(3)
+    );
+    assert_eq!(
+        op_levenshtein(&[DataValue::from(""), DataValue::from("abc")]).unwrap(),
+        DataValue::from(3)
+    );
+    assert_eq!(
+        op_jaro_winkler(&[DataValue::from("abc"), DataValue::from("abc")]).unwrap(),
+        DataValue::from(1.0)
+    );
+    let jw = op_jaro_winkler(&[DataValue::from("MARTHA"), DataValue::from("MARHTA")])
+        .unwrap()
+        .get_float()
+        .unwrap();
+    assert!((jw - 0.9611).abs() < 1e-4);
+    assert_eq!(
+        op_jaro_winkler(&[DataValue::from("abc"), DataValue::from("xyz")]).unwrap(),
+        DataValue::from(0.0)
+    );
+}
+
+#[test]
+fn test_hashing() {
+    let digest = op_sha256(&[DataValue::from("abc")]).unwrap();
+    assert_eq!(
+        op_encode_base64(&[digest]).unwrap(),
+        DataValue::from("ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=")
+    );
+    assert_eq!(
+        op_sha256(&[DataValue::from("abc")]).unwrap(),
+        op_sha256(&[DataValue::Bytes(b"abc".to_vec())]).unwrap()
+    );
+    assert!(op_sha256(&[DataValue::from(1)]).is_err());
+
+    let h = op_xxhash64(&[DataValue::from("")]).unwrap();
+    assert_eq!(h, DataValue::from(0xEF46DB3751D8E999u64 as i64));
+    assert_ne!(
+        op_xxhash64(&[DataValue::from("abc"), DataValue::from(1)]).unwrap(),
+        op_xxhash64(&[DataValue::from("abc")]).unwrap()
+    );
+}
+
+#[test]
+fn test_geo() {
+    let square = DataValue::Json(JsonData(json!({
+        "type": "Polygon",
+        "coordinates": [[[0, 0], [4, 0], [4, 4], [0, 4], [0, 0]], [[1, 1], [2, 1], [2, 2], [1, 2], [1, 1]]]
+    })));
+    let pt = |x: f64, y: f64| op_st_point(&[DataValue::from(x), DataValue::from(y)]).unwrap();
+
+    assert_eq!(
+        op_st_contains(&[square.clone(), pt(3., 3.)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_st_contains(&[square.clone(), pt(1.5, 1.5)]).unwrap(),
+        DataValue::from(false)
+    );
+    assert_eq!(
+        op_st_contains(&[square.clone(), pt(5., 1.)]).unwrap(),
+        DataValue::from(false)
+    );
+    let inner = DataValue::Json(JsonData(json!({
+        "type": "LineString",
+        "coordinates": [[2.5, 0.5], [3.5, 3.5]]
+    })));
+    assert_eq!(
+        op_st_contains(&[square.clone(), inner]).unwrap(),
+        DataValue::from(true)
+    );
+    let crossing = DataValue::Json(JsonData(json!({
+        "type": "LineString",
+        "coordinates": [[0.5, 1.5], [3.5, 1.5]]
+    })));
+    assert_eq!(
+        op_st_contains(&[square.clone(), crossing.clone()]).unwrap(),
+        DataValue::from(false)
+    );
+    assert_eq!(
+        op_st_intersects(&[square.clone(), crossing]).unwrap(),
+        DataValue::from(true)
+    );
+
+    assert_eq!(
+        op_st_distance(&[square.clone(), pt(7., 4.)]).unwrap(),
+        DataValue::from(3.)
+    );
+    assert_eq!(
+        op_st_distance(&[
+            pt(0., 0.),
+            DataValue::List(vec![DataValue::from(3), DataValue::from(4)])
+        ])
+        .unwrap(),
+        DataValue::from(5.)
+    );
+    assert_eq!(
+        op_st_dwithin(&[square.clone(), pt(5., 5.), DataValue::from(1.5)]).unwrap(),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        op_st_dwithin(&[square.clone(), pt(5., 5.), DataValue::from(1.)]).unwrap(),
+        DataValue::from(false)
+    );
+    assert_eq!(
+        op_st_bbox(&[square]).unwrap(),
+        DataValue::List(vec![
+            DataValue::from(0.),
+            DataValue::from(0.),
+            DataValue::from(4.),
+            DataValue::from(4.)
+        ])
+    );
+    assert!(op_st_contains(&[DataValue::from(1), pt(0., 0.)]).is_err());
+}
+
+#[test]
+fn test_json_query() {
+    let doc = DataValue::Json(JsonData(json!({
+        "name": "order",
+        "items": [
+            {"sku": "A1", "qty": 2, "tags": ["red"]},
+            {"sku": "B2", "qty": 1.0},
+            {"sku": "C3", "qty": 5, "odd key": true}
+        ]
+    })));
+    let q = |path: &str| op_json_query(&[doc.clone(), DataValue::from(path)]).unwrap();
+
+    assert_eq!(
+        q("$.items[*].sku"),
+        DataValue::List(vec![
+            DataValue::from("A1"),
+            DataValue::from("B2"),
+            DataValue::from("C3")
+        ])
+    );
+    assert_eq!(q("$.name"), DataValue::List(vec![DataValue::from("order")]));
+    assert_eq!(
+        q("$['items'][-1].qty"),
+        DataValue::List(vec![DataValue::from(5)])
+    );
+    assert_eq!(
+        q("$.items[0:2].sku"),
+        DataValue::List(vec![DataValue::from("A1"), DataValue::from("B2")])
+    );
+    assert_eq!(
+        q("$..tags[0]"),
+        DataValue::List(vec![DataValue::from("red")])
+    );
+    assert_eq!(
+        q("$.items[2]['odd key']"),
+        DataValue::List(vec![DataValue::from(true)])
+    );
+    assert_eq!(q("$.missing"), DataValue::List(vec![]));
+    assert_eq!(
+        q("$.items[0].tags"),
+        DataValue::List(vec![DataValue::Json(JsonData(json!(["red"])))])
+    );
+    assert!(op_json_query(&[doc.clone(), DataValue::from("items")]).is_err());
+    assert!(op_json_query(&[doc.clone(), DataValue::from("$.items[")]).is_err());
+
+    let contains = |pattern: serde_json::Value| {
+        op_json_contains(&[doc.clone(), DataValue::Json(JsonData(pattern))]).unwrap()
+    };
+    assert_eq!(
+        contains(json!({"items": [{"sku": "B2", "qty": 1}]})),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        contains(json!({"items": [{"sku": "B2"}, {"tags": ["red"]}]})),
+        DataValue::from(true)
+    );
+    assert_eq!(
+        contains(json!({"items": [{"sku": "B2", "qty": 2}]})),
+        DataValue::from(false)
+    );
+    assert_eq!(contains(json!({"name": ["order"]})), DataValue::from(false));
+    assert_eq!(contains(json!({})), DataValue::from(true));
+}
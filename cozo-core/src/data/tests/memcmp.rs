@@ -10,7 +10,7 @@
 use uuid::Uuid;
 
 use crate::data::memcmp::{decode_bytes, MemCmpEncoder};
-use crate::data::value::{DataValue, Num, UuidWrapper};
+use crate::data::value::{DataValue, Decimal, Num, UuidWrapper};
 
 #[test]
 fn encode_decode_num() {
@@ -135,3 +135,155 @@ fn encode_decode_datavalues() {
     assert!(remaining.is_empty());
     assert_eq!(decoded, v);
 }
+
+#[test]
+fn encode_decode_decimal() {
+    let mut collected = vec![];
+    for s in [
+        "0",
+        "0.00",
+        "1",
+        "1.5",
+        "1.50",
+        "-1.5",
+        "-1.50",
+        "-0.001",
+        "0.001",
+        "123456.789",
+        "-123456.789",
+        "99999999999999999999.99",
+        "-99999999999999999999.99",
+        "0.0000000000000000000000000001",
+        "-3",
+        "-2.999",
+    ] {
+        let d = Decimal::parse(s).unwrap();
+        let v = DataValue::Decimal(d);
+        let mut encoder = vec![];
+        encoder.encode_datavalue(&v);
+        let (decoded, rest) = DataValue::decode_from_key(&encoder);
+        assert_eq!(decoded, v);
+        assert_eq!(decoded.to_string(), v.to_string());
+        assert!(rest.is_empty());
+        collected.push((encoder, v));
+    }
+    let mut by_bytes = collected.clone();
+    by_bytes.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut by_value = collected;
+    by_value.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(by_bytes, by_value);
+}
+
+#[test]
+fn encode_decode_mixed_numbers() {
+    let mut collected = vec![];
+    let mut values = vec![];
+    for i in [
+        -3i64,
+        0,
+        1,
+        2,
+        9007199254740992,
+        9007199254740993,
+        9007199254740994,
+        -9007199254740993,
+        i64::MIN,
+        i64::MAX,
+    ] {
+        values.push(DataValue::from(i));
+    }
+    for f in [
+        -2.5f64,
+        -0.0,
+        0.0,
+        0.5,
+        1.0,
+        1.25,
+        2.0,
+        0.1,
+        9007199254740992.0,
+        9223372036854775808.0,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    ] {
+        values.push(DataValue::from(f));
+    }
+    for s in [
+        "0.5",
+        "-2.999",
+        "1",
+        "1.0",
+        "1.2500000000000000000000000001",
+        "1.2499999999999999999999999999",
+        "2",
+        "9007199254740993",
+        "9007199254740992",
+        "9007199254740992.5",
+        "-9007199254740993.5",
+        "1.99999999999999999999",
+        "2.00000000000000000001",
+        "0.1",
+        "9223372036854775807.5",
+        "9223372036854775808",
+        "-9223372036854775809",
+        "-0.0000000000000000000000000001",
+    ] {
+        values.push(DataValue::Decimal(Decimal::parse(s).unwrap()));
+    }
+    for v in values {
+        let mut encoder = vec![];
+        encoder.encode_datavalue(&v);
+        let (decoded, rest) = DataValue::decode_from_key(&encoder);
+        assert_eq!(decoded, v);
+        assert!(rest.is_empty());
+        collected.push((encoder, v));
+    }
+    let mut by_bytes = collected.clone();
+    by_bytes.sort_by(|a, b| a.0.cmp(&b.0));
+    let mut by_value = collected;
+    by_value.sort_by(|a, b| a.1.cmp(&b.1));
+    assert_eq!(by_bytes, by_value);
+
+    // numbers and decimals interleave by value
+    let decimal = |s: &str| DataValue::Decimal(Decimal::parse(s).unwrap());
+    let mut sorted = vec![
+        DataValue::from(2),
+        decimal("0.5"),
+        DataValue::from(1),
+        decimal("1.5"),
+        DataValue::from(0.75),
+    ];
+    sorted.sort();
+    assert_eq!(
+        sorted,
+        vec![
+            decimal("0.5"),
+            DataValue::from(0.75),
+            DataValue::from(1),
+            decimal("1.5"),
+            DataValue::from(2),
+        ]
+    );
+
+    // ints too large for a float to hold exactly are still compared exactly
+    let mut sorted = vec![
+        DataValue::from(9007199254740993i64),
+        decimal("9007199254740992.5"),
+        decimal("9007199254740992"),
+        DataValue::from(9007199254740992i64),
+        decimal("1.99999999999999999999"),
+        DataValue::from(2),
+    ];
+    sorted.sort();
+    assert_eq!(
+        sorted,
+        vec![
+            decimal("1.99999999999999999999"),
+            DataValue::from(2),
+            DataValue::from(9007199254740992i64),
+            decimal("9007199254740992"),
+            decimal("9007199254740992.5"),
+            DataValue::from(9007199254740993i64),
+        ]
+    );
+}
@@ -16,6 +16,7 @@ use std::hash::{Hash, Hasher};
 use std::ops::Deref;
 
 use crate::data::json::JsonValue;
+use crate::data::memcmp::DecimalPosition;
 use crate::data::relation::VecElementType;
 use ordered_float::OrderedFloat;
 use regex::Regex;
@@ -140,9 +141,7 @@ impl From<(i64, bool)> for Validity {
 }
 
 /// A Value in the database
-#[derive(
-Clone, PartialEq, Eq, PartialOrd, Ord, serde_derive::Deserialize, serde_derive::Serialize, Hash,
-)]
+#[derive(Clone, PartialEq, Eq, serde_derive::Deserialize, serde_derive::Serialize, Hash)]
 pub enum DataValue {
     /// null
     Null,
@@ -169,10 +168,64 @@ pub enum DataValue {
     Json(JsonData),
     /// validity,
     Validity(Validity),
+    /// exact decimal number
+    Decimal(Decimal),
     /// bottom type, used internally only
     Bot,
 }
 
+impl DataValue {
+    /// Position of the variant in the ordering of values of different kinds.
+    /// Numbers and decimals share a position since they are ordered together.
+    fn kind_order(&self) -> u8 {
+        match self {
+            DataValue::Null => 0,
+            DataValue::Bool(_) => 1,
+            DataValue::Num(_) | DataValue::Decimal(_) => 2,
+            DataValue::Str(_) => 3,
+            DataValue::Bytes(_) => 4,
+            DataValue::Uuid(_) => 5,
+            DataValue::Regex(_) => 6,
+            DataValue::List(_) => 7,
+            DataValue::Set(_) => 8,
+            DataValue::Vec(_) => 9,
+            DataValue::Json(_) => 10,
+            DataValue::Validity(_) => 11,
+            DataValue::Bot => 12,
+        }
+    }
+}
+
+impl PartialOrd for DataValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DataValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (DataValue::Null, DataValue::Null) => Ordering::Equal,
+            (DataValue::Bool(l), DataValue::Bool(r)) => l.cmp(r),
+            (DataValue::Num(l), DataValue::Num(r)) => l.cmp(r),
+            (DataValue::Num(l), DataValue::Decimal(r)) => l.cmp_decimal(r),
+            (DataValue::Decimal(l), DataValue::Num(r)) => r.cmp_decimal(l).reverse(),
+            (DataValue::Decimal(l), DataValue::Decimal(r)) => l.cmp(r),
+            (DataValue::Str(l), DataValue::Str(r)) => l.cmp(r),
+            (DataValue::Bytes(l), DataValue::Bytes(r)) => l.cmp(r),
+            (DataValue::Uuid(l), DataValue::Uuid(r)) => l.cmp(r),
+            (DataValue::Regex(l), DataValue::Regex(r)) => l.cmp(r),
+            (DataValue::List(l), DataValue::List(r)) => l.cmp(r),
+            (DataValue::Set(l), DataValue::Set(r)) => l.cmp(r),
+            (DataValue::Vec(l), DataValue::Vec(r)) => l.cmp(r),
+            (DataValue::Json(l), DataValue::Json(r)) => l.cmp(r),
+            (DataValue::Validity(l), DataValue::Validity(r)) => l.cmp(r),
+            (DataValue::Bot, DataValue::Bot) => Ordering::Equal,
+            _ => self.kind_order().cmp(&other.kind_order()),
+        }
+    }
+}
+
 /// Wrapper for JsonValue
 #[derive(Clone, PartialEq, Eq, serde_derive::Deserialize, serde_derive::Serialize)]
 pub struct JsonData(pub JsonValue);
@@ -462,6 +515,12 @@ impl From<String> for DataValue {
     }
 }
 
+impl From<Decimal> for DataValue {
+    fn from(v: Decimal) -> Self {
+        DataValue::Decimal(v)
+    }
+}
+
 impl From<bool> for DataValue {
     fn from(value: bool) -> Self {
         DataValue::Bool(value)
@@ -576,6 +635,414 @@ impl Ord for Num {
     }
 }
 
+impl Num {
+    /// Ints and decimals are ordered exactly by value, ints first if equal. Floats are ordered
+    /// with decimals by the bucket of the decimal, the same as in the memcmp encoding.
+    pub(crate) fn cmp_decimal(&self, other: &Decimal) -> Ordering {
+        match self {
+            Num::Int(i) => match Decimal::from(*i).cmp_value(other) {
+                Ordering::Equal => Ordering::Less,
+                ord => ord,
+            },
+            Num::Float(f) => {
+                let (bucket, position) = other.num_position();
+                match f.total_cmp(&bucket) {
+                    Ordering::Equal if position == DecimalPosition::AboveFloat => Ordering::Less,
+                    Ordering::Equal => Ordering::Greater,
+                    ord => ord,
+                }
+            }
+        }
+    }
+}
+
+/// Largest number of fractional digits a decimal can hold
+pub(crate) const DECIMAL_MAX_SCALE: u32 = 28;
+
+/// Extra fractional digits kept when dividing decimals
+const DECIMAL_DIV_EXTRA_SCALE: u32 = 8;
+
+fn pow10(n: u32) -> i128 {
+    10i128.pow(n)
+}
+
+/// Integer division rounding half away from zero
+fn div_round(n: i128, d: i128) -> i128 {
+    let q = n / d;
+    let r = n % d;
+    if r.unsigned_abs() * 2 >= d.unsigned_abs() {
+        if (n < 0) != (d < 0) {
+            q - 1
+        } else {
+            q + 1
+        }
+    } else {
+        q
+    }
+}
+
+/// Exact decimal number, represented as an integer mantissa
+/// and a fixed number of digits after the decimal point
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+impl Decimal {
+    /// Construct a decimal with value `mantissa * 10^-scale`
+    pub fn new(mantissa: i128, scale: u32) -> Option<Self> {
+        if scale > DECIMAL_MAX_SCALE {
+            None
+        } else {
+            Some(Self { mantissa, scale })
+        }
+    }
+    /// The unscaled integer value
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+    /// Number of digits after the decimal point
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+    /// Parse from a string such as `-123.4500`
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let (neg, digits) = match s.as_bytes().first()? {
+            b'-' => (true, &s[1..]),
+            b'+' => (false, &s[1..]),
+            _ => (false, s),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        if int_part.is_empty() && frac_part.is_empty() {
+            return None;
+        }
+        let scale = frac_part.len() as u32;
+        if scale > DECIMAL_MAX_SCALE {
+            return None;
+        }
+        let mut mantissa = 0i128;
+        for c in int_part.bytes().chain(frac_part.bytes()) {
+            if !c.is_ascii_digit() {
+                return None;
+            }
+            mantissa = mantissa.checked_mul(10)?.checked_add((c - b'0') as i128)?;
+        }
+        Some(Self {
+            mantissa: if neg { -mantissa } else { mantissa },
+            scale,
+        })
+    }
+    /// Convert from a float, using the shortest representation that round-trips
+    pub fn from_f64(f: f64) -> Option<Self> {
+        if !f.is_finite() {
+            return None;
+        }
+        let shortest = f.to_string();
+        match Self::parse(&shortest) {
+            Some(d) => Some(d),
+            None => Self::parse(&format!("{:.*}", DECIMAL_MAX_SCALE as usize, f))
+                .map(|d| d.normalize()),
+        }
+    }
+    /// Nearest float to this decimal
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap()
+    }
+    pub(crate) fn get_int(&self) -> Option<i64> {
+        let p = pow10(self.scale);
+        if self.mantissa % p == 0 {
+            i64::try_from(self.mantissa / p).ok()
+        } else {
+            None
+        }
+    }
+    pub(crate) fn is_zero(&self) -> bool {
+        self.mantissa == 0
+    }
+    /// Change the number of fractional digits, rounding half away from zero if digits are dropped
+    pub fn rescale(&self, scale: u32) -> Option<Self> {
+        if scale > DECIMAL_MAX_SCALE {
+            return None;
+        }
+        Some(match scale.cmp(&self.scale) {
+            Ordering::Equal => *self,
+            Ordering::Greater => Self {
+                mantissa: self.mantissa.checked_mul(pow10(scale - self.scale))?,
+                scale,
+            },
+            Ordering::Less => Self {
+                mantissa: div_round(self.mantissa, pow10(self.scale - scale)),
+                scale,
+            },
+        })
+    }
+    /// Strip trailing zeros of the fractional part
+    pub fn normalize(&self) -> Self {
+        self.trim_to(0)
+    }
+    fn trim_to(&self, min_scale: u32) -> Self {
+        let mut ret = *self;
+        while ret.scale > min_scale && ret.mantissa % 10 == 0 {
+            ret.mantissa /= 10;
+            ret.scale -= 1;
+        }
+        ret
+    }
+    fn aligned(&self, other: &Self) -> Option<(i128, i128, u32)> {
+        let scale = self.scale.max(other.scale);
+        Some((
+            self.rescale(scale)?.mantissa,
+            other.rescale(scale)?.mantissa,
+            scale,
+        ))
+    }
+    pub(crate) fn checked_add(&self, other: &Self) -> Option<Self> {
+        let (l, r, scale) = self.aligned(other)?;
+        Some(Self {
+            mantissa: l.checked_add(r)?,
+            scale,
+        })
+    }
+    pub(crate) fn checked_sub(&self, other: &Self) -> Option<Self> {
+        let (l, r, scale) = self.aligned(other)?;
+        Some(Self {
+            mantissa: l.checked_sub(r)?,
+            scale,
+        })
+    }
+    pub(crate) fn checked_mul(&self, other: &Self) -> Option<Self> {
+        let product = Self {
+            mantissa: self.mantissa.checked_mul(other.mantissa)?,
+            scale: self.scale + other.scale,
+        };
+        if product.scale > DECIMAL_MAX_SCALE {
+            product.rescale(DECIMAL_MAX_SCALE)
+        } else {
+            Some(product)
+        }
+    }
+    pub(crate) fn checked_div(&self, other: &Self) -> Option<Self> {
+        if other.is_zero() {
+            return None;
+        }
+        let min_scale = self.scale.max(other.scale);
+        let mut scale = (min_scale + DECIMAL_DIV_EXTRA_SCALE).min(DECIMAL_MAX_SCALE);
+        loop {
+            let shift = scale + other.scale - self.scale;
+            let numerator = 10i128
+                .checked_pow(shift)
+                .and_then(|p| p.checked_mul(self.mantissa));
+            if let Some(numerator) = numerator {
+                let ret = Self {
+                    mantissa: div_round(numerator, other.mantissa),
+                    scale,
+                };
+                return Some(ret.trim_to(min_scale));
+            }
+            if scale == self.scale {
+                return None;
+            }
+            scale -= 1;
+        }
+    }
+    pub(crate) fn checked_rem(&self, other: &Self) -> Option<Self> {
+        let (l, r, scale) = self.aligned(other)?;
+        Some(Self {
+            mantissa: l.checked_rem(r)?,
+            scale,
+        })
+    }
+    pub(crate) fn checked_neg(&self) -> Option<Self> {
+        Some(Self {
+            mantissa: self.mantissa.checked_neg()?,
+            scale: self.scale,
+        })
+    }
+    pub(crate) fn signum(&self) -> i64 {
+        self.mantissa.signum() as i64
+    }
+    pub(crate) fn floor(&self) -> Self {
+        Self {
+            mantissa: self.mantissa.div_euclid(pow10(self.scale)),
+            scale: 0,
+        }
+    }
+    pub(crate) fn ceil(&self) -> Self {
+        let p = pow10(self.scale);
+        let floor = self.mantissa.div_euclid(p);
+        Self {
+            mantissa: if self.mantissa.rem_euclid(p) == 0 {
+                floor
+            } else {
+                floor + 1
+            },
+            scale: 0,
+        }
+    }
+    /// Integer part rounded towards negative infinity,
+    /// and the remaining fraction expressed with `DECIMAL_MAX_SCALE` digits
+    pub(crate) fn split_parts(&self) -> (i128, u128) {
+        let p = pow10(self.scale);
+        let int_part = self.mantissa.div_euclid(p);
+        let frac_part =
+            self.mantissa.rem_euclid(p) as u128 * pow10(DECIMAL_MAX_SCALE - self.scale) as u128;
+        (int_part, frac_part)
+    }
+    pub(crate) fn from_parts(int_part: i128, frac_part: u128, scale: u32) -> Self {
+        let frac = (frac_part / pow10(DECIMAL_MAX_SCALE - scale) as u128) as i128;
+        Self {
+            mantissa: int_part * pow10(scale) + frac,
+            scale,
+        }
+    }
+    /// Compare by numerical value only, so that `1.5` and `1.50` are equal
+    pub(crate) fn cmp_value(&self, other: &Self) -> Ordering {
+        self.split_parts().cmp(&other.split_parts())
+    }
+    /// Compare exactly with a finite float whose integer part fits in an `i128`
+    pub(crate) fn cmp_f64(&self, f: f64) -> Ordering {
+        let p = pow10(self.scale);
+        let int_part = self.mantissa / p;
+        let frac_part = self.mantissa % p * pow10(DECIMAL_MAX_SCALE - self.scale);
+        // both split towards zero, which for the float is exact
+        let f_int = f.trunc();
+        int_part.cmp(&(f_int as i128)).then_with(|| {
+            let f_frac = f - f_int;
+            if f_frac == 0. {
+                return frac_part.cmp(&0);
+            }
+            // the fraction is `n / 2^k` with `n < 2^53` and `k > 52`, so scaled to
+            // `DECIMAL_MAX_SCALE` digits it is `n * 5^28 / 2^(k - 28)`, which fits in a `u128`
+            let bits = f_frac.to_bits();
+            let exp = ((bits >> 52) & 0x7ff) as u32;
+            let mantissa = bits & ((1 << 52) - 1);
+            let (n, k) = if exp == 0 {
+                (mantissa, 1074)
+            } else {
+                (mantissa | (1 << 52), 1075 - exp)
+            };
+            let scaled = n as u128 * 5u128.pow(DECIMAL_MAX_SCALE);
+            let shift = k - DECIMAL_MAX_SCALE;
+            let (trunc, is_exact) = if shift >= 128 {
+                (0, false)
+            } else {
+                ((scaled >> shift) as i128, scaled & ((1 << shift) - 1) == 0)
+            };
+            let rest = if is_exact {
+                Ordering::Equal
+            } else if f_frac > 0. {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            };
+            if f_frac > 0. {
+                frac_part.cmp(&trunc).then(rest)
+            } else {
+                frac_part.cmp(&-trunc).then(rest)
+            }
+        })
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(i: i64) -> Self {
+        Self {
+            mantissa: i as i128,
+            scale: 0,
+        }
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_value(other)
+            .then_with(|| self.scale.cmp(&other.scale))
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.unsigned_abs().to_string();
+        if self.mantissa < 0 {
+            f.write_str("-")?;
+        }
+        let scale = self.scale as usize;
+        if scale == 0 {
+            f.write_str(&digits)
+        } else if digits.len() > scale {
+            let (int_part, frac_part) = digits.split_at(digits.len() - scale);
+            write!(f, "{int_part}.{frac_part}")
+        } else {
+            write!(f, "0.{}{digits}", "0".repeat(scale - digits.len()))
+        }
+    }
+}
+
+impl Debug for Decimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+impl serde::Serialize for Decimal {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+    {
+        let mut state = serializer.serialize_tuple(2)?;
+        state.serialize_element(&VecBytes(&self.mantissa.to_be_bytes()))?;
+        state.serialize_element(&self.scale)?;
+        state.end()
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Decimal {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(2, DecimalVisitor)
+    }
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = Decimal;
+
+    fn expecting(&self, formatter: &mut Formatter<'_>) -> std::fmt::Result {
+        formatter.write_str("decimal representation")
+    }
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+        where
+            A: SeqAccess<'de>,
+    {
+        let bytes: &[u8] = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(0, &self))?;
+        let scale: u32 = seq
+            .next_element()?
+            .ok_or_else(|| serde::de::Error::invalid_length(1, &self))?;
+        let mantissa_bytes: [u8; 16] = bytes
+            .try_into()
+            .map_err(|_| serde::de::Error::invalid_length(bytes.len(), &self))?;
+        Decimal::new(i128::from_be_bytes(mantissa_bytes), scale).ok_or_else(|| {
+            serde::de::Error::invalid_value(
+                serde::de::Unexpected::Unsigned(scale as u64),
+                &self,
+            )
+        })
+    }
+}
+
 impl Debug for DataValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{self}")
@@ -608,6 +1075,7 @@ impl Display for DataValue {
                 .field("timestamp", &v.timestamp.0)
                 .field("retracted", &v.is_assert)
                 .finish(),
+            DataValue::Decimal(d) => write!(f, "to_decimal({:?})", d.to_string()),
             DataValue::Vec(a) => match a {
                 Vector::F32(a) => {
                     write!(f, "vec({:?})", a.to_vec())
@@ -653,6 +1121,7 @@ impl DataValue {
     pub fn get_int(&self) -> Option<i64> {
         match self {
             DataValue::Num(n) => n.get_int(),
+            DataValue::Decimal(d) => d.get_int(),
             _ => None,
         }
     }
@@ -661,6 +1130,9 @@ impl DataValue {
             DataValue::Num(n) => n
                 .get_int()
                 .and_then(|i| if i < 0 { None } else { Some(i as u64) }),
+            DataValue::Decimal(d) => d
+                .get_int()
+                .and_then(|i| if i < 0 { None } else { Some(i as u64) }),
             _ => None,
         }
     }
@@ -668,6 +1140,7 @@ impl DataValue {
     pub fn get_float(&self) -> Option<f64> {
         match self {
             DataValue::Num(n) => Some(n.get_float()),
+            DataValue::Decimal(d) => Some(d.to_f64()),
             _ => None,
         }
     }
@@ -21,7 +21,9 @@ use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
 use crate::fixed_rule::algos::shortest_path_dijkstra::dijkstra_keep_ties;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRulePayload, FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -84,6 +86,16 @@ impl FixedRule for BetweennessCentrality {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![FixedRuleOptionSpec::undirected()],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -132,6 +144,16 @@ impl FixedRule for ClosenessCentrality {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![FixedRuleOptionSpec::undirected()],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
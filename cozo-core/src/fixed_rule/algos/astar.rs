@@ -19,7 +19,8 @@ use crate::data::symb::Symbol;
 use crate::data::tuple::Tuple;
 use crate::data::value::DataValue;
 use crate::fixed_rule::{
-    BadExprValueError, FixedRule, FixedRuleInputRelation, FixedRulePayload, NodeNotFoundError,
+    BadExprValueError, FixedRule, FixedRuleInputRelation, FixedRuleInputSpec, FixedRuleOptionSpec,
+    FixedRuleOptionType, FixedRulePayload, FixedRuleSchema, NodeNotFoundError,
 };
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
@@ -61,6 +62,26 @@ impl FixedRule for ShortestPathAStar {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`"),
+                FixedRuleInputSpec::new(
+                    "nodes",
+                    1,
+                    "nodes as `[node, ..]`, whose columns the heuristic can refer to",
+                ),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach"),
+            ],
+            options: vec![FixedRuleOptionSpec::new(
+                "heuristic",
+                FixedRuleOptionType::Expr,
+                "estimate of the remaining cost, in terms of the bindings of the nodes and the goal",
+            )
+            .required()],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -0,0 +1,477 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use either::{Left, Right};
+use graph::prelude::{
+    CsrLayout, DirectedCsrGraph, DirectedNeighborsWithValues, Graph, GraphBuilder,
+};
+use itertools::Itertools;
+use miette::{bail, Diagnostic, Result};
+use rayon::prelude::*;
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::tuple::Tuple;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::shortest_path_dijkstra::dijkstra;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputRelation, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("The graph contains a negative cycle, so shortest paths are not defined")]
+#[diagnostic(code(algo::negative_cycle))]
+#[diagnostic(help("The cycle goes through {0}"))]
+struct NegativeCycleError(String, #[label] SourceSpan);
+
+/// Single-source shortest paths allowing negative edge weights, with the same inputs and
+/// output as `ShortestPathDijkstra`.
+pub(crate) struct ShortestPathBellmanFord;
+
+impl FixedRule for ShortestPathBellmanFord {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let starting = payload.get_input(1)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+
+        let (graph, indices, inv_indices) = edges.as_directed_weighted_graph(undirected, true)?;
+        let starting_nodes = collect_nodes(&starting, &inv_indices)?;
+        let goals = match payload.get_input(2) {
+            Err(_) => None,
+            Ok(t) => Some(collect_nodes(&t, &inv_indices)?),
+        };
+
+        let span = edges.span();
+        let rows: Vec<Vec<Tuple>> = starting_nodes
+            .into_par_iter()
+            .map(|start| -> Result<Vec<Tuple>> {
+                let (distance, back_pointers) =
+                    bellman_ford(&graph, &[start], &indices, span, &poison)?;
+                let targets = match &goals {
+                    None => Left(0..graph.node_count()),
+                    Some(goals) => Right(goals.iter().copied()),
+                };
+                Ok(targets
+                    .map(|target| {
+                        path_row(&indices, start, target, &distance, |node| {
+                            back_pointers[node as usize]
+                        })
+                    })
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+        for row in rows.into_iter().flatten() {
+            out.put(row);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    2,
+                    "edges as `[from, to, weight?]`, where weights may be negative",
+                ),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach, defaulting to all nodes")
+                    .optional(),
+            ],
+            options: vec![FixedRuleOptionSpec::undirected()],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+/// Shortest paths allowing negative edge weights by Johnson's algorithm: edges are reweighted
+/// to be non-negative using potentials from a single Bellman–Ford run, after which Dijkstra
+/// is run from every starting node. Without the starting nodes, all pairs are computed.
+pub(crate) struct ShortestPathJohnson;
+
+impl FixedRule for ShortestPathJohnson {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+
+        let (graph, indices, inv_indices) = edges.as_directed_weighted_graph(undirected, true)?;
+        // a graph built without any edges still counts one node
+        let n = indices.len() as u32;
+        let starting_nodes = match payload.get_input(1) {
+            Err(_) => (0..n).collect(),
+            Ok(starting) => collect_nodes(&starting, &inv_indices)?,
+        };
+        let goals = match payload.get_input(2) {
+            Err(_) => None,
+            Ok(t) => Some(collect_nodes(&t, &inv_indices)?),
+        };
+
+        let all_nodes = (0..n).collect_vec();
+        let (potential, _) = bellman_ford(&graph, &all_nodes, &indices, edges.span(), &poison)?;
+        let reweighted: DirectedCsrGraph<u32, (), f32> = GraphBuilder::new()
+            .csr_layout(CsrLayout::Sorted)
+            .edges_with_values((0..n).flat_map(|from| {
+                let potential = &potential;
+                graph.out_neighbors_with_values(from).map(move |t| {
+                    // the new weights are non-negative up to rounding
+                    let weight = t.value + potential[from as usize] - potential[t.target as usize];
+                    (from, t.target, weight.max(0.))
+                })
+            }))
+            .build();
+
+        let rows: Vec<Vec<Tuple>> = starting_nodes
+            .into_par_iter()
+            .map(|start| -> Result<Vec<Tuple>> {
+                let res = match &goals {
+                    None => dijkstra(&reweighted, start, &(), &(), &()),
+                    Some(goals) => dijkstra(&reweighted, start, goals, &(), &()),
+                };
+                poison.check()?;
+                Ok(res
+                    .into_iter()
+                    .map(|(target, cost, path)| {
+                        let cost = if path.is_empty() {
+                            cost
+                        } else {
+                            cost - potential[start as usize] + potential[target as usize]
+                        };
+                        vec![
+                            indices[start as usize].clone(),
+                            indices[target as usize].clone(),
+                            DataValue::from(cost as f64),
+                            DataValue::List(
+                                path.into_iter()
+                                    .map(|u| indices[u as usize].clone())
+                                    .collect_vec(),
+                            ),
+                        ]
+                    })
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+        for row in rows.into_iter().flatten() {
+            out.put(row);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    2,
+                    "edges as `[from, to, weight?]`, where weights may be negative",
+                ),
+                FixedRuleInputSpec::new(
+                    "starting",
+                    1,
+                    "nodes to start from, defaulting to all nodes",
+                )
+                .optional(),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach, defaulting to all nodes")
+                    .optional(),
+            ],
+            options: vec![FixedRuleOptionSpec::undirected()],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+pub(crate) fn collect_nodes(
+    relation: &FixedRuleInputRelation<'_, '_>,
+    inv_indices: &BTreeMap<DataValue, u32>,
+) -> Result<BTreeSet<u32>> {
+    let mut nodes = BTreeSet::new();
+    for tuple in relation.iter()? {
+        let tuple = tuple?;
+        if let Some(idx) = inv_indices.get(&tuple[0]) {
+            nodes.insert(*idx);
+        }
+    }
+    Ok(nodes)
+}
+
+/// A row in the output format of `ShortestPathDijkstra`, with infinite cost and an empty path
+/// for unreachable targets.
+pub(crate) fn path_row(
+    indices: &[DataValue],
+    start: u32,
+    target: u32,
+    distance: &[f32],
+    back_pointer: impl Fn(u32) -> u32,
+) -> Tuple {
+    let cost = distance[target as usize];
+    let mut path = vec![];
+    if cost.is_finite() {
+        let mut current = target;
+        while current != start {
+            path.push(indices[current as usize].clone());
+            current = back_pointer(current);
+        }
+        path.push(indices[start as usize].clone());
+        path.reverse();
+    }
+    vec![
+        indices[start as usize].clone(),
+        indices[target as usize].clone(),
+        DataValue::from(cost as f64),
+        DataValue::List(path),
+    ]
+}
+
+/// Bellman–Ford from all of `sources` at distance zero, where each round only relaxes the
+/// edges out of nodes whose distance changed in the previous round. Starting from every node
+/// is the same as starting from a virtual node with zero-weight edges to all nodes, which is
+/// what Johnson's algorithm needs.
+///
+/// Returns the distances and back pointers, or fails if a negative cycle is reachable.
+pub(crate) fn bellman_ford(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    sources: &[u32],
+    indices: &[DataValue],
+    span: SourceSpan,
+    poison: &Poison,
+) -> Result<(Vec<f32>, Vec<u32>)> {
+    let n = graph.node_count() as usize;
+    let mut distance = vec![f32::INFINITY; n];
+    let mut back_pointers = vec![u32::MAX; n];
+    let mut queued = vec![false; n];
+    let mut active = vec![];
+    for &source in sources {
+        distance[source as usize] = 0.;
+        if !queued[source as usize] {
+            queued[source as usize] = true;
+            active.push(source);
+        }
+    }
+
+    // with no negative cycles, every distance is final after `n - 1` rounds,
+    // so nothing changes in round `n`
+    for _ in 0..n {
+        if active.is_empty() {
+            return Ok((distance, back_pointers));
+        }
+        let mut next = vec![];
+        for node in active {
+            queued[node as usize] = false;
+            let base = distance[node as usize];
+            for t in graph.out_neighbors_with_values(node) {
+                let candidate = base + t.value;
+                if candidate < distance[t.target as usize] {
+                    distance[t.target as usize] = candidate;
+                    back_pointers[t.target as usize] = node;
+                    if !queued[t.target as usize] {
+                        queued[t.target as usize] = true;
+                        next.push(t.target);
+                    }
+                }
+            }
+        }
+        active = next;
+        poison.check()?;
+    }
+    if active.is_empty() {
+        return Ok((distance, back_pointers));
+    }
+
+    // walking back `n` steps from a node still changing lands on the cycle
+    let mut on_cycle = active[0];
+    for _ in 0..n {
+        match back_pointers[on_cycle as usize] {
+            u32::MAX => break,
+            prev => on_cycle = prev,
+        }
+    }
+    let mut cycle = vec![on_cycle];
+    let mut current = back_pointers[on_cycle as usize];
+    while current != on_cycle && current != u32::MAX && cycle.len() <= n {
+        cycle.push(current);
+        current = back_pointers[current as usize];
+    }
+    cycle.push(on_cycle);
+    cycle.reverse();
+    let description = cycle
+        .iter()
+        .map(|node| indices[*node as usize].to_string())
+        .join(" -> ");
+    bail!(NegativeCycleError(description, span))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_negative_weights() {
+        let db = DbInstance::default();
+        let edges = r#"
+        edges[] <- [['a', 'b', 4], ['a', 'c', 2], ['c', 'b', -1], ['b', 'd', 2], ['c', 'd', 5]]
+        "#;
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                start[] <- [['a']]
+                ?[s, t, cost, path] <~ ShortestPathBellmanFord(edges[], start[])
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([
+                ["a", "a", 0.0, ["a"]],
+                ["a", "b", 1.0, ["a", "c", "b"]],
+                ["a", "c", 2.0, ["a", "c"]],
+                ["a", "d", 3.0, ["a", "c", "b", "d"]]
+            ])
+        );
+
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                ?[s, t, cost, path] <~ ShortestPathJohnson(edges[])
+                "#
+            ))
+            .unwrap();
+        let rows = res.into_json()["rows"].clone();
+        assert!(rows.as_array().unwrap().contains(&serde_json::json!([
+            "c",
+            "d",
+            1.0,
+            ["c", "b", "d"]
+        ])));
+        assert!(rows.as_array().unwrap().contains(&serde_json::json!([
+            "a",
+            "d",
+            3.0,
+            ["a", "c", "b", "d"]
+        ])));
+
+        let res = db.run_default(
+            r#"
+            edges[] <- [['a', 'b', 1], ['b', 'c', -2], ['c', 'b', 1]]
+            start[] <- [['a']]
+            ?[s, t, cost, path] <~ ShortestPathBellmanFord(edges[], start[])
+            "#,
+        );
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_negative_weights_edge_cases() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+                edges[a, b, w] <- []
+                start[] <- [['a']]
+                ?[s, t, cost, path] <~ ShortestPathBellmanFord(edges[], start[])
+                "#,
+            )
+            .unwrap();
+        assert!(res.rows.is_empty());
+        let res = db
+            .run_default("edges[a, b, w] <- [] ?[s, t, cost, path] <~ ShortestPathJohnson(edges[])")
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        // 'x' cannot be reached, and a start node that is not in the graph is skipped
+        let edges = r#"
+        edges[] <- [['a', 'a', 1], ['a', 'b', -1], ['x', 'y', 1]]
+        start[] <- [['a'], ['nowhere']]
+        goals[] <- [['b'], ['x']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, cost, path] <~ ShortestPathBellmanFord(edges[], start[], goals[])"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b", -1.0, ["a", "b"]], ["a", "x", "INFINITY", []]])
+        );
+        let johnson = db
+            .run_default(&format!(
+                "{edges} ?[s, t, cost, path] <~ ShortestPathJohnson(edges[], start[], goals[])"
+            ))
+            .unwrap();
+        assert_eq!(
+            johnson.into_json()["rows"],
+            serde_json::json!([["a", "b", -1.0, ["a", "b"]], ["a", "x", "INFINITY", []]])
+        );
+
+        // a negative self-loop is a negative cycle, also for Johnson's algorithm, which
+        // looks at the whole graph even if the cycle cannot be reached from the start
+        for rule in ["ShortestPathBellmanFord", "ShortestPathJohnson"] {
+            let err = db
+                .run_default(&format!(
+                    r#"
+                    edges[] <- [['a', 'b', 1], ['a', 'a', -1]]
+                    start[] <- [['a']]
+                    ?[s, t, cost, path] <~ {rule}(edges[], start[])
+                    "#
+                ))
+                .unwrap_err();
+            assert!(err.to_string().contains("negative cycle"), "{rule}");
+        }
+        assert!(db
+            .run_default(
+                r#"
+                edges[] <- [['a', 'b', 1], ['c', 'd', -3], ['d', 'c', 1]]
+                start[] <- [['a']]
+                ?[s, t, cost, path] <~ ShortestPathJohnson(edges[], start[])
+                "#
+            )
+            .is_err());
+        // an undirected negative edge is a negative cycle of length two
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[s, t, cost, path] <~ ShortestPathBellmanFord(edges[], start[], undirected: true)"
+            ))
+            .is_err());
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[s, t, cost, path] <~ ShortestPathBellmanFord(edges[], start[], directed: true)"
+            ))
+            .is_err());
+    }
+}
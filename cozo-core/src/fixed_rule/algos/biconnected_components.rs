@@ -0,0 +1,326 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::cmp::min;
+use std::collections::{BTreeMap, BTreeSet};
+
+use graph::prelude::{DirectedCsrGraph, DirectedNeighbors, Graph};
+use miette::Result;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{FixedRule, FixedRuleInputSpec, FixedRulePayload, FixedRuleSchema};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+pub(crate) struct ArticulationPoints;
+
+impl FixedRule for ArticulationPoints {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let result = Biconnectivity::run(&simple_undirected_adjacency(&graph), &poison)?;
+        for node in result.articulation_points {
+            out.put(vec![indices[node as usize].clone()]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(1)
+    }
+}
+
+pub(crate) struct Bridges;
+
+impl FixedRule for Bridges {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let result = Biconnectivity::run(&simple_undirected_adjacency(&graph), &poison)?;
+        for (from, to) in result.bridges {
+            out.put(vec![
+                indices[from as usize].clone(),
+                indices[to as usize].clone(),
+            ]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+pub(crate) struct BiconnectedComponents;
+
+impl FixedRule for BiconnectedComponents {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let result = Biconnectivity::run(&simple_undirected_adjacency(&graph), &poison)?;
+        for (grp_id, component) in result.components.into_iter().enumerate() {
+            for node in component {
+                out.put(vec![
+                    indices[node as usize].clone(),
+                    DataValue::from(grp_id as i64),
+                ]);
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Neighbour lists of the simple undirected graph underlying `graph`:
+/// self-loops are dropped, and an edge given in both directions counts once.
+pub(crate) fn simple_undirected_adjacency(graph: &DirectedCsrGraph<u32>) -> Vec<Vec<u32>> {
+    // nodes only come from edges, but a graph built without any still counts one node
+    if graph.edge_count() == 0 {
+        return vec![];
+    }
+    (0..graph.node_count())
+        .map(|node| {
+            let mut neighbours = graph
+                .out_neighbors(node)
+                .copied()
+                .filter(|n| *n != node)
+                .collect::<Vec<_>>();
+            neighbours.dedup();
+            neighbours
+        })
+        .collect()
+}
+
+/// Articulation points, bridges and biconnected components by the Hopcroft–Tarjan algorithm.
+/// The depth-first search keeps its own stack so that long paths do not exhaust the call stack.
+struct Biconnectivity {
+    articulation_points: BTreeSet<u32>,
+    bridges: Vec<(u32, u32)>,
+    components: Vec<BTreeSet<u32>>,
+}
+
+impl Biconnectivity {
+    fn run(adj: &[Vec<u32>], poison: &Poison) -> Result<Self> {
+        let n = adj.len();
+        let mut ret = Self {
+            articulation_points: Default::default(),
+            bridges: vec![],
+            components: vec![],
+        };
+        let mut disc: Vec<Option<usize>> = vec![None; n];
+        let mut low = vec![0; n];
+        let mut time = 0;
+        let mut edge_stack: Vec<(u32, u32)> = vec![];
+        let mut steps = 0usize;
+
+        for root in 0..n {
+            if disc[root].is_some() {
+                continue;
+            }
+            disc[root] = Some(time);
+            low[root] = time;
+            time += 1;
+            let mut root_children = 0;
+            // frames of (node, parent, index of the next neighbour to visit)
+            let mut stack: Vec<(usize, Option<usize>, usize)> = vec![(root, None, 0)];
+            while let Some(frame) = stack.last_mut() {
+                let (node, parent) = (frame.0, frame.1);
+                if let Some(&next) = adj[node].get(frame.2) {
+                    frame.2 += 1;
+                    let next = next as usize;
+                    if Some(next) == parent {
+                        continue;
+                    }
+                    match disc[next] {
+                        None => {
+                            disc[next] = Some(time);
+                            low[next] = time;
+                            time += 1;
+                            if node == root {
+                                root_children += 1;
+                            }
+                            edge_stack.push((node as u32, next as u32));
+                            stack.push((next, Some(node), 0));
+                        }
+                        Some(d) => {
+                            if d < disc[node].unwrap() {
+                                low[node] = min(low[node], d);
+                                edge_stack.push((node as u32, next as u32));
+                            }
+                        }
+                    }
+                    continue;
+                }
+                stack.pop();
+                if let Some(parent) = parent {
+                    low[parent] = min(low[parent], low[node]);
+                    let parent_disc = disc[parent].unwrap();
+                    if low[node] > parent_disc {
+                        ret.bridges.push((parent as u32, node as u32));
+                    }
+                    if low[node] >= parent_disc {
+                        if parent != root {
+                            ret.articulation_points.insert(parent as u32);
+                        }
+                        let mut component = BTreeSet::new();
+                        while let Some((a, b)) = edge_stack.pop() {
+                            component.insert(a);
+                            component.insert(b);
+                            if (a, b) == (parent as u32, node as u32) {
+                                break;
+                            }
+                        }
+                        ret.components.push(component);
+                    }
+                }
+                steps += 1;
+                if steps % 1024 == 0 {
+                    poison.check()?;
+                }
+            }
+            if root_children > 1 {
+                ret.articulation_points.insert(root as u32);
+            }
+            poison.check()?;
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_biconnectivity() {
+        let db = DbInstance::default();
+        // two triangles joined by the bridge c-d, with a pendant node e hanging off d
+        let edges = r#"
+        edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['c', 'd'],
+                    ['d', 'f'], ['f', 'g'], ['g', 'd'], ['d', 'e'], ['e', 'd']]
+        "#;
+        let res = db
+            .run_default(&format!("{edges} ?[n] <~ ArticulationPoints(edges[])"))
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], serde_json::json!([["c"], ["d"]]));
+
+        let res = db
+            .run_default(&format!("{edges} ?[x, y] <~ Bridges(edges[])"))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["c", "d"], ["d", "e"]])
+        );
+
+        let res = db
+            .run_default(&format!(
+                "{edges} c[n, g] <~ BiconnectedComponents(edges[]) ?[count_unique(g)] := c[n, g]"
+            ))
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], serde_json::json!([[4]]));
+    }
+
+    #[test]
+    fn test_biconnectivity_edge_cases() {
+        let db = DbInstance::default();
+        for (rule, head) in [
+            ("ArticulationPoints", "n"),
+            ("Bridges", "x, y"),
+            ("BiconnectedComponents", "n, g"),
+        ] {
+            let res = db
+                .run_default(&format!("edges[a, b] <- [] ?[{head}] <~ {rule}(edges[])"))
+                .unwrap();
+            assert!(res.rows.is_empty(), "{rule}");
+            assert!(db
+                .run_default(&format!(
+                    "edges[] <- [['a', 'b']] ?[{head}] <~ {rule}(edges[], undirected: true)"
+                ))
+                .is_err());
+        }
+
+        // self-loops are ignored, so a node with only a self-loop is in no component,
+        // and a path of two edges is cut by its middle node and by either edge
+        let edges = "edges[] <- [['z', 'z'], ['a', 'b'], ['b', 'b'], ['b', 'c']]";
+        let res = db
+            .run_default(&format!("{edges} ?[n] <~ ArticulationPoints(edges[])"))
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], serde_json::json!([["b"]]));
+        let res = db
+            .run_default(&format!("{edges} ?[x, y] <~ Bridges(edges[])"))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b"], ["b", "c"]])
+        );
+        let res = db
+            .run_default(&format!(
+                "{edges} c[n, g] <~ BiconnectedComponents(edges[]) ?[n, count(g)] := c[n, g]"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", 1], ["b", 2], ["c", 1]])
+        );
+    }
+}
@@ -0,0 +1,384 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+
+use graph::prelude::{DirectedNeighborsWithValues, Graph};
+use miette::{bail, Diagnostic, Result};
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Matching in a bipartite graph whose edges go from the left side to the right side.
+///
+/// By default a maximum cardinality matching is found by Hopcroft–Karp. With `weighted: true`
+/// a maximum weight matching is found by the Hungarian algorithm instead, which works on a
+/// dense weight matrix and is meant for assignment problems of moderate size.
+/// The rule returns `[left, right, weight]` for every matched pair.
+pub(crate) struct BipartiteMatching;
+
+impl FixedRule for BipartiteMatching {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?.ensure_min_len(2)?;
+        let weighted = payload.bool_option("weighted", Some(false))?;
+        let (graph, indices, _) = edges.as_directed_weighted_graph(false, false)?;
+        let n = graph.node_count() as usize;
+
+        // parallel edges collapse to the heaviest one
+        let mut adj: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); n];
+        let mut is_right = vec![false; n];
+        for (from, targets) in adj.iter_mut().enumerate() {
+            for t in graph.out_neighbors_with_values(from as u32) {
+                let to = t.target as usize;
+                let weight = targets.entry(to).or_insert(f64::NEG_INFINITY);
+                *weight = weight.max(t.value as f64);
+                is_right[to] = true;
+            }
+        }
+        if let Some(node) = (0..n).find(|&i| is_right[i] && !adj[i].is_empty()) {
+            #[derive(Debug, Error, Diagnostic)]
+            #[error("The node {0:?} appears on both sides of the bipartite graph")]
+            #[diagnostic(code(algo::not_bipartite))]
+            #[diagnostic(help("Edges must go from the left side to the right side"))]
+            struct NotBipartite(DataValue, #[label] SourceSpan);
+
+            bail!(NotBipartite(indices[node].clone(), edges.span()))
+        }
+
+        let matching = if weighted {
+            hungarian(&adj, &is_right, &poison)?
+        } else {
+            hopcroft_karp(&adj, &poison)?
+        };
+        for (left, right) in matching {
+            out.put(vec![
+                indices[left].clone(),
+                indices[right].clone(),
+                DataValue::from(adj[left][&right]),
+            ]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[left, right, weight?]`",
+            )],
+            options: vec![FixedRuleOptionSpec::new(
+                "weighted",
+                FixedRuleOptionType::Bool,
+                "whether to maximise the total weight instead of the number of matched pairs",
+            )
+            .default_value(false)],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(3)
+    }
+}
+
+fn hopcroft_karp(adj: &[BTreeMap<usize, f64>], poison: &Poison) -> Result<Vec<(usize, usize)>> {
+    let n = adj.len();
+    let adj: Vec<Vec<usize>> = adj.iter().map(|m| m.keys().copied().collect()).collect();
+    let left: Vec<usize> = (0..n).filter(|&u| !adj[u].is_empty()).collect();
+    let mut pair_left: Vec<Option<usize>> = vec![None; n];
+    let mut pair_right: Vec<Option<usize>> = vec![None; n];
+    let mut dist: Vec<Option<usize>> = vec![None; n];
+
+    loop {
+        // layer the free left nodes and everything reachable by alternating paths
+        let mut queue = VecDeque::new();
+        for &u in &left {
+            if pair_left[u].is_none() {
+                dist[u] = Some(0);
+                queue.push_back(u);
+            } else {
+                dist[u] = None;
+            }
+        }
+        let mut found = false;
+        while let Some(u) = queue.pop_front() {
+            for &v in &adj[u] {
+                match pair_right[v] {
+                    None => found = true,
+                    Some(w) => {
+                        if dist[w].is_none() {
+                            dist[w] = dist[u].map(|d| d + 1);
+                            queue.push_back(w);
+                        }
+                    }
+                }
+            }
+        }
+        if !found {
+            break;
+        }
+
+        // augment along vertex-disjoint shortest paths, using an explicit stack
+        for &root in &left {
+            if pair_left[root].is_some() {
+                continue;
+            }
+            let mut stack: Vec<(usize, usize)> = vec![(root, 0)];
+            while let Some(&(u, i)) = stack.last() {
+                if i >= adj[u].len() {
+                    dist[u] = None;
+                    stack.pop();
+                    if let Some(parent) = stack.last_mut() {
+                        parent.1 += 1;
+                    }
+                    continue;
+                }
+                let v = adj[u][i];
+                match pair_right[v] {
+                    None => {
+                        for &(u, i) in &stack {
+                            let v = adj[u][i];
+                            pair_left[u] = Some(v);
+                            pair_right[v] = Some(u);
+                        }
+                        break;
+                    }
+                    Some(w) if dist[w] == dist[u].map(|d| d + 1) => {
+                        stack.push((w, 0));
+                    }
+                    _ => {
+                        stack.last_mut().unwrap().1 += 1;
+                    }
+                }
+            }
+        }
+        poison.check()?;
+    }
+
+    Ok(pair_left
+        .into_iter()
+        .enumerate()
+        .filter_map(|(u, v)| v.map(|v| (u, v)))
+        .collect())
+}
+
+/// Maximum weight matching by the Hungarian algorithm on the dense matrix of the smaller side
+/// against the larger one. Missing edges have weight zero and are dropped from the result.
+fn hungarian(
+    adj: &[BTreeMap<usize, f64>],
+    is_right: &[bool],
+    poison: &Poison,
+) -> Result<Vec<(usize, usize)>> {
+    let left: Vec<usize> = (0..adj.len()).filter(|&u| !adj[u].is_empty()).collect();
+    let right: Vec<usize> = (0..adj.len()).filter(|&v| is_right[v]).collect();
+    let right_pos: BTreeMap<usize, usize> =
+        right.iter().enumerate().map(|(i, v)| (*v, i)).collect();
+    let mut weights = vec![vec![0.; right.len()]; left.len()];
+    let mut max_weight: f64 = 0.;
+    for (i, u) in left.iter().enumerate() {
+        for (v, w) in &adj[*u] {
+            weights[i][right_pos[v]] = *w;
+            max_weight = max_weight.max(*w);
+        }
+    }
+    let transposed = left.len() > right.len();
+    let (rows, cols) = if transposed {
+        (right.len(), left.len())
+    } else {
+        (left.len(), right.len())
+    };
+    // minimising `max_weight - weight` maximises the weight
+    let cost = |r: usize, c: usize| {
+        let w = if transposed {
+            weights[c][r]
+        } else {
+            weights[r][c]
+        };
+        max_weight - w
+    };
+
+    // potentials and assignments are 1-based, with index 0 as the virtual column
+    let mut row_pot = vec![0.; rows + 1];
+    let mut col_pot = vec![0.; cols + 1];
+    let mut col_row = vec![0usize; cols + 1];
+    let mut way = vec![0usize; cols + 1];
+    for r in 1..=rows {
+        col_row[0] = r;
+        let mut c0 = 0;
+        let mut min_slack = vec![f64::INFINITY; cols + 1];
+        let mut used = vec![false; cols + 1];
+        loop {
+            used[c0] = true;
+            let r0 = col_row[c0];
+            let mut delta = f64::INFINITY;
+            let mut c1 = 0;
+            for c in 1..=cols {
+                if used[c] {
+                    continue;
+                }
+                let slack = cost(r0 - 1, c - 1) - row_pot[r0] - col_pot[c];
+                if slack < min_slack[c] {
+                    min_slack[c] = slack;
+                    way[c] = c0;
+                }
+                if min_slack[c] < delta {
+                    delta = min_slack[c];
+                    c1 = c;
+                }
+            }
+            for (c, &is_used) in used.iter().enumerate() {
+                if is_used {
+                    row_pot[col_row[c]] += delta;
+                    col_pot[c] -= delta;
+                } else {
+                    min_slack[c] -= delta;
+                }
+            }
+            c0 = c1;
+            if col_row[c0] == 0 {
+                break;
+            }
+        }
+        loop {
+            let c1 = way[c0];
+            col_row[c0] = col_row[c1];
+            c0 = c1;
+            if c0 == 0 {
+                break;
+            }
+        }
+        poison.check()?;
+    }
+
+    let mut ret = vec![];
+    for c in 1..=cols {
+        let r = col_row[c];
+        if r == 0 {
+            continue;
+        }
+        let (l, v) = if transposed {
+            (left[c - 1], right[r - 1])
+        } else {
+            (left[r - 1], right[c - 1])
+        };
+        if adj[l].contains_key(&v) {
+            ret.push((l, v));
+        }
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_bipartite_matching() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+        edges[] <- [['a', 'x'], ['a', 'y'], ['b', 'x'], ['c', 'x'], ['c', 'z']]
+        ?[l, r, w] <~ BipartiteMatching(edges[])
+        "#,
+            )
+            .unwrap();
+        assert_eq!(res.rows.len(), 3);
+
+        let res = db
+            .run_default(
+                r#"
+        edges[] <- [['a', 'x', 3], ['a', 'y', 2], ['b', 'x', 2], ['b', 'y', 0.5]]
+        ?[l, r, w] <~ BipartiteMatching(edges[], weighted: true)
+        :order l
+        "#,
+            )
+            .unwrap();
+        let pairs = res
+            .rows
+            .iter()
+            .map(|row| (row[0].get_str().unwrap(), row[1].get_str().unwrap()))
+            .collect::<Vec<_>>();
+        // 'a'-'y' and 'b'-'x' weigh 4, more than the 3.5 of 'a'-'x' and 'b'-'y'
+        assert_eq!(pairs, vec![("a", "y"), ("b", "x")]);
+
+        assert!(db
+            .run_default(
+                r#"
+        edges[] <- [['a', 'b'], ['b', 'c']]
+        ?[l, r, w] <~ BipartiteMatching(edges[])
+        "#,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_bipartite_matching_edge_cases() {
+        let db = DbInstance::default();
+        for weighted in [false, true] {
+            let res = db
+                .run_default(&format!(
+                    "edges[l, r] <- [] ?[l, r, w] <~ BipartiteMatching(edges[], weighted: {weighted})"
+                ))
+                .unwrap();
+            assert!(res.rows.is_empty());
+
+            // parallel edges collapse, and 'b' can only take the right node 'a' already has
+            let res = db
+                .run_default(&format!(
+                    r#"
+        edges[] <- [['a', 'x', 1], ['a', 'x', 2], ['b', 'x', 1]]
+        ?[l, r, w] <~ BipartiteMatching(edges[], weighted: {weighted})
+        "#
+                ))
+                .unwrap();
+            assert_eq!(res.rows.len(), 1);
+            if weighted {
+                assert_eq!(
+                    res.into_json()["rows"],
+                    serde_json::json!([["a", "x", 2.0]])
+                );
+            }
+
+            // a self-loop puts the node on both sides
+            let err = db
+                .run_default(&format!(
+                    r#"
+        edges[] <- [['a', 'a'], ['b', 'x']]
+        ?[l, r, w] <~ BipartiteMatching(edges[], weighted: {weighted})
+        "#
+                ))
+                .unwrap_err();
+            assert!(err.to_string().contains("both sides"));
+        }
+        assert!(db
+            .run_default(
+                "edges[] <- [['a', 'x']] ?[l, r, w] <~ BipartiteMatching(edges[], weight: true)"
+            )
+            .is_err());
+    }
+}
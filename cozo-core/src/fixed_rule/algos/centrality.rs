@@ -0,0 +1,639 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use graph::prelude::{DirectedCsrGraph, DirectedNeighborsWithValues, Graph};
+use miette::{bail, Diagnostic, Result};
+use ordered_float::OrderedFloat;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use rayon::prelude::*;
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::all_pairs_shortest_path::dijkstra_cost_only;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Eigenvector centrality by power iteration: a node is central if it is pointed to by
+/// central nodes. The iteration uses `A + I`, which has the same leading eigenvector as the
+/// adjacency matrix `A` but also converges on bipartite graphs.
+pub(crate) struct EigenvectorCentrality;
+
+impl FixedRule for EigenvectorCentrality {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let iterations = payload.pos_integer_option("iterations", Some(100))?;
+        let epsilon = payload.unit_interval_option("epsilon", Some(0.000001))?;
+
+        let (graph, indices, _) = edges.as_directed_weighted_graph(undirected, false)?;
+        let n = indices.len();
+        let mut scores = vec![1. / (n as f64).sqrt(); n];
+        for _ in 0..iterations {
+            let mut next = scores.clone();
+            propagate_in(&graph, &scores, &mut next);
+            normalize(&mut next);
+            let diff = l1_distance(&scores, &next);
+            scores = next;
+            if diff < epsilon {
+                break;
+            }
+            poison.check()?;
+        }
+        put_scores(out, &indices, &scores);
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "iterations",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations",
+                )
+                .default_value(100),
+                FixedRuleOptionSpec::new(
+                    "epsilon",
+                    FixedRuleOptionType::UnitInterval,
+                    "stop once the scores change by less than this",
+                )
+                .default_value(0.000001),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Katz centrality, the solution of `x = alpha * A^T x + beta`, computed by fixed-point
+/// iteration. The iteration only converges if `alpha` is below the reciprocal of the largest
+/// eigenvalue of the adjacency matrix.
+pub(crate) struct KatzCentrality;
+
+impl FixedRule for KatzCentrality {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let alpha = payload.unit_interval_option("alpha", Some(0.1))?;
+        let beta = payload.float_option("beta", Some(1.))?;
+        let iterations = payload.pos_integer_option("iterations", Some(100))?;
+        let epsilon = payload.unit_interval_option("epsilon", Some(0.000001))?;
+        let normalized = payload.bool_option("normalized", Some(true))?;
+
+        let (graph, indices, _) = edges.as_directed_weighted_graph(undirected, false)?;
+        let n = indices.len();
+        let mut scores = vec![beta; n];
+        for _ in 0..iterations {
+            let mut incoming = vec![0.; n];
+            propagate_in(&graph, &scores, &mut incoming);
+            let next: Vec<f64> = incoming.into_iter().map(|x| alpha * x + beta).collect();
+            if next.iter().any(|x| !x.is_finite()) {
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("Katz centrality diverged")]
+                #[diagnostic(code(algo::katz_diverged))]
+                #[diagnostic(help(
+                    "'alpha' must be smaller than the reciprocal of the largest eigenvalue \
+                    of the adjacency matrix"
+                ))]
+                struct KatzDiverged(#[label] SourceSpan);
+
+                bail!(KatzDiverged(payload.span()))
+            }
+            let diff = l1_distance(&scores, &next);
+            scores = next;
+            if diff < epsilon * n as f64 {
+                break;
+            }
+            poison.check()?;
+        }
+        if normalized {
+            normalize(&mut scores);
+        }
+        put_scores(out, &indices, &scores);
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "alpha",
+                    FixedRuleOptionType::UnitInterval,
+                    "attenuation factor for longer walks",
+                )
+                .default_value(0.1),
+                FixedRuleOptionSpec::new(
+                    "beta",
+                    FixedRuleOptionType::Float,
+                    "score every node gets on its own",
+                )
+                .default_value(1.),
+                FixedRuleOptionSpec::new(
+                    "iterations",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations",
+                )
+                .default_value(100),
+                FixedRuleOptionSpec::new(
+                    "epsilon",
+                    FixedRuleOptionType::UnitInterval,
+                    "stop once the scores change by less than this",
+                )
+                .default_value(0.000001),
+                FixedRuleOptionSpec::new(
+                    "normalized",
+                    FixedRuleOptionType::Bool,
+                    "whether to scale the scores to unit length",
+                )
+                .default_value(true),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Hub and authority scores of Kleinberg's HITS algorithm, returned as `[node, hub, authority]`.
+pub(crate) struct Hits;
+
+impl FixedRule for Hits {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let iterations = payload.pos_integer_option("iterations", Some(100))?;
+        let epsilon = payload.unit_interval_option("epsilon", Some(0.000001))?;
+
+        let (graph, indices, _) = edges.as_directed_weighted_graph(undirected, false)?;
+        let n = indices.len();
+        let mut hubs = vec![1. / (n as f64).sqrt(); n];
+        let mut authorities = vec![0.; n];
+        for _ in 0..iterations {
+            let mut next_authorities = vec![0.; n];
+            propagate_in(&graph, &hubs, &mut next_authorities);
+            normalize(&mut next_authorities);
+            let mut next_hubs = vec![0.; n];
+            for (node, hub) in next_hubs.iter_mut().enumerate() {
+                for t in graph.out_neighbors_with_values(node as u32) {
+                    *hub += t.value as f64 * next_authorities[t.target as usize];
+                }
+            }
+            normalize(&mut next_hubs);
+            let diff =
+                l1_distance(&hubs, &next_hubs) + l1_distance(&authorities, &next_authorities);
+            hubs = next_hubs;
+            authorities = next_authorities;
+            if diff < epsilon {
+                break;
+            }
+            poison.check()?;
+        }
+        for (idx, node) in indices.into_iter().enumerate() {
+            out.put(vec![
+                node,
+                DataValue::from(hubs[idx]),
+                DataValue::from(authorities[idx]),
+            ]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "iterations",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations",
+                )
+                .default_value(100),
+                FixedRuleOptionSpec::new(
+                    "epsilon",
+                    FixedRuleOptionType::UnitInterval,
+                    "stop once the scores change by less than this",
+                )
+                .default_value(0.000001),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(3)
+    }
+}
+
+/// Harmonic centrality, the sum of the reciprocals of the distances to all other nodes,
+/// divided by `n - 1`. Unreachable nodes contribute zero, so unlike closeness centrality
+/// it is meaningful for disconnected graphs.
+pub(crate) struct HarmonicCentrality;
+
+impl FixedRule for HarmonicCentrality {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+
+        let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+
+        let n = graph.node_count();
+        if n == 0 {
+            return Ok(());
+        }
+        let res: Vec<_> = (0..n)
+            .into_par_iter()
+            .map(|start| -> Result<f64> {
+                let distances = dijkstra_cost_only(&graph, start, poison.clone())?;
+                let total: f64 = distances
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, d)| *i != start as usize && d.is_finite() && **d > 0.)
+                    .fold(0., |total, (_, d)| total + 1. / *d as f64);
+                Ok(if n > 1 { total / (n - 1) as f64 } else { 0. })
+            })
+            .collect::<Result<_>>()?;
+        put_scores(out, &indices, &res);
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![FixedRuleOptionSpec::undirected()],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Betweenness centrality estimated from the shortest paths of `samples` randomly chosen
+/// sources by Brandes' accumulation, scaled up by `n / samples`. With as many samples as
+/// nodes the result is exact.
+pub(crate) struct ApproxBetweennessCentrality;
+
+impl FixedRule for ApproxBetweennessCentrality {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let samples = payload.pos_integer_option("samples", Some(100))?;
+        let seed = payload.integer_option("seed", Some(0))?;
+
+        let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+        let n = indices.len();
+        if n == 0 {
+            return Ok(());
+        }
+        let samples = samples.min(n);
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let sources = rand::seq::index::sample(&mut rng, n, samples).into_vec();
+
+        let mut centrality = sources
+            .into_par_iter()
+            .map(|source| brandes_single_source(&graph, source, &poison))
+            .try_reduce(
+                || vec![0.; n],
+                |mut acc, partial| {
+                    for (a, p) in acc.iter_mut().zip(partial) {
+                        *a += p;
+                    }
+                    Ok(acc)
+                },
+            )?;
+        let scale = n as f64 / samples as f64;
+        for c in centrality.iter_mut() {
+            *c *= scale;
+        }
+        put_scores(out, &indices, &centrality);
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "samples",
+                    FixedRuleOptionType::PosInt,
+                    "number of nodes to sample as sources",
+                )
+                .default_value(100),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// The dependencies of every node on the shortest paths from `source`.
+fn brandes_single_source(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    source: usize,
+    poison: &Poison,
+) -> Result<Vec<f64>> {
+    let n = graph.node_count() as usize;
+    let mut distance = vec![f64::INFINITY; n];
+    let mut path_counts = vec![0.; n];
+    let mut predecessors: Vec<Vec<usize>> = vec![vec![]; n];
+    let mut settled = vec![false; n];
+    let mut order = Vec::with_capacity(n);
+
+    distance[source] = 0.;
+    path_counts[source] = 1.;
+    let mut heap = BinaryHeap::from([Reverse((OrderedFloat(0.), source))]);
+    while let Some(Reverse((OrderedFloat(dist), node))) = heap.pop() {
+        if settled[node] {
+            continue;
+        }
+        settled[node] = true;
+        order.push(node);
+        for t in graph.out_neighbors_with_values(node as u32) {
+            let next = t.target as usize;
+            let next_dist = dist + t.value as f64;
+            let tolerance = f64::EPSILON * next_dist.abs().max(1.) * 4.;
+            if next_dist < distance[next] - tolerance {
+                distance[next] = next_dist;
+                path_counts[next] = path_counts[node];
+                predecessors[next] = vec![node];
+                heap.push(Reverse((OrderedFloat(next_dist), next)));
+            } else if !settled[next] && (next_dist - distance[next]).abs() <= tolerance {
+                path_counts[next] += path_counts[node];
+                predecessors[next].push(node);
+            }
+        }
+        if order.len() % 1024 == 0 {
+            poison.check()?;
+        }
+    }
+
+    let mut dependency = vec![0.; n];
+    let mut centrality = vec![0.; n];
+    while let Some(node) = order.pop() {
+        for &pred in &predecessors[node] {
+            dependency[pred] += path_counts[pred] / path_counts[node] * (1. + dependency[node]);
+        }
+        if node != source {
+            centrality[node] = dependency[node];
+        }
+    }
+    Ok(centrality)
+}
+
+/// Adds `sum_{u -> v} w(u, v) * scores[u]` to `acc[v]` for every node `v`.
+fn propagate_in(graph: &DirectedCsrGraph<u32, (), f32>, scores: &[f64], acc: &mut [f64]) {
+    for (node, score) in scores.iter().enumerate() {
+        for t in graph.out_neighbors_with_values(node as u32) {
+            acc[t.target as usize] += t.value as f64 * score;
+        }
+    }
+}
+
+fn normalize(v: &mut [f64]) {
+    let norm = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+    if norm > 0. {
+        for x in v.iter_mut() {
+            *x /= norm;
+        }
+    }
+}
+
+fn l1_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum()
+}
+
+fn put_scores(out: &mut RegularTempStore, indices: &[DataValue], scores: &[f64]) {
+    for (node, score) in indices.iter().zip(scores) {
+        out.put(vec![node.clone(), DataValue::from(*score)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    fn scores(db: &DbInstance, query: &str) -> Vec<(String, f64)> {
+        db.run_default(query)
+            .unwrap()
+            .rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row[0].get_str().unwrap().to_string(),
+                    row[1].get_float().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_centrality_measures() {
+        let db = DbInstance::default();
+        // a star centred on 'c', plus a separate edge 'x'-'y'
+        let edges = r#"edges[] <- [['c', 'a'], ['c', 'b'], ['c', 'd'], ['c', 'e'], ['x', 'y']]"#;
+
+        for rule in [
+            "EigenvectorCentrality",
+            "KatzCentrality",
+            "HarmonicCentrality",
+        ] {
+            let res = scores(
+                &db,
+                &format!("{edges} ?[n, s] <~ {rule}(edges[], undirected: true)"),
+            );
+            let top = res.iter().max_by(|a, b| a.1.total_cmp(&b.1)).unwrap();
+            assert_eq!(top.0, "c", "{rule}");
+        }
+
+        let harmonic = scores(
+            &db,
+            &format!("{edges} ?[n, s] <~ HarmonicCentrality(edges[], undirected: true)"),
+        );
+        // 'c' reaches four of the six other nodes at distance one
+        let (_, c_harmonic) = harmonic.iter().find(|(n, _)| n == "c").unwrap();
+        assert!((c_harmonic - 4. / 6.).abs() < 1e-9);
+
+        let approx = scores(
+            &db,
+            &format!(
+                "{edges} ?[n, s] <~ ApproxBetweennessCentrality(edges[], undirected: true, samples: 100)"
+            ),
+        );
+        let exact = scores(
+            &db,
+            &format!("{edges} ?[n, s] <~ BetweennessCentrality(edges[], undirected: true)"),
+        );
+        for ((n1, s1), (n2, s2)) in approx.iter().zip(exact.iter()) {
+            assert_eq!(n1, n2);
+            assert!((s1 - s2).abs() < 1e-6, "{n1}: {s1} vs {s2}");
+        }
+
+        let hits = db
+            .run_default(&format!("{edges} ?[n, h, a] <~ HITS(edges[])"))
+            .unwrap();
+        let hub_of = |name: &str| {
+            hits.rows
+                .iter()
+                .find(|row| row[0].get_str().unwrap() == name)
+                .unwrap()[1]
+                .get_float()
+                .unwrap()
+        };
+        assert!(hub_of("c") > hub_of("a"));
+    }
+
+    #[test]
+    fn test_centrality_edge_cases() {
+        let db = DbInstance::default();
+        for rule in [
+            "EigenvectorCentrality",
+            "KatzCentrality",
+            "HarmonicCentrality",
+            "ApproxBetweennessCentrality",
+        ] {
+            let res = scores(
+                &db,
+                &format!("edges[a, b] <- [] ?[n, s] <~ {rule}(edges[])"),
+            );
+            assert!(res.is_empty(), "{rule}");
+            assert!(db
+                .run_default(&format!(
+                    "edges[] <- [['a', 'b']] ?[n, s] <~ {rule}(edges[], undirect: true)"
+                ))
+                .is_err());
+        }
+        let res = db
+            .run_default("edges[a, b] <- [] ?[n, h, a] <~ HITS(edges[])")
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        // 'b' reaches nothing, and the self-loop on 'a' does not count as reaching itself
+        let harmonic = scores(
+            &db,
+            "edges[] <- [['a', 'a'], ['a', 'b']] ?[n, s] <~ HarmonicCentrality(edges[])",
+        );
+        assert_eq!(harmonic.len(), 2);
+        let (_, a_harmonic) = harmonic.iter().find(|(n, _)| n == "a").unwrap();
+        assert!((a_harmonic - 1.).abs() < 1e-9);
+        let (_, b_harmonic) = harmonic.iter().find(|(n, _)| n == "b").unwrap();
+        assert_eq!(*b_harmonic, 0.);
+        assert!(b_harmonic.is_sign_positive());
+
+        // the largest eigenvalue of a complete graph on four nodes is 3
+        let complete = r#"edges[a, b] := a in [1, 2, 3, 4], b in [1, 2, 3, 4], a != b"#;
+        let err = db
+            .run_default(&format!(
+                "{complete} ?[n, s] <~ KatzCentrality(edges[], alpha: 0.9, iterations: 1000, \
+                normalized: false)"
+            ))
+            .unwrap_err();
+        assert!(err.to_string().contains("diverged"));
+        let katz = db
+            .run_default(&format!(
+                "{complete} ?[n, s] <~ KatzCentrality(edges[], alpha: 0.2, normalized: false)"
+            ))
+            .unwrap();
+        // all nodes are alike, with `x = 0.2 * 3 * x + 1`
+        for row in katz.rows {
+            assert!((row[1].get_float().unwrap() - 2.5).abs() < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,312 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+use miette::Result;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::biconnected_components::simple_undirected_adjacency;
+use crate::fixed_rule::algos::k_core::core_decomposition;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Enumerates the maximal cliques of the undirected graph, returning `[node, clique_id]`.
+///
+/// Only cliques with at least `min_size` nodes are returned, and with `limit`, enumeration
+/// stops after that many cliques. Nodes may belong to several cliques.
+pub(crate) struct MaximalCliques;
+
+impl FixedRule for MaximalCliques {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let min_size = payload.pos_integer_option("min_size", Some(1))?;
+        let limit = payload.pos_integer_option("limit", None).ok();
+
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let adj = simple_undirected_adjacency(&graph);
+        let mut clique_id = 0;
+        let mut search = BronKerbosch {
+            adj: &adj,
+            min_size,
+            poison: &poison,
+            calls: 0,
+            emit: |clique: &[u32]| {
+                for node in clique {
+                    out.put(vec![
+                        indices[*node as usize].clone(),
+                        DataValue::from(clique_id as i64),
+                    ]);
+                }
+                clique_id += 1;
+                limit.is_none_or(|limit| clique_id < limit)
+            },
+        };
+        search.run()
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "min_size",
+                    FixedRuleOptionType::PosInt,
+                    "smallest clique to return",
+                )
+                .default_value(1),
+                FixedRuleOptionSpec::new(
+                    "limit",
+                    FixedRuleOptionType::PosInt,
+                    "stop after this many results",
+                ),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Bron–Kerbosch with Tomita pivoting, where the outermost level follows a degeneracy
+/// ordering as proposed by Eppstein, Löffler and Strash. This bounds the candidate sets at
+/// the top level by the degeneracy of the graph, which is small for most real graphs.
+struct BronKerbosch<'a, F> {
+    adj: &'a [Vec<u32>],
+    min_size: usize,
+    poison: &'a Poison,
+    calls: usize,
+    /// Called with every clique found, returning `false` to stop the enumeration
+    emit: F,
+}
+
+impl<F: FnMut(&[u32]) -> bool> BronKerbosch<'_, F> {
+    fn run(&mut self) -> Result<()> {
+        let (_, order) = core_decomposition(self.adj, self.poison)?;
+        let mut position = vec![0; order.len()];
+        for (i, node) in order.iter().enumerate() {
+            position[*node] = i;
+        }
+        let mut clique = vec![];
+        for (i, node) in order.iter().enumerate() {
+            let (mut later, mut earlier): (Vec<u32>, Vec<u32>) = self.adj[*node]
+                .iter()
+                .partition(|neighbour| position[**neighbour as usize] > i);
+            later.sort_unstable();
+            earlier.sort_unstable();
+            clique.push(*node as u32);
+            let go_on = self.expand(&mut clique, later, earlier)?;
+            clique.pop();
+            if !go_on {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Extends `clique` by the `candidates`, with the nodes in `excluded` already covered by
+    /// earlier branches. Both sets are sorted. Returns `false` once the enumeration should stop.
+    fn expand(
+        &mut self,
+        clique: &mut Vec<u32>,
+        mut candidates: Vec<u32>,
+        mut excluded: Vec<u32>,
+    ) -> Result<bool> {
+        self.calls += 1;
+        if self.calls % 1024 == 0 {
+            self.poison.check()?;
+        }
+        if candidates.is_empty() {
+            if excluded.is_empty() && clique.len() >= self.min_size {
+                return Ok((self.emit)(clique.as_slice()));
+            }
+            return Ok(true);
+        }
+        if clique.len() + candidates.len() < self.min_size {
+            return Ok(true);
+        }
+
+        // branching only on the non-neighbours of the pivot still finds every maximal clique
+        let pivot = candidates
+            .iter()
+            .chain(excluded.iter())
+            .max_by_key(|node| count_common(&candidates, &self.adj[**node as usize]))
+            .copied()
+            .unwrap();
+        let branches: Vec<u32> = candidates
+            .iter()
+            .copied()
+            .filter(|node| self.adj[pivot as usize].binary_search(node).is_err())
+            .collect();
+        for node in branches {
+            let neighbours = &self.adj[node as usize];
+            let next_candidates = intersect(&candidates, neighbours);
+            let next_excluded = intersect(&excluded, neighbours);
+            clique.push(node);
+            let go_on = self.expand(clique, next_candidates, next_excluded)?;
+            clique.pop();
+            if !go_on {
+                return Ok(false);
+            }
+            if let Ok(idx) = candidates.binary_search(&node) {
+                candidates.remove(idx);
+            }
+            if let Err(idx) = excluded.binary_search(&node) {
+                excluded.insert(idx, node);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn intersect(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut ret = vec![];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                ret.push(a[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    ret
+}
+
+fn count_common(a: &[u32], b: &[u32]) -> usize {
+    let (mut i, mut j, mut count) = (0, 0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                count += 1;
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_maximal_cliques() {
+        let db = DbInstance::default();
+        let edges = r#"
+        edges[] <- [['a', 'b'], ['a', 'c'], ['b', 'c'], ['b', 'd'], ['c', 'd'], ['d', 'e']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                cliques[node, id] <~ MaximalCliques(edges[])
+                ?[id, collect(node)] := cliques[node, id]
+                "#
+            ))
+            .unwrap();
+        let mut cliques = res.into_json()["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row[1].clone())
+            .collect::<Vec<_>>();
+        cliques.sort_by_key(|c| c.to_string());
+        assert_eq!(
+            cliques,
+            vec![
+                serde_json::json!(["a", "b", "c"]),
+                serde_json::json!(["b", "c", "d"]),
+                serde_json::json!(["d", "e"]),
+            ]
+        );
+
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[node, id] <~ MaximalCliques(edges[], min_size: 3, limit: 1)"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 3);
+    }
+
+    #[test]
+    fn test_maximal_cliques_edge_cases() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default("edges[a, b] <- [] ?[node, id] <~ MaximalCliques(edges[])")
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        // 'z' only has a self-loop, so it is a clique on its own,
+        // and the edge given both ways is a single clique
+        let edges = "edges[] <- [['z', 'z'], ['a', 'b'], ['b', 'a'], ['x', 'y']]";
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                cliques[node, id] <~ MaximalCliques(edges[])
+                ?[id, collect(node)] := cliques[node, id]
+                "#
+            ))
+            .unwrap();
+        let mut cliques = res.into_json()["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row[1].clone())
+            .collect::<Vec<_>>();
+        cliques.sort_by_key(|c| c.to_string());
+        assert_eq!(
+            cliques,
+            vec![
+                serde_json::json!(["a", "b"]),
+                serde_json::json!(["x", "y"]),
+                serde_json::json!(["z"]),
+            ]
+        );
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[node, id] <~ MaximalCliques(edges[], min_size: 3)"
+            ))
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        for opts in ["min_size: 0", "limit: 0", "max_size: 3"] {
+            assert!(
+                db.run_default(&format!(
+                    "{edges} ?[node, id] <~ MaximalCliques(edges[], {opts})"
+                ))
+                .is_err(),
+                "{opts}"
+            );
+        }
+    }
+}
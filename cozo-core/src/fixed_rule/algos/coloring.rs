@@ -0,0 +1,302 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use miette::{bail, Result};
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::biconnected_components::simple_undirected_adjacency;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Colours the undirected graph so that no two neighbours share a colour, returning
+/// `[node, color]` with colours numbered from zero. The colouring is greedy, so it may use
+/// more colours than necessary. Self-loops are ignored.
+///
+/// The `strategy` is either `'dsatur'`, which always colours next the node seeing the most
+/// distinct colours among its neighbours, or `'largest_first'`, which colours in order of
+/// decreasing degree.
+pub(crate) struct GraphColoring;
+
+impl FixedRule for GraphColoring {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let dsatur = match &payload.string_option("strategy", Some("dsatur"))? as &str {
+            "dsatur" => true,
+            "largest_first" => false,
+            _ => bail!(WrongFixedRuleOptionError {
+                name: "strategy".to_string(),
+                span: payload.option_span("strategy")?,
+                rule_name: payload.name().to_string(),
+                help: "strategy must be either 'dsatur' or 'largest_first'".to_string(),
+            }),
+        };
+
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let adj = simple_undirected_adjacency(&graph);
+        let colors = if dsatur {
+            dsatur_coloring(&adj, &poison)?
+        } else {
+            largest_first_coloring(&adj, &poison)?
+        };
+        for (node, color) in colors.into_iter().enumerate() {
+            out.put(vec![indices[node].clone(), DataValue::from(color as i64)]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![FixedRuleOptionSpec::new(
+                "strategy",
+                FixedRuleOptionType::String,
+                "either `'dsatur'` or `'largest_first'`",
+            )
+            .default_value("dsatur")],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// An independent set of the undirected graph, returning `[node, 0]` for its members,
+/// so that the rows have the `[node, group]` shape of the other partitioning rules.
+/// The set is built by repeatedly taking a node of minimum remaining degree and discarding
+/// its neighbours, which gives a large set in practice but not necessarily a maximum one.
+/// Self-loops are ignored.
+pub(crate) struct MaximumIndependentSet;
+
+impl FixedRule for MaximumIndependentSet {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let adj = simple_undirected_adjacency(&graph);
+        let n = adj.len();
+
+        let mut degree = adj.iter().map(|a| a.len()).collect::<Vec<_>>();
+        let mut removed = vec![false; n];
+        let mut queue: BTreeSet<(usize, u32)> =
+            (0..n).map(|node| (degree[node], node as u32)).collect();
+        while let Some((_, node)) = queue.pop_first() {
+            removed[node as usize] = true;
+            out.put(vec![indices[node as usize].clone(), DataValue::from(0)]);
+            for &neighbour in &adj[node as usize] {
+                if removed[neighbour as usize] {
+                    continue;
+                }
+                removed[neighbour as usize] = true;
+                queue.remove(&(degree[neighbour as usize], neighbour));
+                for &next in &adj[neighbour as usize] {
+                    if !removed[next as usize] {
+                        queue.remove(&(degree[next as usize], next));
+                        degree[next as usize] -= 1;
+                        queue.insert((degree[next as usize], next));
+                    }
+                }
+            }
+            poison.check()?;
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Brélaz's DSatur heuristic. Ties in saturation are broken by degree, then by node index.
+fn dsatur_coloring(adj: &[Vec<u32>], poison: &Poison) -> Result<Vec<usize>> {
+    let n = adj.len();
+    let mut colors = vec![usize::MAX; n];
+    let mut neighbour_colors: Vec<BTreeSet<usize>> = vec![Default::default(); n];
+    // the last element is the node to colour next
+    let mut queue: BTreeSet<(usize, usize, Reverse<u32>)> = (0..n)
+        .map(|node| (0, adj[node].len(), Reverse(node as u32)))
+        .collect();
+    while let Some((_, _, Reverse(node))) = queue.pop_last() {
+        let seen = &neighbour_colors[node as usize];
+        let color = (0..).find(|c| !seen.contains(c)).unwrap();
+        colors[node as usize] = color;
+        for &neighbour in &adj[node as usize] {
+            let u = neighbour as usize;
+            if colors[u] != usize::MAX {
+                continue;
+            }
+            let saturation = neighbour_colors[u].len();
+            if neighbour_colors[u].insert(color) {
+                let degree = adj[u].len();
+                queue.remove(&(saturation, degree, Reverse(neighbour)));
+                queue.insert((saturation + 1, degree, Reverse(neighbour)));
+            }
+        }
+        poison.check()?;
+    }
+    Ok(colors)
+}
+
+/// Welsh–Powell: each node in order of decreasing degree takes the smallest colour
+/// not used by its neighbours.
+fn largest_first_coloring(adj: &[Vec<u32>], poison: &Poison) -> Result<Vec<usize>> {
+    let n = adj.len();
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_by_key(|node| Reverse(adj[*node].len()));
+    let mut colors = vec![usize::MAX; n];
+    // `used[c] == node` marks colour `c` as taken by a neighbour of the current node
+    let mut used = vec![usize::MAX; n + 1];
+    for node in order {
+        for &neighbour in &adj[node] {
+            let c = colors[neighbour as usize];
+            if c != usize::MAX {
+                used[c] = node;
+            }
+        }
+        colors[node] = (0..).find(|c| used[*c] != node).unwrap();
+        poison.check()?;
+    }
+    Ok(colors)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_coloring_and_independent_set() {
+        let db = DbInstance::default();
+        // a 5-cycle with a chord, which needs three colours
+        let edges = r#"
+        edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'd'], ['d', 'e'], ['e', 'a'], ['a', 'c']]
+        "#;
+        for strategy in ["dsatur", "largest_first"] {
+            let colors = format!(
+                "{edges} colors[node, color] <~ GraphColoring(edges[], strategy: '{strategy}')"
+            );
+            let res = db
+                .run_default(&format!(
+                    "{colors} ?[a, b] := edges[a, b], colors[a, c], colors[b, c]"
+                ))
+                .unwrap();
+            assert!(res.rows.is_empty());
+            let res = db
+                .run_default(&format!("{colors} ?[max(c)] := colors[_, c]"))
+                .unwrap();
+            assert_eq!(res.into_json()["rows"], serde_json::json!([[2]]));
+        }
+
+        let independent = format!("{edges} s[node, group] <~ MaximumIndependentSet(edges[])");
+        let res = db
+            .run_default(&format!(
+                "{independent} ?[a, b] := edges[a, b], s[a, _], s[b, _]"
+            ))
+            .unwrap();
+        assert!(res.rows.is_empty());
+        let res = db
+            .run_default(&format!("{independent} ?[n, g] := s[n, g]"))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["b", 0], ["d", 0]])
+        );
+    }
+
+    #[test]
+    fn test_coloring_and_independent_set_edge_cases() {
+        let db = DbInstance::default();
+        for strategy in ["dsatur", "largest_first"] {
+            let res = db
+                .run_default(&format!(
+                    "edges[a, b] <- [] ?[n, c] <~ GraphColoring(edges[], strategy: '{strategy}')"
+                ))
+                .unwrap();
+            assert!(res.rows.is_empty());
+        }
+        let res = db
+            .run_default("edges[a, b] <- [] ?[n, g] <~ MaximumIndependentSet(edges[])")
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        // self-loops are ignored, and the two components are coloured independently
+        let edges = "edges[] <- [['a', 'a'], ['a', 'b'], ['x', 'y'], ['z', 'z']]";
+        for strategy in ["dsatur", "largest_first"] {
+            let res = db
+                .run_default(&format!(
+                    "{edges} ?[n, c] <~ GraphColoring(edges[], strategy: '{strategy}')"
+                ))
+                .unwrap();
+            let colors = res.into_json()["rows"].clone();
+            assert_eq!(colors.as_array().unwrap().len(), 5);
+            assert_eq!(colors[4], serde_json::json!(["z", 0]), "{strategy}");
+            assert_ne!(colors[0][1], colors[1][1], "{strategy}");
+            assert_ne!(colors[2][1], colors[3][1], "{strategy}");
+        }
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[n, g] <~ MaximumIndependentSet(edges[])"
+            ))
+            .unwrap();
+        let members = res.into_json()["rows"].clone();
+        assert_eq!(members.as_array().unwrap().len(), 3);
+        assert!(members
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!(["z", 0])));
+
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[n, c] <~ GraphColoring(edges[], strategy: 'greedy')"
+            ))
+            .is_err());
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[n, g] <~ MaximumIndependentSet(edges[], strategy: 'greedy')"
+            ))
+            .is_err());
+        assert!(db
+            .run_default(&format!("{edges} ?[n] <~ MaximumIndependentSet(edges[])"))
+            .is_err());
+    }
+}
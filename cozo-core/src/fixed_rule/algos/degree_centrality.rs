@@ -14,7 +14,7 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{FixedRule, FixedRuleInputSpec, FixedRulePayload, FixedRuleSchema};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -65,6 +65,16 @@ impl FixedRule for DegreeCentrality {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`"),
+                FixedRuleInputSpec::new("nodes", 1, "nodes to include even without edges")
+                    .optional(),
+            ],
+            options: vec![],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -14,7 +14,10 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::{eval_bytecode_pred, Expr};
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload, NodeNotFoundError};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema, NodeNotFoundError,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -111,6 +114,38 @@ impl FixedRule for Dfs {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`"),
+                FixedRuleInputSpec::new(
+                    "nodes",
+                    1,
+                    "nodes as `[node, ..]`, whose columns the condition can refer to",
+                ),
+                FixedRuleInputSpec::new(
+                    "starting",
+                    1,
+                    "nodes to start from, defaulting to all nodes",
+                )
+                .optional(),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "condition",
+                    FixedRuleOptionType::Expr,
+                    "the search stops at nodes satisfying this condition",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "limit",
+                    FixedRuleOptionType::PosInt,
+                    "number of nodes to find from each starting node",
+                )
+                .default_value(1),
+            ],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
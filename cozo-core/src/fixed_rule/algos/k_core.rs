@@ -0,0 +1,254 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+
+use miette::Result;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::biconnected_components::simple_undirected_adjacency;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Returns `[node, core_number]`, restricted to the nodes of the `k`-core if `k` is given.
+pub(crate) struct KCore;
+
+impl FixedRule for KCore {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let k = payload.non_neg_integer_option("k", Some(0))?;
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let (cores, _) = core_decomposition(&simple_undirected_adjacency(&graph), &poison)?;
+        for (node, core) in cores.into_iter().enumerate() {
+            if core >= k {
+                out.put(vec![indices[node].clone(), DataValue::from(core as i64)]);
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![FixedRuleOptionSpec::new(
+                "k",
+                FixedRuleOptionType::NonNegInt,
+                "only nodes with core number at least this are returned",
+            )
+            .default_value(0)],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Returns `[node, position, core_number]`, where the positions give the order in which nodes
+/// are peeled off by repeatedly removing a node of minimum degree.
+pub(crate) struct DegeneracyOrdering;
+
+impl FixedRule for DegeneracyOrdering {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let (graph, indices, _) = edges.as_directed_graph(true)?;
+        let (cores, order) = core_decomposition(&simple_undirected_adjacency(&graph), &poison)?;
+        for (position, node) in order.into_iter().enumerate() {
+            out.put(vec![
+                indices[node].clone(),
+                DataValue::from(position as i64),
+                DataValue::from(cores[node] as i64),
+            ]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(3)
+    }
+}
+
+/// The O(m) bucket algorithm of Batagelj and Zaversnik.
+/// Returns the core number of every node and the degeneracy ordering.
+pub(crate) fn core_decomposition(
+    adj: &[Vec<u32>],
+    poison: &Poison,
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    let n = adj.len();
+    let mut degree = adj.iter().map(|a| a.len()).collect::<Vec<_>>();
+    let max_degree = degree.iter().copied().max().unwrap_or(0);
+
+    // `bin[d]` is the position in `vert` where the nodes of current degree `d` start
+    let mut bin = vec![0; max_degree + 1];
+    for d in &degree {
+        bin[*d] += 1;
+    }
+    let mut start = 0;
+    for b in bin.iter_mut() {
+        let count = *b;
+        *b = start;
+        start += count;
+    }
+    let mut pos = vec![0; n];
+    let mut vert = vec![0; n];
+    for (node, d) in degree.iter().enumerate() {
+        pos[node] = bin[*d];
+        vert[pos[node]] = node;
+        bin[*d] += 1;
+    }
+    bin.rotate_right(1);
+    bin[0] = 0;
+
+    for i in 0..n {
+        let node = vert[i];
+        for &neighbour in &adj[node] {
+            let u = neighbour as usize;
+            if degree[u] > degree[node] {
+                let du = degree[u];
+                let pu = pos[u];
+                let pw = bin[du];
+                let w = vert[pw];
+                if u != w {
+                    pos[u] = pw;
+                    vert[pu] = w;
+                    pos[w] = pu;
+                    vert[pw] = u;
+                }
+                bin[du] += 1;
+                degree[u] -= 1;
+            }
+        }
+        if i % 1024 == 0 {
+            poison.check()?;
+        }
+    }
+    Ok((degree, vert))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_k_core() {
+        let db = DbInstance::default();
+        // a 4-clique with a triangle hanging off 'd' and a pendant node 'h'
+        let edges = r#"
+        edges[] <- [['a', 'b'], ['a', 'c'], ['a', 'd'], ['b', 'c'], ['b', 'd'], ['c', 'd'],
+                    ['d', 'e'], ['e', 'f'], ['f', 'd'], ['f', 'h']]
+        "#;
+        let res = db
+            .run_default(&format!("{edges} ?[n, c] <~ KCore(edges[])"))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([
+                ["a", 3],
+                ["b", 3],
+                ["c", 3],
+                ["d", 3],
+                ["e", 2],
+                ["f", 2],
+                ["h", 1]
+            ])
+        );
+
+        let res = db
+            .run_default(&format!("{edges} ?[n, c] <~ KCore(edges[], k: 3)"))
+            .unwrap();
+        assert_eq!(res.rows.len(), 4);
+
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[n, p, c] <~ DegeneracyOrdering(edges[]) :order p"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 7);
+        assert_eq!(res.rows[0][0].get_str().unwrap(), "h");
+        // core numbers never decrease along the ordering
+        assert!(res
+            .rows
+            .windows(2)
+            .all(|w| w[0][2].get_int().unwrap() <= w[1][2].get_int().unwrap()));
+    }
+
+    #[test]
+    fn test_k_core_edge_cases() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default("edges[a, b] <- [] ?[n, c] <~ KCore(edges[])")
+            .unwrap();
+        assert!(res.rows.is_empty());
+        let res = db
+            .run_default("edges[a, b] <- [] ?[n, p, c] <~ DegeneracyOrdering(edges[])")
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        // self-loops do not add to the degree, and 'x'-'y' is disconnected from the triangle
+        let edges = r#"
+        edges[] <- [['a', 'a'], ['a', 'b'], ['b', 'c'], ['c', 'a'], ['x', 'y'], ['z', 'z']]
+        "#;
+        let res = db
+            .run_default(&format!("{edges} ?[n, c] <~ KCore(edges[])"))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", 2], ["b", 2], ["c", 2], ["x", 1], ["y", 1], ["z", 0]])
+        );
+        let res = db
+            .run_default(&format!("{edges} ?[n, c] <~ KCore(edges[], k: 3)"))
+            .unwrap();
+        assert!(res.rows.is_empty());
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[n, p, c] <~ DegeneracyOrdering(edges[]) :order p"
+            ))
+            .unwrap();
+        assert_eq!(res.rows[0][0].get_str().unwrap(), "z");
+
+        assert!(db
+            .run_default(&format!("{edges} ?[n, c] <~ KCore(edges[], k: -1)"))
+            .is_err());
+        assert!(db
+            .run_default(&format!("{edges} ?[n, c] <~ KCore(edges[], min_k: 1)"))
+            .is_err());
+    }
+}
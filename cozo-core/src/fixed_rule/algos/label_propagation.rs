@@ -17,7 +17,10 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -43,6 +46,24 @@ impl FixedRule for LabelPropagation {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "max_iter",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations",
+                )
+                .default_value(10),
+            ],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -0,0 +1,511 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+
+use graph::prelude::{DirectedCsrGraph, DirectedNeighborsWithValues, Graph};
+use miette::{bail, IntoDiagnostic, Result};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Community detection by the Leiden algorithm of Traag, Waltman and van Eck, which refines
+/// the communities found by local moving before aggregating, so that every community is
+/// guaranteed to be connected.
+///
+/// Edge directions are ignored. The rule returns `[level, community, node, modularity]`,
+/// where level `i` holds the partition after the `i`-th aggregation pass together with its
+/// modularity, and the last level is the final partition.
+pub(crate) struct CommunityDetectionLeiden;
+
+impl FixedRule for CommunityDetectionLeiden {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let max_iter = payload.pos_integer_option("max_iter", Some(10))?;
+        let resolution = payload.float_option("resolution", Some(1.))?;
+        let randomness = payload.float_option("randomness", Some(0.01))?;
+        let seed = payload.integer_option("seed", Some(0))?;
+        if resolution < 0. {
+            bail!(WrongFixedRuleOptionError {
+                name: "resolution".to_string(),
+                span: payload.option_span("resolution")?,
+                rule_name: payload.name().to_string(),
+                help: "resolution must be non-negative".to_string(),
+            })
+        }
+        if randomness <= 0. {
+            bail!(WrongFixedRuleOptionError {
+                name: "randomness".to_string(),
+                span: payload.option_span("randomness")?,
+                rule_name: payload.name().to_string(),
+                help: "randomness must be positive".to_string(),
+            })
+        }
+
+        let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let levels = leiden(&graph, resolution, randomness, max_iter, &mut rng, &poison)?;
+        for (level, (assignment, modularity)) in levels.into_iter().enumerate() {
+            for (node, community) in assignment.into_iter().enumerate() {
+                out.put(vec![
+                    DataValue::from(level as i64),
+                    DataValue::from(community as i64),
+                    indices[node].clone(),
+                    DataValue::from(modularity),
+                ]);
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "max_iter",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations",
+                )
+                .default_value(10),
+                FixedRuleOptionSpec::new(
+                    "resolution",
+                    FixedRuleOptionType::Float,
+                    "higher values give smaller communities",
+                )
+                .default_value(1.),
+                FixedRuleOptionSpec::new(
+                    "randomness",
+                    FixedRuleOptionType::Float,
+                    "randomness of the refinement phase",
+                )
+                .default_value(0.01),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+/// A symmetric weighted network. Self-loops are kept separately so that aggregated
+/// networks can carry the weight inside each community.
+struct Network {
+    adj: Vec<Vec<(usize, f64)>>,
+    self_loops: Vec<f64>,
+    degrees: Vec<f64>,
+    /// Twice the total edge weight.
+    total: f64,
+}
+
+impl Network {
+    fn from_graph(graph: &DirectedCsrGraph<u32, (), f32>) -> Self {
+        let n = graph.node_count() as usize;
+        let mut links: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); n];
+        let mut self_loops = vec![0.; n];
+        for from in 0..n {
+            for t in graph.out_neighbors_with_values(from as u32) {
+                let to = t.target as usize;
+                let weight = t.value as f64;
+                if to == from {
+                    self_loops[from] += 2. * weight;
+                } else {
+                    *links[from].entry(to).or_default() += weight;
+                    *links[to].entry(from).or_default() += weight;
+                }
+            }
+        }
+        Self::new(links, self_loops)
+    }
+
+    fn new(links: Vec<BTreeMap<usize, f64>>, self_loops: Vec<f64>) -> Self {
+        let adj: Vec<Vec<(usize, f64)>> =
+            links.into_iter().map(|l| l.into_iter().collect()).collect();
+        let degrees: Vec<f64> = adj
+            .iter()
+            .zip(&self_loops)
+            .map(|(a, s)| s + a.iter().map(|(_, w)| w).sum::<f64>())
+            .collect();
+        let total = degrees.iter().sum();
+        Self {
+            adj,
+            self_loops,
+            degrees,
+            total,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.adj.len()
+    }
+
+    fn aggregate(&self, partition: &[usize], count: usize) -> Self {
+        let mut links: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); count];
+        let mut self_loops = vec![0.; count];
+        for (node, neighbours) in self.adj.iter().enumerate() {
+            let from = partition[node];
+            self_loops[from] += self.self_loops[node];
+            for (other, weight) in neighbours {
+                let to = partition[*other];
+                if from == to {
+                    self_loops[from] += weight;
+                } else {
+                    *links[from].entry(to).or_default() += weight;
+                }
+            }
+        }
+        Self::new(links, self_loops)
+    }
+
+    fn modularity(&self, partition: &[usize], count: usize, resolution: f64) -> f64 {
+        if self.total <= 0. {
+            return 0.;
+        }
+        let mut internal = vec![0.; count];
+        let mut totals = vec![0.; count];
+        for (node, neighbours) in self.adj.iter().enumerate() {
+            let c = partition[node];
+            totals[c] += self.degrees[node];
+            internal[c] += self.self_loops[node];
+            for (other, weight) in neighbours {
+                if partition[*other] == c {
+                    internal[c] += weight;
+                }
+            }
+        }
+        internal
+            .iter()
+            .zip(&totals)
+            .map(|(i, t)| i - resolution * t * t / self.total)
+            .sum::<f64>()
+            / self.total
+    }
+
+    /// Sums the weights from `node` to each community, excluding self-loops.
+    /// If `within` is given as `(outer, c)`, only neighbours in community `c` of `outer` count.
+    fn links_by_community(
+        &self,
+        node: usize,
+        partition: &[usize],
+        within: Option<(&[usize], usize)>,
+    ) -> BTreeMap<usize, f64> {
+        let mut links: BTreeMap<usize, f64> = BTreeMap::new();
+        for (other, weight) in &self.adj[node] {
+            if let Some((outer, c)) = within {
+                if outer[*other] != c {
+                    continue;
+                }
+            }
+            *links.entry(partition[*other]).or_default() += weight;
+        }
+        links
+    }
+}
+
+/// Renumbers community labels by order of first appearance, returning the number of communities.
+fn renumber(partition: &mut [usize]) -> usize {
+    let mut mapping: BTreeMap<usize, usize> = BTreeMap::new();
+    for c in partition.iter_mut() {
+        let next = mapping.len();
+        *c = *mapping.entry(*c).or_insert(next);
+    }
+    mapping.len()
+}
+
+fn leiden(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    resolution: f64,
+    randomness: f64,
+    max_iter: usize,
+    rng: &mut StdRng,
+    poison: &Poison,
+) -> Result<Vec<(Vec<usize>, f64)>> {
+    let mut network = Network::from_graph(graph);
+    let mut node_to_aggregate: Vec<usize> = (0..network.len()).collect();
+    let mut partition: Vec<usize> = (0..network.len()).collect();
+    let mut levels: Vec<(Vec<usize>, f64)> = vec![];
+    if network.len() == 0 {
+        return Ok(levels);
+    }
+
+    for _ in 0..max_iter {
+        move_nodes_fast(&network, &mut partition, resolution, rng, poison)?;
+        let count = renumber(&mut partition);
+
+        let mut assignment: Vec<usize> = node_to_aggregate.iter().map(|a| partition[*a]).collect();
+        renumber(&mut assignment);
+        if levels.last().map(|(prev, _)| prev) != Some(&assignment) {
+            let modularity = network.modularity(&partition, count, resolution);
+            levels.push((assignment, modularity));
+        }
+        if count == network.len() {
+            break;
+        }
+
+        let mut refined = refine_partition(&network, &partition, resolution, randomness, rng)?;
+        let mut refined_count = renumber(&mut refined);
+        if refined_count == network.len() {
+            // nothing was merged by refinement, so aggregate by the communities themselves
+            refined = partition.clone();
+            refined_count = count;
+        }
+        let mut aggregate_partition = vec![0; refined_count];
+        for (node, r) in refined.iter().enumerate() {
+            aggregate_partition[*r] = partition[node];
+        }
+        for a in node_to_aggregate.iter_mut() {
+            *a = refined[*a];
+        }
+        network = network.aggregate(&refined, refined_count);
+        partition = aggregate_partition;
+        poison.check()?;
+    }
+    Ok(levels)
+}
+
+/// Visits nodes from a queue, moving each to the community with the largest gain in quality.
+/// Only the neighbours of moved nodes are visited again.
+fn move_nodes_fast(
+    network: &Network,
+    partition: &mut [usize],
+    resolution: f64,
+    rng: &mut StdRng,
+    poison: &Poison,
+) -> Result<()> {
+    let n = network.len();
+    let mut community_weights = vec![0.; n];
+    let mut community_sizes = vec![0usize; n];
+    for (node, c) in partition.iter().enumerate() {
+        community_weights[*c] += network.degrees[node];
+        community_sizes[*c] += 1;
+    }
+    let mut empty_communities = (0..n)
+        .filter(|c| community_sizes[*c] == 0)
+        .collect::<Vec<_>>();
+
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.shuffle(rng);
+    let mut queue = VecDeque::from(order);
+    let mut in_queue = vec![true; n];
+    let mut steps = 0usize;
+    while let Some(node) = queue.pop_front() {
+        in_queue[node] = false;
+        let current = partition[node];
+        let degree = network.degrees[node];
+        let links = network.links_by_community(node, partition, None);
+
+        community_weights[current] -= degree;
+        community_sizes[current] -= 1;
+        let gain = |c: usize| {
+            links.get(&c).copied().unwrap_or(0.)
+                - resolution * degree * community_weights[c] / network.total
+        };
+        let mut best = current;
+        let mut best_gain = gain(current);
+        for c in links.keys() {
+            let g = gain(*c);
+            if g > best_gain {
+                best = *c;
+                best_gain = g;
+            }
+        }
+        if best_gain < 0. {
+            if let Some(c) = empty_communities.pop() {
+                best = c;
+            }
+        }
+        community_weights[best] += degree;
+        community_sizes[best] += 1;
+        partition[node] = best;
+
+        if best != current {
+            if community_sizes[current] == 0 {
+                empty_communities.push(current);
+            }
+            for (other, _) in &network.adj[node] {
+                if !in_queue[*other] && partition[*other] != best {
+                    in_queue[*other] = true;
+                    queue.push_back(*other);
+                }
+            }
+        }
+        steps += 1;
+        if steps % 1024 == 0 {
+            poison.check()?;
+        }
+    }
+    Ok(())
+}
+
+/// Splits every community into well-connected sub-communities by merging singletons,
+/// choosing among the merges that do not decrease quality at random, with a preference
+/// for larger gains controlled by `randomness`.
+fn refine_partition(
+    network: &Network,
+    partition: &[usize],
+    resolution: f64,
+    randomness: f64,
+    rng: &mut StdRng,
+) -> Result<Vec<usize>> {
+    let n = network.len();
+    let mut community_weights = vec![0.; n];
+    for (node, c) in partition.iter().enumerate() {
+        community_weights[*c] += network.degrees[node];
+    }
+    // weight from each node to the rest of its community
+    let external: Vec<f64> = (0..n)
+        .map(|node| {
+            network.adj[node]
+                .iter()
+                .filter(|(other, _)| partition[*other] == partition[node])
+                .map(|(_, w)| w)
+                .sum()
+        })
+        .collect();
+
+    let mut refined: Vec<usize> = (0..n).collect();
+    let mut refined_weights = network.degrees.clone();
+    let mut refined_sizes = vec![1usize; n];
+    let mut refined_external = external.clone();
+
+    let well_connected = |ext: f64, weight: f64, community: f64| {
+        ext >= resolution * weight * (community - weight) / network.total
+    };
+
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.shuffle(rng);
+    for node in order {
+        let own = refined[node];
+        if refined_sizes[own] > 1 {
+            continue;
+        }
+        let community = partition[node];
+        let community_weight = community_weights[community];
+        let degree = network.degrees[node];
+        if !well_connected(external[node], degree, community_weight) {
+            continue;
+        }
+        let links = network.links_by_community(node, &refined, Some((partition, community)));
+
+        let mut candidates = vec![(own, 0.)];
+        for (r, w) in &links {
+            if *r == own {
+                continue;
+            }
+            let weight = refined_weights[*r];
+            if !well_connected(refined_external[*r], weight, community_weight) {
+                continue;
+            }
+            let gain = (w - resolution * degree * weight / network.total) * 2. / network.total;
+            if gain >= 0. {
+                candidates.push((*r, gain));
+            }
+        }
+        let max_gain = candidates
+            .iter()
+            .map(|(_, g)| *g)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let probabilities = candidates
+            .iter()
+            .map(|(_, g)| ((g - max_gain) / randomness).exp())
+            .collect::<Vec<_>>();
+        let chosen = candidates[WeightedIndex::new(&probabilities)
+            .into_diagnostic()?
+            .sample(rng)]
+        .0;
+        if chosen != own {
+            refined[node] = chosen;
+            refined_sizes[own] -= 1;
+            refined_sizes[chosen] += 1;
+            refined_weights[own] -= degree;
+            refined_weights[chosen] += degree;
+            refined_external[chosen] += external[node] - 2. * links[&chosen];
+        }
+    }
+    Ok(refined)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_leiden() {
+        let db = DbInstance::default();
+        let query = r#"
+        edges[] <- [['a', 'b'], ['a', 'c'], ['a', 'd'], ['b', 'c'], ['b', 'd'], ['c', 'd'],
+                    ['e', 'f'], ['e', 'g'], ['e', 'h'], ['f', 'g'], ['f', 'h'], ['g', 'h'],
+                    ['d', 'e']]
+        ?[level, community, node, modularity] <~ CommunityDetectionLeiden(edges[], seed: 42)
+        "#;
+        let res = db.run_default(query).unwrap();
+        let last_level = res
+            .rows
+            .iter()
+            .map(|row| row[0].get_int().unwrap())
+            .max()
+            .unwrap();
+        let final_rows = res
+            .rows
+            .iter()
+            .filter(|row| row[0].get_int().unwrap() == last_level)
+            .collect::<Vec<_>>();
+        assert_eq!(final_rows.len(), 8);
+        let community_of = |name: &str| {
+            final_rows
+                .iter()
+                .find(|row| row[2].get_str().unwrap() == name)
+                .unwrap()[1]
+                .get_int()
+                .unwrap()
+        };
+        assert_eq!(community_of("a"), community_of("d"));
+        assert_eq!(community_of("e"), community_of("h"));
+        assert_ne!(community_of("a"), community_of("e"));
+        assert!(final_rows[0][3].get_float().unwrap() > 0.3);
+
+        // the same seed gives the same result
+        let again = db.run_default(query).unwrap();
+        assert_eq!(res.rows, again.rows);
+    }
+}
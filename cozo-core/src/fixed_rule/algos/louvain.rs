@@ -19,11 +19,16 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRuleOutputs,
+    FixedRulePayload, FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
 
+/// Besides the community labels of each node, the application can bind the output `levels`,
+/// which has `[level, communities, modularity]` for each level of the labels, coarsest first.
 pub(crate) struct CommunityDetectionLouvain;
 
 impl FixedRule for CommunityDetectionLouvain {
@@ -32,6 +37,16 @@ impl FixedRule for CommunityDetectionLouvain {
         payload: FixedRulePayload<'_, '_>,
         out: &mut RegularTempStore,
         poison: Poison,
+    ) -> Result<()> {
+        self.run_with_outputs(payload, out, &mut Default::default(), poison)
+    }
+
+    fn run_with_outputs(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        outputs: &mut FixedRuleOutputs,
+        poison: Poison,
     ) -> Result<()> {
         let edges = payload.get_input(0)?;
         let undirected = payload.bool_option("undirected", Some(false))?;
@@ -41,6 +56,23 @@ impl FixedRule for CommunityDetectionLouvain {
 
         let (graph, indices, _inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
         let result = louvain(&graph, delta, max_iter, poison)?;
+        if let Some(levels) = outputs.get_mut("levels") {
+            let mut communities = (0..graph.node_count()).collect_vec();
+            for (step, hierarchy) in result.iter().enumerate() {
+                for comm in communities.iter_mut() {
+                    *comm = hierarchy[*comm as usize];
+                }
+                let level = result.len() - step - 1;
+                if keep_depth.is_some_and(|l| level >= l) {
+                    continue;
+                }
+                levels.put(vec![
+                    DataValue::from(level as i64),
+                    DataValue::from(communities.iter().unique().count() as i64),
+                    DataValue::from(partition_modularity(&graph, &communities)),
+                ]);
+            }
+        }
         for (idx, node) in indices.into_iter().enumerate() {
             let mut labels = vec![];
             let mut cur_idx = idx as u32;
@@ -59,6 +91,35 @@ impl FixedRule for CommunityDetectionLouvain {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "max_iter",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations at each level",
+                )
+                .default_value(10),
+                FixedRuleOptionSpec::new(
+                    "delta",
+                    FixedRuleOptionType::UnitInterval,
+                    "minimal improvement of modularity to keep iterating",
+                )
+                .default_value(0.0001),
+                FixedRuleOptionSpec::new(
+                    "keep_depth",
+                    FixedRuleOptionType::NonNegInt,
+                    "only keep this many levels of labels, coarsest first",
+                ),
+            ],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -67,6 +128,49 @@ impl FixedRule for CommunityDetectionLouvain {
     ) -> Result<usize> {
         Ok(2)
     }
+
+    fn output_arity(
+        &self,
+        output: &str,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _span: SourceSpan,
+    ) -> Result<Option<usize>> {
+        Ok(match output {
+            "levels" => Some(3),
+            _ => None,
+        })
+    }
+}
+
+/// Modularity of the partition of the whole graph into the given communities
+fn partition_modularity(graph: &DirectedCsrGraph<u32, (), f32>, communities: &[u32]) -> f64 {
+    let n_communities = communities.iter().max().map_or(0, |c| *c as usize + 1);
+    let mut out_totals = vec![0.; n_communities];
+    let mut in_totals = vec![0.; n_communities];
+    let mut total_weight = 0.;
+    let mut internal_weight = 0.;
+    for from in 0..graph.node_count() {
+        let from_comm = communities[from as usize] as usize;
+        for target in graph.out_neighbors_with_values(from) {
+            let to_comm = communities[target.target as usize] as usize;
+            let weight = target.value as f64;
+            total_weight += weight;
+            out_totals[from_comm] += weight;
+            in_totals[to_comm] += weight;
+            if from_comm == to_comm {
+                internal_weight += weight;
+            }
+        }
+    }
+    if total_weight == 0. {
+        return 0.;
+    }
+    let expected: f64 = out_totals
+        .iter()
+        .zip(in_totals.iter())
+        .map(|(o, i)| o * i)
+        .sum();
+    internal_weight / total_weight - expected / (total_weight * total_weight)
 }
 
 fn louvain(
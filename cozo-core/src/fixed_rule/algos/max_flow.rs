@@ -0,0 +1,393 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+
+use graph::prelude::{DirectedCsrGraph, DirectedNeighborsWithValues, Graph};
+use miette::{bail, Diagnostic, Result};
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{
+    CannotDetermineArity, FixedRule, FixedRuleInputRelation, FixedRuleInputSpec,
+    FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload, FixedRuleSchema, NodeNotFoundError,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Maximum flow from a source to a sink by Dinic's algorithm, with edge weights as capacities.
+///
+/// With `output: 'flow'` (the default) the rule returns `[from, to, flow]` for every edge
+/// carrying flow. With `output: 'partition'` it returns `[node, side]` for the minimum cut,
+/// where `side` is `'source'` or `'sink'`.
+pub(crate) struct MaxFlow;
+
+const FLOW_EPSILON: f64 = 1e-12;
+
+impl FixedRule for MaxFlow {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?.ensure_min_len(2)?;
+        let source_rel = payload.get_input(1)?.ensure_min_len(1)?;
+        let sink_rel = payload.get_input(2)?.ensure_min_len(1)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let output = payload.string_option("output", Some("flow"))?;
+        if output != "flow" && output != "partition" {
+            bail!(WrongFixedRuleOptionError {
+                name: "output".to_string(),
+                span: payload.option_span("output")?,
+                rule_name: payload.name().to_string(),
+                help: "output must be 'flow' or 'partition'".to_string(),
+            })
+        }
+
+        let (graph, indices, inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+        let source = terminal_node(&source_rel, &inv_indices)?;
+        let sink = terminal_node(&sink_rel, &inv_indices)?;
+        if source == sink {
+            #[derive(Debug, Error, Diagnostic)]
+            #[error("The source and the sink of a flow must be different nodes")]
+            #[diagnostic(code(algo::same_source_sink))]
+            struct SameSourceSink(#[label] SourceSpan);
+
+            bail!(SameSourceSink(sink_rel.span()))
+        }
+
+        let mut network = FlowNetwork::new(&graph);
+        network.max_flow(source, sink, &poison)?;
+
+        if output == "flow" {
+            for ((from, to), flow) in network.edge_flows() {
+                out.put(vec![
+                    indices[from].clone(),
+                    indices[to].clone(),
+                    DataValue::from(flow),
+                ]);
+            }
+        } else {
+            let source_side = network.reachable_from(source);
+            for (node, on_source_side) in source_side.into_iter().enumerate() {
+                let side = if on_source_side { "source" } else { "sink" };
+                out.put(vec![indices[node].clone(), DataValue::from(side)]);
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    2,
+                    "edges as `[from, to, capacity?]`, with capacities defaulting to 1",
+                ),
+                FixedRuleInputSpec::new("source", 1, "the source node"),
+                FixedRuleInputSpec::new("sink", 1, "the sink node"),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "output",
+                    FixedRuleOptionType::String,
+                    "either `'flow'` or `'partition'`",
+                )
+                .default_value("flow"),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(match options.get("output") {
+            None => 3,
+            Some(Expr::Const {
+                val: DataValue::Str(s),
+                ..
+            }) if s == "flow" => 3,
+            Some(Expr::Const {
+                val: DataValue::Str(s),
+                ..
+            }) if s == "partition" => 2,
+            _ => bail!(CannotDetermineArity(
+                "MaxFlow".to_string(),
+                "invalid option 'output' given, expect 'flow' or 'partition'".to_string(),
+                span
+            )),
+        })
+    }
+}
+
+fn terminal_node(
+    rel: &FixedRuleInputRelation<'_, '_>,
+    inv_indices: &BTreeMap<DataValue, u32>,
+) -> Result<usize> {
+    let tuple = match rel.iter()?.next() {
+        Some(tuple) => tuple?,
+        None => {
+            #[derive(Debug, Error, Diagnostic)]
+            #[error("The provided terminal node relation is empty")]
+            #[diagnostic(code(algo::empty_terminal))]
+            struct EmptyTerminal(#[label] SourceSpan);
+
+            bail!(EmptyTerminal(rel.span()))
+        }
+    };
+    let node = &tuple[0];
+    let idx = inv_indices.get(node).ok_or_else(|| NodeNotFoundError {
+        missing: node.clone(),
+        span: rel.span(),
+    })?;
+    Ok(*idx as usize)
+}
+
+/// Residual network: arc `2i` is the `i`-th input edge and arc `2i + 1` is its reverse.
+struct FlowNetwork {
+    adj: Vec<Vec<usize>>,
+    heads: Vec<usize>,
+    residual: Vec<f64>,
+    capacity: Vec<f64>,
+}
+
+impl FlowNetwork {
+    fn new(graph: &DirectedCsrGraph<u32, (), f32>) -> Self {
+        let n = graph.node_count() as usize;
+        let mut network = Self {
+            adj: vec![vec![]; n],
+            heads: vec![],
+            residual: vec![],
+            capacity: vec![],
+        };
+        for from in 0..n {
+            for t in graph.out_neighbors_with_values(from as u32) {
+                let to = t.target as usize;
+                if to == from {
+                    continue;
+                }
+                let cap = t.value as f64;
+                network.adj[from].push(network.heads.len());
+                network.heads.push(to);
+                network.residual.push(cap);
+                network.capacity.push(cap);
+                network.adj[to].push(network.heads.len());
+                network.heads.push(from);
+                network.residual.push(0.);
+                network.capacity.push(0.);
+            }
+        }
+        network
+    }
+
+    fn levels(&self, source: usize) -> Vec<Option<usize>> {
+        let mut level = vec![None; self.adj.len()];
+        level[source] = Some(0);
+        let mut queue = VecDeque::from([source]);
+        while let Some(node) = queue.pop_front() {
+            let next_level = level[node].unwrap() + 1;
+            for &arc in &self.adj[node] {
+                let head = self.heads[arc];
+                if self.residual[arc] > FLOW_EPSILON && level[head].is_none() {
+                    level[head] = Some(next_level);
+                    queue.push_back(head);
+                }
+            }
+        }
+        level
+    }
+
+    fn max_flow(&mut self, source: usize, sink: usize, poison: &Poison) -> Result<f64> {
+        let mut total = 0.;
+        loop {
+            let level = self.levels(source);
+            if level[sink].is_none() {
+                break;
+            }
+            total += self.blocking_flow(source, sink, &level);
+            poison.check()?;
+        }
+        Ok(total)
+    }
+
+    /// Saturates every shortest augmenting path in the level graph, walking iteratively
+    /// so that long paths do not exhaust the stack.
+    fn blocking_flow(&mut self, source: usize, sink: usize, level: &[Option<usize>]) -> f64 {
+        let mut total = 0.;
+        let mut next_arc = vec![0; self.adj.len()];
+        let mut path: Vec<usize> = vec![];
+        let mut node = source;
+        loop {
+            if node == sink {
+                let bottleneck = path
+                    .iter()
+                    .map(|&arc| self.residual[arc])
+                    .fold(f64::INFINITY, f64::min);
+                for &arc in &path {
+                    self.residual[arc] -= bottleneck;
+                    self.residual[arc ^ 1] += bottleneck;
+                }
+                total += bottleneck;
+                path.clear();
+                node = source;
+                continue;
+            }
+            let mut advanced = false;
+            while next_arc[node] < self.adj[node].len() {
+                let arc = self.adj[node][next_arc[node]];
+                let head = self.heads[arc];
+                if self.residual[arc] > FLOW_EPSILON && level[head] == level[node].map(|l| l + 1) {
+                    path.push(arc);
+                    node = head;
+                    advanced = true;
+                    break;
+                }
+                next_arc[node] += 1;
+            }
+            if advanced {
+                continue;
+            }
+            match path.pop() {
+                None => break,
+                Some(arc) => {
+                    node = self.heads[arc ^ 1];
+                    next_arc[node] += 1;
+                }
+            }
+        }
+        total
+    }
+
+    fn edge_flows(&self) -> BTreeMap<(usize, usize), f64> {
+        let mut flows: BTreeMap<(usize, usize), f64> = BTreeMap::new();
+        for arc in (0..self.heads.len()).step_by(2) {
+            let flow = self.capacity[arc] - self.residual[arc];
+            if flow > FLOW_EPSILON {
+                let from = self.heads[arc ^ 1];
+                let to = self.heads[arc];
+                *flows.entry((from, to)).or_default() += flow;
+            }
+        }
+        flows
+    }
+
+    fn reachable_from(&self, source: usize) -> Vec<bool> {
+        self.levels(source)
+            .into_iter()
+            .map(|l| l.is_some())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_max_flow() {
+        let db = DbInstance::default();
+        let edges = r#"
+        edges[] <- [['s', 'v1', 16], ['s', 'v2', 13], ['v1', 'v3', 12], ['v2', 'v1', 4],
+                    ['v2', 'v4', 14], ['v3', 'v2', 9], ['v3', 't', 20], ['v4', 'v3', 7],
+                    ['v4', 't', 4]]
+        source[] <- [['s']]
+        sink[] <- [['t']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+        flow[from, to, f] <~ MaxFlow(edges[], source[], sink[])
+        ?[to, f] := flow['s', to, f]
+        "#
+            ))
+            .unwrap();
+        let total: f64 = res.rows.iter().map(|row| row[1].get_float().unwrap()).sum();
+        assert_eq!(total, 23.);
+
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+        ?[node, side] <~ MaxFlow(edges[], source[], sink[], output: 'partition')
+        :order node
+        "#
+            ))
+            .unwrap();
+        let sides = res
+            .rows
+            .iter()
+            .map(|row| row[1].get_str().unwrap())
+            .collect::<Vec<_>>();
+        // nodes in order: s, t, v1, v2, v3, v4
+        assert_eq!(
+            sides,
+            vec!["source", "sink", "source", "source", "sink", "source"]
+        );
+    }
+
+    #[test]
+    fn test_max_flow_edge_cases() {
+        let db = DbInstance::default();
+        let run = |edges: &str, source: &str, sink: &str, opts: &str| {
+            db.run_default(&format!(
+                r#"
+        edges[] <- {edges}
+        source[] <- {source}
+        sink[] <- {sink}
+        ?[from, to, f] <~ MaxFlow(edges[], source[], sink[]{opts})
+        "#
+            ))
+        };
+
+        // the self-loop carries nothing, and 'c' cannot be reached from 'a'
+        let res = run(
+            "[['a', 'a', 5], ['a', 'b', 2], ['c', 'd', 1]]",
+            "[['a']]",
+            "[['b']]",
+            "",
+        )
+        .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b", 2.0]])
+        );
+        let res = run("[['a', 'b', 2], ['c', 'd', 1]]", "[['a']]", "[['d']]", "").unwrap();
+        assert!(res.rows.is_empty());
+        // against the direction of the edge, unless undirected
+        let res = run("[['a', 'b', 2]]", "[['b']]", "[['a']]", "").unwrap();
+        assert!(res.rows.is_empty());
+        let res = run(
+            "[['a', 'b', 2]]",
+            "[['b']]",
+            "[['a']]",
+            ", undirected: true",
+        )
+        .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["b", "a", 2.0]])
+        );
+
+        let err = run("[['a', 'b']]", "[['a']]", "[['a']]", "").unwrap_err();
+        assert!(err.to_string().contains("must be different"));
+        let err = run("[['a', 'b']]", "[['a']]", "[['z']]", "").unwrap_err();
+        assert!(err.to_string().contains("not found"), "{err}");
+        assert!(run("[['a', 'b']]", "[]", "[['b']]", "").is_err());
+        assert!(run("[['a', 'b']]", "[['a']]", "[['b']]", ", output: 'cut'").is_err());
+        assert!(run("[['a', 'b']]", "[['a']]", "[['b']]", ", outputs: 'flow'").is_err());
+    }
+}
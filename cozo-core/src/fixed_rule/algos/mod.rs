@@ -8,36 +8,72 @@
 
 pub(crate) mod all_pairs_shortest_path;
 pub(crate) mod astar;
+pub(crate) mod bellman_ford;
 pub(crate) mod bfs;
+pub(crate) mod biconnected_components;
+pub(crate) mod bidirectional_dijkstra;
+pub(crate) mod bipartite_matching;
+pub(crate) mod centrality;
+pub(crate) mod cliques;
+pub(crate) mod coloring;
 pub(crate) mod degree_centrality;
 pub(crate) mod dfs;
+pub(crate) mod k_core;
 pub(crate) mod kruskal;
 pub(crate) mod label_propagation;
+pub(crate) mod leiden;
 pub(crate) mod louvain;
+pub(crate) mod max_flow;
+pub(crate) mod node_embeddings;
+pub(crate) mod node_similarity;
 pub(crate) mod pagerank;
+pub(crate) mod personalized_pagerank;
 pub(crate) mod prim;
 pub(crate) mod random_walk;
 pub(crate) mod shortest_path_bfs;
 pub(crate) mod shortest_path_dijkstra;
+pub(crate) mod simple_paths;
 pub(crate) mod strongly_connected_components;
+pub(crate) mod subgraph_match;
+pub(crate) mod temporal_paths;
 pub(crate) mod top_sort;
 pub(crate) mod triangles;
 pub(crate) mod yen;
 
 pub(crate) use all_pairs_shortest_path::{BetweennessCentrality, ClosenessCentrality};
 pub(crate) use astar::ShortestPathAStar;
+pub(crate) use bellman_ford::{ShortestPathBellmanFord, ShortestPathJohnson};
 pub(crate) use bfs::Bfs;
+pub(crate) use biconnected_components::{ArticulationPoints, BiconnectedComponents, Bridges};
+pub(crate) use bidirectional_dijkstra::ShortestPathBidirectionalDijkstra;
+pub(crate) use bipartite_matching::BipartiteMatching;
+pub(crate) use centrality::{
+    ApproxBetweennessCentrality, EigenvectorCentrality, HarmonicCentrality, Hits, KatzCentrality,
+};
+pub(crate) use cliques::MaximalCliques;
+pub(crate) use coloring::{GraphColoring, MaximumIndependentSet};
 pub(crate) use degree_centrality::DegreeCentrality;
 pub(crate) use dfs::Dfs;
+pub(crate) use k_core::{DegeneracyOrdering, KCore};
 pub(crate) use kruskal::MinimumSpanningForestKruskal;
 pub(crate) use label_propagation::LabelPropagation;
+pub(crate) use leiden::CommunityDetectionLeiden;
 pub(crate) use louvain::CommunityDetectionLouvain;
+pub(crate) use max_flow::MaxFlow;
+pub(crate) use node_embeddings::NodeEmbeddings;
+pub(crate) use node_similarity::NodeSimilarity;
 pub(crate) use pagerank::PageRank;
+pub(crate) use personalized_pagerank::PersonalizedPageRank;
 pub(crate) use prim::MinimumSpanningTreePrim;
 pub(crate) use random_walk::RandomWalk;
 pub(crate) use shortest_path_bfs::ShortestPathBFS;
 pub(crate) use shortest_path_dijkstra::ShortestPathDijkstra;
+pub(crate) use simple_paths::{AllSimplePaths, SimpleCycles};
 pub(crate) use strongly_connected_components::StronglyConnectedComponent;
+pub(crate) use subgraph_match::SubgraphMatch;
+pub(crate) use temporal_paths::{
+    TemporalEarliestArrival, TemporalFastestPath, TemporalLatestDeparture,
+};
 pub(crate) use top_sort::TopSort;
 pub(crate) use triangles::ClusteringCoefficients;
 pub(crate) use yen::KShortestPathYen;
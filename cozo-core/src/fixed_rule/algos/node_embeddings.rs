@@ -0,0 +1,363 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+
+use graph::prelude::{DirectedCsrGraph, DirectedNeighborsWithValues, Graph};
+use miette::{bail, IntoDiagnostic, Result};
+use ndarray::{Array1, Array2};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::{DataValue, Vector};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Node embeddings by node2vec: biased second-order random walks, followed by training
+/// skip-gram with negative sampling on the walks. With `p = q = 1` this is DeepWalk.
+///
+/// The rule returns `[node, embedding]`, where the embedding is a `<F32; dim>` vector.
+pub(crate) struct NodeEmbeddings;
+
+struct WalkConfig {
+    walk_length: usize,
+    walks_per_node: usize,
+    p: f64,
+    q: f64,
+}
+
+struct TrainConfig {
+    dim: usize,
+    window: usize,
+    negative: usize,
+    epochs: usize,
+    learning_rate: f32,
+}
+
+impl FixedRule for NodeEmbeddings {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let walk_config = WalkConfig {
+            walk_length: payload.pos_integer_option("walk_length", Some(40))?,
+            walks_per_node: payload.pos_integer_option("walks_per_node", Some(10))?,
+            p: payload.float_option("p", Some(1.))?,
+            q: payload.float_option("q", Some(1.))?,
+        };
+        let train_config = TrainConfig {
+            dim: payload.pos_integer_option("dim", Some(64))?,
+            window: payload.pos_integer_option("window", Some(5))?,
+            negative: payload.non_neg_integer_option("negative", Some(5))?,
+            epochs: payload.pos_integer_option("epochs", Some(1))?,
+            learning_rate: payload.float_option("learning_rate", Some(0.025))? as f32,
+        };
+        let seed = payload.integer_option("seed", Some(0))?;
+        for (name, val) in [("p", walk_config.p), ("q", walk_config.q)] {
+            if val <= 0. {
+                bail!(WrongFixedRuleOptionError {
+                    name: name.to_string(),
+                    span: payload.option_span(name)?,
+                    rule_name: payload.name().to_string(),
+                    help: "the return and in-out parameters must be positive".to_string(),
+                })
+            }
+        }
+
+        let (graph, indices, _) = edges.as_directed_weighted_graph(undirected, false)?;
+        let mut rng = StdRng::seed_from_u64(seed as u64);
+        let walks = node2vec_walks(&graph, &walk_config, &mut rng, &poison)?;
+        let embeddings = train_skip_gram(&walks, indices.len(), &train_config, &mut rng, &poison)?;
+        for (node, key) in indices.into_iter().enumerate() {
+            out.put(vec![
+                key,
+                DataValue::Vec(Vector::F32(embeddings.row(node).to_owned())),
+            ]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "edges",
+                2,
+                "edges as `[from, to, weight?]`, with weights defaulting to 1",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "walk_length",
+                    FixedRuleOptionType::PosInt,
+                    "length of each random walk",
+                )
+                .default_value(40),
+                FixedRuleOptionSpec::new(
+                    "walks_per_node",
+                    FixedRuleOptionType::PosInt,
+                    "number of walks starting from each node",
+                )
+                .default_value(10),
+                FixedRuleOptionSpec::new(
+                    "p",
+                    FixedRuleOptionType::Float,
+                    "return parameter of the walks",
+                )
+                .default_value(1.),
+                FixedRuleOptionSpec::new(
+                    "q",
+                    FixedRuleOptionType::Float,
+                    "in-out parameter of the walks",
+                )
+                .default_value(1.),
+                FixedRuleOptionSpec::new(
+                    "dim",
+                    FixedRuleOptionType::PosInt,
+                    "dimension of the embeddings",
+                )
+                .default_value(64),
+                FixedRuleOptionSpec::new(
+                    "window",
+                    FixedRuleOptionType::PosInt,
+                    "context window size",
+                )
+                .default_value(5),
+                FixedRuleOptionSpec::new(
+                    "negative",
+                    FixedRuleOptionType::NonNegInt,
+                    "number of negative samples",
+                )
+                .default_value(5),
+                FixedRuleOptionSpec::new(
+                    "epochs",
+                    FixedRuleOptionType::PosInt,
+                    "number of training epochs",
+                )
+                .default_value(1),
+                FixedRuleOptionSpec::new(
+                    "learning_rate",
+                    FixedRuleOptionType::Float,
+                    "initial learning rate",
+                )
+                .default_value(0.025),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+/// Picks an index with probability proportional to its weight, or `None` if all weights are zero.
+fn choose_weighted(weights: &[f64], rng: &mut StdRng) -> Option<usize> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0. {
+        return None;
+    }
+    let mut pick = rng.gen::<f64>() * total;
+    for (i, w) in weights.iter().enumerate() {
+        pick -= w;
+        if pick < 0. {
+            return Some(i);
+        }
+    }
+    weights.iter().rposition(|w| *w > 0.)
+}
+
+fn node2vec_walks(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    config: &WalkConfig,
+    rng: &mut StdRng,
+    poison: &Poison,
+) -> Result<Vec<Vec<usize>>> {
+    let n = graph.node_count() as usize;
+    // neighbours are sorted by target, which the membership test below relies on
+    let neighbours: Vec<Vec<(usize, f64)>> = (0..n)
+        .map(|node| {
+            graph
+                .out_neighbors_with_values(node as u32)
+                .map(|t| (t.target as usize, t.value as f64))
+                .collect()
+        })
+        .collect();
+    let is_neighbour = |from: usize, to: usize| {
+        neighbours[from]
+            .binary_search_by_key(&to, |(t, _)| *t)
+            .is_ok()
+    };
+
+    let mut walks = vec![];
+    let mut order = (0..n).collect::<Vec<_>>();
+    for _ in 0..config.walks_per_node {
+        order.shuffle(rng);
+        for &start in &order {
+            let mut walk = vec![start];
+            while walk.len() < config.walk_length {
+                let current = *walk.last().unwrap();
+                let candidates = &neighbours[current];
+                let weights = match walk.len().checked_sub(2).map(|i| walk[i]) {
+                    None => candidates.iter().map(|(_, w)| *w).collect::<Vec<_>>(),
+                    Some(previous) => candidates
+                        .iter()
+                        .map(|(next, w)| {
+                            if *next == previous {
+                                w / config.p
+                            } else if is_neighbour(previous, *next) {
+                                *w
+                            } else {
+                                w / config.q
+                            }
+                        })
+                        .collect(),
+                };
+                match choose_weighted(&weights, rng) {
+                    None => break,
+                    Some(i) => walk.push(candidates[i].0),
+                }
+            }
+            walks.push(walk);
+        }
+        poison.check()?;
+    }
+    Ok(walks)
+}
+
+fn train_skip_gram(
+    walks: &[Vec<usize>],
+    n: usize,
+    config: &TrainConfig,
+    rng: &mut StdRng,
+    poison: &Poison,
+) -> Result<Array2<f32>> {
+    let dim = config.dim;
+    let mut input = Array2::from_shape_fn((n, dim), |_| (rng.gen::<f32>() - 0.5) / dim as f32);
+    let mut output = Array2::<f32>::zeros((n, dim));
+    if n == 0 {
+        return Ok(input);
+    }
+
+    // negatives are drawn from the unigram distribution raised to the 3/4 power
+    let mut frequencies = vec![0.; n];
+    for walk in walks {
+        for node in walk {
+            frequencies[*node] += 1.;
+        }
+    }
+    let noise = frequencies
+        .iter()
+        .map(|f: &f64| f.powf(0.75))
+        .collect::<Vec<_>>();
+    let noise = WeightedIndex::new(&noise).into_diagnostic()?;
+
+    let total_steps = (config.epochs * walks.iter().map(|w| w.len()).sum::<usize>()).max(1);
+    let mut step = 0;
+    let mut gradient = Array1::<f32>::zeros(dim);
+    for _ in 0..config.epochs {
+        for walk in walks {
+            for (i, &center) in walk.iter().enumerate() {
+                let learning_rate =
+                    config.learning_rate * (1. - step as f32 / total_steps as f32).max(0.0001);
+                step += 1;
+                let from = i.saturating_sub(config.window);
+                let to = (i + config.window + 1).min(walk.len());
+                for (j, &context) in walk.iter().enumerate().take(to).skip(from) {
+                    if j == i {
+                        continue;
+                    }
+                    gradient.fill(0.);
+                    let samples = std::iter::once((context, true))
+                        .chain((0..config.negative).map(|_| (noise.sample(rng), false)));
+                    for (target, positive) in samples {
+                        if !positive && target == context {
+                            continue;
+                        }
+                        let label = if positive { 1. } else { 0. };
+                        let score = input.row(center).dot(&output.row(target));
+                        let g = (label - sigmoid(score)) * learning_rate;
+                        gradient.scaled_add(g, &output.row(target));
+                        output.row_mut(target).scaled_add(g, &input.row(center));
+                    }
+                    input.row_mut(center).scaled_add(1., &gradient);
+                }
+            }
+        }
+        poison.check()?;
+    }
+    Ok(input)
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1. / (1. + (-x.clamp(-6., 6.)).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::value::{DataValue, Vector};
+    use crate::DbInstance;
+
+    #[test]
+    fn test_node_embeddings() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+        edges[] <- [['a', 'b'], ['a', 'c'], ['a', 'd'], ['b', 'c'], ['b', 'd'], ['c', 'd'],
+                    ['e', 'f'], ['e', 'g'], ['e', 'h'], ['f', 'g'], ['f', 'h'], ['g', 'h'],
+                    ['d', 'e']]
+        ?[node, embedding] <~ NodeEmbeddings(edges[], undirected: true, dim: 16, window: 3,
+                                             walk_length: 20, walks_per_node: 20, epochs: 5,
+                                             q: 2.0, seed: 7)
+        "#,
+            )
+            .unwrap();
+        assert_eq!(res.rows.len(), 8);
+        let embedding = |name: &str| {
+            let row = res
+                .rows
+                .iter()
+                .find(|row| row[0].get_str().unwrap() == name)
+                .unwrap();
+            match &row[1] {
+                DataValue::Vec(Vector::F32(v)) => v.clone(),
+                v => panic!("not an embedding: {v:?}"),
+            }
+        };
+        let cosine = |a: &str, b: &str| {
+            let (a, b) = (embedding(a), embedding(b));
+            a.dot(&b) / (a.dot(&a).sqrt() * b.dot(&b).sqrt())
+        };
+        assert_eq!(embedding("a").len(), 16);
+        assert!(cosine("a", "b") > cosine("a", "h"));
+        assert!(cosine("g", "h") > cosine("b", "h"));
+    }
+}
@@ -0,0 +1,370 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+
+use graph::prelude::{DirectedNeighbors, Graph};
+use miette::{bail, Result};
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Neighbourhood-based node similarity for link prediction, returning `[a, b, score]`.
+///
+/// Without a second input relation, every pair of nodes sharing a neighbour is scored once,
+/// and pairs scoring below `threshold` are dropped. With a second input relation of source
+/// nodes, the `k` most similar nodes to each source are returned instead.
+/// Preferential attachment does not depend on shared neighbours, so it scores all pairs.
+pub(crate) struct NodeSimilarity;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Measure {
+    CommonNeighbors,
+    Jaccard,
+    Overlap,
+    AdamicAdar,
+    ResourceAllocation,
+    PreferentialAttachment,
+}
+
+impl FixedRule for NodeSimilarity {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?.ensure_min_len(2)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let measure = match &payload.string_option("measure", Some("jaccard"))? as &str {
+            "common_neighbors" => Measure::CommonNeighbors,
+            "jaccard" => Measure::Jaccard,
+            "overlap" => Measure::Overlap,
+            "adamic_adar" => Measure::AdamicAdar,
+            "resource_allocation" => Measure::ResourceAllocation,
+            "preferential_attachment" => Measure::PreferentialAttachment,
+            _ => bail!(WrongFixedRuleOptionError {
+                name: "measure".to_string(),
+                span: payload.option_span("measure")?,
+                rule_name: payload.name().to_string(),
+                help: "measure must be one of 'common_neighbors', 'jaccard', 'overlap', \
+                'adamic_adar', 'resource_allocation' or 'preferential_attachment'"
+                    .to_string(),
+            }),
+        };
+        let threshold = payload.float_option("threshold", Some(0.))?;
+
+        let (graph, indices, inv_indices) = edges.as_directed_graph(undirected)?;
+        let n = graph.node_count() as usize;
+        let out_adj: Vec<Vec<usize>> = (0..n)
+            .map(|node| {
+                let mut neighbours = graph
+                    .out_neighbors(node as u32)
+                    .map(|t| *t as usize)
+                    .filter(|t| *t != node)
+                    .collect::<Vec<_>>();
+                neighbours.dedup();
+                neighbours
+            })
+            .collect();
+        let mut in_adj: Vec<Vec<usize>> = vec![vec![]; n];
+        for (from, targets) in out_adj.iter().enumerate() {
+            for to in targets {
+                in_adj[*to].push(from);
+            }
+        }
+        let mut scorer = Scorer {
+            measure,
+            out_adj,
+            in_adj,
+            shared: vec![0.; n],
+            seen: vec![false; n],
+            touched: vec![],
+        };
+
+        match payload.get_input(1) {
+            Ok(sources) => {
+                let k = payload.pos_integer_option("k", Some(10))?;
+                for tuple in sources.iter()? {
+                    let tuple = tuple?;
+                    let source = &tuple[0];
+                    // a node without edges is similar to nothing
+                    let u = match inv_indices.get(source) {
+                        Some(u) => *u as usize,
+                        None => continue,
+                    };
+                    let mut scores = scorer.scores_for(u);
+                    scores.sort_by(|(a_node, a), (b_node, b)| {
+                        b.total_cmp(a).then_with(|| a_node.cmp(b_node))
+                    });
+                    scores.truncate(k);
+                    for (v, score) in scores {
+                        out.put(vec![
+                            source.clone(),
+                            indices[v].clone(),
+                            DataValue::from(score),
+                        ]);
+                    }
+                    poison.check()?;
+                }
+            }
+            Err(_) => {
+                for u in 0..n {
+                    for (v, score) in scorer.scores_for(u) {
+                        if score >= threshold && indices[u] < indices[v] {
+                            out.put(vec![
+                                indices[u].clone(),
+                                indices[v].clone(),
+                                DataValue::from(score),
+                            ]);
+                        }
+                    }
+                    poison.check()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`"),
+                FixedRuleInputSpec::new("sources", 1, "nodes to find the most similar nodes for")
+                    .optional(),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "measure",
+                    FixedRuleOptionType::String,
+                    "the similarity measure",
+                )
+                .default_value("jaccard"),
+                FixedRuleOptionSpec::new(
+                    "threshold",
+                    FixedRuleOptionType::Float,
+                    "pairs scoring below this are dropped, without sources",
+                )
+                .default_value(0.),
+                FixedRuleOptionSpec::new(
+                    "k",
+                    FixedRuleOptionType::PosInt,
+                    "number of similar nodes for each source",
+                )
+                .default_value(10),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(3)
+    }
+}
+
+struct Scorer {
+    measure: Measure,
+    out_adj: Vec<Vec<usize>>,
+    in_adj: Vec<Vec<usize>>,
+    /// Per-node accumulator of the shared-neighbour contributions, reset after each source.
+    shared: Vec<f64>,
+    seen: Vec<bool>,
+    touched: Vec<usize>,
+}
+
+impl Scorer {
+    /// Scores every node other than `u` that shares a neighbour with it. The shared
+    /// neighbours are enumerated through two hops, so the cost is bounded by the degrees
+    /// of the neighbours of `u` rather than the number of nodes.
+    fn scores_for(&mut self, u: usize) -> Vec<(usize, f64)> {
+        let degree_u = self.out_adj[u].len() as f64;
+        if self.measure == Measure::PreferentialAttachment {
+            return (0..self.out_adj.len())
+                .filter(|v| *v != u)
+                .map(|v| (v, degree_u * self.out_adj[v].len() as f64))
+                .filter(|(_, score)| *score > 0.)
+                .collect();
+        }
+
+        for &w in &self.out_adj[u] {
+            let degree_w = self.in_adj[w].len() as f64;
+            let contribution = match self.measure {
+                Measure::AdamicAdar => 1. / degree_w.ln(),
+                Measure::ResourceAllocation => 1. / degree_w,
+                _ => 1.,
+            };
+            for &v in &self.in_adj[w] {
+                if v == u {
+                    continue;
+                }
+                if !self.seen[v] {
+                    self.seen[v] = true;
+                    self.touched.push(v);
+                }
+                self.shared[v] += contribution;
+            }
+        }
+
+        let mut ret = Vec::with_capacity(self.touched.len());
+        for v in self.touched.drain(..) {
+            let shared = self.shared[v];
+            self.shared[v] = 0.;
+            self.seen[v] = false;
+            let degree_v = self.out_adj[v].len() as f64;
+            let score = match self.measure {
+                Measure::Jaccard => shared / (degree_u + degree_v - shared),
+                Measure::Overlap => shared / degree_u.min(degree_v),
+                _ => shared,
+            };
+            ret.push((v, score));
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_node_similarity() {
+        let db = DbInstance::default();
+        let edges = r#"
+        edges[] <- [['a', 'x'], ['a', 'y'], ['b', 'x'], ['b', 'y'], ['b', 'z'], ['c', 'z'],
+                    ['d', 'x'], ['d', 'y'], ['d', 'z'], ['e', 'x']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[a, b, s] <~ NodeSimilarity(edges[], measure: 'jaccard', threshold: 0.5)"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([
+                ["a", "b", 2. / 3.],
+                ["a", "d", 2. / 3.],
+                ["a", "e", 0.5],
+                ["b", "d", 1.0]
+            ])
+        );
+
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                sources[] <- [['a']]
+                ?[a, b, s] <~ NodeSimilarity(edges[], sources[], measure: 'common_neighbors', k: 2)
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b", 2.0], ["a", "d", 2.0]])
+        );
+
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                sources[] <- [['c']]
+                ?[a, b, s] <~ NodeSimilarity(edges[], sources[], measure: 'resource_allocation')
+                "#
+            ))
+            .unwrap();
+        // 'z' is pointed to by 'b', 'c' and 'd'
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["c", "b", 1. / 3.], ["c", "d", 1. / 3.]])
+        );
+    }
+
+    #[test]
+    fn test_node_similarity_edge_cases() {
+        let db = DbInstance::default();
+        for measure in [
+            "common_neighbors",
+            "jaccard",
+            "overlap",
+            "adamic_adar",
+            "resource_allocation",
+            "preferential_attachment",
+        ] {
+            let res = db
+                .run_default(&format!(
+                    "edges[a, b] <- [] ?[a, b, s] <~ NodeSimilarity(edges[], measure: '{measure}')"
+                ))
+                .unwrap();
+            assert!(res.rows.is_empty(), "{measure}");
+        }
+
+        // self-loops are not neighbours, 'p'-'q' shares nothing with the rest,
+        // and a source without edges is similar to nothing
+        let edges = r#"
+        edges[] <- [['a', 'a'], ['a', 'x'], ['b', 'x'], ['b', 'b'], ['p', 'q']]
+        sources[] <- [['a'], ['nowhere']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[a, b, s] <~ NodeSimilarity(edges[], sources[])"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b", 1.0]])
+        );
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[a, b, s] <~ NodeSimilarity(edges[], measure: 'overlap')"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b", 1.0]])
+        );
+        // preferential attachment scores the disconnected pairs too
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                ?[a, b, s] <~ NodeSimilarity(edges[], measure: 'preferential_attachment')
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b", 1.0], ["a", "p", 1.0], ["b", "p", 1.0]])
+        );
+
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[a, b, s] <~ NodeSimilarity(edges[], measure: 'cosine')"
+            ))
+            .is_err());
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[a, b, s] <~ NodeSimilarity(edges[], sources[], k: 0)"
+            ))
+            .is_err());
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[a, b, s] <~ NodeSimilarity(edges[], top_k: 1)"
+            ))
+            .is_err());
+    }
+}
@@ -17,7 +17,10 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -55,6 +58,32 @@ impl FixedRule for PageRank {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "theta",
+                    FixedRuleOptionType::UnitInterval,
+                    "probability of following an edge instead of jumping",
+                )
+                .default_value(0.85),
+                FixedRuleOptionSpec::new(
+                    "epsilon",
+                    FixedRuleOptionType::UnitInterval,
+                    "stop once the scores change by less than this",
+                )
+                .default_value(0.0001),
+                FixedRuleOptionSpec::new(
+                    "iterations",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations",
+                )
+                .default_value(10),
+            ],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
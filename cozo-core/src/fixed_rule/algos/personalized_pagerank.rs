@@ -0,0 +1,418 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, VecDeque};
+
+use graph::prelude::{DirectedCsrGraph, DirectedDegrees, DirectedNeighborsWithValues, Graph};
+use itertools::Itertools;
+use miette::{bail, IntoDiagnostic, Result};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::random_walk::choose_step;
+use crate::fixed_rule::{
+    BadExprValueError, FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType,
+    FixedRulePayload, FixedRuleSchema, NodeNotFoundError,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// PageRank personalised to a weighted seed set, also known as random walk with restart.
+///
+/// Walkers continue along an out-edge with probability `theta`, chosen in proportion to the
+/// edge weights, and otherwise jump back to a seed. Walkers stuck at nodes without out-edges
+/// also jump back to the seeds.
+pub(crate) struct PersonalizedPageRank;
+
+impl FixedRule for PersonalizedPageRank {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let seeds = payload.get_input(1)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let theta = payload.unit_interval_option("theta", Some(0.85))?;
+        let epsilon = payload.unit_interval_option("epsilon", Some(0.0001))?;
+        let iterations = payload.pos_integer_option("iterations", Some(20))?;
+        let walks = payload.pos_integer_option("walks", Some(10000))?;
+        let k = payload.non_neg_integer_option("k", Some(0))?;
+        let method = payload.string_option("method", Some("power"))?;
+        let seed = payload.integer_option("seed", Some(0))?;
+        if theta >= 1. {
+            bail!(WrongFixedRuleOptionError {
+                name: "theta".to_string(),
+                span: payload.option_span("theta")?,
+                rule_name: payload.name().to_string(),
+                help: "the restart probability `1 - theta` must be positive".to_string(),
+            })
+        }
+
+        let (graph, indices, inv_indices) = edges.as_directed_weighted_graph(undirected, false)?;
+
+        let mut seed_weights: BTreeMap<usize, f64> = BTreeMap::new();
+        for tuple in seeds.iter()? {
+            let tuple = tuple?;
+            let node = &tuple[0];
+            let idx = *inv_indices.get(node).ok_or_else(|| NodeNotFoundError {
+                missing: node.clone(),
+                span: seeds.span(),
+            })?;
+            let weight = match tuple.get(1) {
+                None => 1.,
+                Some(d) => match d.get_float() {
+                    Some(f) if f >= 0. && f.is_finite() => f,
+                    _ => bail!(BadExprValueError(
+                        d.clone(),
+                        seeds.span(),
+                        "seed weights must be non-negative numbers".to_string()
+                    )),
+                },
+            };
+            *seed_weights.entry(idx as usize).or_default() += weight;
+        }
+        let total: f64 = seed_weights.values().sum();
+        if total <= 0. {
+            return Ok(());
+        }
+        let mut restart = vec![0.; indices.len()];
+        for (idx, weight) in seed_weights {
+            restart[idx] = weight / total;
+        }
+
+        let scores = match &method as &str {
+            "power" => ppr_power(&graph, &restart, theta, epsilon, iterations, &poison)?,
+            "push" => ppr_push(&graph, &restart, theta, epsilon, &poison)?,
+            "monte_carlo" => {
+                let mut rng = StdRng::seed_from_u64(seed as u64);
+                ppr_monte_carlo(&graph, &restart, theta, walks, &mut rng, &poison)?
+            }
+            _ => bail!(WrongFixedRuleOptionError {
+                name: "method".to_string(),
+                span: payload.option_span("method")?,
+                rule_name: payload.name().to_string(),
+                help: "method must be one of 'power', 'push' or 'monte_carlo'".to_string(),
+            }),
+        };
+
+        let mut ranked = scores
+            .into_iter()
+            .enumerate()
+            .filter(|(_, score)| *score > 0.)
+            .collect_vec();
+        if k > 0 {
+            ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+            ranked.truncate(k);
+        }
+        for (idx, score) in ranked {
+            out.put(vec![indices[idx].clone(), DataValue::from(score)]);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    2,
+                    "edges as `[from, to, weight?]`, with weights defaulting to 1",
+                ),
+                FixedRuleInputSpec::new("seeds", 1, "seed nodes as `[node, weight?]`"),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "theta",
+                    FixedRuleOptionType::UnitInterval,
+                    "probability of following an edge instead of jumping back to the seeds",
+                )
+                .default_value(0.85),
+                FixedRuleOptionSpec::new(
+                    "epsilon",
+                    FixedRuleOptionType::UnitInterval,
+                    "stop once the scores change by less than this",
+                )
+                .default_value(0.0001),
+                FixedRuleOptionSpec::new(
+                    "iterations",
+                    FixedRuleOptionType::PosInt,
+                    "maximum number of iterations",
+                )
+                .default_value(20),
+                FixedRuleOptionSpec::new(
+                    "walks",
+                    FixedRuleOptionType::PosInt,
+                    "number of walks for the Monte Carlo method",
+                )
+                .default_value(10000),
+                FixedRuleOptionSpec::new(
+                    "k",
+                    FixedRuleOptionType::NonNegInt,
+                    "only keep this many top nodes, or all nodes when 0",
+                )
+                .default_value(0),
+                FixedRuleOptionSpec::new(
+                    "method",
+                    FixedRuleOptionType::String,
+                    "one of `'power'`, `'push'` or `'monte_carlo'`",
+                )
+                .default_value("power"),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator for the Monte Carlo method",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+fn out_weight_sums(graph: &DirectedCsrGraph<u32, (), f32>) -> Vec<f64> {
+    (0..graph.node_count())
+        .map(|node| {
+            graph
+                .out_neighbors_with_values(node)
+                .map(|t| t.value as f64)
+                .sum()
+        })
+        .collect()
+}
+
+/// Power iteration on the full graph, until the L1 change falls below `epsilon`.
+fn ppr_power(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    restart: &[f64],
+    theta: f64,
+    epsilon: f64,
+    iterations: usize,
+    poison: &Poison,
+) -> Result<Vec<f64>> {
+    let out_sums = out_weight_sums(graph);
+    let mut scores = restart.to_vec();
+    for _ in 0..iterations {
+        let mut next = vec![0.; scores.len()];
+        let mut dangling = 0.;
+        for (node, &mass) in scores.iter().enumerate() {
+            if mass == 0. {
+                continue;
+            }
+            if out_sums[node] <= 0. {
+                dangling += mass;
+                continue;
+            }
+            for t in graph.out_neighbors_with_values(node as u32) {
+                next[t.target as usize] += theta * mass * t.value as f64 / out_sums[node];
+            }
+        }
+        let restart_mass = 1. - theta + theta * dangling;
+        for (n, r) in next.iter_mut().zip(restart) {
+            *n += restart_mass * r;
+        }
+        let diff: f64 = next.iter().zip(&scores).map(|(a, b)| (a - b).abs()).sum();
+        scores = next;
+        if diff < epsilon {
+            break;
+        }
+        poison.check()?;
+    }
+    Ok(scores)
+}
+
+/// Forward push: only nodes whose residual exceeds `epsilon` times their out-degree are
+/// touched, so the cost depends on the neighbourhood of the seeds rather than the whole graph.
+fn ppr_push(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    restart: &[f64],
+    theta: f64,
+    epsilon: f64,
+    poison: &Poison,
+) -> Result<Vec<f64>> {
+    let out_sums = out_weight_sums(graph);
+    let threshold = |node: usize| epsilon * (graph.out_degree(node as u32) as f64).max(1.);
+    let seeds = restart
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| **r > 0.)
+        .map(|(i, r)| (i, *r))
+        .collect_vec();
+
+    let mut estimate = vec![0.; restart.len()];
+    let mut residual = restart.to_vec();
+    let mut in_queue = vec![false; restart.len()];
+    let mut queue = VecDeque::new();
+    for (node, _) in &seeds {
+        queue.push_back(*node);
+        in_queue[*node] = true;
+    }
+    let mut pushes = 0usize;
+    while let Some(node) = queue.pop_front() {
+        in_queue[node] = false;
+        let mass = residual[node];
+        if mass <= threshold(node) {
+            continue;
+        }
+        estimate[node] += (1. - theta) * mass;
+        residual[node] = 0.;
+        let spread = theta * mass;
+        let mut targets = vec![];
+        if out_sums[node] <= 0. {
+            for (seed, r) in &seeds {
+                targets.push((*seed, spread * r));
+            }
+        } else {
+            for t in graph.out_neighbors_with_values(node as u32) {
+                targets.push((t.target as usize, spread * t.value as f64 / out_sums[node]));
+            }
+        }
+        for (target, amount) in targets {
+            residual[target] += amount;
+            if !in_queue[target] && residual[target] > threshold(target) {
+                in_queue[target] = true;
+                queue.push_back(target);
+            }
+        }
+        pushes += 1;
+        if pushes % 1024 == 0 {
+            poison.check()?;
+        }
+    }
+    Ok(estimate)
+}
+
+/// Monte Carlo estimation: the score of a node is the fraction of walks that end there.
+fn ppr_monte_carlo(
+    graph: &DirectedCsrGraph<u32, (), f32>,
+    restart: &[f64],
+    theta: f64,
+    walks: usize,
+    rng: &mut impl Rng,
+    poison: &Poison,
+) -> Result<Vec<f64>> {
+    let seed_dist = WeightedIndex::new(restart).into_diagnostic()?;
+    let mut counts = vec![0usize; restart.len()];
+    for i in 0..walks {
+        let mut node = seed_dist.sample(rng);
+        while rng.gen::<f64>() < theta {
+            let targets = graph.out_neighbors_with_values(node as u32).collect_vec();
+            let weights = targets.iter().map(|t| t.value as f64).collect_vec();
+            node = match choose_step(targets.len(), Some(&weights), rng) {
+                Some(idx) => targets[idx].target as usize,
+                None => seed_dist.sample(rng),
+            };
+        }
+        counts[node] += 1;
+        if i % 1024 == 0 {
+            poison.check()?;
+        }
+    }
+    Ok(counts
+        .into_iter()
+        .map(|c| c as f64 / walks as f64)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_personalized_pagerank() {
+        let db = DbInstance::default();
+        let query = |method: &str, k: usize| {
+            let res = db
+                .run_default(&format!(
+                    r#"
+        edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['d', 'a'], ['c', 'e']]
+        seeds[] <- [['a']]
+        ?[node, score] <~ PersonalizedPageRank(edges[], seeds[], method: '{method}', k: {k},
+                                               iterations: 100, epsilon: 0.000001)
+        :order -score
+        "#
+                ))
+                .unwrap();
+            res.rows
+                .into_iter()
+                .map(|row| {
+                    (
+                        row[0].get_str().unwrap().to_string(),
+                        row[1].get_float().unwrap(),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let power = query("power", 0);
+        assert_eq!(power[0].0, "a");
+        // 'd' cannot be reached from the seed
+        assert!(power.iter().all(|(n, _)| n != "d"));
+        let total: f64 = power.iter().map(|(_, s)| s).sum();
+        assert!((total - 1.).abs() < 1e-6);
+
+        let push = query("push", 0);
+        for (node, score) in &power {
+            let (_, approx) = push.iter().find(|(n, _)| n == node).unwrap();
+            assert!((score - approx).abs() < 1e-3);
+        }
+
+        let monte_carlo = query("monte_carlo", 0);
+        for (node, score) in &power {
+            let approx = monte_carlo
+                .iter()
+                .find(|(n, _)| n == node)
+                .map(|(_, s)| *s)
+                .unwrap_or(0.);
+            assert!((score - approx).abs() < 0.05);
+        }
+
+        let seeded = |seed: i64| {
+            db.run_default(&format!(
+                r#"
+        edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['d', 'a'], ['c', 'e']]
+        seeds[] <- [['a']]
+        ?[node, score] <~ PersonalizedPageRank(edges[], seeds[], method: 'monte_carlo',
+                                               walks: 100, seed: {seed})
+        "#
+            ))
+            .unwrap()
+            .rows
+        };
+        assert_eq!(seeded(42), seeded(42));
+
+        let top = query("power", 2);
+        assert_eq!(top.len(), 2);
+        assert_eq!(top[0].0, "a");
+
+        assert!(db
+            .run_default(
+                r#"
+        edges[] <- [['a', 'b']]
+        seeds[] <- [['z']]
+        ?[node, score] <~ PersonalizedPageRank(edges[], seeds[])
+        "#
+            )
+            .is_err());
+    }
+}
@@ -20,7 +20,7 @@ use thiserror::Error;
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{FixedRule, FixedRuleInputSpec, FixedRulePayload, FixedRuleSchema};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -72,6 +72,24 @@ impl FixedRule for MinimumSpanningTreePrim {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    2,
+                    "edges as `[from, to, weight?]`, with weights defaulting to 1",
+                ),
+                FixedRuleInputSpec::new(
+                    "starting",
+                    1,
+                    "the node to start from, defaulting to the first node",
+                )
+                .optional(),
+            ],
+            options: vec![],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
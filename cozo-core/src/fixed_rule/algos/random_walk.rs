@@ -17,7 +17,10 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::{eval_bytecode, Expr};
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{BadExprValueError, FixedRule, FixedRulePayload, NodeNotFoundError};
+use crate::fixed_rule::{
+    BadExprValueError, FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType,
+    FixedRulePayload, FixedRuleSchema, NodeNotFoundError,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -70,10 +73,7 @@ impl FixedRule for RandomWalk {
                 for _ in 0..steps {
                     let cur_node_key = &current_tuple[0];
                     let candidate_steps: Vec<_> = edges.prefix_iter(cur_node_key)?.try_collect()?;
-                    if candidate_steps.is_empty() {
-                        break;
-                    }
-                    let next_step = if let Some((weight_expr, span)) = &maybe_weight_bytecode {
+                    let weights = if let Some((weight_expr, span)) = &maybe_weight_bytecode {
                         let weights: Vec<_> = candidate_steps
                             .iter()
                             .map(|t| -> Result<f64> {
@@ -102,11 +102,15 @@ impl FixedRule for RandomWalk {
                                 })
                             })
                             .try_collect()?;
-                        let dist = WeightedIndex::new(&weights).unwrap();
-                        &candidate_steps[dist.sample(&mut rng)]
+                        Some(weights)
                     } else {
-                        candidate_steps.choose(&mut rng).unwrap()
+                        None
                     };
+                    let next_step =
+                        match choose_step(candidate_steps.len(), weights.as_deref(), &mut rng) {
+                            None => break,
+                            Some(i) => &candidate_steps[i],
+                        };
                     let next_node = &next_step[1];
                     path.push(next_node.clone());
                     current_tuple = nodes.prefix_iter(next_node)?.next().ok_or_else(|| {
@@ -127,6 +131,38 @@ impl FixedRule for RandomWalk {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`"),
+                FixedRuleInputSpec::new(
+                    "nodes",
+                    1,
+                    "nodes as `[node, ..]`, whose columns the weight can refer to",
+                ),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "iterations",
+                    FixedRuleOptionType::PosInt,
+                    "number of walks from each starting node",
+                )
+                .default_value(1),
+                FixedRuleOptionSpec::new(
+                    "steps",
+                    FixedRuleOptionType::PosInt,
+                    "number of steps of each walk",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "weight",
+                    FixedRuleOptionType::Expr,
+                    "weight of choosing the next node, in terms of the bindings of the nodes",
+                ),
+            ],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -136,3 +172,15 @@ impl FixedRule for RandomWalk {
         Ok(3)
     }
 }
+
+/// Picks the index of the next step among `n` candidates, in proportion to `weights` if given
+/// and uniformly otherwise. Returns `None` if there is no candidate with a positive weight.
+pub(crate) fn choose_step(n: usize, weights: Option<&[f64]>, rng: &mut impl Rng) -> Option<usize> {
+    match weights {
+        Some(weights) => WeightedIndex::new(weights)
+            .ok()
+            .map(|dist| dist.sample(rng)),
+        None if n == 0 => None,
+        None => Some(rng.gen_range(0..n)),
+    }
+}
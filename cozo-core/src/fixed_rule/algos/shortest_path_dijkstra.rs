@@ -22,7 +22,10 @@ use smartstring::{LazyCompact, SmartString};
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -152,6 +155,29 @@ impl FixedRule for ShortestPathDijkstra {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    2,
+                    "edges as `[from, to, weight?]`, with weights defaulting to 1",
+                ),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach, defaulting to all nodes")
+                    .optional(),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "keep_ties",
+                    FixedRuleOptionType::Bool,
+                    "whether to return all shortest paths when there are ties",
+                )
+                .default_value(false),
+            ],
+        })
+    }
     fn arity(
         &self,
         _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
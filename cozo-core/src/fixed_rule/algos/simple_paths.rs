@@ -0,0 +1,572 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use graph::prelude::{DirectedCsrGraph, DirectedNeighbors, Graph};
+use itertools::Itertools;
+use miette::Result;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::bellman_ford::collect_nodes;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Enumerates the elementary cycles of a directed graph by Johnson's algorithm, returning
+/// each cycle once as `[cycle]`, a list of nodes without the first node repeated at the end.
+///
+/// With `max_len`, only cycles of at most that many edges are returned. Johnson's blocking
+/// scheme does not hold under a length bound, so a depth-limited search is used instead.
+/// With `limit`, enumeration stops after that many cycles.
+pub(crate) struct SimpleCycles;
+
+impl FixedRule for SimpleCycles {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let max_len = payload.pos_integer_option("max_len", None).ok();
+        let limit = payload.pos_integer_option("limit", None).ok();
+
+        let (graph, indices, _) = edges.as_directed_graph(false)?;
+        let adj = adjacency(&graph);
+        let mut finder = CycleFinder::new(&adj, &poison);
+        let mut emitted = 0;
+        finder.run(max_len, |cycle| {
+            out.put(vec![DataValue::List(
+                cycle
+                    .iter()
+                    .map(|node| indices[*node as usize].clone())
+                    .collect_vec(),
+            )]);
+            emitted += 1;
+            limit.is_none_or(|limit| emitted < limit)
+        })
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`")],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "max_len",
+                    FixedRuleOptionType::PosInt,
+                    "longest cycle to return",
+                ),
+                FixedRuleOptionSpec::new(
+                    "limit",
+                    FixedRuleOptionType::PosInt,
+                    "stop after this many results",
+                ),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(1)
+    }
+}
+
+/// Enumerates the simple paths from every starting node to every goal, returning
+/// `[start, goal, path]`. A path visits no node twice, so a start is never its own goal.
+///
+/// With `max_len`, only paths of at most that many edges are returned.
+/// With `limit`, at most that many paths are returned for each starting node.
+pub(crate) struct AllSimplePaths;
+
+impl FixedRule for AllSimplePaths {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let edges = payload.get_input(0)?;
+        let starting = payload.get_input(1)?;
+        let goals = payload.get_input(2)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let max_len = payload.pos_integer_option("max_len", None).ok();
+        let limit = payload.pos_integer_option("limit", None).ok();
+
+        let (graph, indices, inv_indices) = edges.as_directed_graph(undirected)?;
+        let adj = adjacency(&graph);
+        let starting_nodes = collect_nodes(&starting, &inv_indices)?;
+        let goal_nodes = collect_nodes(&goals, &inv_indices)?;
+
+        // nodes from which no goal can be reached are never worth entering
+        let n = adj.len();
+        let mut is_goal = vec![false; n];
+        let mut reaches_goal = vec![false; n];
+        let mut stack = vec![];
+        for goal in goal_nodes {
+            is_goal[goal as usize] = true;
+            reaches_goal[goal as usize] = true;
+            stack.push(goal);
+        }
+        while let Some(node) = stack.pop() {
+            for prev in graph.in_neighbors(node) {
+                if !reaches_goal[*prev as usize] {
+                    reaches_goal[*prev as usize] = true;
+                    stack.push(*prev);
+                }
+            }
+        }
+
+        let mut on_path = vec![false; n];
+        let mut steps = 0usize;
+        for start in starting_nodes {
+            if !reaches_goal[start as usize] {
+                continue;
+            }
+            let mut emitted = 0;
+            let mut path = vec![start];
+            let mut frames: Vec<(u32, usize)> = vec![(start, 0)];
+            on_path[start as usize] = true;
+            'search: while let Some((node, pos)) = frames.last_mut() {
+                let node = *node;
+                let too_long = max_len.is_some_and(|max_len| path.len() > max_len);
+                if too_long || *pos >= adj[node as usize].len() {
+                    frames.pop();
+                    path.pop();
+                    on_path[node as usize] = false;
+                    continue;
+                }
+                let next = adj[node as usize][*pos];
+                *pos += 1;
+                if on_path[next as usize] || !reaches_goal[next as usize] {
+                    continue;
+                }
+                path.push(next);
+                if is_goal[next as usize] {
+                    out.put(vec![
+                        indices[start as usize].clone(),
+                        indices[next as usize].clone(),
+                        DataValue::List(
+                            path.iter()
+                                .map(|node| indices[*node as usize].clone())
+                                .collect_vec(),
+                        ),
+                    ]);
+                    emitted += 1;
+                    if limit.is_some_and(|limit| emitted >= limit) {
+                        for node in path.drain(..) {
+                            on_path[node as usize] = false;
+                        }
+                        break 'search;
+                    }
+                }
+                on_path[next as usize] = true;
+                frames.push((next, 0));
+
+                steps += 1;
+                if steps % 1024 == 0 {
+                    poison.check()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new("edges", 2, "edges as `[from, to]`"),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach"),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "max_len",
+                    FixedRuleOptionType::PosInt,
+                    "longest path to return, in edges",
+                ),
+                FixedRuleOptionSpec::new(
+                    "limit",
+                    FixedRuleOptionType::PosInt,
+                    "stop after this many paths from each starting node",
+                ),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(3)
+    }
+}
+
+/// Sorted out-neighbours without duplicates, as parallel edges give no new paths.
+fn adjacency(graph: &DirectedCsrGraph<u32>) -> Vec<Vec<u32>> {
+    (0..graph.node_count())
+        .map(|node| {
+            graph
+                .out_neighbors(node)
+                .copied()
+                .sorted()
+                .dedup()
+                .collect()
+        })
+        .collect()
+}
+
+struct CycleFinder<'a> {
+    adj: &'a [Vec<u32>],
+    poison: &'a Poison,
+    /// Whether a node is in the strongly connected component of the current start node,
+    /// within the subgraph induced by the start node and the nodes after it
+    in_component: Vec<bool>,
+    blocked: Vec<bool>,
+    blocked_by: Vec<BTreeSet<u32>>,
+    closed: Vec<bool>,
+    steps: usize,
+}
+
+impl<'a> CycleFinder<'a> {
+    fn new(adj: &'a [Vec<u32>], poison: &'a Poison) -> Self {
+        let n = adj.len();
+        Self {
+            adj,
+            poison,
+            in_component: vec![false; n],
+            blocked: vec![false; n],
+            blocked_by: vec![Default::default(); n],
+            closed: vec![false; n],
+            steps: 0,
+        }
+    }
+
+    /// Calls `emit` with every cycle, stopping early once it returns `false`.
+    /// Each cycle is found from its node with the smallest index only.
+    fn run(&mut self, max_len: Option<usize>, mut emit: impl FnMut(&[u32]) -> bool) -> Result<()> {
+        let n = self.adj.len() as u32;
+        for start in 0..n {
+            let component = self.component_of(start);
+            for node in &component {
+                self.in_component[*node as usize] = true;
+            }
+            let go_on = match max_len {
+                None => self.circuits(start, &mut emit)?,
+                Some(max_len) => self.bounded_circuits(start, max_len, &mut emit)?,
+            };
+            for node in component {
+                self.in_component[node as usize] = false;
+                self.blocked[node as usize] = false;
+                self.blocked_by[node as usize].clear();
+                self.closed[node as usize] = false;
+            }
+            if !go_on {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// The nodes not before `start` that both reach and are reached from it.
+    fn component_of(&self, start: u32) -> Vec<u32> {
+        let n = self.adj.len();
+        let mut forward = vec![false; n];
+        forward[start as usize] = true;
+        let mut stack = vec![start];
+        let mut reverse_adj: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+        while let Some(node) = stack.pop() {
+            for next in &self.adj[node as usize] {
+                if *next < start {
+                    continue;
+                }
+                reverse_adj.entry(*next).or_default().push(node);
+                if !forward[*next as usize] {
+                    forward[*next as usize] = true;
+                    stack.push(*next);
+                }
+            }
+        }
+        // every node on a path from a reached node back to `start` is itself reached,
+        // so the reverse edges among reached nodes are all the backward search needs
+        let mut component = vec![start];
+        let mut backward = BTreeSet::from([start]);
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for prev in reverse_adj.get(&node).into_iter().flatten() {
+                if backward.insert(*prev) {
+                    component.push(*prev);
+                    stack.push(*prev);
+                }
+            }
+        }
+        component
+    }
+
+    fn check_poison(&mut self) -> Result<()> {
+        self.steps += 1;
+        if self.steps % 1024 == 0 {
+            self.poison.check()?;
+        }
+        Ok(())
+    }
+
+    /// Johnson's circuit search from `start`, written with an explicit stack.
+    fn circuits(&mut self, start: u32, emit: &mut impl FnMut(&[u32]) -> bool) -> Result<bool> {
+        let mut path = vec![start];
+        let mut frames: Vec<(u32, usize)> = vec![(start, 0)];
+        self.blocked[start as usize] = true;
+        while let Some((node, pos)) = frames.last_mut() {
+            let node = *node;
+            if let Some(next) = self.adj[node as usize].get(*pos).copied() {
+                *pos += 1;
+                if !self.in_component[next as usize] {
+                    continue;
+                }
+                if next == start {
+                    if !emit(&path) {
+                        return Ok(false);
+                    }
+                    for node in &path {
+                        self.closed[*node as usize] = true;
+                    }
+                } else if !self.blocked[next as usize] {
+                    path.push(next);
+                    frames.push((next, 0));
+                    self.closed[next as usize] = false;
+                    self.blocked[next as usize] = true;
+                    self.check_poison()?;
+                }
+                continue;
+            }
+            if self.closed[node as usize] {
+                self.unblock(node);
+            } else {
+                for next in &self.adj[node as usize] {
+                    if self.in_component[*next as usize] {
+                        self.blocked_by[*next as usize].insert(node);
+                    }
+                }
+            }
+            frames.pop();
+            path.pop();
+        }
+        Ok(true)
+    }
+
+    fn unblock(&mut self, node: u32) {
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if self.blocked[node as usize] {
+                self.blocked[node as usize] = false;
+                stack.extend(std::mem::take(&mut self.blocked_by[node as usize]));
+            }
+        }
+    }
+
+    /// Depth-limited search for the cycles through `start` of at most `max_len` edges.
+    fn bounded_circuits(
+        &mut self,
+        start: u32,
+        max_len: usize,
+        emit: &mut impl FnMut(&[u32]) -> bool,
+    ) -> Result<bool> {
+        let mut path = vec![start];
+        let mut frames: Vec<(u32, usize)> = vec![(start, 0)];
+        // `blocked` marks the nodes on the current path here
+        self.blocked[start as usize] = true;
+        while let Some((node, pos)) = frames.last_mut() {
+            let node = *node;
+            let next = match self.adj[node as usize].get(*pos) {
+                Some(next) => *next,
+                None => {
+                    frames.pop();
+                    path.pop();
+                    self.blocked[node as usize] = false;
+                    continue;
+                }
+            };
+            *pos += 1;
+            if !self.in_component[next as usize] {
+                continue;
+            }
+            if next == start {
+                if !emit(&path) {
+                    return Ok(false);
+                }
+            } else if !self.blocked[next as usize] && path.len() < max_len {
+                path.push(next);
+                frames.push((next, 0));
+                self.blocked[next as usize] = true;
+                self.check_poison()?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_simple_cycles() {
+        let db = DbInstance::default();
+        let edges = r#"
+        edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['b', 'a'], ['c', 'd'], ['d', 'd'],
+                    ['d', 'e']]
+        "#;
+        let res = db
+            .run_default(&format!("{edges} ?[cycle] <~ SimpleCycles(edges[])"))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([[["a", "b"]], [["a", "b", "c"]], [["d"]]])
+        );
+
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[cycle] <~ SimpleCycles(edges[], max_len: 2)"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([[["a", "b"]], [["d"]]])
+        );
+
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[cycle] <~ SimpleCycles(edges[], limit: 1)"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_all_simple_paths() {
+        let db = DbInstance::default();
+        let edges = r#"
+        edges[] <- [['a', 'b'], ['a', 'c'], ['b', 'c'], ['c', 'd'], ['b', 'd'], ['d', 'a']]
+        start[] <- [['a']]
+        goal[] <- [['d']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, path] <~ AllSimplePaths(edges[], start[], goal[])"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([
+                ["a", "d", ["a", "b", "c", "d"]],
+                ["a", "d", ["a", "b", "d"]],
+                ["a", "d", ["a", "c", "d"]]
+            ])
+        );
+
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, path] <~ AllSimplePaths(edges[], start[], goal[], max_len: 2)"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 2);
+
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, path] <~ AllSimplePaths(edges[], start[], goal[], limit: 1)"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_simple_paths_edge_cases() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default("edges[a, b] <- [] ?[cycle] <~ SimpleCycles(edges[])")
+            .unwrap();
+        assert!(res.rows.is_empty());
+        let res = db
+            .run_default(
+                r#"
+                edges[a, b] <- []
+                start[] <- [['a']]
+                goal[] <- [['b']]
+                ?[s, t, path] <~ AllSimplePaths(edges[], start[], goal[])
+                "#,
+            )
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        // a self-loop is a cycle but never part of a simple path, a start is not its own goal,
+        // and 'x' cannot reach 'c'
+        let edges = r#"
+        edges[] <- [['a', 'a'], ['a', 'b'], ['b', 'c'], ['x', 'y']]
+        start[] <- [['a'], ['x'], ['nowhere']]
+        goal[] <- [['a'], ['c']]
+        "#;
+        let res = db
+            .run_default(&format!("{edges} ?[cycle] <~ SimpleCycles(edges[])"))
+            .unwrap();
+        assert_eq!(res.into_json()["rows"], serde_json::json!([[["a"]]]));
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, path] <~ AllSimplePaths(edges[], start[], goal[])"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "c", ["a", "b", "c"]]])
+        );
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, path] <~ AllSimplePaths(edges[], start[], goal[], max_len: 1)"
+            ))
+            .unwrap();
+        assert!(res.rows.is_empty());
+        // going back along the edges reaches the start from the goal
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, path] <~ AllSimplePaths(edges[], goal[], start[], undirected: true)"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["c", "a", ["c", "b", "a"]]])
+        );
+
+        for opts in ["max_len: 0", "limit: 0", "limit: 'all'", "max_depth: 2"] {
+            assert!(
+                db.run_default(&format!(
+                    "{edges} ?[cycle] <~ SimpleCycles(edges[], {opts})"
+                ))
+                .is_err(),
+                "{opts}"
+            );
+            assert!(
+                db.run_default(&format!(
+                    "{edges} ?[s, t, path] <~ AllSimplePaths(edges[], start[], goal[], {opts})"
+                ))
+                .is_err(),
+                "{opts}"
+            );
+        }
+    }
+}
@@ -0,0 +1,569 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
+
+use miette::{bail, ensure, Diagnostic, Result};
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputRelation, FixedRuleInputSpec, FixedRuleOptionSpec,
+    FixedRuleOptionType, FixedRulePayload, FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Finds the embeddings of a small pattern graph in the data graph, returning one row per
+/// embedding with the data nodes matched to the pattern nodes, in ascending order of the
+/// pattern nodes.
+///
+/// The inputs are the data edges `[from, to, label?]`, the pattern edges `[from, to, label?]`
+/// and optionally the data node labels `[node, label]` and the pattern node labels
+/// `[node, label]`. A pattern edge without a label, or with a null label, matches any edge.
+/// A pattern node matches only data nodes carrying all of its labels.
+///
+/// In the `'isomorphism'` mode, distinct pattern nodes are matched to distinct data nodes,
+/// though the data nodes may have more edges among them than the pattern. In the
+/// `'homomorphism'` mode, pattern nodes may share data nodes.
+pub(crate) struct SubgraphMatch;
+
+impl FixedRule for SubgraphMatch {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let data_edges = payload.get_input(0)?.ensure_min_len(2)?;
+        let pattern_edges = payload.get_input(1)?.ensure_min_len(2)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let injective = match &payload.string_option("mode", Some("isomorphism"))? as &str {
+            "isomorphism" => true,
+            "homomorphism" => false,
+            _ => bail!(WrongFixedRuleOptionError {
+                name: "mode".to_string(),
+                span: payload.option_span("mode")?,
+                rule_name: payload.name().to_string(),
+                help: "mode must be either 'isomorphism' or 'homomorphism'".to_string(),
+            }),
+        };
+        let limit = payload.pos_integer_option("limit", None).ok();
+
+        let mut data = DataGraph::default();
+        for tuple in data_edges.iter()? {
+            let tuple = tuple?;
+            let from = data.intern(&tuple[0]);
+            let to = data.intern(&tuple[1]);
+            let label = tuple.get(2).cloned();
+            data.add_edge(from, to, label.clone());
+            if undirected {
+                data.add_edge(to, from, label);
+            }
+        }
+        if let Ok(labels) = payload.get_input(2) {
+            for tuple in labels.ensure_min_len(2)?.iter()? {
+                let tuple = tuple?;
+                let node = data.intern(&tuple[0]);
+                data.labels[node as usize].insert(tuple[1].clone());
+            }
+        }
+        let pattern = Pattern::new(&pattern_edges, payload.get_input(3).ok(), undirected)?;
+
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("The pattern has {0} nodes, but the rule head has {1} columns")]
+        #[diagnostic(code(algo::pattern_size_mismatch))]
+        #[diagnostic(help("The rule head needs one column for each pattern node"))]
+        struct PatternSizeMismatch(usize, usize, #[label] SourceSpan);
+
+        ensure!(
+            pattern.nodes.len() == payload.manifest.arity,
+            PatternSizeMismatch(pattern.nodes.len(), payload.manifest.arity, payload.span())
+        );
+
+        let candidates = data.candidates(&pattern, injective, &poison)?;
+        let mut emitted = 0;
+        let mut matcher = Matcher::new(
+            &data,
+            &pattern,
+            candidates,
+            injective,
+            &poison,
+            |mapping: &[u32]| {
+                out.put(
+                    mapping
+                        .iter()
+                        .map(|node| data.indices[*node as usize].clone())
+                        .collect(),
+                );
+                emitted += 1;
+                limit.is_none_or(|limit| emitted < limit)
+            },
+        );
+        matcher.search(0)?;
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new("edges", 2, "edges of the data graph as `[from, to, label?]`"),
+                FixedRuleInputSpec::new(
+                    "pattern",
+                    2,
+                    "edges of the pattern as `[from, to, label?]`, where a null label matches any label",
+                ),
+                FixedRuleInputSpec::new("labels", 2, "labels of the data nodes as `[node, label]`")
+                    .optional(),
+                FixedRuleInputSpec::new(
+                    "pattern_labels",
+                    2,
+                    "labels of the pattern nodes as `[node, label]`",
+                )
+                .optional(),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "mode",
+                    FixedRuleOptionType::String,
+                    "either `'isomorphism'` or `'homomorphism'`",
+                )
+                .default_value("isomorphism"),
+                FixedRuleOptionSpec::new(
+                    "limit",
+                    FixedRuleOptionType::PosInt,
+                    "stop after this many results",
+                ),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        rule_head: &[Symbol],
+        span: SourceSpan,
+    ) -> Result<usize> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("SubgraphMatch requires an explicit rule head")]
+        #[diagnostic(code(algo::pattern_no_head))]
+        #[diagnostic(help("Give one variable in the head for each pattern node"))]
+        struct SubgraphMatchNoHead(#[label] SourceSpan);
+
+        ensure!(!rule_head.is_empty(), SubgraphMatchNoHead(span));
+        Ok(rule_head.len())
+    }
+}
+
+#[derive(Default)]
+struct DataGraph {
+    indices: Vec<DataValue>,
+    inv_indices: BTreeMap<DataValue, u32>,
+    /// For each node, its successors with the labels of the edges to them
+    out_edges: Vec<BTreeMap<u32, Vec<DataValue>>>,
+    /// For each node, its predecessors with the labels of the edges from them
+    in_edges: Vec<BTreeMap<u32, Vec<DataValue>>>,
+    labels: Vec<BTreeSet<DataValue>>,
+}
+
+impl DataGraph {
+    fn intern(&mut self, node: &DataValue) -> u32 {
+        if let Some(idx) = self.inv_indices.get(node) {
+            return *idx;
+        }
+        let idx = self.indices.len() as u32;
+        self.inv_indices.insert(node.clone(), idx);
+        self.indices.push(node.clone());
+        self.out_edges.push(Default::default());
+        self.in_edges.push(Default::default());
+        self.labels.push(Default::default());
+        idx
+    }
+
+    fn add_edge(&mut self, from: u32, to: u32, label: Option<DataValue>) {
+        let out_labels = self.out_edges[from as usize].entry(to).or_default();
+        let in_labels = self.in_edges[to as usize].entry(from).or_default();
+        if let Some(label) = label {
+            out_labels.push(label.clone());
+            in_labels.push(label);
+        }
+    }
+
+    fn has_edge(&self, from: u32, to: u32, label: &Option<DataValue>) -> bool {
+        match self.out_edges[from as usize].get(&to) {
+            None => false,
+            Some(labels) => label_matches(labels, label),
+        }
+    }
+
+    /// The data nodes each pattern node may be matched to. Nodes with the wrong labels or
+    /// too few neighbours are dropped first, then nodes lacking a candidate neighbour for some
+    /// pattern edge are dropped until nothing changes.
+    fn candidates(
+        &self,
+        pattern: &Pattern,
+        injective: bool,
+        poison: &Poison,
+    ) -> Result<Vec<Vec<bool>>> {
+        let n = self.indices.len();
+        let distinct_neighbours = |edges: &BTreeMap<u32, Vec<DataValue>>, node: u32| {
+            edges.len() - usize::from(edges.contains_key(&node))
+        };
+        let mut candidates = (0..pattern.nodes.len())
+            .map(|p| {
+                (0..n as u32)
+                    .map(|c| {
+                        let i = c as usize;
+                        pattern.labels[p].is_subset(&self.labels[i])
+                            && (!injective
+                                || (distinct_neighbours(&self.out_edges[i], c)
+                                    >= pattern.out_degree[p]
+                                    && distinct_neighbours(&self.in_edges[i], c)
+                                        >= pattern.in_degree[p]))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (p, q, label) in &pattern.edges {
+                let (p, q) = (*p, *q);
+                for c in 0..n as u32 {
+                    if !candidates[p][c as usize] {
+                        continue;
+                    }
+                    let supported = self.out_edges[c as usize].iter().any(|(d, labels)| {
+                        candidates[q][*d as usize]
+                            && endpoints_allowed(p, q, c, *d, injective)
+                            && label_matches(labels, label)
+                    });
+                    if !supported {
+                        candidates[p][c as usize] = false;
+                        changed = true;
+                    }
+                }
+                for d in 0..n as u32 {
+                    if !candidates[q][d as usize] {
+                        continue;
+                    }
+                    let supported = self.in_edges[d as usize].iter().any(|(c, labels)| {
+                        candidates[p][*c as usize]
+                            && endpoints_allowed(p, q, *c, d, injective)
+                            && label_matches(labels, label)
+                    });
+                    if !supported {
+                        candidates[q][d as usize] = false;
+                        changed = true;
+                    }
+                }
+                poison.check()?;
+            }
+        }
+        Ok(candidates)
+    }
+}
+
+fn label_matches(labels: &[DataValue], label: &Option<DataValue>) -> bool {
+    match label {
+        None => true,
+        Some(label) => labels.contains(label),
+    }
+}
+
+/// Whether the pattern edge from `p` to `q` may be matched to the data edge from `c` to `d`,
+/// ignoring labels: a self-loop must be matched to a self-loop, and under isomorphism
+/// nothing else may be.
+fn endpoints_allowed(p: usize, q: usize, c: u32, d: u32, injective: bool) -> bool {
+    if p == q {
+        c == d
+    } else {
+        !injective || c != d
+    }
+}
+
+struct Pattern {
+    nodes: Vec<DataValue>,
+    /// Each edge as `(from, to, label)`, with both directions present if undirected
+    edges: Vec<(usize, usize, Option<DataValue>)>,
+    labels: Vec<BTreeSet<DataValue>>,
+    /// Numbers of distinct neighbours other than the node itself
+    out_degree: Vec<usize>,
+    in_degree: Vec<usize>,
+}
+
+impl Pattern {
+    fn new(
+        edges: &FixedRuleInputRelation<'_, '_>,
+        labels: Option<FixedRuleInputRelation<'_, '_>>,
+        undirected: bool,
+    ) -> Result<Self> {
+        let mut raw_edges = vec![];
+        for tuple in edges.iter()? {
+            let tuple = tuple?;
+            let label = tuple.get(2).filter(|l| **l != DataValue::Null).cloned();
+            raw_edges.push((tuple[0].clone(), tuple[1].clone(), label));
+        }
+        let mut raw_labels = vec![];
+        if let Some(labels) = labels {
+            for tuple in labels.ensure_min_len(2)?.iter()? {
+                let tuple = tuple?;
+                raw_labels.push((tuple[0].clone(), tuple[1].clone()));
+            }
+        }
+
+        let nodes: BTreeSet<DataValue> = raw_edges
+            .iter()
+            .flat_map(|(from, to, _)| [from.clone(), to.clone()])
+            .chain(raw_labels.iter().map(|(node, _)| node.clone()))
+            .collect();
+        let nodes = nodes.into_iter().collect::<Vec<_>>();
+        let idx = |node: &DataValue| nodes.binary_search(node).unwrap();
+
+        let mut pattern_edges = vec![];
+        for (from, to, label) in &raw_edges {
+            pattern_edges.push((idx(from), idx(to), label.clone()));
+            if undirected {
+                pattern_edges.push((idx(to), idx(from), label.clone()));
+            }
+        }
+        let mut pattern_labels = vec![BTreeSet::new(); nodes.len()];
+        for (node, label) in raw_labels {
+            pattern_labels[idx(&node)].insert(label);
+        }
+        let mut out_neighbours = vec![BTreeSet::new(); nodes.len()];
+        let mut in_neighbours = vec![BTreeSet::new(); nodes.len()];
+        for (from, to, _) in &pattern_edges {
+            if from != to {
+                out_neighbours[*from].insert(*to);
+                in_neighbours[*to].insert(*from);
+            }
+        }
+        Ok(Self {
+            edges: pattern_edges,
+            labels: pattern_labels,
+            out_degree: out_neighbours.iter().map(|s| s.len()).collect(),
+            in_degree: in_neighbours.iter().map(|s| s.len()).collect(),
+            nodes,
+        })
+    }
+}
+
+/// Backtracking over the pattern nodes in a fixed order, in which each node after the first
+/// of its connected component is adjacent to an earlier one. The candidates for such a node
+/// are then drawn from the neighbours of the data node already matched to that earlier node.
+struct Matcher<'a, F> {
+    data: &'a DataGraph,
+    pattern: &'a Pattern,
+    candidates: Vec<Vec<bool>>,
+    injective: bool,
+    poison: &'a Poison,
+    order: Vec<usize>,
+    /// For each position in the order, an earlier pattern node with an edge to (`true`) or
+    /// from (`false`) the node at that position
+    anchors: Vec<Option<(usize, bool)>>,
+    /// For each pattern node, the indices of the pattern edges touching it
+    incident: Vec<Vec<usize>>,
+    mapping: Vec<u32>,
+    used: Vec<bool>,
+    steps: usize,
+    /// Called with every embedding found, returning `false` to stop the search
+    emit: F,
+}
+
+impl<'a, F: FnMut(&[u32]) -> bool> Matcher<'a, F> {
+    fn new(
+        data: &'a DataGraph,
+        pattern: &'a Pattern,
+        candidates: Vec<Vec<bool>>,
+        injective: bool,
+        poison: &'a Poison,
+        emit: F,
+    ) -> Self {
+        let k = pattern.nodes.len();
+        let mut incident = vec![vec![]; k];
+        for (i, (from, to, _)) in pattern.edges.iter().enumerate() {
+            incident[*from].push(i);
+            if from != to {
+                incident[*to].push(i);
+            }
+        }
+
+        // most constrained first: the node with the most edges to nodes already placed,
+        // then the one with the fewest candidates
+        let candidate_counts = candidates
+            .iter()
+            .map(|c| c.iter().filter(|x| **x).count())
+            .collect::<Vec<_>>();
+        let mut placed = vec![false; k];
+        let mut order = vec![];
+        let mut anchors = vec![];
+        while order.len() < k {
+            let connections = |p: usize| {
+                incident[p]
+                    .iter()
+                    .filter(|e| {
+                        let (from, to, _) = &pattern.edges[**e];
+                        placed[*from] || placed[*to]
+                    })
+                    .count()
+            };
+            let next = (0..k)
+                .filter(|p| !placed[*p])
+                .max_by_key(|p| (connections(*p), Reverse(candidate_counts[*p]), Reverse(*p)))
+                .unwrap();
+            let anchor = incident[next].iter().find_map(|e| {
+                let (from, to, _) = &pattern.edges[*e];
+                if *to == next && *from != next && placed[*from] {
+                    Some((*from, true))
+                } else if *from == next && *to != next && placed[*to] {
+                    Some((*to, false))
+                } else {
+                    None
+                }
+            });
+            placed[next] = true;
+            order.push(next);
+            anchors.push(anchor);
+        }
+
+        Self {
+            data,
+            pattern,
+            candidates,
+            injective,
+            poison,
+            order,
+            anchors,
+            incident,
+            mapping: vec![u32::MAX; k],
+            used: vec![false; data.indices.len()],
+            steps: 0,
+            emit,
+        }
+    }
+
+    /// Matches the pattern nodes from position `depth` of the order onwards.
+    /// Returns `false` once the search should stop.
+    fn search(&mut self, depth: usize) -> Result<bool> {
+        if depth == self.order.len() {
+            let mapping = self.mapping.clone();
+            return Ok((self.emit)(&mapping));
+        }
+        let p = self.order[depth];
+        let pool: Vec<u32> = match self.anchors[depth] {
+            Some((q, outgoing)) => {
+                let image = self.mapping[q] as usize;
+                let neighbours = if outgoing {
+                    &self.data.out_edges[image]
+                } else {
+                    &self.data.in_edges[image]
+                };
+                neighbours.keys().copied().collect()
+            }
+            None => (0..self.data.indices.len() as u32).collect(),
+        };
+        for c in pool {
+            if !self.candidates[p][c as usize] || (self.injective && self.used[c as usize]) {
+                continue;
+            }
+            self.mapping[p] = c;
+            if self.consistent(p) {
+                self.used[c as usize] = true;
+                let go_on = self.search(depth + 1)?;
+                self.used[c as usize] = false;
+                if !go_on {
+                    self.mapping[p] = u32::MAX;
+                    return Ok(false);
+                }
+            }
+            self.mapping[p] = u32::MAX;
+
+            self.steps += 1;
+            if self.steps % 1024 == 0 {
+                self.poison.check()?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Whether the edges between `p` and the pattern nodes already matched are all present.
+    fn consistent(&self, p: usize) -> bool {
+        self.incident[p].iter().all(|e| {
+            let (from, to, label) = &self.pattern.edges[*e];
+            let (from, to) = (self.mapping[*from], self.mapping[*to]);
+            from == u32::MAX || to == u32::MAX || self.data.has_edge(from, to, label)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_subgraph_match() {
+        let db = DbInstance::default();
+        let data = r#"
+        edges[] <- [['a', 'b', 'knows'], ['b', 'c', 'knows'], ['c', 'a', 'knows'],
+                    ['c', 'd', 'owns'], ['a', 'd', 'knows'], ['d', 'd', 'knows']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                r#"{data}
+                pattern[] <- [[1, 2, 'knows'], [2, 3, 'knows'], [3, 1, 'knows']]
+                ?[x, y, z] <~ SubgraphMatch(edges[], pattern[])
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "b", "c"], ["b", "c", "a"], ["c", "a", "b"]])
+        );
+
+        let res = db
+            .run_default(&format!(
+                r#"{data}
+                pattern[] <- [[1, 2, null], [2, 3, 'owns']]
+                ?[x, y, z] <~ SubgraphMatch(edges[], pattern[], limit: 1)
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["b", "c", "d"]])
+        );
+
+        // an edge from a node to itself matches the self-loop on 'd' only as a homomorphism
+        let pattern = "pattern[] <- [[1, 2, 'knows']]";
+        let res = db
+            .run_default(&format!(
+                "{data} {pattern} ?[x, y] <~ SubgraphMatch(edges[], pattern[], mode: 'homomorphism')"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 5);
+        let res = db
+            .run_default(&format!(
+                "{data} {pattern} ?[x, y] <~ SubgraphMatch(edges[], pattern[])"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 4);
+
+        let res = db.run_default(&format!(
+            "{data} {pattern} ?[x] <~ SubgraphMatch(edges[], pattern[])"
+        ));
+        assert!(res.is_err());
+    }
+}
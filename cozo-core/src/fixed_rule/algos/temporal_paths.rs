@@ -0,0 +1,708 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops::Range;
+
+use itertools::Itertools;
+use miette::{bail, Diagnostic, Result};
+use rayon::prelude::*;
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::data::tuple::Tuple;
+use crate::data::value::DataValue;
+use crate::fixed_rule::algos::bellman_ford::collect_nodes;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+// The three rules below share their inputs: the edges `[from, to, t_start, t_end]`, where an
+// edge leaves `from` at `t_start` and reaches `to` at `t_end`, the starting nodes and
+// optionally the goals. A path respects time if each edge leaves no earlier than the previous
+// one arrives. If `t_end` is missing, edges arrive when they leave. The options `start_time`
+// and `end_time` restrict paths to edges within that window, and `undirected` lets every edge
+// be traversed both ways. Only goals that can be reached are reported.
+//
+// To read a relation with validity as of some time, pass it through a rule using time travel.
+
+/// Returns `[start, goal, arrival, path]` for the earliest arrival at each goal.
+pub(crate) struct TemporalEarliestArrival;
+
+/// Returns `[start, goal, departure, path]` for the latest departure from each start that
+/// still reaches the goal.
+pub(crate) struct TemporalLatestDeparture;
+
+/// Returns `[start, goal, duration, path]` for the path taking the least time between leaving
+/// the start and arriving at the goal, waiting at the start not included.
+pub(crate) struct TemporalFastestPath;
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("The edge times {0:?} and {1:?} cannot be interpreted as a departure and an arrival")]
+#[diagnostic(code(algo::invalid_edge_time))]
+#[diagnostic(help(
+    "Edge times must be finite numbers, and an edge cannot arrive before it departs"
+))]
+struct BadEdgeTimeError(DataValue, DataValue, #[label] SourceSpan);
+
+struct TemporalEdge {
+    from: u32,
+    to: u32,
+    depart: f64,
+    arrive: f64,
+}
+
+struct TemporalGraph {
+    indices: Vec<DataValue>,
+    inv_indices: BTreeMap<DataValue, u32>,
+    /// Sorted by departure
+    edges: Vec<TemporalEdge>,
+}
+
+struct TemporalInputs {
+    graph: TemporalGraph,
+    starting: BTreeSet<u32>,
+    goals: Option<BTreeSet<u32>>,
+}
+
+impl TemporalInputs {
+    fn new(payload: &FixedRulePayload<'_, '_>) -> Result<Self> {
+        let edges = payload.get_input(0)?.ensure_min_len(3)?;
+        let undirected = payload.bool_option("undirected", Some(false))?;
+        let start_time = payload.float_option("start_time", Some(f64::NEG_INFINITY))?;
+        let end_time = payload.float_option("end_time", Some(f64::INFINITY))?;
+
+        let mut graph = TemporalGraph {
+            indices: vec![],
+            inv_indices: Default::default(),
+            edges: vec![],
+        };
+        for tuple in edges.iter()? {
+            let tuple = tuple?;
+            let depart_value = &tuple[2];
+            let arrive_value = tuple.get(3).unwrap_or(depart_value);
+            let (depart, arrive) = match (depart_value.get_float(), arrive_value.get_float()) {
+                (Some(d), Some(a)) if d.is_finite() && a.is_finite() && d <= a => (d, a),
+                _ => bail!(BadEdgeTimeError(
+                    depart_value.clone(),
+                    arrive_value.clone(),
+                    edges.span()
+                )),
+            };
+            if depart < start_time || arrive > end_time {
+                continue;
+            }
+            let from = graph.intern(&tuple[0]);
+            let to = graph.intern(&tuple[1]);
+            graph.edges.push(TemporalEdge {
+                from,
+                to,
+                depart,
+                arrive,
+            });
+            if undirected {
+                graph.edges.push(TemporalEdge {
+                    from: to,
+                    to: from,
+                    depart,
+                    arrive,
+                });
+            }
+        }
+        graph.edges.sort_by(|a, b| {
+            a.depart
+                .total_cmp(&b.depart)
+                .then(a.arrive.total_cmp(&b.arrive))
+        });
+
+        let starting = collect_nodes(&payload.get_input(1)?, &graph.inv_indices)?;
+        let goals = match payload.get_input(2) {
+            Err(_) => None,
+            Ok(goals) => Some(collect_nodes(&goals, &graph.inv_indices)?),
+        };
+        Ok(Self {
+            graph,
+            starting,
+            goals,
+        })
+    }
+
+    fn is_goal(&self, node: u32) -> bool {
+        match &self.goals {
+            None => true,
+            Some(goals) => goals.contains(&node),
+        }
+    }
+
+    fn row(&self, start: u32, goal: u32, value: f64, path: &[u32]) -> Tuple {
+        vec![
+            self.graph.indices[start as usize].clone(),
+            self.graph.indices[goal as usize].clone(),
+            DataValue::from(value),
+            DataValue::List(
+                path.iter()
+                    .map(|node| self.graph.indices[*node as usize].clone())
+                    .collect_vec(),
+            ),
+        ]
+    }
+}
+
+impl TemporalGraph {
+    fn intern(&mut self, node: &DataValue) -> u32 {
+        if let Some(idx) = self.inv_indices.get(node) {
+            return *idx;
+        }
+        let idx = self.indices.len() as u32;
+        self.inv_indices.insert(node.clone(), idx);
+        self.indices.push(node.clone());
+        idx
+    }
+
+    /// Follows the edges chosen for each node back to `start`, giving the path from it.
+    /// `via[node]` is the index of the edge by which `node` is reached.
+    fn path_to(&self, start: u32, goal: u32, via: &[usize]) -> Vec<u32> {
+        let mut path = vec![goal];
+        let mut current = goal;
+        while current != start {
+            current = self.edges[via[current as usize]].from;
+            path.push(current);
+        }
+        path.reverse();
+        path
+    }
+}
+
+/// Splits `0..len` into the maximal ranges over which `time` is constant.
+fn same_time_groups(len: usize, time: impl Fn(usize) -> f64) -> Vec<Range<usize>> {
+    let mut groups = vec![];
+    let mut start = 0;
+    for i in 1..=len {
+        if i == len || time(i) != time(start) {
+            groups.push(start..i);
+            start = i;
+        }
+    }
+    groups
+}
+
+impl FixedRule for TemporalEarliestArrival {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let inputs = TemporalInputs::new(&payload)?;
+        let graph = &inputs.graph;
+        let n = graph.indices.len();
+        let groups = same_time_groups(graph.edges.len(), |i| graph.edges[i].depart);
+        let rows: Vec<Vec<Tuple>> = inputs
+            .starting
+            .par_iter()
+            .map(|start| -> Result<Vec<Tuple>> {
+                let start = *start;
+                // a single pass over the edges in order of departure: a node can only be
+                // left after it is reached, and it is never reached earlier by a later edge
+                let mut arrival = vec![f64::INFINITY; n];
+                let mut via = vec![usize::MAX; n];
+                arrival[start as usize] = f64::NEG_INFINITY;
+                for group in &groups {
+                    // edges taking no time may enable others leaving at the same time
+                    let mut repeat = true;
+                    while repeat {
+                        repeat = false;
+                        for i in group.clone() {
+                            let edge = &graph.edges[i];
+                            if edge.depart >= arrival[edge.from as usize]
+                                && edge.arrive < arrival[edge.to as usize]
+                            {
+                                arrival[edge.to as usize] = edge.arrive;
+                                via[edge.to as usize] = i;
+                                repeat |= edge.arrive == edge.depart;
+                            }
+                        }
+                    }
+                }
+                poison.check()?;
+                Ok((0..n as u32)
+                    .filter(|node| {
+                        *node != start
+                            && arrival[*node as usize].is_finite()
+                            && inputs.is_goal(*node)
+                    })
+                    .map(|node| {
+                        inputs.row(
+                            start,
+                            node,
+                            arrival[node as usize],
+                            &graph.path_to(start, node, &via),
+                        )
+                    })
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+        for row in rows.into_iter().flatten() {
+            out.put(row);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    3,
+                    "edges as `[from, to, start, end?]`, with the end defaulting to the start",
+                ),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach, defaulting to all nodes")
+                    .optional(),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "start_time",
+                    FixedRuleOptionType::Float,
+                    "journeys cannot depart before this, unbounded by default",
+                ),
+                FixedRuleOptionSpec::new(
+                    "end_time",
+                    FixedRuleOptionType::Float,
+                    "journeys cannot arrive after this, unbounded by default",
+                ),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+impl FixedRule for TemporalLatestDeparture {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let inputs = TemporalInputs::new(&payload)?;
+        let graph = &inputs.graph;
+        let n = graph.indices.len();
+        let goals = match &inputs.goals {
+            Some(goals) => goals.iter().copied().collect_vec(),
+            None => (0..n as u32).collect_vec(),
+        };
+        let mut by_arrival = (0..graph.edges.len()).collect_vec();
+        by_arrival.sort_by(|a, b| {
+            let (a, b) = (&graph.edges[*a], &graph.edges[*b]);
+            b.arrive
+                .total_cmp(&a.arrive)
+                .then(b.depart.total_cmp(&a.depart))
+        });
+        let groups = same_time_groups(by_arrival.len(), |i| graph.edges[by_arrival[i]].arrive);
+        let rows: Vec<Vec<Tuple>> = goals
+            .into_par_iter()
+            .map(|goal| -> Result<Vec<Tuple>> {
+                // the mirror image of the earliest arrival, over edges in reverse order of arrival
+                let mut departure = vec![f64::NEG_INFINITY; n];
+                let mut next = vec![usize::MAX; n];
+                departure[goal as usize] = f64::INFINITY;
+                for group in &groups {
+                    let mut repeat = true;
+                    while repeat {
+                        repeat = false;
+                        for i in group.clone() {
+                            let i = by_arrival[i];
+                            let edge = &graph.edges[i];
+                            if edge.arrive <= departure[edge.to as usize]
+                                && edge.depart > departure[edge.from as usize]
+                            {
+                                departure[edge.from as usize] = edge.depart;
+                                next[edge.from as usize] = i;
+                                repeat |= edge.arrive == edge.depart;
+                            }
+                        }
+                    }
+                }
+                poison.check()?;
+                Ok(inputs
+                    .starting
+                    .iter()
+                    .filter(|start| **start != goal && departure[**start as usize].is_finite())
+                    .map(|start| {
+                        let mut path = vec![*start];
+                        let mut current = *start;
+                        while current != goal {
+                            current = graph.edges[next[current as usize]].to;
+                            path.push(current);
+                        }
+                        inputs.row(*start, goal, departure[*start as usize], &path)
+                    })
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+        for row in rows.into_iter().flatten() {
+            out.put(row);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    3,
+                    "edges as `[from, to, start, end?]`, with the end defaulting to the start",
+                ),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach, defaulting to all nodes")
+                    .optional(),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "start_time",
+                    FixedRuleOptionType::Float,
+                    "journeys cannot depart before this, unbounded by default",
+                ),
+                FixedRuleOptionSpec::new(
+                    "end_time",
+                    FixedRuleOptionType::Float,
+                    "journeys cannot arrive after this, unbounded by default",
+                ),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+/// A journey from the start reaching `node`, kept only while no other journey to the same
+/// node leaves later and arrives no later.
+struct Journey {
+    node: u32,
+    depart: f64,
+    arrive: f64,
+    prev: Option<usize>,
+}
+
+impl FixedRule for TemporalFastestPath {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let inputs = TemporalInputs::new(&payload)?;
+        let graph = &inputs.graph;
+        let n = graph.indices.len();
+        let rows: Vec<Vec<Tuple>> = inputs
+            .starting
+            .par_iter()
+            .map(|start| -> Result<Vec<Tuple>> {
+                let start = *start;
+                // the one-pass algorithm of Wu et al., keeping for each node the journeys
+                // that are not dominated, in increasing order of both departure and arrival
+                let mut journeys: Vec<Journey> = vec![];
+                let mut frontier: Vec<Vec<usize>> = vec![vec![]; n];
+                let mut fastest = vec![usize::MAX; n];
+                for edge in &graph.edges {
+                    if edge.to == start {
+                        continue;
+                    }
+                    let (depart, prev) = if edge.from == start {
+                        (edge.depart, None)
+                    } else {
+                        // among the journeys arriving in time, the one leaving latest
+                        let reached = &frontier[edge.from as usize];
+                        let pos = reached.partition_point(|j| journeys[*j].arrive <= edge.depart);
+                        if pos == 0 {
+                            continue;
+                        }
+                        let j = reached[pos - 1];
+                        (journeys[j].depart, Some(j))
+                    };
+
+                    let targets = &mut frontier[edge.to as usize];
+                    let pos = targets.partition_point(|j| journeys[*j].depart < depart);
+                    if targets[pos..]
+                        .first()
+                        .is_some_and(|j| journeys[*j].arrive <= edge.arrive)
+                    {
+                        continue;
+                    }
+                    // journeys leaving no later but arriving no earlier are dominated
+                    let mut keep_from = pos;
+                    while keep_from > 0 && journeys[targets[keep_from - 1]].arrive >= edge.arrive {
+                        keep_from -= 1;
+                    }
+                    let end = if targets[pos..]
+                        .first()
+                        .is_some_and(|j| journeys[*j].depart == depart)
+                    {
+                        pos + 1
+                    } else {
+                        pos
+                    };
+                    let idx = journeys.len();
+                    journeys.push(Journey {
+                        node: edge.to,
+                        depart,
+                        arrive: edge.arrive,
+                        prev,
+                    });
+                    targets.splice(keep_from..end, [idx]);
+                    let best = fastest[edge.to as usize];
+                    if best == usize::MAX
+                        || journeys[idx].arrive - journeys[idx].depart
+                            < journeys[best].arrive - journeys[best].depart
+                    {
+                        fastest[edge.to as usize] = idx;
+                    }
+                }
+                poison.check()?;
+                Ok((0..n as u32)
+                    .filter(|node| fastest[*node as usize] != usize::MAX && inputs.is_goal(*node))
+                    .map(|node| {
+                        let best = &journeys[fastest[node as usize]];
+                        let mut path = vec![node];
+                        let mut current = best.prev;
+                        while let Some(j) = current {
+                            path.push(journeys[j].node);
+                            current = journeys[j].prev;
+                        }
+                        path.push(start);
+                        path.reverse();
+                        inputs.row(start, node, best.arrive - best.depart, &path)
+                    })
+                    .collect())
+            })
+            .collect::<Result<_>>()?;
+        for row in rows.into_iter().flatten() {
+            out.put(row);
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![
+                FixedRuleInputSpec::new(
+                    "edges",
+                    3,
+                    "edges as `[from, to, start, end?]`, with the end defaulting to the start",
+                ),
+                FixedRuleInputSpec::new("starting", 1, "nodes to start from"),
+                FixedRuleInputSpec::new("goals", 1, "nodes to reach, defaulting to all nodes")
+                    .optional(),
+            ],
+            options: vec![
+                FixedRuleOptionSpec::undirected(),
+                FixedRuleOptionSpec::new(
+                    "start_time",
+                    FixedRuleOptionType::Float,
+                    "journeys cannot depart before this, unbounded by default",
+                ),
+                FixedRuleOptionSpec::new(
+                    "end_time",
+                    FixedRuleOptionType::Float,
+                    "journeys cannot arrive after this, unbounded by default",
+                ),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(4)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    #[test]
+    fn test_temporal_paths() {
+        let db = DbInstance::default();
+        // 'c' is reached at 3 only after the edge from 'c' to 'd' at 2 has left
+        let edges = r#"
+        edges[] <- [['a', 'b', 1, 2], ['b', 'c', 2, 3], ['c', 'd', 2, 3], ['c', 'd', 5, 6],
+                    ['a', 'c', 4, 4], ['a', 'b', 6, 7], ['b', 'd', 8, 9]]
+        start[] <- [['a']]
+        "#;
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, time, path] <~ TemporalEarliestArrival(edges[], start[])"
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([
+                ["a", "b", 2.0, ["a", "b"]],
+                ["a", "c", 3.0, ["a", "b", "c"]],
+                ["a", "d", 6.0, ["a", "b", "c", "d"]]
+            ])
+        );
+
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                goal[] <- [['d']]
+                ?[s, t, time, path] <~ TemporalLatestDeparture(edges[], start[], goal[])
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "d", 6.0, ["a", "b", "d"]]])
+        );
+
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                goal[] <- [['d']]
+                ?[s, t, time, path] <~ TemporalFastestPath(edges[], start[], goal[])
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["a", "d", 2.0, ["a", "c", "d"]]])
+        );
+
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, time, path] <~ TemporalEarliestArrival(edges[], start[], end_time: 5)"
+            ))
+            .unwrap();
+        assert_eq!(res.rows.len(), 2);
+    }
+
+    #[test]
+    fn test_temporal_paths_edge_cases() {
+        let db = DbInstance::default();
+        let rules = [
+            "TemporalEarliestArrival",
+            "TemporalLatestDeparture",
+            "TemporalFastestPath",
+        ];
+        for rule in rules {
+            let res = db
+                .run_default(&format!(
+                    r#"
+                    edges[a, b, t] <- []
+                    start[] <- [['a']]
+                    ?[s, t, time, path] <~ {rule}(edges[], start[])
+                    "#
+                ))
+                .unwrap();
+            assert!(res.rows.is_empty(), "{rule}");
+        }
+
+        // self-loops go nowhere, edges taking no time can be chained at the same instant,
+        // and a start is never its own goal
+        let edges = r#"
+        edges[] <- [['a', 'a', 0, 5], ['a', 'b', 1, 1], ['b', 'b', 1, 1], ['b', 'c', 1, 1],
+                    ['x', 'y', 0, 1]]
+        start[] <- [['a'], ['nowhere']]
+        goal[] <- [['a'], ['c'], ['y']]
+        "#;
+        for rule in rules {
+            let res = db
+                .run_default(&format!(
+                    "{edges} ?[s, t, time, path] <~ {rule}(edges[], start[], goal[])"
+                ))
+                .unwrap();
+            let expected = if rule == "TemporalFastestPath" {
+                0.
+            } else {
+                1.
+            };
+            assert_eq!(
+                res.into_json()["rows"],
+                serde_json::json!([["a", "c", expected, ["a", "b", "c"]]]),
+                "{rule}"
+            );
+        }
+        // against the direction of the edge, unless undirected
+        let res = db
+            .run_default(&format!(
+                r#"{edges}
+                from_y[] <- [['y']]
+                ?[s, t, time, path] <~ TemporalEarliestArrival(edges[], from_y[], undirected: true)
+                "#
+            ))
+            .unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            serde_json::json!([["y", "x", 1.0, ["y", "x"]]])
+        );
+        // the window leaves out every edge
+        let res = db
+            .run_default(&format!(
+                "{edges} ?[s, t, time, path] <~ TemporalEarliestArrival(edges[], start[], start_time: 2)"
+            ))
+            .unwrap();
+        assert!(res.rows.is_empty());
+
+        for bad_edges in [
+            "[['a', 'b', 2, 1]]",
+            "[['a', 'b', 'noon', 'night']]",
+            "[['a', 'b', 1, null]]",
+        ] {
+            let err = db
+                .run_default(&format!(
+                    r#"
+                    edges[] <- {bad_edges}
+                    start[] <- [['a']]
+                    ?[s, t, time, path] <~ TemporalEarliestArrival(edges[], start[])
+                    "#
+                ))
+                .unwrap_err();
+            assert!(err.to_string().contains("edge times"), "{bad_edges}");
+        }
+        assert!(db
+            .run_default(
+                r#"
+                edges[] <- [['a', 'b']]
+                start[] <- [['a']]
+                ?[s, t, time, path] <~ TemporalEarliestArrival(edges[], start[])
+                "#
+            )
+            .is_err());
+        assert!(db
+            .run_default(&format!(
+                "{edges} ?[s, t, time, path] <~ TemporalEarliestArrival(edges[], start[], at: 1)"
+            ))
+            .is_err());
+    }
+}
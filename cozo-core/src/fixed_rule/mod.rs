@@ -7,9 +7,11 @@
  */
 
 use std::collections::BTreeMap;
-use std::sync::Arc;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use crossbeam::channel::{bounded, Receiver, Sender};
+use crossbeam::channel::{bounded, unbounded, Receiver, RecvTimeoutError, Sender};
 #[allow(unused_imports)]
 use either::{Left, Right};
 #[cfg(feature = "graph-algo")]
@@ -28,13 +30,15 @@ use crate::data::program::{
     WrongFixedRuleOptionError,
 };
 use crate::data::symb::Symbol;
-use crate::data::tuple::TupleIter;
+use crate::data::tuple::{Tuple, TupleIter};
 use crate::data::value::DataValue;
 #[cfg(feature = "graph-algo")]
 use crate::fixed_rule::algos::*;
 use crate::fixed_rule::utilities::*;
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
+#[cfg(feature = "graph-algo")]
+use crate::runtime::graph_projection::ProjectedEdges;
 use crate::runtime::temp_store::{EpochStore, RegularTempStore};
 use crate::runtime::transact::SessionTx;
 use crate::NamedRows;
@@ -48,6 +52,23 @@ pub struct FixedRulePayload<'a, 'b> {
     pub(crate) manifest: &'a MagicFixedRuleApply,
     pub(crate) stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     pub(crate) tx: &'a SessionTx<'b>,
+    /// Additional outputs produced by earlier applications, keyed by the rules they are bound to
+    pub(crate) named_outputs: &'a Mutex<BTreeMap<Symbol, RegularTempStore>>,
+}
+
+/// The additional outputs of a fixed rule application that are bound to rule heads in the query,
+/// see [FixedRule::run_with_outputs]
+#[derive(Default)]
+pub struct FixedRuleOutputs {
+    pub(crate) stores: BTreeMap<SmartString<LazyCompact>, RegularTempStore>,
+}
+
+impl FixedRuleOutputs {
+    /// Get the store for the named output, or `None` if the query does not use the output,
+    /// in which case there is no need to compute it.
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut RegularTempStore> {
+        self.stores.get_mut(name)
+    }
 }
 
 /// Represents an input relation during the execution of a fixed rule
@@ -82,6 +103,19 @@ impl<'a, 'b> FixedRuleInputRelation<'a, 'b> {
     pub fn get_binding_map(&self, offset: usize) -> BTreeMap<Symbol, usize> {
         self.arg_manifest.get_binding_map(offset)
     }
+    /// Column names of the input relation, with unbound columns named by position
+    fn headers(&self) -> Result<Vec<String>> {
+        let mut headers = self
+            .arg_manifest
+            .bindings()
+            .iter()
+            .map(|s| s.name.to_string())
+            .collect_vec();
+        for i in headers.len()..self.arity()? {
+            headers.push(format!("_{i}"));
+        }
+        Ok(headers)
+    }
     /// Iterate the input relation
     pub fn iter(&self) -> Result<TupleIter<'a>> {
         Ok(match &self.arg_manifest {
@@ -99,6 +133,12 @@ impl<'a, 'b> FixedRuleInputRelation<'a, 'b> {
                     Box::new(relation.scan_all(self.tx))
                 }
             }
+
+            MagicFixedRuleRuleArg::Graph { name, .. } => {
+                let rows = self.tx.get_graph_projection(name)?.rows(self.tx)?;
+                let tuples = rows.values().cloned().collect_vec();
+                Box::new(tuples.into_iter().map(Ok))
+            }
         })
     }
     /// Iterate the relation with the given single-value prefix
@@ -120,12 +160,34 @@ impl<'a, 'b> FixedRuleInputRelation<'a, 'b> {
                     Box::new(relation.scan_prefix(self.tx, &t))
                 }
             }
+
+            MagicFixedRuleRuleArg::Graph { name, .. } => {
+                let rows = self.tx.get_graph_projection(name)?.rows(self.tx)?;
+                // projected tuples start with the leading key column of the base relation
+                let tuples = rows
+                    .range(vec![prefix.clone()]..)
+                    .take_while(|(key, _)| key.first() == Some(prefix))
+                    .map(|(_, tuple)| tuple.clone())
+                    .collect_vec();
+                Box::new(tuples.into_iter().map(Ok))
+            }
         })
     }
     /// Get the source span of the input relation. Useful for generating informative error messages.
     pub fn span(&self) -> SourceSpan {
         self.arg_manifest.span()
     }
+    /// The cached edge list if the input is a graph projection.
+    #[cfg(feature = "graph-algo")]
+    fn projected_edges(&self) -> Result<Option<Arc<ProjectedEdges>>> {
+        match self.arg_manifest {
+            MagicFixedRuleRuleArg::Graph { name, .. } => {
+                let projected = self.tx.get_graph_projection(name)?.edges(self.tx)?;
+                Ok(Some(projected.ok_or_else(|| NotAnEdgeError(self.span()))?))
+            }
+            _ => Ok(None),
+        }
+    }
     /// Convert the input relation into a directed graph.
     /// If `undirected` is true, then each edge in the input relation is treated as a pair
     /// of edges, one for each direction.
@@ -141,6 +203,23 @@ impl<'a, 'b> FixedRuleInputRelation<'a, 'b> {
         Vec<DataValue>,
         BTreeMap<DataValue, u32>,
     )> {
+        if let Some(projected) = self.projected_edges()? {
+            let it = projected.edges.iter().copied();
+            let it = if undirected {
+                Right(it.flat_map(|(f, t)| [(f, t), (t, f)]))
+            } else {
+                Left(it)
+            };
+            let graph: DirectedCsrGraph<u32> = GraphBuilder::new()
+                .csr_layout(CsrLayout::Sorted)
+                .edges(it)
+                .build();
+            return Ok((
+                graph,
+                projected.indices.clone(),
+                projected.inv_indices.clone(),
+            ));
+        }
         let mut indices: Vec<DataValue> = vec![];
         let mut inv_indices: BTreeMap<DataValue, u32> = Default::default();
         let mut error: Option<Report> = None;
@@ -214,6 +293,41 @@ impl<'a, 'b> FixedRuleInputRelation<'a, 'b> {
         Vec<DataValue>,
         BTreeMap<DataValue, u32>,
     )> {
+        if let Some(projected) = self.projected_edges()? {
+            let bad_weight = match &projected.bad_weight {
+                None if !allow_negative_weights => projected.negative_weight.as_ref(),
+                bad_weight => bad_weight.as_ref(),
+            };
+            if let Some(d) = bad_weight {
+                bail!(BadEdgeWeightError(
+                    d.clone(),
+                    self.arg_manifest
+                        .bindings()
+                        .get(2)
+                        .map(|s| s.span)
+                        .unwrap_or_else(|| self.span()),
+                ))
+            }
+            let it = projected
+                .edges
+                .iter()
+                .zip(projected.weights.iter())
+                .map(|(&(f, t), &w)| (f, t, w));
+            let it = if undirected {
+                Right(it.flat_map(|(f, t, w)| [(f, t, w), (t, f, w)]))
+            } else {
+                Left(it)
+            };
+            let graph: DirectedCsrGraph<u32, (), f32> = GraphBuilder::new()
+                .csr_layout(CsrLayout::Sorted)
+                .edges_with_values(it)
+                .build();
+            return Ok((
+                graph,
+                projected.indices.clone(),
+                projected.inv_indices.clone(),
+            ));
+        }
         let mut indices: Vec<DataValue> = vec![];
         let mut inv_indices: BTreeMap<DataValue, u32> = Default::default();
         let mut error: Option<Report> = None;
@@ -564,18 +678,355 @@ pub trait FixedRule: Send + Sync {
         out: &'_ mut RegularTempStore,
         poison: Poison,
     ) -> Result<()>;
+    /// Returns the row width of the additional output with the given name,
+    /// or `None` if there is no such output.
+    /// Additional outputs are bound to rule heads in a query as in `main[..], name: other[..] <~ Rule(..)`.
+    /// The default implementation has no additional outputs.
+    fn output_arity(
+        &self,
+        _output: &str,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _span: SourceSpan,
+    ) -> Result<Option<usize>> {
+        Ok(None)
+    }
+    /// Declares the input relations and options of the rule.
+    /// Applications of the rule are checked against the declaration when queries are parsed,
+    /// and `::fixed_rules` lists it.
+    /// The default implementation declares nothing, and then no checks are made.
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        None
+    }
+    /// Same as [FixedRule::run], but additional outputs bound in the query are also written to `outputs`.
+    /// This is the method actually called during evaluation, and rules with additional outputs
+    /// should implement it. The default implementation calls [FixedRule::run].
+    fn run_with_outputs(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &'_ mut RegularTempStore,
+        _outputs: &'_ mut FixedRuleOutputs,
+        poison: Poison,
+    ) -> Result<()> {
+        self.run(payload, out, poison)
+    }
+}
+
+/// Declares the input relations and options of a fixed rule, see [FixedRule::schema].
+#[derive(Debug, Clone, Default)]
+pub struct FixedRuleSchema {
+    /// The input relations, in the order they are passed
+    pub inputs: Vec<FixedRuleInputSpec>,
+    /// The named options
+    pub options: Vec<FixedRuleOptionSpec>,
+}
+
+/// Declares an input relation of a fixed rule
+#[derive(Debug, Clone)]
+pub struct FixedRuleInputSpec {
+    /// Name of the input, for documentation only
+    pub name: String,
+    /// The minimal number of columns the relation must have
+    pub min_arity: usize,
+    /// Whether the input can be left out. Only trailing inputs can be optional.
+    pub optional: bool,
+    /// Description of the input
+    pub doc: String,
+}
+
+impl FixedRuleInputSpec {
+    /// Declare a required input relation
+    pub fn new(name: &str, min_arity: usize, doc: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            min_arity,
+            optional: false,
+            doc: doc.to_string(),
+        }
+    }
+    /// Mark the input relation as optional
+    pub fn optional(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+/// The type of values a fixed rule option takes, matching the getters of [FixedRulePayload]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixedRuleOptionType {
+    /// A boolean
+    Bool,
+    /// An integer
+    Int,
+    /// A positive integer
+    PosInt,
+    /// A non-negative integer
+    NonNegInt,
+    /// A number
+    Float,
+    /// A number between 0. and 1.
+    UnitInterval,
+    /// A string
+    String,
+    /// Any value, or an expression evaluated by the rule itself
+    Expr,
+}
+
+impl FixedRuleOptionType {
+    /// The name of the type as listed by `::fixed_rules`
+    pub fn name(&self) -> &'static str {
+        match self {
+            FixedRuleOptionType::Bool => "Bool",
+            FixedRuleOptionType::Int => "Int",
+            FixedRuleOptionType::PosInt => "PosInt",
+            FixedRuleOptionType::NonNegInt => "NonNegInt",
+            FixedRuleOptionType::Float => "Float",
+            FixedRuleOptionType::UnitInterval => "UnitInterval",
+            FixedRuleOptionType::String => "String",
+            FixedRuleOptionType::Expr => "Expr",
+        }
+    }
+    /// Returns the reason the value is not acceptable, if it is not
+    fn check(&self, val: &DataValue) -> Option<&'static str> {
+        let num = match val {
+            DataValue::Num(n) => Some(n),
+            _ => None,
+        };
+        match self {
+            FixedRuleOptionType::Bool => match val {
+                DataValue::Bool(_) => None,
+                _ => Some("a boolean value is required"),
+            },
+            FixedRuleOptionType::Int => match num.and_then(|n| n.get_int()) {
+                Some(_) => None,
+                None => Some("an integer is required"),
+            },
+            FixedRuleOptionType::PosInt => match num.and_then(|n| n.get_int()) {
+                Some(i) if i > 0 => None,
+                _ => Some("a positive integer is required"),
+            },
+            FixedRuleOptionType::NonNegInt => match num.and_then(|n| n.get_int()) {
+                Some(i) if i >= 0 => None,
+                _ => Some("a non-negative integer is required"),
+            },
+            FixedRuleOptionType::Float => match num {
+                Some(_) => None,
+                None => Some("a floating number is required"),
+            },
+            FixedRuleOptionType::UnitInterval => match num.map(|n| n.get_float()) {
+                Some(f) if (0. ..=1.).contains(&f) => None,
+                _ => Some("a number between 0. and 1. is required"),
+            },
+            FixedRuleOptionType::String => match val {
+                DataValue::Str(_) => None,
+                _ => Some("a string is required"),
+            },
+            FixedRuleOptionType::Expr => None,
+        }
+    }
+}
+
+/// Declares a named option of a fixed rule
+#[derive(Debug, Clone)]
+pub struct FixedRuleOptionSpec {
+    /// Name of the option
+    pub name: String,
+    /// The type of values the option takes
+    pub typ: FixedRuleOptionType,
+    /// The value used when the option is not given, for documentation only
+    pub default: Option<DataValue>,
+    /// Whether the option must be given
+    pub required: bool,
+    /// Description of the option
+    pub doc: String,
+}
+
+impl FixedRuleOptionSpec {
+    /// Declare an option that may be left out
+    pub fn new(name: &str, typ: FixedRuleOptionType, doc: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            typ,
+            default: None,
+            required: false,
+            doc: doc.to_string(),
+        }
+    }
+    /// Set the value used when the option is not given
+    pub fn default_value(mut self, val: impl Into<DataValue>) -> Self {
+        self.default = Some(val.into());
+        self
+    }
+    /// Mark the option as required
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+    /// The `undirected` option shared by most graph algorithms
+    pub(crate) fn undirected() -> Self {
+        Self::new(
+            "undirected",
+            FixedRuleOptionType::Bool,
+            "whether the edges should be traversed in both directions",
+        )
+        .default_value(false)
+    }
+}
+
+impl FixedRuleSchema {
+    /// Checks the options and the number of inputs given to the rule when the query is parsed
+    pub(crate) fn check_application(
+        &self,
+        rule_name: &str,
+        options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        inputs_count: usize,
+        span: SourceSpan,
+    ) -> Result<()> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Fixed rule '{0}' has no option named '{1}'")]
+        #[diagnostic(code(parser::fixed_rule_option_unknown))]
+        struct UnknownFixedRuleOption(String, String, #[label] SourceSpan, #[help] String);
+
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Fixed rule '{0}' is given {1} input relations")]
+        #[diagnostic(code(parser::fixed_rule_inputs_count))]
+        #[diagnostic(help("The rule takes at least {2} and at most {3} input relations"))]
+        struct WrongFixedRuleInputsCount(String, usize, usize, usize, #[label] SourceSpan);
+
+        for (name, expr) in options {
+            let spec = match self.options.iter().find(|spec| spec.name == name.as_str()) {
+                Some(spec) => spec,
+                None => bail!(UnknownFixedRuleOption(
+                    rule_name.to_string(),
+                    name.to_string(),
+                    expr.span(),
+                    format!(
+                        "Available options: {}",
+                        self.options.iter().map(|spec| &spec.name).join(", ")
+                    )
+                )),
+            };
+            // options that are not constant are left for the rule to deal with
+            if let Ok(val) = expr.clone().eval_to_const() {
+                if let Some(help) = spec.typ.check(&val) {
+                    bail!(WrongFixedRuleOptionError {
+                        name: name.to_string(),
+                        span: expr.span(),
+                        rule_name: rule_name.to_string(),
+                        help: help.to_string(),
+                    })
+                }
+            }
+        }
+        for spec in &self.options {
+            ensure!(
+                !spec.required || options.contains_key(&spec.name as &str),
+                FixedRuleOptionNotFoundError {
+                    name: spec.name.clone(),
+                    span,
+                    rule_name: rule_name.to_string(),
+                }
+            );
+        }
+        let min_inputs = self.inputs.iter().filter(|input| !input.optional).count();
+        ensure!(
+            (min_inputs..=self.inputs.len()).contains(&inputs_count),
+            WrongFixedRuleInputsCount(
+                rule_name.to_string(),
+                inputs_count,
+                min_inputs,
+                self.inputs.len(),
+                span
+            )
+        );
+        Ok(())
+    }
+    /// Checks the arities of the input relations, which are only known during evaluation
+    pub(crate) fn check_inputs(&self, payload: &FixedRulePayload<'_, '_>) -> Result<()> {
+        for (i, spec) in self.inputs.iter().enumerate().take(payload.inputs_count()) {
+            payload.get_input(i)?.ensure_min_len(spec.min_arity)?;
+        }
+        Ok(())
+    }
 }
 
 /// Simple wrapper for custom fixed rule. You have less control than implementing [FixedRule] directly,
 /// but implementation is simpler.
 pub struct SimpleFixedRule {
     return_arity: usize,
-    rule: Box<
-        dyn Fn(Vec<NamedRows>, BTreeMap<String, DataValue>) -> Result<NamedRows>
-            + Send
-            + Sync
-            + 'static,
-    >,
+    rule: SimpleFixedRuleImpl,
+}
+
+enum SimpleFixedRuleImpl {
+    Whole(
+        Box<
+            dyn Fn(Vec<NamedRows>, BTreeMap<String, DataValue>) -> Result<NamedRows>
+                + Send
+                + Sync
+                + 'static,
+        >,
+    ),
+    Paged {
+        page_size: usize,
+        sender: Sender<PagedFixedRuleCall>,
+    },
+    Host {
+        timeout: Option<Duration>,
+        sender: Sender<HostFixedRuleCall>,
+    },
+}
+
+/// An invocation of a fixed rule constructed by [SimpleFixedRule::rule_with_host_channel].
+/// Invocations from different queries, or from different parts of the same query,
+/// may be outstanding at the same time, and the host is free to answer them in any order.
+pub struct HostFixedRuleCall {
+    /// The input relations, realized into NamedRows
+    pub inputs: Vec<NamedRows>,
+    /// The options passed in
+    pub options: BTreeMap<String, DataValue>,
+    /// The result is sent here. Every row must have length equal to the return arity.
+    /// Sending never blocks, but fails if the query has already given up on the invocation.
+    pub reply: Sender<Result<NamedRows>>,
+    /// Tripped when the query gives up on the invocation, because it is killed or
+    /// the invocation times out. Long-running hosts can check it to stop early.
+    pub poison: Poison,
+}
+
+/// Error raised by the host implementing a fixed rule constructed by
+/// [SimpleFixedRule::rule_with_host_channel], keeping the stack trace of the host if there is one.
+#[derive(Debug, Error, Diagnostic)]
+#[error("{message}")]
+#[diagnostic(code(eval::host_fixed_rule_failed))]
+pub struct HostFixedRuleError {
+    /// The error message
+    pub message: String,
+    /// The stack trace in the host language
+    #[help]
+    pub stack: Option<String>,
+}
+
+/// How often an evaluating thread waiting for the host checks for kills and timeouts
+const HOST_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// An application of a fixed rule constructed by [SimpleFixedRule::rule_with_paged_channel].
+pub struct PagedFixedRuleCall {
+    /// The number of input relations
+    pub inputs_count: usize,
+    /// The options passed in
+    pub options: BTreeMap<String, DataValue>,
+    /// The application is driven by sending requests here. It ends when
+    /// [PagedFixedRuleRequest::Finish] is sent, and dropping the sender before that aborts the query.
+    pub requests: Sender<PagedFixedRuleRequest>,
+}
+
+/// Requests made during a [PagedFixedRuleCall].
+pub enum PagedFixedRuleRequest {
+    /// Fetch the next page of the input relation at the given position.
+    /// An empty page is returned once the input is exhausted.
+    NextInputPage(usize, Sender<Result<NamedRows>>),
+    /// Append a page of rows to the output. Every row must have length equal to the return arity.
+    PutOutputPage(NamedRows),
+    /// End the application, with an error to abort the query.
+    Finish(Result<()>),
 }
 
 impl SimpleFixedRule {
@@ -596,7 +1047,7 @@ impl SimpleFixedRule {
     {
         Self {
             return_arity,
-            rule: Box::new(rule),
+            rule: SimpleFixedRuleImpl::Whole(Box::new(rule)),
         }
     }
     /// Construct a SimpleFixedRule that uses channels for communication.
@@ -610,23 +1061,65 @@ impl SimpleFixedRule {
             Sender<Result<NamedRows>>,
         )>,
     ) {
+        let (db2app_sender, db2app_receiver) = bounded(0);
+        (
+            Self::new(return_arity, move |inputs, options| -> Result<NamedRows> {
+                let (app2db_sender, app2db_receiver) = bounded(0);
+                db2app_sender
+                    .send((inputs, options, app2db_sender))
+                    .into_diagnostic()?;
+                app2db_receiver.recv().into_diagnostic()?
+            }),
+            db2app_receiver,
+        )
+    }
+    /// Construct a SimpleFixedRule that uses channels for communication, without realizing
+    /// the inputs or the output as a whole.
+    /// Input relations are fetched in pages of at most `page_size` rows, and the output
+    /// is sent back in pages as it is produced, see [PagedFixedRuleCall].
+    pub fn rule_with_paged_channel(
+        return_arity: usize,
+        page_size: usize,
+    ) -> (Self, Receiver<PagedFixedRuleCall>) {
         let (db2app_sender, db2app_receiver) = bounded(0);
         (
             Self {
                 return_arity,
-                rule: Box::new(move |inputs, options| -> Result<NamedRows> {
-                    let (app2db_sender, app2db_receiver) = bounded(0);
-                    db2app_sender
-                        .send((inputs, options, app2db_sender))
-                        .into_diagnostic()?;
-                    app2db_receiver.recv().into_diagnostic()?
-                }),
+                rule: SimpleFixedRuleImpl::Paged {
+                    page_size: page_size.max(1),
+                    sender: db2app_sender,
+                },
+            },
+            db2app_receiver,
+        )
+    }
+    /// Construct a SimpleFixedRule implemented by the host, typically in another language.
+    /// Unlike [SimpleFixedRule::rule_with_channel], the evaluating thread does not wait
+    /// for the host unconditionally: the invocation is abandoned when the query is killed,
+    /// or when the host takes longer than `timeout` to answer, see [HostFixedRuleCall].
+    pub fn rule_with_host_channel(
+        return_arity: usize,
+        timeout: Option<Duration>,
+    ) -> (Self, Receiver<HostFixedRuleCall>) {
+        let (db2app_sender, db2app_receiver) = unbounded();
+        (
+            Self {
+                return_arity,
+                rule: SimpleFixedRuleImpl::Host {
+                    timeout,
+                    sender: db2app_sender,
+                },
             },
             db2app_receiver,
         )
     }
 }
 
+#[derive(Debug, Error, Diagnostic)]
+#[error("arity mismatch: expect {1}, got {2}")]
+#[diagnostic(code(parser::simple_fixed_rule_arity_mismatch))]
+struct ArityMismatch(#[label] SourceSpan, usize, usize);
+
 impl FixedRule for SimpleFixedRule {
     fn arity(
         &self,
@@ -641,7 +1134,7 @@ impl FixedRule for SimpleFixedRule {
         &self,
         payload: FixedRulePayload<'_, '_>,
         out: &'_ mut RegularTempStore,
-        _poison: Poison,
+        poison: Poison,
     ) -> Result<()> {
         let options: BTreeMap<_, _> = payload
             .manifest
@@ -653,38 +1146,127 @@ impl FixedRule for SimpleFixedRule {
             })
             .try_collect()?;
         let input_arity = payload.manifest.rule_args.len();
-        let inputs: Vec<_> = (0..input_arity)
-            .map(|i| -> Result<_> {
-                let input = payload.get_input(i).unwrap();
-                let rows: Vec<_> = input.iter()?.try_collect()?;
-                let mut headers = input
-                    .arg_manifest
-                    .bindings()
-                    .iter()
-                    .map(|s| s.name.to_string())
-                    .collect_vec();
-                let l = headers.len();
-                let m = input.arg_manifest.arity(payload.tx, payload.stores)?;
-                for i in l..m {
-                    headers.push(format!("_{i}"));
-                }
-                Ok(NamedRows::new(headers, rows))
-            })
-            .try_collect()?;
-        let results: NamedRows = (self.rule)(inputs, options)?;
-        for row in results.rows {
-            #[derive(Debug, Error, Diagnostic)]
-            #[error("arity mismatch: expect {1}, got {2}")]
-            #[diagnostic(code(parser::simple_fixed_rule_arity_mismatch))]
-            struct ArityMismatch(#[label] SourceSpan, usize, usize);
+        let realize_inputs = || -> Result<Vec<NamedRows>> {
+            (0..input_arity)
+                .map(|i| -> Result<_> {
+                    let input = payload.get_input(i)?;
+                    let rows: Vec<_> = input.iter()?.try_collect()?;
+                    Ok(NamedRows::new(input.headers()?, rows))
+                })
+                .try_collect()
+        };
+        let mut put_rows = |rows: Vec<Tuple>| -> Result<()> {
+            for row in rows {
+                ensure!(
+                    row.len() == self.return_arity,
+                    ArityMismatch(payload.span(), self.return_arity, row.len())
+                );
+                out.put(row);
+            }
+            Ok(())
+        };
+        match &self.rule {
+            SimpleFixedRuleImpl::Whole(rule) => {
+                let results: NamedRows = rule(realize_inputs()?, options)?;
+                put_rows(results.rows)
+            }
+            SimpleFixedRuleImpl::Host { timeout, sender } => {
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("The host implementing fixed rule {0} did not answer within {1:?}")]
+                #[diagnostic(code(eval::host_fixed_rule_timeout))]
+                struct HostFixedRuleTimeout(String, Duration, #[label] SourceSpan);
 
-            ensure!(
-                row.len() == self.return_arity,
-                ArityMismatch(payload.span(), self.return_arity, row.len())
-            );
-            out.put(row);
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("The host implementing fixed rule {0} dropped the invocation")]
+                #[diagnostic(code(eval::host_fixed_rule_dropped))]
+                struct HostFixedRuleDropped(String, #[label] SourceSpan);
+
+                // room for the reply, so that the host never blocks on it
+                let (app2db_sender, app2db_receiver) = bounded(1);
+                let call_poison = Poison::default();
+                sender
+                    .send(HostFixedRuleCall {
+                        inputs: realize_inputs()?,
+                        options,
+                        reply: app2db_sender,
+                        poison: call_poison.clone(),
+                    })
+                    .into_diagnostic()?;
+                let deadline = timeout.map(|t| Instant::now() + t);
+                let give_up = |err: Report| -> Result<()> {
+                    call_poison.0.store(true, Ordering::Relaxed);
+                    Err(err)
+                };
+                loop {
+                    match app2db_receiver.recv_timeout(HOST_POLL_INTERVAL) {
+                        Ok(res) => return put_rows(res?.rows),
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => {
+                            return give_up(
+                                HostFixedRuleDropped(payload.name().to_string(), payload.span())
+                                    .into(),
+                            )
+                        }
+                    }
+                    if let Err(err) = poison.check() {
+                        return give_up(err);
+                    }
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        return give_up(
+                            HostFixedRuleTimeout(
+                                payload.name().to_string(),
+                                timeout.unwrap(),
+                                payload.span(),
+                            )
+                            .into(),
+                        );
+                    }
+                }
+            }
+            SimpleFixedRuleImpl::Paged { page_size, sender } => {
+                let (app2db_sender, app2db_receiver) = bounded(0);
+                sender
+                    .send(PagedFixedRuleCall {
+                        inputs_count: input_arity,
+                        options,
+                        requests: app2db_sender,
+                    })
+                    .into_diagnostic()?;
+                // inputs are only scanned once the application asks for them
+                let mut scans: Vec<Option<(Vec<String>, TupleIter<'_>)>> =
+                    (0..input_arity).map(|_| None).collect();
+                loop {
+                    let request = match app2db_receiver.recv_timeout(HOST_POLL_INTERVAL) {
+                        Ok(request) => request,
+                        Err(RecvTimeoutError::Timeout) => {
+                            poison.check()?;
+                            continue;
+                        }
+                        Err(err @ RecvTimeoutError::Disconnected) => {
+                            return Err(err).into_diagnostic()
+                        }
+                    };
+                    match request {
+                        PagedFixedRuleRequest::NextInputPage(idx, reply) => {
+                            let mut next_page = || -> Result<NamedRows> {
+                                let input = payload.get_input(idx)?;
+                                if scans[idx].is_none() {
+                                    scans[idx] = Some((input.headers()?, input.iter()?));
+                                }
+                                let (headers, scan) = scans[idx].as_mut().unwrap();
+                                let rows: Vec<_> = scan.by_ref().take(*page_size).try_collect()?;
+                                Ok(NamedRows::new(headers.clone(), rows))
+                            };
+                            // the application may have stopped listening, which is not our concern
+                            let _ = reply.send(next_page());
+                        }
+                        PagedFixedRuleRequest::PutOutputPage(page) => put_rows(page.rows)?,
+                        PagedFixedRuleRequest::Finish(res) => return res,
+                    }
+                    poison.check()?;
+                }
+            }
         }
-        Ok(())
     }
 }
 
@@ -716,6 +1298,11 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(DegreeCentrality)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "NodeSimilarity".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(NodeSimilarity)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "ClosenessCentrality".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(ClosenessCentrality)),
@@ -726,6 +1313,31 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(BetweennessCentrality)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "EigenvectorCentrality".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(EigenvectorCentrality)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "KatzCentrality".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(KatzCentrality)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "HITS".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(Hits)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "HarmonicCentrality".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(HarmonicCentrality)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "ApproxBetweennessCentrality".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ApproxBetweennessCentrality)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "DepthFirstSearch".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(Dfs)),
@@ -761,11 +1373,51 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(ShortestPathAStar)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "ShortestPathBidirectionalDijkstra".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ShortestPathBidirectionalDijkstra)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "ShortestPathBellmanFord".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ShortestPathBellmanFord)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "ShortestPathJohnson".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ShortestPathJohnson)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "TemporalEarliestArrival".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(TemporalEarliestArrival)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "TemporalLatestDeparture".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(TemporalLatestDeparture)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "TemporalFastestPath".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(TemporalFastestPath)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "KShortestPathYen".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(KShortestPathYen)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "AllSimplePaths".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(AllSimplePaths)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "SimpleCycles".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(SimpleCycles)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "MinimumSpanningTreePrim".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(MinimumSpanningTreePrim)),
@@ -776,6 +1428,16 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(MinimumSpanningForestKruskal)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "MaxFlow".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(MaxFlow)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "BipartiteMatching".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(BipartiteMatching)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "TopSort".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(TopSort)),
@@ -796,16 +1458,76 @@ lazy_static! {
                 Arc::<Box<dyn FixedRule>>::new(Box::new(StronglyConnectedComponent::new(true))),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "ArticulationPoints".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ArticulationPoints)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "Bridges".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(Bridges)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "BiconnectedComponents".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(BiconnectedComponents)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "KCore".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(KCore)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "DegeneracyOrdering".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(DegeneracyOrdering)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "MaximalCliques".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(MaximalCliques)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "GraphColoring".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(GraphColoring)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "MaximumIndependentSet".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(MaximumIndependentSet)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "SubgraphMatch".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(SubgraphMatch)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "PageRank".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(PageRank)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "PersonalizedPageRank".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(PersonalizedPageRank)),
+            ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "RandomWalkWithRestart".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(PersonalizedPageRank)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "CommunityDetectionLouvain".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(CommunityDetectionLouvain)),
             ),
             #[cfg(feature = "graph-algo")]
+            (
+                "CommunityDetectionLeiden".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(CommunityDetectionLeiden)),
+            ),
+            #[cfg(feature = "graph-algo")]
             (
                 "LabelPropagation".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(LabelPropagation)),
@@ -815,10 +1537,19 @@ lazy_static! {
                 "RandomWalk".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(RandomWalk)),
             ),
+            #[cfg(feature = "graph-algo")]
+            (
+                "NodeEmbeddings".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(NodeEmbeddings)),
+            ),
             (
                 "ReorderSort".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(ReorderSort)),
             ),
+            (
+                "Window".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(Window)),
+            ),
             (
                 "JsonReader".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(JsonReader)),
@@ -831,6 +1562,30 @@ lazy_static! {
                 "Constant".to_string(),
                 Arc::<Box<dyn FixedRule>>::new(Box::new(Constant)),
             ),
+            (
+                "ErdosRenyi".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(ErdosRenyi)),
+            ),
+            (
+                "BarabasiAlbert".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(BarabasiAlbert)),
+            ),
+            (
+                "WattsStrogatz".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(WattsStrogatz)),
+            ),
+            (
+                "StochasticBlockModel".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(StochasticBlockModel)),
+            ),
+            (
+                "GridGraph".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(GridGraph)),
+            ),
+            (
+                "CompleteGraph".to_string(),
+                Arc::<Box<dyn FixedRule>>::new(Box::new(CompleteGraph)),
+            ),
         ])
     };
 }
@@ -915,6 +1670,7 @@ impl MagicFixedRuleRuleArg {
                 let handle = tx.get_relation(name, false)?;
                 handle.arity()
             }
+            MagicFixedRuleRuleArg::Graph { name, .. } => tx.get_graph_projection(name)?.arity(),
         })
     }
 }
@@ -16,7 +16,9 @@ use crate::data::expr::Expr;
 use crate::data::program::WrongFixedRuleOptionError;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    FixedRule, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload, FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -39,6 +41,17 @@ impl FixedRule for Constant {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![FixedRuleOptionSpec::new(
+                "data",
+                FixedRuleOptionType::Expr,
+                "the rows, as a list of lists",
+            )
+            .required()],
+        })
+    }
     fn arity(
         &self,
         options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -20,7 +20,10 @@ use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
 #[cfg(feature = "requests")]
 use crate::fixed_rule::utilities::jlines::get_file_content_from_url;
-use crate::fixed_rule::{CannotDetermineArity, FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    CannotDetermineArity, FixedRule, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
 use crate::parse::{parse_type, SourceSpan};
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -173,6 +176,43 @@ impl FixedRule for CsvReader {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "url",
+                    FixedRuleOptionType::String,
+                    "where to read the file from, either `file://` or `http(s)://`",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "types",
+                    FixedRuleOptionType::Expr,
+                    "list of column types",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "delimiter",
+                    FixedRuleOptionType::String,
+                    "the field delimiter",
+                )
+                .default_value(","),
+                FixedRuleOptionSpec::new(
+                    "prepend_index",
+                    FixedRuleOptionType::Bool,
+                    "whether to add the row number as the first column",
+                )
+                .default_value(false),
+                FixedRuleOptionSpec::new(
+                    "has_headers",
+                    FixedRuleOptionType::Bool,
+                    "whether the first row is a header",
+                )
+                .default_value(true),
+            ],
+        })
+    }
     fn arity(
         &self,
         options: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -0,0 +1,672 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! Generators of synthetic graphs. Each returns the edges `[from, to]` of a graph on the nodes
+//! `0..n`, so that the result can be written directly to a stored relation with `:put`.
+//! Undirected graphs list each edge once, with `from < to`. Randomised generators take a
+//! `seed` option, and give the same graph for the same seed and options.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use miette::{bail, ensure, Result};
+use rand::prelude::*;
+use rand::rngs::StdRng;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{
+    FixedRule, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload, FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// The G(n, p) model, where each pair of nodes is joined independently with probability `p`,
+/// or the G(n, m) model with exactly `m` edges chosen uniformly if `m` is given instead.
+pub(crate) struct ErdosRenyi;
+
+/// Preferential attachment: starting from a star on `m + 1` nodes, each new node is joined to
+/// `m` distinct existing nodes chosen with probability proportional to their degrees.
+pub(crate) struct BarabasiAlbert;
+
+/// A ring in which every node is joined to its `k` nearest neighbours, after which the far end
+/// of each edge is moved to a uniformly chosen node with probability `p`.
+pub(crate) struct WattsStrogatz;
+
+/// Nodes are split into consecutive blocks of the given `sizes`, and each pair of nodes is
+/// joined with probability `p_in` within a block and `p_out` across blocks.
+pub(crate) struct StochasticBlockModel;
+
+/// A `rows` by `cols` lattice with node `r * cols + c` at row `r` and column `c`,
+/// wrapping around at the borders if `periodic` is true.
+pub(crate) struct GridGraph;
+
+/// Every pair of the `n` nodes joined.
+pub(crate) struct CompleteGraph;
+
+fn rng_from(payload: &FixedRulePayload<'_, '_>) -> Result<StdRng> {
+    let seed = payload.integer_option("seed", Some(0))?;
+    Ok(StdRng::seed_from_u64(seed as u64))
+}
+
+fn put_edge(out: &mut RegularTempStore, from: u64, to: u64) {
+    out.put(vec![
+        DataValue::from(from as i64),
+        DataValue::from(to as i64),
+    ]);
+}
+
+/// Calls `f` with each index in `0..count` independently with probability `p`, skipping over
+/// the indices not chosen by drawing the gaps from a geometric distribution, so that the cost
+/// is proportional to the number of indices chosen rather than to `count`.
+fn sample_indices(
+    count: u64,
+    p: f64,
+    rng: &mut StdRng,
+    poison: &Poison,
+    mut f: impl FnMut(u64),
+) -> Result<()> {
+    if p <= 0. {
+        return Ok(());
+    }
+    let log_q = (-p).ln_1p();
+    let mut idx = 0u64;
+    let mut chosen = 0usize;
+    loop {
+        if p < 1. {
+            let skip = ((1. - rng.gen::<f64>()).ln() / log_q).floor();
+            if skip >= (count - idx) as f64 {
+                return Ok(());
+            }
+            idx += skip as u64;
+        }
+        if idx >= count {
+            return Ok(());
+        }
+        f(idx);
+        idx += 1;
+        chosen += 1;
+        if chosen % 65536 == 0 {
+            poison.check()?;
+        }
+    }
+}
+
+/// Calls `f` with each pair `(u, v)` with `u < v < n` independently with probability `p`.
+fn sample_undirected_pairs(
+    n: u64,
+    p: f64,
+    rng: &mut StdRng,
+    poison: &Poison,
+    mut f: impl FnMut(u64, u64),
+) -> Result<()> {
+    // pairs are numbered row by row, the pair `(u, v)` being `v * (v - 1) / 2 + u`
+    let mut v = 1;
+    let mut row_start = 0;
+    sample_indices(n * n.saturating_sub(1) / 2, p, rng, poison, |idx| {
+        while idx >= row_start + v {
+            row_start += v;
+            v += 1;
+        }
+        f(idx - row_start, v)
+    })
+}
+
+/// Calls `f` with each pair `(u, v)` with `u != v` and both below `n` independently with
+/// probability `p`.
+fn sample_directed_pairs(
+    n: u64,
+    p: f64,
+    rng: &mut StdRng,
+    poison: &Poison,
+    mut f: impl FnMut(u64, u64),
+) -> Result<()> {
+    sample_indices(n * n.saturating_sub(1), p, rng, poison, |idx| {
+        let u = idx / (n - 1);
+        let v = idx % (n - 1);
+        f(u, if v >= u { v + 1 } else { v })
+    })
+}
+
+impl FixedRule for ErdosRenyi {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let n = payload.pos_integer_option("n", None)? as u64;
+        let directed = payload.bool_option("directed", Some(false))?;
+        let mut rng = rng_from(&payload)?;
+
+        match payload.non_neg_integer_option("m", None).ok() {
+            None => {
+                let p = payload.unit_interval_option("p", None)?;
+                if directed {
+                    sample_directed_pairs(n, p, &mut rng, &poison, |u, v| put_edge(out, u, v))
+                } else {
+                    sample_undirected_pairs(n, p, &mut rng, &poison, |u, v| put_edge(out, u, v))
+                }
+            }
+            Some(m) => {
+                let pairs = if directed {
+                    n * (n - 1)
+                } else {
+                    n * (n - 1) / 2
+                };
+                ensure!(
+                    m as u64 <= pairs,
+                    WrongFixedRuleOptionError {
+                        name: "m".to_string(),
+                        span: payload.option_span("m")?,
+                        rule_name: payload.name().to_string(),
+                        help: format!("a graph on {n} nodes has at most {pairs} edges"),
+                    }
+                );
+                let mut edges = BTreeSet::new();
+                while edges.len() < m {
+                    let u = rng.gen_range(0..n);
+                    let v = rng.gen_range(0..n);
+                    if u == v {
+                        continue;
+                    }
+                    let edge = if directed {
+                        (u, v)
+                    } else {
+                        (u.min(v), u.max(v))
+                    };
+                    if edges.insert(edge) && edges.len() % 65536 == 0 {
+                        poison.check()?;
+                    }
+                }
+                for (u, v) in edges {
+                    put_edge(out, u, v);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new("n", FixedRuleOptionType::PosInt, "number of nodes")
+                    .required(),
+                FixedRuleOptionSpec::new(
+                    "p",
+                    FixedRuleOptionType::UnitInterval,
+                    "probability of each edge, when `m` is not given",
+                ),
+                FixedRuleOptionSpec::new(
+                    "m",
+                    FixedRuleOptionType::NonNegInt,
+                    "exact number of edges",
+                ),
+                FixedRuleOptionSpec::new(
+                    "directed",
+                    FixedRuleOptionType::Bool,
+                    "whether to generate a directed graph",
+                )
+                .default_value(false),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+impl FixedRule for BarabasiAlbert {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let n = payload.pos_integer_option("n", None)? as u64;
+        let m = payload.pos_integer_option("m", None)?;
+        ensure!(
+            (m as u64) < n,
+            WrongFixedRuleOptionError {
+                name: "m".to_string(),
+                span: payload.option_span("m")?,
+                rule_name: payload.name().to_string(),
+                help: "m must be less than the number of nodes n".to_string(),
+            }
+        );
+        let mut rng = rng_from(&payload)?;
+
+        // each node appears once for every edge it has, so that uniform choices from this
+        // list are proportional to degree
+        let mut ends = vec![];
+        for v in 1..=m as u64 {
+            put_edge(out, 0, v);
+            ends.extend([0, v]);
+        }
+        let mut targets = BTreeSet::new();
+        for source in (m as u64 + 1)..n {
+            targets.clear();
+            while targets.len() < m {
+                targets.insert(ends[rng.gen_range(0..ends.len())]);
+            }
+            for target in &targets {
+                put_edge(out, *target, source);
+                ends.extend([*target, source]);
+            }
+            if source % 1024 == 0 {
+                poison.check()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new("n", FixedRuleOptionType::PosInt, "number of nodes")
+                    .required(),
+                FixedRuleOptionSpec::new(
+                    "m",
+                    FixedRuleOptionType::PosInt,
+                    "number of edges attaching each new node",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+impl FixedRule for WattsStrogatz {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let n = payload.pos_integer_option("n", None)? as u64;
+        let k = payload.pos_integer_option("k", None)? as u64;
+        let p = payload.unit_interval_option("p", None)?;
+        ensure!(
+            k % 2 == 0 && k < n,
+            WrongFixedRuleOptionError {
+                name: "k".to_string(),
+                span: payload.option_span("k")?,
+                rule_name: payload.name().to_string(),
+                help: "k must be even and less than the number of nodes n".to_string(),
+            }
+        );
+        let mut rng = rng_from(&payload)?;
+
+        let mut adj = vec![BTreeSet::new(); n as usize];
+        for u in 0..n {
+            for offset in 1..=k / 2 {
+                let v = (u + offset) % n;
+                adj[u as usize].insert(v);
+                adj[v as usize].insert(u);
+            }
+        }
+        // rewiring goes around the ring once for each distance, as in the original paper
+        for offset in 1..=k / 2 {
+            for u in 0..n {
+                let v = (u + offset) % n;
+                if !adj[u as usize].contains(&v) || !rng.gen_bool(p) {
+                    continue;
+                }
+                // a node joined to everything else has nowhere to rewire to
+                if adj[u as usize].len() as u64 >= n - 1 {
+                    continue;
+                }
+                let w = loop {
+                    let w = rng.gen_range(0..n);
+                    if w != u && !adj[u as usize].contains(&w) {
+                        break w;
+                    }
+                };
+                adj[u as usize].remove(&v);
+                adj[v as usize].remove(&u);
+                adj[u as usize].insert(w);
+                adj[w as usize].insert(u);
+            }
+            poison.check()?;
+        }
+        for (u, neighbours) in adj.into_iter().enumerate() {
+            for v in neighbours.range(u as u64 + 1..) {
+                put_edge(out, u as u64, *v);
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new("n", FixedRuleOptionType::PosInt, "number of nodes")
+                    .required(),
+                FixedRuleOptionSpec::new(
+                    "k",
+                    FixedRuleOptionType::PosInt,
+                    "number of nearest neighbours in the ring, must be even",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "p",
+                    FixedRuleOptionType::UnitInterval,
+                    "probability of rewiring each edge",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+impl FixedRule for StochasticBlockModel {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let sizes = match payload.expr_option("sizes", None)?.eval_to_const()? {
+            DataValue::List(l) => l
+                .iter()
+                .map(|size| size.get_non_neg_int())
+                .collect::<Option<Vec<_>>>(),
+            _ => None,
+        };
+        let sizes = match sizes {
+            Some(sizes) => sizes,
+            None => bail!(WrongFixedRuleOptionError {
+                name: "sizes".to_string(),
+                span: payload.option_span("sizes")?,
+                rule_name: payload.name().to_string(),
+                help: "a list of non-negative integers is required".to_string(),
+            }),
+        };
+        let p_in = payload.unit_interval_option("p_in", None)?;
+        let p_out = payload.unit_interval_option("p_out", None)?;
+        let directed = payload.bool_option("directed", Some(false))?;
+        let mut rng = rng_from(&payload)?;
+
+        let mut offsets = vec![0];
+        for size in &sizes {
+            offsets.push(offsets.last().unwrap() + size);
+        }
+        for (a, (&size_a, &offset_a)) in sizes.iter().zip(offsets.iter()).enumerate() {
+            let mut put = |u: u64, v: u64| put_edge(out, offset_a + u, offset_a + v);
+            if directed {
+                sample_directed_pairs(size_a, p_in, &mut rng, &poison, &mut put)?;
+            } else {
+                sample_undirected_pairs(size_a, p_in, &mut rng, &poison, &mut put)?;
+            }
+            for (b, (&size_b, &offset_b)) in sizes.iter().zip(offsets.iter()).enumerate() {
+                if a == b || (!directed && b < a) {
+                    continue;
+                }
+                sample_indices(size_a * size_b, p_out, &mut rng, &poison, |idx| {
+                    put_edge(out, offset_a + idx / size_b, offset_b + idx % size_b)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "sizes",
+                    FixedRuleOptionType::Expr,
+                    "list of the block sizes",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "p_in",
+                    FixedRuleOptionType::UnitInterval,
+                    "probability of edges within a block",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "p_out",
+                    FixedRuleOptionType::UnitInterval,
+                    "probability of edges between blocks",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "directed",
+                    FixedRuleOptionType::Bool,
+                    "whether to generate a directed graph",
+                )
+                .default_value(false),
+                FixedRuleOptionSpec::new(
+                    "seed",
+                    FixedRuleOptionType::Int,
+                    "seed of the random number generator",
+                )
+                .default_value(0),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+impl FixedRule for GridGraph {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        _poison: Poison,
+    ) -> Result<()> {
+        let rows = payload.pos_integer_option("rows", None)? as u64;
+        let cols = payload.pos_integer_option("cols", None)? as u64;
+        let periodic = payload.bool_option("periodic", Some(false))?;
+
+        let mut put = |a: u64, b: u64| {
+            if a != b {
+                put_edge(out, a.min(b), a.max(b));
+            }
+        };
+        for r in 0..rows {
+            for c in 0..cols {
+                let node = r * cols + c;
+                if c + 1 < cols {
+                    put(node, node + 1);
+                } else if periodic {
+                    put(node, r * cols);
+                }
+                if r + 1 < rows {
+                    put(node, node + cols);
+                } else if periodic {
+                    put(node, c);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new("rows", FixedRuleOptionType::PosInt, "number of rows")
+                    .required(),
+                FixedRuleOptionSpec::new("cols", FixedRuleOptionType::PosInt, "number of columns")
+                    .required(),
+                FixedRuleOptionSpec::new(
+                    "periodic",
+                    FixedRuleOptionType::Bool,
+                    "whether to wrap around the borders",
+                )
+                .default_value(false),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+impl FixedRule for CompleteGraph {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let n = payload.pos_integer_option("n", None)? as u64;
+        let directed = payload.bool_option("directed", Some(false))?;
+        for u in 0..n {
+            for v in 0..n {
+                if u != v && (directed || u < v) {
+                    put_edge(out, u, v);
+                }
+            }
+            poison.check()?;
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new("n", FixedRuleOptionType::PosInt, "number of nodes")
+                    .required(),
+                FixedRuleOptionSpec::new(
+                    "directed",
+                    FixedRuleOptionType::Bool,
+                    "whether to generate a directed graph",
+                )
+                .default_value(false),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DbInstance;
+
+    fn count(db: &DbInstance, generator: &str) -> usize {
+        db.run_default(&format!("?[a, b] <~ {generator}"))
+            .unwrap()
+            .rows
+            .len()
+    }
+
+    #[test]
+    fn test_generators() {
+        let db = DbInstance::default();
+        assert_eq!(count(&db, "CompleteGraph(n: 5)"), 10);
+        assert_eq!(count(&db, "CompleteGraph(n: 5, directed: true)"), 20);
+        assert_eq!(count(&db, "GridGraph(rows: 2, cols: 3)"), 7);
+        assert_eq!(
+            count(&db, "GridGraph(rows: 3, cols: 3, periodic: true)"),
+            18
+        );
+        assert_eq!(count(&db, "BarabasiAlbert(n: 10, m: 2, seed: 3)"), 16);
+        assert_eq!(count(&db, "WattsStrogatz(n: 10, k: 4, p: 0.3)"), 20);
+        assert_eq!(count(&db, "ErdosRenyi(n: 30, m: 40)"), 40);
+        assert_eq!(count(&db, "ErdosRenyi(n: 10, p: 1.0)"), 45);
+        assert_eq!(
+            count(
+                &db,
+                "StochasticBlockModel(sizes: [3, 4], p_in: 1.0, p_out: 0.0)"
+            ),
+            9
+        );
+        assert_eq!(
+            count(
+                &db,
+                "StochasticBlockModel(sizes: [3, 4], p_in: 0.0, p_out: 1.0)"
+            ),
+            12
+        );
+
+        let sample = |seed: i64| {
+            db.run_default(&format!(
+                "?[a, b] <~ ErdosRenyi(n: 50, p: 0.1, seed: {seed})"
+            ))
+            .unwrap()
+            .rows
+        };
+        assert_eq!(sample(1), sample(1));
+        assert_ne!(sample(1), sample(2));
+
+        db.run_default(":create g {a: Int, b: Int}").unwrap();
+        db.run_default("?[a, b] <~ GridGraph(rows: 4, cols: 4) :put g {a, b}")
+            .unwrap();
+        let res = db.run_default("?[count(a)] := *g[a, b]").unwrap();
+        assert_eq!(res.into_json()["rows"], serde_json::json!([[24]]));
+    }
+}
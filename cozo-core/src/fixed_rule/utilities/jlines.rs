@@ -24,7 +24,10 @@ use crate::data::expr::Expr;
 use crate::data::json::JsonValue;
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{CannotDetermineArity, FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    CannotDetermineArity, FixedRule, FixedRuleOptionSpec, FixedRuleOptionType, FixedRulePayload,
+    FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
@@ -134,6 +137,43 @@ impl FixedRule for JsonReader {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "url",
+                    FixedRuleOptionType::String,
+                    "where to read the file from, either `file://` or `http(s)://`",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "fields",
+                    FixedRuleOptionType::Expr,
+                    "list of the fields to extract",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "json_lines",
+                    FixedRuleOptionType::Bool,
+                    "whether the file has one JSON object on each line, instead of a JSON array",
+                )
+                .default_value(true),
+                FixedRuleOptionSpec::new(
+                    "null_if_absent",
+                    FixedRuleOptionType::Bool,
+                    "whether missing fields become null instead of an error",
+                )
+                .default_value(false),
+                FixedRuleOptionSpec::new(
+                    "prepend_index",
+                    FixedRuleOptionType::Bool,
+                    "whether to add the row number as the first column",
+                )
+                .default_value(false),
+            ],
+        })
+    }
     fn arity(
         &self,
         opts: &BTreeMap<SmartString<LazyCompact>, Expr>,
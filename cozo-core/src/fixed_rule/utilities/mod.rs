@@ -8,10 +8,18 @@
 
 pub(crate) mod constant;
 pub(crate) mod csv;
+pub(crate) mod generators;
 pub(crate) mod jlines;
+pub(crate) mod named_output;
 pub(crate) mod reorder_sort;
+pub(crate) mod window;
 
 pub(crate) use self::csv::CsvReader;
 pub(crate) use constant::Constant;
+pub(crate) use generators::{
+    BarabasiAlbert, CompleteGraph, ErdosRenyi, GridGraph, StochasticBlockModel, WattsStrogatz,
+};
 pub(crate) use jlines::JsonReader;
+pub(crate) use named_output::NamedOutput;
 pub(crate) use reorder_sort::ReorderSort;
+pub(crate) use window::Window;
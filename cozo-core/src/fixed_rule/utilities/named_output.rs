@@ -0,0 +1,52 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+
+use miette::Result;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::Expr;
+use crate::data::symb::Symbol;
+use crate::fixed_rule::{FixedRule, FixedRulePayload};
+use crate::parse::SourceSpan;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Stands in for an additional output of another fixed rule application, as in
+/// `main[..], levels: stats[..] <~ Rule(..)`. The parser makes `stats` a fixed rule of its own
+/// taking `main` as its only input, so that it is always evaluated after the application
+/// producing it. It then simply takes over the rows the application left for it.
+pub(crate) struct NamedOutput {
+    /// The rule the output is bound to
+    pub(crate) rule: Symbol,
+    pub(crate) arity: usize,
+}
+
+impl FixedRule for NamedOutput {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        _poison: Poison,
+    ) -> Result<()> {
+        if let Some(store) = payload.named_outputs.lock().unwrap().remove(&self.rule) {
+            *out = store;
+        }
+        Ok(())
+    }
+
+    fn arity(
+        &self,
+        _options: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        _span: SourceSpan,
+    ) -> Result<usize> {
+        Ok(self.arity)
+    }
+}
@@ -14,11 +14,15 @@ use smartstring::{LazyCompact, SmartString};
 
 use crate::data::expr::{eval_bytecode, Expr};
 use crate::data::functions::OP_LIST;
-use crate::data::program::WrongFixedRuleOptionError;
+use crate::data::program::{SortDir, WrongFixedRuleOptionError};
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
-use crate::fixed_rule::{CannotDetermineArity, FixedRule, FixedRulePayload};
+use crate::fixed_rule::{
+    CannotDetermineArity, FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType,
+    FixedRulePayload, FixedRuleSchema,
+};
 use crate::parse::SourceSpan;
+use crate::query::sort::compare_by_sorters;
 use crate::runtime::db::Poison;
 use crate::runtime::temp_store::RegularTempStore;
 
@@ -88,11 +92,15 @@ impl FixedRule for ReorderSort {
             buffer.push(s_tuple);
             poison.check()?;
         }
-        if sort_descending {
-            buffer.sort_by(|l, r| r.last().cmp(&l.last()));
-        } else {
-            buffer.sort_by(|l, r| l.last().cmp(&r.last()));
-        }
+        let sorters = [(
+            out_list.len(),
+            if sort_descending {
+                SortDir::Dsc
+            } else {
+                SortDir::Asc
+            },
+        )];
+        buffer.sort_by(|l, r| compare_by_sorters(l, r, &sorters));
 
         let mut count = 0usize;
         let mut rank = 0usize;
@@ -124,6 +132,48 @@ impl FixedRule for ReorderSort {
         Ok(())
     }
 
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new("rel", 0, "the relation to sort")],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "out",
+                    FixedRuleOptionType::Expr,
+                    "list of the expressions to output",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "sort_by",
+                    FixedRuleOptionType::Expr,
+                    "expression to sort by, defaulting to no sorting",
+                ),
+                FixedRuleOptionSpec::new(
+                    "descending",
+                    FixedRuleOptionType::Bool,
+                    "whether to sort in descending order",
+                )
+                .default_value(false),
+                FixedRuleOptionSpec::new(
+                    "break_ties",
+                    FixedRuleOptionType::Bool,
+                    "whether rows sorting equal get distinct positions",
+                )
+                .default_value(false),
+                FixedRuleOptionSpec::new(
+                    "skip",
+                    FixedRuleOptionType::NonNegInt,
+                    "number of rows to skip",
+                )
+                .default_value(0),
+                FixedRuleOptionSpec::new(
+                    "take",
+                    FixedRuleOptionType::NonNegInt,
+                    "number of rows to take, or all rows when 0",
+                )
+                .default_value(0),
+            ],
+        })
+    }
     fn arity(
         &self,
         opts: &BTreeMap<SmartString<LazyCompact>, Expr>,
@@ -0,0 +1,543 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+
+use itertools::Itertools;
+use miette::{bail, Result};
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::{eval_bytecode, Bytecode, Expr};
+use crate::data::functions::{op_add, op_max, op_min, OP_LIST};
+use crate::data::program::{SortDir, WrongFixedRuleOptionError};
+use crate::data::symb::Symbol;
+use crate::data::value::DataValue;
+use crate::fixed_rule::{
+    CannotDetermineArity, FixedRule, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType,
+    FixedRulePayload, FixedRuleSchema,
+};
+use crate::parse::SourceSpan;
+use crate::query::sort::compare_by_sorters;
+use crate::runtime::db::Poison;
+use crate::runtime::temp_store::RegularTempStore;
+
+/// Computes window functions over sorted partitions of the input relation.
+///
+/// Each output row consists of the values of `out` followed by the value of every
+/// entry of `functions` for that row. Frames are counted in rows, so rows that tie
+/// on `sort_by` still get different running sums.
+pub(crate) struct Window;
+
+#[derive(Debug, Clone, Copy)]
+enum MovingAggr {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+}
+
+#[derive(Debug, Clone)]
+enum WindowFn {
+    RowNumber,
+    Rank,
+    DenseRank,
+    Lag(usize, DataValue),
+    Lead(usize, DataValue),
+    CumulativeSum,
+    Moving(MovingAggr, usize, usize),
+}
+
+/// Splits an option into its elements if it is a list, otherwise treats it as a single element.
+fn option_elements(expr: Expr) -> Vec<Expr> {
+    match expr {
+        Expr::Const {
+            val: DataValue::List(l),
+            span,
+        } => l
+            .into_iter()
+            .map(|val| Expr::Const { val, span })
+            .collect_vec(),
+        Expr::Apply { op, args, .. } if *op == OP_LIST => args.to_vec(),
+        expr => vec![expr],
+    }
+}
+
+fn parse_window_fn(
+    spec: Expr,
+    payload: &FixedRulePayload<'_, '_>,
+) -> Result<(WindowFn, Option<Expr>)> {
+    let bad = |help: String| WrongFixedRuleOptionError {
+        name: "functions".to_string(),
+        span: payload.span(),
+        rule_name: payload.name().to_string(),
+        help,
+    };
+    let mut args = option_elements(spec).into_iter();
+    let name = match args.next() {
+        Some(Expr::Const {
+            val: DataValue::Str(s),
+            ..
+        }) => s,
+        _ => bail!(bad(
+            "Each window function must be a name, or a list starting with a name".to_string()
+        )),
+    };
+    let args = args.collect_vec();
+    let n_args = args.len();
+    let mut args = args.into_iter();
+    let arg = args.next();
+    let const_arg = |e: Option<Expr>, default: DataValue| -> Result<DataValue> {
+        match e {
+            None => Ok(default),
+            Some(e) => e.eval_to_const(),
+        }
+    };
+    let count_arg = |v: DataValue, what: &str| -> Result<usize> {
+        match v.get_int() {
+            Some(i) if i >= 0 => Ok(i as usize),
+            _ => bail!(bad(format!(
+                "The {what} of '{name}' must be a non-negative integer"
+            ))),
+        }
+    };
+    let check_args = |min: usize, max: usize| -> Result<()> {
+        if n_args < min || n_args > max {
+            bail!(bad(format!(
+                "'{name}' takes between {min} and {max} arguments, {n_args} given"
+            )))
+        }
+        Ok(())
+    };
+
+    let func = match &name as &str {
+        "row_number" | "rank" | "dense_rank" => {
+            check_args(0, 0)?;
+            match &name as &str {
+                "row_number" => WindowFn::RowNumber,
+                "rank" => WindowFn::Rank,
+                _ => WindowFn::DenseRank,
+            }
+        }
+        "lag" | "lead" => {
+            check_args(1, 3)?;
+            let offset = count_arg(const_arg(args.next(), DataValue::from(1))?, "offset")?;
+            let default = const_arg(args.next(), DataValue::Null)?;
+            if name == "lag" {
+                WindowFn::Lag(offset, default)
+            } else {
+                WindowFn::Lead(offset, default)
+            }
+        }
+        "cumulative_sum" => {
+            check_args(1, 1)?;
+            WindowFn::CumulativeSum
+        }
+        "moving_sum" | "moving_avg" | "moving_min" | "moving_max" | "moving_count" => {
+            check_args(2, 3)?;
+            let preceding = count_arg(const_arg(args.next(), DataValue::Null)?, "preceding size")?;
+            let following = count_arg(
+                const_arg(args.next(), DataValue::from(0))?,
+                "following size",
+            )?;
+            let aggr = match &name as &str {
+                "moving_sum" => MovingAggr::Sum,
+                "moving_avg" => MovingAggr::Avg,
+                "moving_min" => MovingAggr::Min,
+                "moving_max" => MovingAggr::Max,
+                _ => MovingAggr::Count,
+            };
+            WindowFn::Moving(aggr, preceding, following)
+        }
+        _ => bail!(bad(format!("Unknown window function '{name}'"))),
+    };
+    Ok((func, arg))
+}
+
+fn moving_aggr(aggr: MovingAggr, vals: &[&DataValue]) -> Result<DataValue> {
+    let vals = vals
+        .iter()
+        .copied()
+        .filter(|v| **v != DataValue::Null)
+        .cloned()
+        .collect_vec();
+    Ok(match aggr {
+        MovingAggr::Count => DataValue::from(vals.len() as i64),
+        _ if vals.is_empty() => DataValue::Null,
+        MovingAggr::Sum => op_add(&vals)?,
+        MovingAggr::Avg => {
+            let sum = op_add(&vals)?.get_float().unwrap();
+            DataValue::from(sum / vals.len() as f64)
+        }
+        MovingAggr::Min => op_min(&vals)?,
+        MovingAggr::Max => op_max(&vals)?,
+    })
+}
+
+struct WindowRow {
+    sort_key: Vec<DataValue>,
+    out: Vec<DataValue>,
+    args: Vec<DataValue>,
+}
+
+impl FixedRule for Window {
+    fn run(
+        &self,
+        payload: FixedRulePayload<'_, '_>,
+        out: &mut RegularTempStore,
+        poison: Poison,
+    ) -> Result<()> {
+        let in_rel = payload.get_input(0)?;
+
+        let mut out_list = match payload.expr_option("out", None)? {
+            e @ Expr::Const {
+                val: DataValue::List(_),
+                ..
+            } => option_elements(e),
+            Expr::Apply { op, args, .. } if *op == OP_LIST => args.to_vec(),
+            _ => {
+                bail!(WrongFixedRuleOptionError {
+                    name: "out".to_string(),
+                    span: payload.span(),
+                    rule_name: payload.name().to_string(),
+                    help: "This option must evaluate to a list".to_string()
+                })
+            }
+        };
+        let mut partition_by = option_elements(payload.expr_option(
+            "partition_by",
+            Some(Expr::Const {
+                val: DataValue::List(vec![]),
+                span: SourceSpan(0, 0),
+            }),
+        )?);
+        let mut sort_by = option_elements(payload.expr_option(
+            "sort_by",
+            Some(Expr::Const {
+                val: DataValue::List(vec![]),
+                span: SourceSpan(0, 0),
+            }),
+        )?);
+        let sort_descending = payload.bool_option("descending", Some(false))?;
+        let mut functions = vec![];
+        let mut fn_args = vec![];
+        for spec in option_elements(payload.expr_option("functions", None)?) {
+            let (func, arg) = parse_window_fn(spec, &payload)?;
+            functions.push(func);
+            fn_args.push(arg.unwrap_or(Expr::Const {
+                val: DataValue::Null,
+                span: SourceSpan(0, 0),
+            }));
+        }
+
+        let binding_map = in_rel.get_binding_map(0);
+        let compile = |exprs: &mut [Expr]| -> Result<Vec<Vec<Bytecode>>> {
+            exprs
+                .iter_mut()
+                .map(|e| {
+                    e.fill_binding_indices(&binding_map)?;
+                    e.compile()
+                })
+                .try_collect()
+        };
+        let out_bytecodes = compile(&mut out_list)?;
+        let partition_bytecodes = compile(&mut partition_by)?;
+        let sort_bytecodes = compile(&mut sort_by)?;
+        let arg_bytecodes = compile(&mut fn_args)?;
+        let mut stack = vec![];
+
+        let mut partitions: BTreeMap<Vec<DataValue>, Vec<WindowRow>> = BTreeMap::new();
+        for tuple in in_rel.iter()? {
+            let tuple = tuple?;
+            let mut eval_all = |codes: &[Vec<Bytecode>]| -> Result<Vec<DataValue>> {
+                codes
+                    .iter()
+                    .map(|code| eval_bytecode(code, &tuple, &mut stack))
+                    .try_collect()
+            };
+            let partition = eval_all(&partition_bytecodes)?;
+            let row = WindowRow {
+                sort_key: eval_all(&sort_bytecodes)?,
+                out: eval_all(&out_bytecodes)?,
+                args: eval_all(&arg_bytecodes)?,
+            };
+            partitions.entry(partition).or_default().push(row);
+            poison.check()?;
+        }
+
+        let sort_dir = if sort_descending {
+            SortDir::Dsc
+        } else {
+            SortDir::Asc
+        };
+        let sorters = (0..sort_bytecodes.len())
+            .map(|i| (i, sort_dir))
+            .collect_vec();
+        for rows in partitions.values_mut() {
+            rows.sort_by(|l, r| compare_by_sorters(&l.sort_key, &r.sort_key, &sorters));
+            let rows: &[WindowRow] = rows;
+            let n = rows.len();
+            let mut results: Vec<Vec<DataValue>> =
+                rows.iter().map(|row| row.out.clone()).collect_vec();
+            for (fn_idx, func) in functions.iter().enumerate() {
+                let arg_of = move |i: usize| &rows[i].args[fn_idx];
+                match func {
+                    WindowFn::RowNumber => {
+                        for (i, res) in results.iter_mut().enumerate() {
+                            res.push(DataValue::from(i as i64 + 1));
+                        }
+                    }
+                    WindowFn::Rank | WindowFn::DenseRank => {
+                        let mut rank = 0;
+                        let mut dense_rank = 0;
+                        for (i, res) in results.iter_mut().enumerate() {
+                            if i == 0 || rows[i - 1].sort_key != rows[i].sort_key {
+                                rank = i + 1;
+                                dense_rank += 1;
+                            }
+                            let r = if matches!(func, WindowFn::Rank) {
+                                rank
+                            } else {
+                                dense_rank
+                            };
+                            res.push(DataValue::from(r as i64));
+                        }
+                    }
+                    WindowFn::Lag(offset, default) => {
+                        for (i, res) in results.iter_mut().enumerate() {
+                            res.push(if i >= *offset {
+                                arg_of(i - offset).clone()
+                            } else {
+                                default.clone()
+                            });
+                        }
+                    }
+                    WindowFn::Lead(offset, default) => {
+                        for (i, res) in results.iter_mut().enumerate() {
+                            res.push(if i + offset < n {
+                                arg_of(i + offset).clone()
+                            } else {
+                                default.clone()
+                            });
+                        }
+                    }
+                    WindowFn::CumulativeSum => {
+                        let mut acc = DataValue::from(0);
+                        for (i, res) in results.iter_mut().enumerate() {
+                            let v = arg_of(i);
+                            if *v != DataValue::Null {
+                                acc = op_add(&[acc, v.clone()])?;
+                            }
+                            res.push(acc.clone());
+                        }
+                    }
+                    WindowFn::Moving(aggr, preceding, following) => {
+                        for (i, res) in results.iter_mut().enumerate() {
+                            let from = i.saturating_sub(*preceding);
+                            let to = i.saturating_add(*following).min(n - 1);
+                            let frame = (from..=to).map(arg_of).collect_vec();
+                            res.push(moving_aggr(*aggr, &frame)?);
+                        }
+                    }
+                }
+                poison.check()?;
+            }
+            for res in results {
+                out.put(res);
+            }
+        }
+        Ok(())
+    }
+
+    fn schema(&self) -> Option<FixedRuleSchema> {
+        Some(FixedRuleSchema {
+            inputs: vec![FixedRuleInputSpec::new(
+                "rel",
+                0,
+                "the relation to compute over",
+            )],
+            options: vec![
+                FixedRuleOptionSpec::new(
+                    "out",
+                    FixedRuleOptionType::Expr,
+                    "list of the expressions to output",
+                )
+                .required(),
+                FixedRuleOptionSpec::new(
+                    "partition_by",
+                    FixedRuleOptionType::Expr,
+                    "list of expressions partitioning the rows",
+                ),
+                FixedRuleOptionSpec::new(
+                    "sort_by",
+                    FixedRuleOptionType::Expr,
+                    "list of expressions ordering each partition",
+                ),
+                FixedRuleOptionSpec::new(
+                    "descending",
+                    FixedRuleOptionType::Bool,
+                    "whether to sort in descending order",
+                )
+                .default_value(false),
+                FixedRuleOptionSpec::new(
+                    "functions",
+                    FixedRuleOptionType::Expr,
+                    "list of the window functions to compute",
+                )
+                .required(),
+            ],
+        })
+    }
+    fn arity(
+        &self,
+        opts: &BTreeMap<SmartString<LazyCompact>, Expr>,
+        _rule_head: &[Symbol],
+        span: SourceSpan,
+    ) -> Result<usize> {
+        let list_len = |name: &str| -> Result<usize> {
+            let opt = opts.get(name).ok_or_else(|| {
+                CannotDetermineArity(
+                    "Window".to_string(),
+                    format!("option '{name}' not provided"),
+                    span,
+                )
+            })?;
+            Ok(match opt {
+                Expr::Const {
+                    val: DataValue::List(l),
+                    ..
+                } => l.len(),
+                Expr::Apply { op, args, .. } if **op == OP_LIST => args.len(),
+                _ => bail!(CannotDetermineArity(
+                    "Window".to_string(),
+                    format!("invalid option '{name}' given, expect a list"),
+                    span
+                )),
+            })
+        };
+        Ok(list_len("out")? + list_len("functions")?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::DbInstance;
+
+    #[test]
+    fn test_window() {
+        let db = DbInstance::default();
+        let res = db
+            .run_default(
+                r#"
+        sales[region, day, amount] <- [['east', 1, 10],
+                                       ['east', 2, 30],
+                                       ['east', 3, 30],
+                                       ['east', 4, 5],
+                                       ['west', 1, 7],
+                                       ['west', 2, 3]]
+        ?[region, day, amount, n, rk, drk, prev, next, total, avg2] <~ Window(
+            sales[region, day, amount],
+            out: [region, day, amount],
+            partition_by: region,
+            sort_by: day,
+            functions: [
+                'row_number',
+                ['rank'],
+                ['dense_rank'],
+                ['lag', amount],
+                ['lead', amount, 1, 0],
+                ['cumulative_sum', amount],
+                ['moving_avg', amount, 1],
+            ]
+        )
+        :order region, day
+        "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["east", 1, 10, 1, 1, 1, null, 30, 10, 10.0],
+                ["east", 2, 30, 2, 2, 2, 10, 30, 40, 20.0],
+                ["east", 3, 30, 3, 3, 3, 30, 5, 70, 30.0],
+                ["east", 4, 5, 4, 4, 4, 30, 0, 75, 17.5],
+                ["west", 1, 7, 1, 1, 1, null, 3, 7, 7.0],
+                ["west", 2, 3, 2, 2, 2, 7, 0, 10, 5.0],
+            ])
+        );
+
+        let res = db
+            .run_default(
+                r#"
+        scores[name, score] <- [['a', 90], ['b', 80], ['c', 90], ['d', 70]]
+        ?[name, rk, drk, best] <~ Window(
+            scores[name, score],
+            out: [name],
+            sort_by: score,
+            descending: true,
+            functions: [['rank'], ['dense_rank'], ['moving_max', score, 10, 10]]
+        )
+        :order rk, name
+        "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"],
+            json!([
+                ["a", 1, 1, 90],
+                ["c", 1, 1, 90],
+                ["b", 3, 2, 90],
+                ["d", 4, 3, 90]
+            ])
+        );
+
+        let res = db
+            .run_default(
+                r#"
+        r[a, b] <- [[1, 'x'], [2, 'y'], [1, 'z'], [2, 'w']]
+        ?[a, b, n] <~ Window(r[a, b], out: [a, b], sort_by: [a, b], descending: true,
+                             functions: ['row_number'])
+        :order n
+        "#,
+            )
+            .unwrap()
+            .into_json();
+        let ordered = db
+            .run_default(
+                r#"
+        r[a, b] <- [[1, 'x'], [2, 'y'], [1, 'z'], [2, 'w']]
+        ?[a, b] := r[a, b]
+        :order -a, -b
+        "#,
+            )
+            .unwrap()
+            .into_json();
+        assert_eq!(
+            res["rows"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|row| json!([row[0], row[1]]))
+                .collect::<Vec<_>>(),
+            *ordered["rows"].as_array().unwrap()
+        );
+
+        assert!(db
+            .run_default(
+                r#"
+        r[x] <- [[1]]
+        ?[x, y] <~ Window(r[x], out: [x], functions: [['median', x]])
+        "#
+            )
+            .is_err());
+    }
+}
@@ -48,8 +48,11 @@ use miette::{
 };
 use serde_json::json;
 
-pub use data::value::{DataValue, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs};
-pub use fixed_rule::{FixedRule, FixedRuleInputRelation, FixedRulePayload};
+pub use data::value::{DataValue, Decimal, Num, RegexWrapper, UuidWrapper, Validity, ValidityTs};
+pub use fixed_rule::{
+    FixedRule, FixedRuleInputRelation, FixedRuleInputSpec, FixedRuleOptionSpec, FixedRuleOptionType,
+    FixedRuleOutputs, FixedRulePayload, FixedRuleSchema,
+};
 pub use runtime::db::Db;
 pub use runtime::db::NamedRows;
 pub use runtime::relation::decode_tuple_from_kv;
@@ -69,7 +72,10 @@ pub use crate::data::expr::Expr;
 use crate::data::json::JsonValue;
 pub use crate::data::symb::Symbol;
 pub use crate::data::value::{JsonData, Vector};
-pub use crate::fixed_rule::SimpleFixedRule;
+pub use crate::fixed_rule::{
+    HostFixedRuleCall, HostFixedRuleError, PagedFixedRuleCall, PagedFixedRuleRequest,
+    SimpleFixedRule,
+};
 pub use crate::parse::SourceSpan;
 pub use crate::runtime::callback::CallbackOp;
 pub use crate::runtime::db::evaluate_expressions;
@@ -11,6 +11,7 @@ use std::collections::btree_map::Entry;
 use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::iter;
 use std::sync::Arc;
 
 use either::{Left, Right};
@@ -32,6 +33,7 @@ use crate::data::relation::{ColType, ColumnDef, NullableColType, StoredRelationM
 use crate::data::symb::{Symbol, PROG_ENTRY};
 use crate::data::value::{DataValue, ValidityTs};
 use crate::fixed_rule::utilities::constant::Constant;
+use crate::fixed_rule::utilities::NamedOutput;
 use crate::fixed_rule::{FixedRuleHandle, FixedRuleNotFoundError};
 use crate::parse::expr::build_expr;
 use crate::parse::schema::parse_schema;
@@ -163,22 +165,25 @@ pub(crate) fn parse_query(
             }
             Rule::fixed_rule => {
                 let rule_span = pair.extract_span();
-                let (name, apply) = parse_fixed_rule(pair, param_pool, fixed_rules, cur_vld)?;
+                let (name, apply, named_outputs) =
+                    parse_fixed_rule(pair, param_pool, fixed_rules, cur_vld)?;
 
-                match progs.entry(name) {
-                    Entry::Vacant(e) => {
-                        e.insert(InputInlineRulesOrFixed::Fixed { fixed: apply });
-                    }
-                    Entry::Occupied(e) => {
-                        let found_name = e.key().name.to_string();
-                        let mut found_span = match e.get() {
-                            InputInlineRulesOrFixed::Rules { rules } => {
-                                rules.iter().map(|r| r.span).collect_vec()
-                            }
-                            InputInlineRulesOrFixed::Fixed { fixed } => vec![fixed.span],
-                        };
-                        found_span.push(rule_span);
-                        bail!(MultipleRuleDefinitionError(found_name, found_span));
+                for (name, apply) in iter::once((name, apply)).chain(named_outputs) {
+                    match progs.entry(name) {
+                        Entry::Vacant(e) => {
+                            e.insert(InputInlineRulesOrFixed::Fixed { fixed: apply });
+                        }
+                        Entry::Occupied(e) => {
+                            let found_name = e.key().name.to_string();
+                            let mut found_span = match e.get() {
+                                InputInlineRulesOrFixed::Rules { rules } => {
+                                    rules.iter().map(|r| r.span).collect_vec()
+                                }
+                                InputInlineRulesOrFixed::Fixed { fixed } => vec![fixed.span],
+                            };
+                            found_span.push(rule_span);
+                            bail!(MultipleRuleDefinitionError(found_name, found_span));
+                        }
                     }
                 }
             }
@@ -253,6 +258,7 @@ pub(crate) fn parse_query(
                             arity,
                             span,
                             fixed_impl: Arc::new(fixed_impl),
+                            named_outputs: Default::default(),
                         },
                     },
                 );
@@ -857,8 +863,8 @@ fn parse_fixed_rule(
     param_pool: &BTreeMap<String, DataValue>,
     fixed_rules: &BTreeMap<String, Arc<Box<dyn FixedRule>>>,
     cur_vld: ValidityTs,
-) -> Result<(Symbol, FixedRuleApply)> {
-    let mut src = src.into_inner();
+) -> Result<(Symbol, FixedRuleApply, Vec<(Symbol, FixedRuleApply)>)> {
+    let mut src = src.into_inner().peekable();
     let (out_symbol, head, aggr) = parse_rule_head(src.next().unwrap(), param_pool)?;
 
     #[derive(Debug, Error, Diagnostic)]
@@ -875,6 +881,18 @@ fn parse_fixed_rule(
         ensure!(a.is_none(), AggrInfixedError(v.span))
     }
 
+    let mut output_heads = vec![];
+    while let Some(p) = src.next_if(|p| p.as_rule() == Rule::fixed_named_output) {
+        let mut els = p.into_inner();
+        let output_name = els.next().unwrap();
+        let (output_symbol, output_head, output_aggr) =
+            parse_rule_head(els.next().unwrap(), param_pool)?;
+        for (a, v) in output_aggr.iter().zip(output_head.iter()) {
+            ensure!(a.is_none(), AggrInfixedError(v.span))
+        }
+        output_heads.push((output_name, output_symbol, output_head));
+    }
+
     let mut seen_bindings = BTreeSet::new();
     let mut binding_gen_id = 0;
 
@@ -1003,6 +1021,30 @@ fn parse_fixed_rule(
                             span,
                         })
                     }
+                    Rule::fixed_graph_rel => {
+                        let mut els = inner.into_inner();
+                        let name = els.next().unwrap();
+                        let mut bindings = Vec::with_capacity(els.size_hint().1.unwrap_or(4));
+                        for v in els {
+                            let s = v.as_str();
+                            if s == "_" {
+                                let symb =
+                                    Symbol::new(format!("*_*{binding_gen_id}"), v.extract_span());
+                                binding_gen_id += 1;
+                                bindings.push(symb);
+                            } else {
+                                if !seen_bindings.insert(s) {
+                                    bail!(DuplicateBindingError(v.extract_span()))
+                                }
+                                bindings.push(Symbol::new(s, v.extract_span()));
+                            }
+                        }
+                        rule_args.push(FixedRuleArg::Graph {
+                            name: Symbol::new(name.as_str(), name.extract_span()),
+                            bindings,
+                            span,
+                        })
+                    }
                     _ => unreachable!(),
                 }
             }
@@ -1022,6 +1064,9 @@ fn parse_fixed_rule(
     let fixed_impl = fixed_rules
         .get(&fixed.name as &str)
         .ok_or_else(|| FixedRuleNotFoundError(fixed.name.to_string(), name_pair.extract_span()))?;
+    if let Some(schema) = fixed_impl.schema() {
+        schema.check_application(&fixed.name, &options, rule_args.len(), args_list_span)?;
+    }
     fixed_impl.init_options(&mut options, args_list_span)?;
     let arity = fixed_impl.arity(&options, &head, name_pair.extract_span())?;
 
@@ -1030,6 +1075,56 @@ fn parse_fixed_rule(
         FixedRuleHeadArityMismatch(arity, head.len(), args_list_span)
     );
 
+    #[derive(Debug, Error, Diagnostic)]
+    #[error("Fixed rule '{0}' has no output named '{1}'")]
+    #[diagnostic(code(parser::fixed_rule_output_not_found))]
+    struct FixedRuleOutputNotFound(String, String, #[label] SourceSpan);
+
+    #[derive(Debug, Error, Diagnostic)]
+    #[error("Output '{0}' is bound more than once")]
+    #[diagnostic(code(parser::duplicate_fixed_rule_output))]
+    struct DuplicateFixedRuleOutput(String, #[label] SourceSpan);
+
+    let mut named_outputs = BTreeMap::new();
+    let mut output_rules = vec![];
+    for (output_name, output_symbol, output_head) in output_heads {
+        let output = SmartString::from(output_name.as_str());
+        let output_span = output_name.extract_span();
+        let output_arity = fixed_impl
+            .output_arity(&output, &options, output_span)?
+            .ok_or_else(|| {
+                FixedRuleOutputNotFound(fixed.name.to_string(), output.to_string(), output_span)
+            })?;
+        ensure!(
+            output_head.is_empty() || output_arity == output_head.len(),
+            FixedRuleHeadArityMismatch(output_arity, output_head.len(), output_span)
+        );
+        ensure!(
+            !named_outputs.contains_key(&output),
+            DuplicateFixedRuleOutput(output.to_string(), output_span)
+        );
+        named_outputs.insert(output.clone(), output_symbol.clone());
+        // evaluated after the main application, from which it takes over the rows of the output
+        let relay = FixedRuleApply {
+            fixed_handle: FixedRuleHandle::new(&format!("{}.{}", fixed.name, output), output_span),
+            rule_args: vec![FixedRuleArg::InMem {
+                name: out_symbol.clone(),
+                bindings: vec![],
+                span: output_span,
+            }],
+            options: Default::default(),
+            head: output_head,
+            arity: output_arity,
+            span: output_span,
+            fixed_impl: Arc::new(Box::new(NamedOutput {
+                rule: output_symbol.clone(),
+                arity: output_arity,
+            })),
+            named_outputs: Default::default(),
+        };
+        output_rules.push((output_symbol, relay));
+    }
+
     Ok((
         out_symbol,
         FixedRuleApply {
@@ -1040,7 +1135,9 @@ fn parse_fixed_rule(
             arity,
             span: args_list_span,
             fixed_impl: fixed_impl.clone(),
+            named_outputs,
         },
+        output_rules,
     ))
 }
 
@@ -1078,6 +1175,7 @@ fn make_empty_const_rule(prog: &mut InputProgram, bindings: &[Symbol]) {
                 arity: bindings.len(),
                 span: Default::default(),
                 fixed_impl: Arc::new(Box::new(Constant)),
+                named_outputs: Default::default(),
             },
         },
     );
@@ -15,7 +15,7 @@ use thiserror::Error;
 
 use crate::data::relation::{VecElementType, ColType, ColumnDef, NullableColType, StoredRelationMetadata};
 use crate::data::symb::Symbol;
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, DECIMAL_MAX_SCALE};
 use crate::parse::expr::{build_expr};
 use crate::parse::{ExtractSpan, Pair, Rule, SourceSpan};
 
@@ -113,6 +113,31 @@ fn parse_type_inner(pair: Pair<'_>) -> Result<ColType> {
         Rule::uuid_type => ColType::Uuid,
         Rule::json_type => ColType::Json,
         Rule::validity_type => ColType::Validity,
+        Rule::decimal_type => {
+            let scale = match pair.into_inner().next() {
+                None => None,
+                Some(scale_p) => {
+                    let span = scale_p.extract_span();
+                    let scale = scale_p
+                        .as_str()
+                        .replace('_', "")
+                        .parse::<u32>()
+                        .into_diagnostic()?;
+
+                    #[derive(Debug, Error, Diagnostic)]
+                    #[error("Decimal scale {0} exceeds the maximum of {1}")]
+                    #[diagnostic(code(parser::bad_decimal_scale))]
+                    struct BadDecimalScale(u32, u32, #[label] SourceSpan);
+
+                    ensure!(
+                        scale <= DECIMAL_MAX_SCALE,
+                        BadDecimalScale(scale, DECIMAL_MAX_SCALE, span)
+                    );
+                    Some(scale)
+                }
+            };
+            ColType::Decimal(scale)
+        }
         Rule::list_type => {
             let mut inner = pair.into_inner();
             let eltype = parse_nullable_type(inner.next().unwrap())?;
@@ -45,8 +45,13 @@ pub(crate) enum SysOp {
     CreateVectorIndex(HnswIndexConfig),
     CreateFtsIndex(FtsIndexConfig),
     CreateMinHashLshIndex(MinHashLshConfig),
+    CreateSpatialIndex(SpatialIndexConfig),
+    CreateJsonIndex(JsonIndexConfig),
     RemoveIndex(Symbol, Symbol),
-    DescribeRelation(Symbol, SmartString<LazyCompact>)
+    DescribeRelation(Symbol, SmartString<LazyCompact>),
+    CreateGraphProjection(Symbol, Symbol, Vec<Symbol>),
+    RemoveGraphProjection(Symbol),
+    ListGraphProjections,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -72,6 +77,21 @@ pub(crate) struct MinHashLshConfig {
     pub(crate) target_threshold: OrderedFloat<f64>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct SpatialIndexConfig {
+    pub(crate) base_relation: SmartString<LazyCompact>,
+    pub(crate) index_name: SmartString<LazyCompact>,
+    pub(crate) extractor: String,
+    pub(crate) max_entries: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct JsonIndexConfig {
+    pub(crate) base_relation: SmartString<LazyCompact>,
+    pub(crate) index_name: SmartString<LazyCompact>,
+    pub(crate) extractor: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub(crate) struct HnswIndexConfig {
     pub(crate) base_relation: SmartString<LazyCompact>,
@@ -140,6 +160,34 @@ pub(crate) fn parse_sys(
             SysOp::DescribeRelation(rel, description)
         }
         Rule::list_relations_op => SysOp::ListRelations,
+        Rule::list_graphs_op => SysOp::ListGraphProjections,
+        Rule::graph_op => {
+            let inner = inner.into_inner().next().unwrap();
+            match inner.as_rule() {
+                Rule::graph_create => {
+                    let mut inner = inner.into_inner();
+                    let name_p = inner.next().unwrap();
+                    let name = Symbol::new(name_p.as_str(), name_p.extract_span());
+                    let rel_p = inner.next().unwrap();
+                    let rel = Symbol::new(
+                        rel_p.as_str().strip_prefix('*').unwrap(),
+                        rel_p.extract_span(),
+                    );
+                    let columns = inner
+                        .map(|col_p| Symbol::new(col_p.as_str(), col_p.extract_span()))
+                        .collect_vec();
+                    SysOp::CreateGraphProjection(name, rel, columns)
+                }
+                Rule::graph_drop => {
+                    let name_p = inner.into_inner().next().unwrap();
+                    SysOp::RemoveGraphProjection(Symbol::new(
+                        name_p.as_str(),
+                        name_p.extract_span(),
+                    ))
+                }
+                _ => unreachable!(),
+            }
+        }
         Rule::remove_relations_op => {
             let rel = inner
                 .into_inner()
@@ -401,6 +449,118 @@ pub(crate) fn parse_sys(
                 r => unreachable!("{:?}", r),
             }
         }
+        Rule::spatial_idx_op => {
+            let inner = inner.into_inner().next().unwrap();
+            match inner.as_rule() {
+                Rule::index_create_adv => {
+                    let mut inner = inner.into_inner();
+                    let rel = inner.next().unwrap();
+                    let name = inner.next().unwrap();
+                    let mut extractor = "".to_string();
+                    let mut extract_filter = "".to_string();
+                    let mut max_entries = 16;
+                    for opt_pair in inner {
+                        let mut opt_inner = opt_pair.into_inner();
+                        let opt_name = opt_inner.next().unwrap();
+                        let opt_val = opt_inner.next().unwrap();
+                        match opt_name.as_str() {
+                            "extractor" => {
+                                let mut ex = build_expr(opt_val, param_pool)?;
+                                ex.partial_eval()?;
+                                extractor = ex.to_string();
+                            }
+                            "extract_filter" => {
+                                let mut ex = build_expr(opt_val, param_pool)?;
+                                ex.partial_eval()?;
+                                extract_filter = ex.to_string();
+                            }
+                            "max_entries" => {
+                                let mut expr = build_expr(opt_val, param_pool)?;
+                                expr.partial_eval()?;
+                                let v = expr.eval_to_const()?;
+                                max_entries = v
+                                    .get_int()
+                                    .ok_or_else(|| miette!("max_entries must be an integer"))?
+                                    as usize;
+                            }
+                            _ => bail!("Unknown option {} for spatial index", opt_name.as_str()),
+                        }
+                    }
+                    ensure!(!extractor.is_empty(), "spatial index requires an extractor");
+                    ensure!(max_entries >= 4, "max_entries must be at least 4");
+                    if !extract_filter.is_empty() {
+                        extractor = format!("if({}, {})", extract_filter, extractor);
+                    }
+                    let config = SpatialIndexConfig {
+                        base_relation: SmartString::from(rel.as_str()),
+                        index_name: SmartString::from(name.as_str()),
+                        extractor,
+                        max_entries,
+                    };
+                    SysOp::CreateSpatialIndex(config)
+                }
+                Rule::index_drop => {
+                    let mut inner = inner.into_inner();
+                    let rel = inner.next().unwrap();
+                    let name = inner.next().unwrap();
+                    SysOp::RemoveIndex(
+                        Symbol::new(rel.as_str(), rel.extract_span()),
+                        Symbol::new(name.as_str(), name.extract_span()),
+                    )
+                }
+                r => unreachable!("{:?}", r),
+            }
+        }
+        Rule::json_idx_op => {
+            let inner = inner.into_inner().next().unwrap();
+            match inner.as_rule() {
+                Rule::index_create_adv => {
+                    let mut inner = inner.into_inner();
+                    let rel = inner.next().unwrap();
+                    let name = inner.next().unwrap();
+                    let mut extractor = "".to_string();
+                    let mut extract_filter = "".to_string();
+                    for opt_pair in inner {
+                        let mut opt_inner = opt_pair.into_inner();
+                        let opt_name = opt_inner.next().unwrap();
+                        let opt_val = opt_inner.next().unwrap();
+                        match opt_name.as_str() {
+                            "extractor" => {
+                                let mut ex = build_expr(opt_val, param_pool)?;
+                                ex.partial_eval()?;
+                                extractor = ex.to_string();
+                            }
+                            "extract_filter" => {
+                                let mut ex = build_expr(opt_val, param_pool)?;
+                                ex.partial_eval()?;
+                                extract_filter = ex.to_string();
+                            }
+                            _ => bail!("Unknown option {} for JSON index", opt_name.as_str()),
+                        }
+                    }
+                    ensure!(!extractor.is_empty(), "JSON index requires an extractor");
+                    if !extract_filter.is_empty() {
+                        extractor = format!("if({}, {})", extract_filter, extractor);
+                    }
+                    let config = JsonIndexConfig {
+                        base_relation: SmartString::from(rel.as_str()),
+                        index_name: SmartString::from(name.as_str()),
+                        extractor,
+                    };
+                    SysOp::CreateJsonIndex(config)
+                }
+                Rule::index_drop => {
+                    let mut inner = inner.into_inner();
+                    let rel = inner.next().unwrap();
+                    let name = inner.next().unwrap();
+                    SysOp::RemoveIndex(
+                        Symbol::new(rel.as_str(), rel.extract_span()),
+                        Symbol::new(name.as_str(), name.extract_span()),
+                    )
+                }
+                r => unreachable!("{:?}", r),
+            }
+        }
         Rule::fts_idx_op => {
             let inner = inner.into_inner().next().unwrap();
             match inner.as_rule() {
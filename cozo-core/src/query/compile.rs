@@ -21,7 +21,7 @@ use crate::data::program::{
 use crate::data::symb::Symbol;
 use crate::data::value::DataValue;
 use crate::parse::SourceSpan;
-use crate::query::ra::RelAlgebra;
+use crate::query::ra::{RelAlgebra, TrieAtom, TrieSource};
 use crate::runtime::relation::{AccessLevel, InsufficientAccessLevel};
 use crate::runtime::transact::SessionTx;
 
@@ -176,7 +176,30 @@ impl<'a> SessionTx<'a> {
             serial_id += 1;
             ret
         };
-        for atom in &rule.body {
+        let mut cyclic_atoms = cyclic_join_core(rule.body.iter().map(|atom| match atom {
+            MagicAtom::Rule(rule_app) => Some(&rule_app.args[..]),
+            MagicAtom::Relation(rel_app) if rel_app.valid_at.is_none() => Some(&rel_app.args[..]),
+            _ => None,
+        }));
+        for (i, atom) in rule.body.iter().enumerate() {
+            if cyclic_atoms.iter().next() == Some(&i) {
+                let trie_join = self.compile_trie_join(
+                    cyclic_atoms.iter().map(|j| &rule.body[*j]),
+                    store_arities,
+                )?;
+                let bindings = trie_join.bindings_after_eliminate();
+                // if some variables are bound already, joining the atoms one at a time
+                // uses them to look up only the relevant parts of the relations
+                if bindings.iter().all(|b| !seen_variables.contains(b)) {
+                    seen_variables.extend(bindings);
+                    let span = trie_join.span();
+                    ret = ret.cartesian_join(trie_join, span);
+                    continue;
+                }
+                cyclic_atoms.clear();
+            } else if cyclic_atoms.contains(&i) {
+                continue;
+            }
             match atom {
                 MagicAtom::Rule(rule_app) => {
                     let store_arity = store_arities.get(&rule_app.name).ok_or_else(|| {
@@ -579,6 +602,74 @@ impl<'a> SessionTx<'a> {
                         ret = ret.filter(Expr::build_and(post_filters, s.span))?;
                     }
                 }
+                MagicAtom::SpatialSearch(s) => {
+                    debug_assert!(
+                        seen_variables.contains(&s.query),
+                        "Spatial search query must be bound"
+                    );
+                    let mut own_bindings = vec![];
+                    let mut post_filters = vec![];
+                    for var in s.all_bindings() {
+                        if seen_variables.contains(var) {
+                            let rk = gen_symb(var.span);
+                            post_filters.push(Expr::build_equate(
+                                vec![
+                                    Expr::Binding {
+                                        var: var.clone(),
+                                        tuple_pos: None,
+                                    },
+                                    Expr::Binding {
+                                        var: rk.clone(),
+                                        tuple_pos: None,
+                                    },
+                                ],
+                                var.span,
+                            ));
+                            own_bindings.push(rk);
+                        } else {
+                            seen_variables.insert(var.clone());
+                            own_bindings.push(var.clone());
+                        }
+                    }
+                    ret = ret.spatial_search(s.clone(), own_bindings)?;
+                    if !post_filters.is_empty() {
+                        ret = ret.filter(Expr::build_and(post_filters, s.span))?;
+                    }
+                }
+                MagicAtom::JsonIndexSearch(s) => {
+                    debug_assert!(
+                        seen_variables.contains(&s.query),
+                        "JSON index search query must be bound"
+                    );
+                    let mut own_bindings = vec![];
+                    let mut post_filters = vec![];
+                    for var in s.all_bindings() {
+                        if seen_variables.contains(var) {
+                            let rk = gen_symb(var.span);
+                            post_filters.push(Expr::build_equate(
+                                vec![
+                                    Expr::Binding {
+                                        var: var.clone(),
+                                        tuple_pos: None,
+                                    },
+                                    Expr::Binding {
+                                        var: rk.clone(),
+                                        tuple_pos: None,
+                                    },
+                                ],
+                                var.span,
+                            ));
+                            own_bindings.push(rk);
+                        } else {
+                            seen_variables.insert(var.clone());
+                            own_bindings.push(var.clone());
+                        }
+                    }
+                    ret = ret.json_index_search(s.clone(), own_bindings)?;
+                    if !post_filters.is_empty() {
+                        ret = ret.filter(Expr::build_and(post_filters, s.span))?;
+                    }
+                }
                 MagicAtom::Unification(u) => {
                     if seen_variables.contains(&u.binding) {
                         let expr = if u.one_many_unif {
@@ -642,4 +733,123 @@ impl<'a> SessionTx<'a> {
 
         Ok(ret)
     }
+    fn compile_trie_join<'b>(
+        &mut self,
+        atoms: impl Iterator<Item = &'b MagicAtom>,
+        store_arities: &BTreeMap<MagicSymbol, usize>,
+    ) -> Result<RelAlgebra> {
+        let mut trie_atoms = vec![];
+        let mut span = None;
+        for atom in atoms {
+            let (args, source, atom_span) = match atom {
+                MagicAtom::Rule(rule_app) => {
+                    let store_arity = store_arities.get(&rule_app.name).ok_or_else(|| {
+                        RuleNotFound(
+                            rule_app.name.symbol().to_string(),
+                            rule_app.name.symbol().span,
+                        )
+                    })?;
+                    ensure!(
+                        *store_arity == rule_app.args.len(),
+                        ArityMismatch(
+                            rule_app.name.symbol().to_string(),
+                            *store_arity,
+                            rule_app.args.len(),
+                            rule_app.span
+                        )
+                    );
+                    (
+                        &rule_app.args,
+                        TrieSource::TempStore(rule_app.name.clone()),
+                        rule_app.span,
+                    )
+                }
+                MagicAtom::Relation(rel_app) => {
+                    let store = self.get_relation(&rel_app.name, false)?;
+                    if store.access_level < AccessLevel::ReadOnly {
+                        bail!(InsufficientAccessLevel(
+                            store.name.to_string(),
+                            "reading rows".to_string(),
+                            store.access_level
+                        ));
+                    }
+                    ensure!(
+                        store.arity() == rel_app.args.len(),
+                        ArityMismatch(
+                            rel_app.name.to_string(),
+                            store.arity(),
+                            rel_app.args.len(),
+                            rel_app.span
+                        )
+                    );
+                    (
+                        &rel_app.args,
+                        TrieSource::Stored(Box::new(store)),
+                        rel_app.span,
+                    )
+                }
+                _ => unreachable!(),
+            };
+            // trailing columns that are ignored need not take part
+            let used = args
+                .iter()
+                .rposition(|arg| !arg.is_generated_ignored_symbol())
+                .map_or(0, |i| i + 1);
+            trie_atoms.push(TrieAtom {
+                bindings: args[..used].to_vec(),
+                source,
+            });
+            span.get_or_insert(atom_span);
+        }
+        Ok(RelAlgebra::trie_join(trie_atoms, span.unwrap()))
+    }
+}
+
+/// Finds the atoms of a rule body whose join graph is cyclic, by GYO reduction of the
+/// hypergraph with the variables as vertices and the atoms as edges: repeatedly remove
+/// variables occurring in only one atom, and atoms whose variables all occur in another
+/// atom. The atoms left over form the cycles, and there are none if the graph is acyclic.
+/// Only positive atoms whose variables are all distinct are considered, which are given as
+/// their arguments, with `None` standing for the other atoms.
+pub(crate) fn cyclic_join_core<'a>(
+    body: impl Iterator<Item = Option<&'a [Symbol]>>,
+) -> BTreeSet<usize> {
+    let mut edges: Vec<(usize, BTreeSet<&Symbol>)> = vec![];
+    for (i, args) in body.enumerate() {
+        let Some(args) = args else {
+            continue;
+        };
+        let vars: BTreeSet<_> = args.iter().collect();
+        if vars.len() == args.len() {
+            edges.push((i, vars));
+        }
+    }
+    loop {
+        let mut counts: BTreeMap<&Symbol, usize> = BTreeMap::new();
+        for (_, vars) in &edges {
+            for var in vars {
+                *counts.entry(var).or_default() += 1;
+            }
+        }
+        let mut changed = false;
+        for (_, vars) in edges.iter_mut() {
+            let before = vars.len();
+            vars.retain(|var| counts[var] > 1);
+            changed |= vars.len() != before;
+        }
+        let contained = (0..edges.len())
+            .find(|i| (0..edges.len()).any(|j| *i != j && edges[*i].1.is_subset(&edges[j].1)));
+        if let Some(i) = contained {
+            edges.remove(i);
+            changed = true;
+        }
+        if !changed {
+            break;
+        }
+    }
+    if edges.len() > 1 {
+        edges.into_iter().map(|(i, _)| i).collect()
+    } else {
+        BTreeSet::new()
+    }
 }
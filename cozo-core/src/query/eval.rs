@@ -9,6 +9,7 @@
 use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 use itertools::Itertools;
 use log::{debug, trace};
@@ -21,13 +22,14 @@ use crate::data::program::{MagicSymbol, NoEntryError};
 use crate::data::symb::{Symbol, PROG_ENTRY};
 use crate::data::tuple::Tuple;
 use crate::data::value::DataValue;
-use crate::fixed_rule::FixedRulePayload;
+use crate::fixed_rule::{FixedRuleOutputs, FixedRulePayload};
 use crate::parse::SourceSpan;
 use crate::query::compile::{
     AggrKind, CompiledProgram, CompiledRule, CompiledRuleSet, ContainedRuleMultiplicity,
 };
+use crate::query::ra::DeltaRule;
 use crate::runtime::db::Poison;
-use crate::runtime::temp_store::{EpochStore, MeetAggrStore, RegularTempStore};
+use crate::runtime::temp_store::{DeltaPartition, EpochStore, MeetAggrStore, RegularTempStore};
 use crate::runtime::transact::SessionTx;
 
 pub(crate) struct QueryLimiter {
@@ -72,6 +74,7 @@ impl<'a> SessionTx<'a> {
     ) -> Result<(EpochStore, bool)> {
         let mut stores: BTreeMap<MagicSymbol, EpochStore> = BTreeMap::new();
         let mut early_return = false;
+        let named_outputs = Mutex::new(BTreeMap::new());
         for (stratum, cur_prog) in strata.iter().enumerate() {
             if stratum > 0 {
                 // remove stores that have outlived their usefulness!
@@ -98,6 +101,7 @@ impl<'a> SessionTx<'a> {
             early_return = self.semi_naive_magic_evaluate(
                 cur_prog,
                 &mut stores,
+                &named_outputs,
                 total_num_to_take,
                 num_to_skip,
                 poison.clone(),
@@ -114,6 +118,7 @@ impl<'a> SessionTx<'a> {
         &self,
         prog: &CompiledProgram,
         stores: &mut BTreeMap<MagicSymbol, EpochStore>,
+        named_outputs: &Mutex<BTreeMap<Symbol, RegularTempStore>>,
         total_num_to_take: Option<usize>,
         num_to_skip: Option<usize>,
         poison: Poison,
@@ -170,12 +175,33 @@ impl<'a> SessionTx<'a> {
                         CompiledRuleSet::Fixed(fixed) => {
                             let fixed_impl = fixed.fixed_impl.as_ref();
                             let mut out = RegularTempStore::default();
+                            let mut outputs = FixedRuleOutputs {
+                                stores: fixed
+                                    .named_outputs
+                                    .keys()
+                                    .map(|name| (name.clone(), RegularTempStore::default()))
+                                    .collect(),
+                            };
                             let payload = FixedRulePayload {
                                 manifest: &fixed,
                                 stores: borrowed_stores,
                                 tx: self,
+                                named_outputs,
                             };
-                            fixed_impl.run(payload, &mut out, poison.clone())?;
+                            if let Some(schema) = fixed_impl.schema() {
+                                schema.check_inputs(&payload)?;
+                            }
+                            fixed_impl.run_with_outputs(
+                                payload,
+                                &mut out,
+                                &mut outputs,
+                                poison.clone(),
+                            )?;
+                            // picked up later by the rules the outputs are bound to
+                            let mut named_outputs = named_outputs.lock().unwrap();
+                            for (name, store) in outputs.stores {
+                                named_outputs.insert(fixed.named_outputs[&name].clone(), store);
+                            }
                             out.wrap()
                         }
                     };
@@ -576,9 +602,29 @@ impl<'a> SessionTx<'a> {
                         "with delta {:?} for rule {:?}.{}",
                         delta_key, rule_symb, rule_n
                     );
-                    for item_res in rule.relation.iter(self, Some(delta_key), stores)? {
+                    let delta_store = stores.get(delta_key).unwrap();
+                    if !should_check_limit {
+                        if let Some(partitions) = delta_partitions(delta_store) {
+                            debug!(
+                                "joining {} partitions of delta {:?} in parallel",
+                                partitions.len(),
+                                delta_key
+                            );
+                            out_store.extend(self.partitioned_delta_eval(
+                                rule,
+                                delta_key,
+                                &partitions,
+                                stores,
+                                prev_store,
+                                limiter,
+                                &poison,
+                            )?);
+                            continue;
+                        }
+                    }
+                    let delta = Some(DeltaRule::new(delta_key));
+                    for item_res in rule.relation.iter(self, delta, stores)? {
                         let item = item_res?;
-                        // improvement: the clauses can actually be evaluated in parallel
                         if prev_store.exists(&item) {
                             trace!(
                                 "item for {:?}.{}: {:?} at {}, rederived",
@@ -658,7 +704,8 @@ impl<'a> SessionTx<'a> {
                         "with delta {:?} for rule {:?}.{}",
                         delta_key, rule_symb, rule_n
                     );
-                    for item_res in rule.relation.iter(self, Some(delta_key), stores)? {
+                    let delta = Some(DeltaRule::new(delta_key));
+                    for item_res in rule.relation.iter(self, delta, stores)? {
                         out_store.meet_put(item_res?)?;
                     }
                     poison.check()?;
@@ -667,4 +714,63 @@ impl<'a> SessionTx<'a> {
         }
         Ok(out_store)
     }
+    /// Joins the partitions of the delta in parallel, each running the whole pipeline of the rule
+    #[allow(clippy::too_many_arguments)]
+    fn partitioned_delta_eval(
+        &self,
+        rule: &CompiledRule,
+        delta_key: &MagicSymbol,
+        partitions: &[DeltaPartition],
+        stores: &BTreeMap<MagicSymbol, EpochStore>,
+        prev_store: &EpochStore,
+        limiter: &QueryLimiter,
+        poison: &Poison,
+    ) -> Result<RegularTempStore> {
+        let execution = |partition: &DeltaPartition| -> Result<RegularTempStore> {
+            let mut out_store = RegularTempStore::default();
+            let delta = DeltaRule {
+                symbol: delta_key,
+                partition: Some(partition),
+            };
+            for item_res in rule.relation.iter(self, Some(delta), stores)? {
+                let item = item_res?;
+                if !prev_store.exists(&item) {
+                    if limiter.should_skip_next() {
+                        out_store.put_with_skip(item);
+                    } else {
+                        out_store.put(item);
+                    }
+                }
+            }
+            poison.check()?;
+            Ok(out_store)
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let results = partitions.par_iter().map(execution).collect::<Vec<_>>();
+        #[cfg(target_arch = "wasm32")]
+        let results = partitions.iter().map(execution).collect::<Vec<_>>();
+
+        let mut out_store = RegularTempStore::default();
+        for res in results {
+            out_store.extend(res?);
+        }
+        Ok(out_store)
+    }
+}
+
+/// Deltas smaller than this are not worth splitting for parallel evaluation
+const MIN_DELTA_PARTITION_SIZE: usize = 1024;
+
+/// Splits the delta of a store for joining it in parallel,
+/// returns `None` if it is too small or if evaluation is single-threaded
+fn delta_partitions(store: &EpochStore) -> Option<Vec<DeltaPartition>> {
+    #[cfg(not(any(target_arch = "wasm32", feature = "compact-single-threaded")))]
+    let threads = rayon::current_num_threads();
+    #[cfg(any(target_arch = "wasm32", feature = "compact-single-threaded"))]
+    let threads = 1;
+    let n = threads.min(store.delta_len() / MIN_DELTA_PARTITION_SIZE);
+    if n < 2 {
+        return None;
+    }
+    Some(store.delta_partitions(n))
 }
@@ -6,7 +6,7 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 
 use itertools::Itertools;
 use miette::{bail, ensure, Diagnostic, Result};
@@ -17,8 +17,11 @@ use crate::data::program::{
     InputAtom, InputNamedFieldRelationApplyAtom, InputRelationApplyAtom, InputRuleApplyAtom,
     NormalFormAtom, NormalFormRelationApplyAtom, NormalFormRuleApplyAtom, TempSymbGen, Unification,
 };
+use crate::data::symb::Symbol;
 use crate::parse::SourceSpan;
 use crate::query::reorder::UnsafeNegation;
+use crate::runtime::json_index::JsonIndexSearch;
+use crate::runtime::relation::AccessLevel;
 use crate::runtime::transact::SessionTx;
 
 #[derive(Debug)]
@@ -57,6 +60,111 @@ impl Disjunction {
 #[derive(Debug)]
 pub(crate) struct Conjunction(pub(crate) Vec<NormalFormAtom>);
 
+impl Conjunction {
+    /// Turns scans of stored relations into searches of their JSON indices, when a predicate
+    /// implies that the indexed document contains a constant pattern. The predicate is kept,
+    /// as the index only narrows down the rows. Relations sharing variables with other atoms
+    /// are left alone, since they are better joined by key.
+    fn use_json_indices(&mut self, gen: &mut TempSymbGen, tx: &SessionTx<'_>) -> Result<()> {
+        let mut usages: BTreeMap<Symbol, usize> = BTreeMap::new();
+        for atom in &self.0 {
+            let vars = match atom {
+                NormalFormAtom::Rule(r) => r.args.clone(),
+                NormalFormAtom::Relation(r) => r.args.clone(),
+                NormalFormAtom::Unification(u) => {
+                    let mut vars = u.bindings_in_expr()?.into_iter().collect_vec();
+                    vars.push(u.binding.clone());
+                    vars
+                }
+                NormalFormAtom::HnswSearch(s) => {
+                    s.all_bindings().chain([&s.query]).cloned().collect()
+                }
+                NormalFormAtom::FtsSearch(s) => {
+                    s.all_bindings().chain([&s.query]).cloned().collect()
+                }
+                NormalFormAtom::LshSearch(s) => {
+                    s.all_bindings().chain([&s.query]).cloned().collect()
+                }
+                NormalFormAtom::SpatialSearch(s) => {
+                    s.all_bindings().chain([&s.query]).cloned().collect()
+                }
+                NormalFormAtom::JsonIndexSearch(s) => {
+                    s.all_bindings().chain([&s.query]).cloned().collect()
+                }
+                NormalFormAtom::NegatedRule(_)
+                | NormalFormAtom::NegatedRelation(_)
+                | NormalFormAtom::Predicate(_) => vec![],
+            };
+            for var in vars {
+                *usages.entry(var).or_default() += 1;
+            }
+        }
+        let predicates = self
+            .0
+            .iter()
+            .filter_map(|atom| match atom {
+                NormalFormAtom::Predicate(p) => Some(p.clone()),
+                _ => None,
+            })
+            .collect_vec();
+        if predicates.is_empty() {
+            return Ok(());
+        }
+
+        let mut unifications = vec![];
+        for atom in self.0.iter_mut() {
+            let rel = match atom {
+                NormalFormAtom::Relation(r) if r.valid_at.is_none() => r,
+                _ => continue,
+            };
+            if rel.args.iter().any(|var| usages[var] > 1) {
+                continue;
+            }
+            // errors are reported when the relation is compiled as usual
+            let base_handle = match tx.get_relation(&rel.name, false) {
+                Ok(handle) => handle,
+                Err(_) => continue,
+            };
+            if base_handle.access_level < AccessLevel::ReadOnly
+                || base_handle.arity() != rel.args.len()
+            {
+                continue;
+            }
+            'indices: for (idx_handle, manifest) in base_handle.json_indices.values() {
+                for pred in &predicates {
+                    let pattern = match manifest.implied_pattern(pred, &base_handle, &rel.args)? {
+                        Some(pattern) => pattern,
+                        None => continue,
+                    };
+                    let query = gen.next(rel.span);
+                    unifications.push(NormalFormAtom::Unification(Unification {
+                        binding: query.clone(),
+                        expr: Expr::Const {
+                            val: pattern,
+                            span: pred.span(),
+                        },
+                        one_many_unif: false,
+                        span: pred.span(),
+                    }));
+                    *atom = NormalFormAtom::JsonIndexSearch(JsonIndexSearch {
+                        base_handle: base_handle.clone(),
+                        idx_handle: idx_handle.clone(),
+                        manifest: manifest.clone(),
+                        bindings: rel.args.clone(),
+                        query,
+                        k: None,
+                        filter: None,
+                        span: rel.span,
+                    });
+                    break 'indices;
+                }
+            }
+        }
+        self.0.extend(unifications);
+        Ok(())
+    }
+}
+
 impl InputAtom {
     pub(crate) fn negation_normal_form(self) -> Result<Self> {
         Ok(match self {
@@ -132,7 +240,11 @@ impl InputAtom {
     pub(crate) fn disjunctive_normal_form(self, tx: &SessionTx<'_>) -> Result<Disjunction> {
         let neg_form = self.negation_normal_form()?;
         let mut gen = TempSymbGen::default();
-        neg_form.do_disjunctive_normal_form(&mut gen, tx)
+        let mut disj = neg_form.do_disjunctive_normal_form(&mut gen, tx)?;
+        for conj in disj.inner.iter_mut() {
+            conj.use_json_indices(&mut gen, tx)?;
+        }
+        Ok(disj)
     }
 
     fn convert_named_field_relation(
@@ -6,7 +6,7 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::mem;
 
 use itertools::Itertools;
@@ -23,6 +23,7 @@ use crate::data::program::{
 use crate::data::relation::{ColType, NullableColType};
 use crate::data::symb::{Symbol, PROG_ENTRY};
 use crate::parse::SourceSpan;
+use crate::query::compile::cyclic_join_core;
 use crate::query::logical::NamedFieldNotFound;
 use crate::query::ra::InvalidTimeTravelScanning;
 use crate::runtime::transact::SessionTx;
@@ -188,6 +189,14 @@ fn magic_rewrite_ruleset(
                     seen_bindings.extend(s.all_bindings().cloned());
                     collected_atoms.push(MagicAtom::LshSearch(s));
                 }
+                MagicAtom::SpatialSearch(s) => {
+                    seen_bindings.extend(s.all_bindings().cloned());
+                    collected_atoms.push(MagicAtom::SpatialSearch(s));
+                }
+                MagicAtom::JsonIndexSearch(s) => {
+                    seen_bindings.extend(s.all_bindings().cloned());
+                    collected_atoms.push(MagicAtom::JsonIndexSearch(s));
+                }
                 MagicAtom::Rule(r_app) => {
                     if r_app.name.has_bound_adornment() {
                         // we are guaranteed to have a magic rule application
@@ -444,11 +453,24 @@ impl NormalFormProgram {
                                                     span: *span,
                                                 }
                                             }
+                                            FixedRuleArg::Graph {
+                                                name,
+                                                bindings,
+                                                span,
+                                            } => {
+                                                tx.get_graph_projection(name)?;
+                                                MagicFixedRuleRuleArg::Graph {
+                                                    name: name.clone(),
+                                                    bindings: bindings.clone(),
+                                                    span: *span,
+                                                }
+                                            }
                                         })
                                     })
                                     .try_collect()?,
                                 options: fixed.options.clone(),
                                 arity: fixed.arity,
+                                named_outputs: fixed.named_outputs.clone(),
                             },
                         },
                     );
@@ -555,6 +577,22 @@ impl NormalFormAtom {
                 }
                 MagicAtom::LshSearch(s.clone())
             }
+            NormalFormAtom::SpatialSearch(s) => {
+                for arg in s.all_bindings() {
+                    if !seen_bindings.contains(arg) {
+                        seen_bindings.insert(arg.clone());
+                    }
+                }
+                MagicAtom::SpatialSearch(s.clone())
+            }
+            NormalFormAtom::JsonIndexSearch(s) => {
+                for arg in s.all_bindings() {
+                    if !seen_bindings.contains(arg) {
+                        seen_bindings.insert(arg.clone());
+                    }
+                }
+                MagicAtom::JsonIndexSearch(s.clone())
+            }
 
             NormalFormAtom::Predicate(p) => {
                 // predicate cannot introduce new bindings
@@ -621,9 +659,42 @@ impl NormalFormInlineRule {
         mut seen_bindings: BTreeSet<Symbol>,
     ) -> MagicInlineRule {
         let mut ret_body = Vec::with_capacity(self.body.len());
-
-        for atom in &self.body {
-            let new_atom = atom.adorn(pending, &mut seen_bindings, rules_to_rewrite);
+        fn join_args(atom: &NormalFormAtom) -> Option<&[Symbol]> {
+            match atom {
+                NormalFormAtom::Rule(rule_app) => Some(&rule_app.args),
+                NormalFormAtom::Relation(rel_app) if rel_app.valid_at.is_none() => {
+                    Some(&rel_app.args)
+                }
+                _ => None,
+            }
+        }
+        // atoms forming cycles are joined all at once if none of their variables are bound
+        // before them, see `compile_magic_rule_body`, so they must not bind each other here,
+        // otherwise the rewrite splits them up into supplementary rules
+        let cyclic_atoms = cyclic_join_core(self.body.iter().map(join_args));
+        let mut adorned_cyclic_atoms = BTreeMap::new();
+
+        for (i, atom) in self.body.iter().enumerate() {
+            if cyclic_atoms.iter().next() == Some(&i) {
+                let core_args = cyclic_atoms
+                    .iter()
+                    .flat_map(|j| join_args(&self.body[*j]).unwrap_or_default());
+                if core_args.clone().all(|arg| !seen_bindings.contains(arg)) {
+                    for j in &cyclic_atoms {
+                        let adorned = self.body[*j].adorn(
+                            pending,
+                            &mut seen_bindings.clone(),
+                            rules_to_rewrite,
+                        );
+                        adorned_cyclic_atoms.insert(*j, adorned);
+                    }
+                    seen_bindings.extend(core_args.cloned());
+                }
+            }
+            let new_atom = match adorned_cyclic_atoms.remove(&i) {
+                Some(adorned) => adorned,
+                None => atom.adorn(pending, &mut seen_bindings, rules_to_rewrite),
+            };
             ret_body.push(new_atom);
         }
         MagicInlineRule {
@@ -6,7 +6,8 @@
  * You can obtain one at https://mozilla.org/MPL/2.0/.
  */
 
-use std::collections::{BTreeMap, BTreeSet};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt::{Debug, Formatter, Write};
 use std::iter;
 
@@ -24,12 +25,31 @@ use crate::data::symb::Symbol;
 use crate::data::tuple::{Tuple, TupleIter};
 use crate::data::value::{DataValue, ValidityTs};
 use crate::parse::SourceSpan;
+use crate::runtime::json_index::JsonIndexSearch;
 use crate::runtime::minhash_lsh::LshSearch;
 use crate::runtime::relation::RelationHandle;
-use crate::runtime::temp_store::EpochStore;
+use crate::runtime::spatial::SpatialSearch;
+use crate::runtime::temp_store::{DeltaPartition, EpochStore};
 use crate::runtime::transact::SessionTx;
 use crate::utils::swap_option_result;
 
+/// The rule whose delta is scanned in a semi-naive step. If a partition is given,
+/// only the part of the delta within it is scanned.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct DeltaRule<'a> {
+    pub(crate) symbol: &'a MagicSymbol,
+    pub(crate) partition: Option<&'a DeltaPartition>,
+}
+
+impl<'a> DeltaRule<'a> {
+    pub(crate) fn new(symbol: &'a MagicSymbol) -> Self {
+        Self {
+            symbol,
+            partition: None,
+        }
+    }
+}
+
 pub(crate) enum RelAlgebra {
     Fixed(InlineFixedRA),
     TempStore(TempStoreRA),
@@ -43,6 +63,9 @@ pub(crate) enum RelAlgebra {
     HnswSearch(HnswSearchRA),
     FtsSearch(FtsSearchRA),
     LshSearch(LshSearchRA),
+    SpatialSearch(SpatialSearchRA),
+    JsonIndexSearch(JsonIndexSearchRA),
+    TrieJoin(TrieJoinRA),
 }
 
 impl RelAlgebra {
@@ -60,6 +83,9 @@ impl RelAlgebra {
             RelAlgebra::HnswSearch(i) => i.hnsw_search.span,
             RelAlgebra::FtsSearch(i) => i.fts_search.span,
             RelAlgebra::LshSearch(i) => i.lsh_search.span,
+            RelAlgebra::SpatialSearch(i) => i.spatial_search.span,
+            RelAlgebra::JsonIndexSearch(i) => i.json_search.span,
+            RelAlgebra::TrieJoin(i) => i.span,
         }
     }
 }
@@ -124,7 +150,7 @@ impl UnificationRA {
     fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let mut bindings = self.parent.bindings_after_eliminate();
@@ -217,7 +243,7 @@ impl FilteredRA {
     fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let bindings = self.parent.bindings_after_eliminate();
@@ -299,6 +325,21 @@ impl Debug for RelAlgebra {
                 .field(&bindings)
                 .field(&s.lsh_search.idx_handle.name)
                 .finish(),
+            RelAlgebra::SpatialSearch(s) => f
+                .debug_tuple("SpatialSearch")
+                .field(&bindings)
+                .field(&s.spatial_search.idx_handle.name)
+                .finish(),
+            RelAlgebra::JsonIndexSearch(s) => f
+                .debug_tuple("JsonIndexSearch")
+                .field(&bindings)
+                .field(&s.json_search.idx_handle.name)
+                .finish(),
+            RelAlgebra::TrieJoin(r) => f
+                .debug_tuple("TrieJoin")
+                .field(&bindings)
+                .field(&r.atoms)
+                .finish(),
             RelAlgebra::StoredWithValidity(r) => f
                 .debug_tuple("StoredWithValidity")
                 .field(&bindings)
@@ -374,6 +415,13 @@ impl RelAlgebra {
             RelAlgebra::LshSearch(s) => {
                 s.fill_binding_indices_and_compile()?;
             }
+            RelAlgebra::SpatialSearch(s) => {
+                s.fill_binding_indices_and_compile()?;
+            }
+            RelAlgebra::JsonIndexSearch(s) => {
+                s.fill_binding_indices_and_compile()?;
+            }
+            RelAlgebra::TrieJoin(_) => {}
             RelAlgebra::StoredWithValidity(v) => {
                 v.fill_binding_indices_and_compile()?;
             }
@@ -424,6 +472,21 @@ impl RelAlgebra {
             span,
         })
     }
+    pub(crate) fn trie_join(atoms: Vec<TrieAtom>, span: SourceSpan) -> Self {
+        let mut bindings: Vec<Symbol> = vec![];
+        for atom in &atoms {
+            for binding in &atom.bindings {
+                if !bindings.contains(binding) {
+                    bindings.push(binding.clone());
+                }
+            }
+        }
+        Self::TrieJoin(TrieJoinRA {
+            bindings,
+            atoms,
+            span,
+        })
+    }
     pub(crate) fn relation(
         bindings: Vec<Symbol>,
         storage: RelationHandle,
@@ -472,7 +535,10 @@ impl RelAlgebra {
             | RelAlgebra::Unification(_)
             | RelAlgebra::HnswSearch(_)
             | RelAlgebra::FtsSearch(_)
-            | RelAlgebra::LshSearch(_)) => {
+            | RelAlgebra::LshSearch(_)
+            | RelAlgebra::SpatialSearch(_)
+            | RelAlgebra::JsonIndexSearch(_)
+            | RelAlgebra::TrieJoin(_)) => {
                 let span = filter.span();
                 RelAlgebra::Filter(FilteredRA {
                     parent: Box::new(s),
@@ -649,6 +715,30 @@ impl RelAlgebra {
             own_bindings,
         }))
     }
+    pub(crate) fn spatial_search(
+        self,
+        spatial_search: SpatialSearch,
+        own_bindings: Vec<Symbol>,
+    ) -> Result<Self> {
+        Ok(Self::SpatialSearch(SpatialSearchRA {
+            parent: Box::new(self),
+            spatial_search,
+            filter_bytecode: None,
+            own_bindings,
+        }))
+    }
+    pub(crate) fn json_index_search(
+        self,
+        json_search: JsonIndexSearch,
+        own_bindings: Vec<Symbol>,
+    ) -> Result<Self> {
+        Ok(Self::JsonIndexSearch(JsonIndexSearchRA {
+            parent: Box::new(self),
+            json_search,
+            filter_bytecode: None,
+            own_bindings,
+        }))
+    }
     pub(crate) fn join(
         self,
         right: RelAlgebra,
@@ -700,7 +790,7 @@ impl ReorderRA {
     fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let old_order = self.relation.bindings_after_eliminate();
@@ -928,7 +1018,7 @@ impl LshSearchRA {
     fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let bindings = self.parent.bindings_after_eliminate();
@@ -973,6 +1063,140 @@ impl LshSearchRA {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct SpatialSearchRA {
+    pub(crate) parent: Box<RelAlgebra>,
+    pub(crate) spatial_search: SpatialSearch,
+    pub(crate) filter_bytecode: Option<(Vec<Bytecode>, SourceSpan)>,
+    pub(crate) own_bindings: Vec<Symbol>,
+}
+
+impl SpatialSearchRA {
+    fn fill_binding_indices_and_compile(&mut self) -> Result<()> {
+        self.parent.fill_binding_indices_and_compile()?;
+        if self.spatial_search.filter.is_some() {
+            let bindings: BTreeMap<_, _> = self
+                .own_bindings
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(a, b)| (b, a))
+                .collect();
+            let filter = self.spatial_search.filter.as_mut().unwrap();
+            filter.fill_binding_indices(&bindings)?;
+            self.filter_bytecode = Some((filter.compile()?, filter.span()));
+        }
+        Ok(())
+    }
+    fn iter<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        delta_rule: Option<DeltaRule<'_>>,
+        stores: &'a BTreeMap<MagicSymbol, EpochStore>,
+    ) -> Result<TupleIter<'a>> {
+        let bindings = self.parent.bindings_after_eliminate();
+        let mut bind_idx = usize::MAX;
+        for (i, b) in bindings.iter().enumerate() {
+            if *b == self.spatial_search.query {
+                bind_idx = i;
+                break;
+            }
+        }
+        let config = self.spatial_search.clone();
+        let filter_code = self.filter_bytecode.clone();
+        let extractor = config.manifest.compile_extractor(&config.base_handle)?;
+        let mut stack = vec![];
+
+        let it = self
+            .parent
+            .iter(tx, delta_rule, stores)?
+            .map_ok(move |tuple| -> Result<_> {
+                let res = tx.spatial_search(
+                    &tuple[bind_idx],
+                    &config,
+                    &extractor,
+                    &mut stack,
+                    &filter_code,
+                )?;
+                Ok(res.into_iter().map(move |t| {
+                    let mut r = tuple.clone();
+                    r.extend(t);
+                    r
+                }))
+            })
+            .map(flatten_err)
+            .flatten_ok();
+        Ok(Box::new(it))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct JsonIndexSearchRA {
+    pub(crate) parent: Box<RelAlgebra>,
+    pub(crate) json_search: JsonIndexSearch,
+    pub(crate) filter_bytecode: Option<(Vec<Bytecode>, SourceSpan)>,
+    pub(crate) own_bindings: Vec<Symbol>,
+}
+
+impl JsonIndexSearchRA {
+    fn fill_binding_indices_and_compile(&mut self) -> Result<()> {
+        self.parent.fill_binding_indices_and_compile()?;
+        if self.json_search.filter.is_some() {
+            let bindings: BTreeMap<_, _> = self
+                .own_bindings
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(a, b)| (b, a))
+                .collect();
+            let filter = self.json_search.filter.as_mut().unwrap();
+            filter.fill_binding_indices(&bindings)?;
+            self.filter_bytecode = Some((filter.compile()?, filter.span()));
+        }
+        Ok(())
+    }
+    fn iter<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        delta_rule: Option<DeltaRule<'_>>,
+        stores: &'a BTreeMap<MagicSymbol, EpochStore>,
+    ) -> Result<TupleIter<'a>> {
+        let bindings = self.parent.bindings_after_eliminate();
+        let mut bind_idx = usize::MAX;
+        for (i, b) in bindings.iter().enumerate() {
+            if *b == self.json_search.query {
+                bind_idx = i;
+                break;
+            }
+        }
+        let config = self.json_search.clone();
+        let filter_code = self.filter_bytecode.clone();
+        let extractor = config.manifest.compile_extractor(&config.base_handle)?;
+        let mut stack = vec![];
+
+        let it = self
+            .parent
+            .iter(tx, delta_rule, stores)?
+            .map_ok(move |tuple| -> Result<_> {
+                let res = tx.json_index_search(
+                    &tuple[bind_idx],
+                    &config,
+                    &extractor,
+                    &mut stack,
+                    &filter_code,
+                )?;
+                Ok(res.into_iter().map(move |t| {
+                    let mut r = tuple.clone();
+                    r.extend(t);
+                    r
+                }))
+            })
+            .map(flatten_err)
+            .flatten_ok();
+        Ok(Box::new(it))
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct FtsSearchRA {
     pub(crate) parent: Box<RelAlgebra>,
@@ -1001,7 +1225,7 @@ impl FtsSearchRA {
     fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let bindings = self.parent.bindings_after_eliminate();
@@ -1085,7 +1309,7 @@ impl HnswSearchRA {
     fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let bindings = self.parent.bindings_after_eliminate();
@@ -1543,17 +1767,18 @@ impl TempStoreRA {
 
     fn iter<'a>(
         &'a self,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let storage = stores.get(&self.storage_key).unwrap();
 
-        let scan_epoch = match delta_rule {
-            None => false,
-            Some(name) => *name == self.storage_key,
-        };
-        let it = if scan_epoch {
-            Left(storage.delta_all_iter().map(|t| Ok(t.into_tuple())))
+        let scan_epoch = delta_rule.filter(|delta| *delta.symbol == self.storage_key);
+        let it = if let Some(delta) = scan_epoch {
+            Left(
+                storage
+                    .delta_all_iter(delta.partition)
+                    .map(|t| Ok(t.into_tuple())),
+            )
         } else {
             Right(storage.all_iter().map(|t| Ok(t.into_tuple())))
         };
@@ -1666,7 +1891,7 @@ impl TempStoreRA {
         left_iter: TupleIter<'a>,
         (left_join_indices, right_join_indices): (Vec<usize>, Vec<usize>),
         eliminate_indices: BTreeSet<usize>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let storage = stores.get(&self.storage_key).unwrap();
@@ -1677,10 +1902,8 @@ impl TempStoreRA {
             .into_iter()
             .map(|(a, _)| left_join_indices[a])
             .collect_vec();
-        let scan_epoch = match delta_rule {
-            None => false,
-            Some(name) => *name == self.storage_key,
-        };
+        let scan_epoch = delta_rule.is_some_and(|delta| *delta.symbol == self.storage_key);
+        let delta_partition = delta_rule.and_then(|delta| delta.partition.cloned());
         let mut skip_range_check = false;
         let it = left_iter
             .map_ok(move |tuple| {
@@ -1704,7 +1927,12 @@ impl TempStoreRA {
                         let mut upper_bound = prefix;
                         upper_bound.extend(u_bound);
                         let it = if scan_epoch {
-                            Left(storage.delta_range_iter(&lower_bound, &upper_bound, true))
+                            Left(storage.delta_range_iter(
+                                &lower_bound,
+                                &upper_bound,
+                                true,
+                                delta_partition.as_ref(),
+                            ))
                         } else {
                             Right(storage.range_iter(&lower_bound, &upper_bound, true))
                         };
@@ -1733,7 +1961,7 @@ impl TempStoreRA {
                 skip_range_check = true;
 
                 let it = if scan_epoch {
-                    Left(storage.delta_prefix_iter(&prefix))
+                    Left(storage.delta_prefix_iter(&prefix, delta_partition.as_ref()))
                 } else {
                     Right(storage.prefix_iter(&prefix))
                 };
@@ -1833,6 +2061,9 @@ impl RelAlgebra {
             RelAlgebra::HnswSearch(_) => Ok(()),
             RelAlgebra::FtsSearch(_) => Ok(()),
             RelAlgebra::LshSearch(_) => Ok(()),
+            RelAlgebra::SpatialSearch(_) => Ok(()),
+            RelAlgebra::JsonIndexSearch(_) => Ok(()),
+            RelAlgebra::TrieJoin(_) => Ok(()),
         }
     }
 
@@ -1850,6 +2081,9 @@ impl RelAlgebra {
             RelAlgebra::HnswSearch(_) => None,
             RelAlgebra::FtsSearch(_) => None,
             RelAlgebra::LshSearch(_) => None,
+            RelAlgebra::SpatialSearch(_) => None,
+            RelAlgebra::JsonIndexSearch(_) => None,
+            RelAlgebra::TrieJoin(_) => None,
         }
     }
 
@@ -1864,6 +2098,41 @@ impl RelAlgebra {
         }
     }
 
+    /// The number of leading columns of `bindings_after_eliminate` that the tuples
+    /// are known to be sorted on.
+    fn sorted_prefix_len(&self) -> usize {
+        let sorted = match self {
+            // scans of stored relations and temp stores come out sorted by their keys,
+            // and the keys are unique, so they are sorted on all columns
+            RelAlgebra::TempStore(r) => r.bindings.len(),
+            RelAlgebra::Stored(r) => r.bindings.len(),
+            RelAlgebra::StoredWithValidity(r) => r.bindings.len(),
+            // the variables are bound one at a time in ascending order
+            RelAlgebra::TrieJoin(r) => r.bindings.len(),
+            RelAlgebra::Filter(r) => r.parent.sorted_prefix_len(),
+            RelAlgebra::Unification(r) => r.parent.sorted_prefix_len(),
+            RelAlgebra::NegJoin(r) => r.left.sorted_prefix_len(),
+            // joins emit the matches for each left tuple in turn
+            RelAlgebra::Join(r) => {
+                if r.left.is_unit() {
+                    r.right.sorted_prefix_len()
+                } else {
+                    r.left.sorted_prefix_len()
+                }
+            }
+            _ => 0,
+        };
+        match self.eliminate_set() {
+            None => sorted,
+            Some(to_eliminate) => self
+                .bindings_before_eliminate()
+                .iter()
+                .take(sorted)
+                .take_while(|b| !to_eliminate.contains(*b))
+                .count(),
+        }
+    }
+
     fn bindings_before_eliminate(&self) -> Vec<Symbol> {
         match self {
             RelAlgebra::Fixed(f) => f.bindings.clone(),
@@ -1894,12 +2163,23 @@ impl RelAlgebra {
                 bindings.extend_from_slice(&s.own_bindings);
                 bindings
             }
+            RelAlgebra::SpatialSearch(s) => {
+                let mut bindings = s.parent.bindings_after_eliminate();
+                bindings.extend_from_slice(&s.own_bindings);
+                bindings
+            }
+            RelAlgebra::JsonIndexSearch(s) => {
+                let mut bindings = s.parent.bindings_after_eliminate();
+                bindings.extend_from_slice(&s.own_bindings);
+                bindings
+            }
+            RelAlgebra::TrieJoin(r) => r.bindings.clone(),
         }
     }
     pub(crate) fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         match self {
@@ -1915,7 +2195,285 @@ impl RelAlgebra {
             RelAlgebra::HnswSearch(r) => r.iter(tx, delta_rule, stores),
             RelAlgebra::FtsSearch(r) => r.iter(tx, delta_rule, stores),
             RelAlgebra::LshSearch(r) => r.iter(tx, delta_rule, stores),
+            RelAlgebra::SpatialSearch(r) => r.iter(tx, delta_rule, stores),
+            RelAlgebra::JsonIndexSearch(r) => r.iter(tx, delta_rule, stores),
+            RelAlgebra::TrieJoin(r) => r.iter(tx, delta_rule, stores),
+        }
+    }
+}
+
+/// Where the tuples of an atom of a [TrieJoinRA] come from
+#[derive(Debug)]
+pub(crate) enum TrieSource {
+    TempStore(MagicSymbol),
+    Stored(Box<RelationHandle>),
+}
+
+#[derive(Debug)]
+pub(crate) struct TrieAtom {
+    /// The bindings of the leading columns of the relation taking part in the join,
+    /// which must be distinct
+    pub(crate) bindings: Vec<Symbol>,
+    pub(crate) source: TrieSource,
+}
+
+/// Joins several relations at once by binding one variable at a time, each time intersecting
+/// the values the relations containing the variable allow given the variables already bound
+/// (leapfrog triejoin). The keys of stored relations and temp stores are sorted, so they can
+/// be used as tries directly if their columns come in the order the variables are bound in.
+/// Other relations are sorted in that order in memory first. Unlike a sequence of binary joins,
+/// this never produces intermediate results larger than the final result can be, which
+/// matters for cyclic queries like finding triangles.
+#[derive(Debug)]
+pub(crate) struct TrieJoinRA {
+    /// The variables in the order they are bound
+    pub(crate) bindings: Vec<Symbol>,
+    pub(crate) atoms: Vec<TrieAtom>,
+    pub(crate) span: SourceSpan,
+}
+
+impl TrieJoinRA {
+    fn iter<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        delta_rule: Option<DeltaRule<'_>>,
+        stores: &'a BTreeMap<MagicSymbol, EpochStore>,
+    ) -> Result<TupleIter<'a>> {
+        let positions: BTreeMap<&Symbol, usize> = self
+            .bindings
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (b, i))
+            .collect();
+        let mut tries = Vec::with_capacity(self.atoms.len());
+        let mut trie_levels = Vec::with_capacity(self.atoms.len());
+        for atom in &self.atoms {
+            let levels = atom.bindings.iter().map(|b| positions[b]).collect_vec();
+            let delta = match &atom.source {
+                TrieSource::TempStore(key) => delta_rule
+                    .filter(|delta| delta.symbol == key)
+                    .map(|delta| delta.partition.cloned()),
+                TrieSource::Stored(_) => None,
+            };
+            if levels.windows(2).all(|w| w[0] < w[1]) {
+                tries.push(match &atom.source {
+                    TrieSource::TempStore(key) => Trie::TempStore {
+                        store: stores.get(key).unwrap(),
+                        delta,
+                    },
+                    TrieSource::Stored(handle) => Trie::Stored(handle),
+                });
+                trie_levels.push(levels);
+            } else {
+                let order = (0..levels.len())
+                    .sorted_by_key(|i| levels[*i])
+                    .collect_vec();
+                let mut sorted = vec![];
+                let mut collect = |tuple: Tuple| {
+                    sorted.push(order.iter().map(|i| tuple[*i].clone()).collect_vec())
+                };
+                match &atom.source {
+                    TrieSource::TempStore(key) => {
+                        let store = stores.get(key).unwrap();
+                        match &delta {
+                            None => store.all_iter().for_each(|t| collect(t.into_tuple())),
+                            Some(partition) => store
+                                .delta_all_iter(partition.as_ref())
+                                .for_each(|t| collect(t.into_tuple())),
+                        }
+                    }
+                    TrieSource::Stored(handle) => {
+                        for tuple in handle.scan_all(tx) {
+                            collect(tuple?)
+                        }
+                    }
+                }
+                sorted.sort();
+                sorted.dedup();
+                tries.push(Trie::Sorted(sorted));
+                trie_levels.push(order.iter().map(|i| levels[*i]).collect_vec());
+            }
         }
+        let mut participants = vec![vec![]; self.bindings.len()];
+        for (trie_idx, levels) in trie_levels.iter().enumerate() {
+            for (col, level) in levels.iter().enumerate() {
+                participants[*level].push((trie_idx, col));
+            }
+        }
+        Ok(Box::new(TrieJoinIterator {
+            tx,
+            tries,
+            trie_levels,
+            participants,
+            bound: vec![],
+            resume: None,
+            done: false,
+        }))
+    }
+}
+
+/// A relation viewed as a trie, with one level for each column
+enum Trie<'a> {
+    TempStore {
+        store: &'a EpochStore,
+        /// If the delta is scanned, the partition of it to scan
+        delta: Option<Option<DeltaPartition>>,
+    },
+    Stored(&'a RelationHandle),
+    Sorted(Vec<Tuple>),
+}
+
+impl Trie<'_> {
+    /// Finds the least value in the column after `prefix` of the tuples starting with `prefix`
+    /// that is no less than `lower`, or greater than `lower` if `exclusive`.
+    fn seek(
+        &self,
+        tx: &SessionTx<'_>,
+        prefix: &[DataValue],
+        lower: &DataValue,
+        exclusive: bool,
+    ) -> Result<Option<DataValue>> {
+        let col = prefix.len();
+        // all tuples having `lower` in the column sort before `[..prefix, lower, Bot]`
+        let mut lower_bound = vec![lower.clone()];
+        if exclusive {
+            lower_bound.push(DataValue::Bot);
+        }
+        Ok(match self {
+            Trie::TempStore { store, delta } => {
+                let mut lower_t = prefix.to_vec();
+                lower_t.extend_from_slice(&lower_bound);
+                let mut upper_t = prefix.to_vec();
+                upper_t.push(DataValue::Bot);
+                let found = match delta {
+                    None => store.range_iter(&lower_t, &upper_t, true).next(),
+                    Some(partition) => store
+                        .delta_range_iter(&lower_t, &upper_t, true, partition.as_ref())
+                        .next(),
+                };
+                found.map(|t| t.get(col).clone())
+            }
+            Trie::Stored(handle) => {
+                let key_len = handle.metadata.keys.len();
+                if col < key_len {
+                    handle
+                        .scan_bounded_prefix(tx, prefix, &lower_bound, &[])
+                        .next()
+                        .transpose()?
+                        .map(|t| t[col].clone())
+                } else {
+                    // the keys are all bound, so there is at most one tuple
+                    match handle.get(tx, &prefix[..key_len])? {
+                        Some(t)
+                            if t[key_len..col] == prefix[key_len..]
+                                && (t[col] > *lower || (!exclusive && t[col] == *lower)) =>
+                        {
+                            Some(t[col].clone())
+                        }
+                        _ => None,
+                    }
+                }
+            }
+            Trie::Sorted(tuples) => {
+                let idx = tuples.partition_point(|t| match t[..col].cmp(prefix) {
+                    Ordering::Less => true,
+                    Ordering::Greater => false,
+                    Ordering::Equal => t[col] < *lower || (exclusive && t[col] == *lower),
+                });
+                tuples
+                    .get(idx)
+                    .filter(|t| t.starts_with(prefix))
+                    .map(|t| t[col].clone())
+            }
+        })
+    }
+}
+
+struct TrieJoinIterator<'a, 's> {
+    tx: &'a SessionTx<'s>,
+    tries: Vec<Trie<'a>>,
+    /// For each trie, the level bound by each of its columns, in ascending order
+    trie_levels: Vec<Vec<usize>>,
+    /// For each level, the tries taking part in it, with the column of the level in each
+    participants: Vec<Vec<(usize, usize)>>,
+    /// The values bound so far, one for each level
+    bound: Vec<DataValue>,
+    /// The value last bound at the next level, to be continued from
+    resume: Option<DataValue>,
+    done: bool,
+}
+
+impl TrieJoinIterator<'_, '_> {
+    /// Finds the least value for `level` no less than `lower` (greater if `exclusive`)
+    /// that all tries taking part in the level agree on, by seeking each trie in turn
+    /// to the largest value seen so far.
+    fn leapfrog(
+        &self,
+        level: usize,
+        mut lower: DataValue,
+        mut exclusive: bool,
+    ) -> Result<Option<DataValue>> {
+        let participants = &self.participants[level];
+        let mut agreed = 0;
+        for (trie_idx, col) in participants.iter().cycle() {
+            let prefix = self.trie_levels[*trie_idx][..*col]
+                .iter()
+                .map(|l| self.bound[*l].clone())
+                .collect_vec();
+            match self.tries[*trie_idx].seek(self.tx, &prefix, &lower, exclusive)? {
+                None => return Ok(None),
+                Some(val) => {
+                    if !exclusive && val == lower {
+                        agreed += 1;
+                    } else {
+                        lower = val;
+                        exclusive = false;
+                        agreed = 1;
+                    }
+                    if agreed == participants.len() {
+                        return Ok(Some(lower));
+                    }
+                }
+            }
+        }
+        unreachable!()
+    }
+    fn next_inner(&mut self) -> Result<Option<Tuple>> {
+        if self.done {
+            return Ok(None);
+        }
+        loop {
+            let level = self.bound.len();
+            let found = match self.resume.take() {
+                None => self.leapfrog(level, DataValue::Null, false)?,
+                Some(prev) => self.leapfrog(level, prev, true)?,
+            };
+            match found {
+                Some(val) => {
+                    self.bound.push(val);
+                    if self.bound.len() == self.participants.len() {
+                        let ret = self.bound.clone();
+                        self.resume = self.bound.pop();
+                        return Ok(Some(ret));
+                    }
+                }
+                None => match self.bound.pop() {
+                    None => {
+                        self.done = true;
+                        return Ok(None);
+                    }
+                    Some(prev) => self.resume = Some(prev),
+                },
+            }
+        }
+    }
+}
+
+impl Iterator for TrieJoinIterator<'_, '_> {
+    type Item = Result<Tuple>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        swap_option_result(self.next_inner())
     }
 }
 
@@ -1981,7 +2539,7 @@ impl NegJoin {
     pub(crate) fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let bindings = self.left.bindings_after_eliminate();
@@ -2063,6 +2621,26 @@ impl InnerJoin {
         debug_assert_eq!(ret.len(), ret.iter().collect::<BTreeSet<_>>().len());
         ret
     }
+    /// Chooses how to join when the join keys are not a prefix of the right relation's key,
+    /// so that the right side must be materialized first.
+    fn materialized_join_algorithm(&self) -> MaterializedJoinAlgorithm {
+        let (left_join_indices, _) = self
+            .joiner
+            .join_indices(
+                &self.left.bindings_after_eliminate(),
+                &self.right.bindings_after_eliminate(),
+            )
+            .unwrap();
+        if left_join_indices.is_empty() {
+            MaterializedJoinAlgorithm::Sorted
+        } else if join_is_prefix(&left_join_indices)
+            && left_join_indices.len() <= self.left.sorted_prefix_len()
+        {
+            MaterializedJoinAlgorithm::Merge
+        } else {
+            MaterializedJoinAlgorithm::Hash
+        }
+    }
     pub(crate) fn join_type(&self) -> &str {
         match &self.right {
             RelAlgebra::Fixed(f) => f.join_type(),
@@ -2077,7 +2655,11 @@ impl InnerJoin {
                 if join_is_prefix(&join_indices.1) {
                     "mem_prefix_join"
                 } else {
-                    "mem_mat_join"
+                    match self.materialized_join_algorithm() {
+                        MaterializedJoinAlgorithm::Sorted => "mem_mat_join",
+                        MaterializedJoinAlgorithm::Hash => "mem_hash_join",
+                        MaterializedJoinAlgorithm::Merge => "mem_merge_join",
+                    }
                 }
             }
             RelAlgebra::Stored(_) => {
@@ -2091,12 +2673,18 @@ impl InnerJoin {
                 if join_is_prefix(&join_indices.1) {
                     "stored_prefix_join"
                 } else {
-                    "stored_mat_join"
+                    match self.materialized_join_algorithm() {
+                        MaterializedJoinAlgorithm::Sorted => "stored_mat_join",
+                        MaterializedJoinAlgorithm::Hash => "stored_hash_join",
+                        MaterializedJoinAlgorithm::Merge => "stored_merge_join",
+                    }
                 }
             }
             RelAlgebra::HnswSearch(_) => "hnsw_search_join",
             RelAlgebra::FtsSearch(_) => "fts_search_join",
             RelAlgebra::LshSearch(_) => "lsh_search_join",
+            RelAlgebra::SpatialSearch(_) => "spatial_search_join",
+            RelAlgebra::JsonIndexSearch(_) => "json_index_search_join",
             RelAlgebra::StoredWithValidity(_) => {
                 let join_indices = self
                     .joiner
@@ -2108,12 +2696,21 @@ impl InnerJoin {
                 if join_is_prefix(&join_indices.1) {
                     "stored_prefix_join"
                 } else {
-                    "stored_mat_join"
+                    match self.materialized_join_algorithm() {
+                        MaterializedJoinAlgorithm::Sorted => "stored_mat_join",
+                        MaterializedJoinAlgorithm::Hash => "stored_hash_join",
+                        MaterializedJoinAlgorithm::Merge => "stored_merge_join",
+                    }
                 }
             }
-            RelAlgebra::Join(_) | RelAlgebra::Filter(_) | RelAlgebra::Unification(_) => {
-                "generic_mat_join"
-            }
+            RelAlgebra::Join(_)
+            | RelAlgebra::Filter(_)
+            | RelAlgebra::Unification(_)
+            | RelAlgebra::TrieJoin(_) => match self.materialized_join_algorithm() {
+                MaterializedJoinAlgorithm::Sorted => "generic_mat_join",
+                MaterializedJoinAlgorithm::Hash => "generic_hash_join",
+                MaterializedJoinAlgorithm::Merge => "generic_merge_join",
+            },
             RelAlgebra::Reorder(_) => {
                 panic!("joining on reordered")
             }
@@ -2125,7 +2722,7 @@ impl InnerJoin {
     pub(crate) fn iter<'a>(
         &'a self,
         tx: &'a SessionTx<'_>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
         let bindings = self.bindings();
@@ -2203,12 +2800,21 @@ impl InnerJoin {
                     self.materialized_join(tx, eliminate_indices, delta_rule, stores)
                 }
             }
+            RelAlgebra::TrieJoin(r) if self.left.is_unit() => {
+                // nothing to join with, so the join can be streamed
+                Ok(Box::new(r.iter(tx, delta_rule, stores)?.map_ok(
+                    move |tuple| eliminate_from_tuple(tuple, &eliminate_indices),
+                )))
+            }
             RelAlgebra::Join(_)
             | RelAlgebra::Filter(_)
             | RelAlgebra::Unification(_)
+            | RelAlgebra::TrieJoin(_)
             | RelAlgebra::HnswSearch(_)
             | RelAlgebra::FtsSearch(_)
-            | RelAlgebra::LshSearch(_) => {
+            | RelAlgebra::LshSearch(_)
+            | RelAlgebra::SpatialSearch(_)
+            | RelAlgebra::JsonIndexSearch(_) => {
                 self.materialized_join(tx, eliminate_indices, delta_rule, stores)
             }
             RelAlgebra::Reorder(_) => {
@@ -2223,15 +2829,24 @@ impl InnerJoin {
         &'a self,
         tx: &'a SessionTx<'_>,
         eliminate_indices: BTreeSet<usize>,
-        delta_rule: Option<&MagicSymbol>,
+        delta_rule: Option<DeltaRule<'_>>,
         stores: &'a BTreeMap<MagicSymbol, EpochStore>,
     ) -> Result<TupleIter<'a>> {
-        debug!("using materialized join");
+        let algorithm = self.materialized_join_algorithm();
+        debug!("using materialized join: {:?}", algorithm);
         let right_bindings = self.right.bindings_after_eliminate();
-        let (left_join_indices, right_join_indices) = self
+        let (mut left_join_indices, mut right_join_indices) = self
             .joiner
             .join_indices(&self.left.bindings_after_eliminate(), &right_bindings)
             .unwrap();
+        if algorithm == MaterializedJoinAlgorithm::Merge {
+            // walk the right side in the order the left side is sorted in
+            (left_join_indices, right_join_indices) = left_join_indices
+                .into_iter()
+                .zip(right_join_indices)
+                .sorted()
+                .unzip();
+        }
 
         let mut left_iter = self.left.iter(tx, delta_rule, stores)?;
         let left_cache = match left_iter.next() {
@@ -2240,6 +2855,19 @@ impl InnerJoin {
             Some(Ok(data)) => data,
         };
 
+        if algorithm == MaterializedJoinAlgorithm::Hash {
+            return self.hash_join(
+                tx,
+                left_iter,
+                left_cache,
+                left_join_indices,
+                right_join_indices,
+                eliminate_indices,
+                delta_rule,
+                stores,
+            );
+        }
+
         let right_join_indices_set = BTreeSet::from_iter(right_join_indices.iter().cloned());
         let mut right_store_indices = right_join_indices;
         for i in 0..right_bindings.len() {
@@ -2283,9 +2911,61 @@ impl InnerJoin {
             right_invert_indices,
             right_idx,
             prefix,
+            merge: algorithm == MaterializedJoinAlgorithm::Merge,
+            run_start: right_idx,
         };
         Ok(Box::new(it))
     }
+    #[allow(clippy::too_many_arguments)]
+    fn hash_join<'a>(
+        &'a self,
+        tx: &'a SessionTx<'_>,
+        left_iter: TupleIter<'a>,
+        left_cache: Tuple,
+        left_join_indices: Vec<usize>,
+        right_join_indices: Vec<usize>,
+        eliminate_indices: BTreeSet<usize>,
+        delta_rule: Option<DeltaRule<'_>>,
+        stores: &'a BTreeMap<MagicSymbol, EpochStore>,
+    ) -> Result<TupleIter<'a>> {
+        let mut table: HashMap<Tuple, Vec<Tuple>> = HashMap::new();
+        for item in self.right.iter(tx, delta_rule, stores)? {
+            let tuple = item?;
+            let key = right_join_indices
+                .iter()
+                .map(|i| tuple[*i].clone())
+                .collect_vec();
+            table.entry(key).or_default().push(tuple);
+        }
+        for bucket in table.values_mut() {
+            bucket.sort();
+            bucket.dedup();
+        }
+
+        let mut it = HashJoinIterator {
+            table,
+            eliminate_indices,
+            left_join_indices,
+            left: left_iter,
+            left_cache: vec![],
+            prefix: vec![],
+            right_idx: 0,
+        };
+        it.probe(left_cache);
+        Ok(Box::new(it))
+    }
+}
+
+/// The ways to join against a right side that has to be materialized first.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum MaterializedJoinAlgorithm {
+    /// The right side is sorted, and a binary search finds the matches for each left tuple
+    Sorted,
+    /// The right side is put into a hash table keyed by its join columns
+    Hash,
+    /// As with `Sorted`, but since the left side arrives sorted on the join columns,
+    /// the matches are found by walking forward from the previous ones
+    Merge,
 }
 
 struct CachedMaterializedIterator<'a> {
@@ -2297,6 +2977,10 @@ struct CachedMaterializedIterator<'a> {
     prefix: Tuple,
     left: TupleIter<'a>,
     left_cache: Tuple,
+    /// Whether the left tuples are expected to arrive sorted on the join columns
+    merge: bool,
+    /// Where the matches for the current prefix start
+    run_start: usize,
 }
 
 impl<'a> CachedMaterializedIterator<'a> {
@@ -2313,6 +2997,35 @@ impl<'a> CachedMaterializedIterator<'a> {
             }
         }
     }
+    fn merge_advance(&mut self, left_tuple: &Tuple) {
+        let prefix = self
+            .left_join_indices
+            .iter()
+            .map(|i| left_tuple[*i].clone())
+            .collect_vec();
+        match prefix.cmp(&self.prefix) {
+            Ordering::Equal => {}
+            Ordering::Greater => {
+                let mut idx = self.run_start;
+                while idx < self.materialized.len()
+                    && self.materialized[idx][..prefix.len()] < prefix[..]
+                {
+                    idx += 1;
+                }
+                self.run_start = idx;
+                self.prefix = prefix;
+            }
+            // the left side is not sorted after all
+            Ordering::Less => {
+                self.run_start = match self.materialized.binary_search(&prefix) {
+                    Ok(i) => i,
+                    Err(i) => i,
+                };
+                self.prefix = prefix;
+            }
+        }
+        self.right_idx = self.run_start;
+    }
     fn next_inner(&mut self) -> Result<Option<Tuple>> {
         loop {
             let right_nxt = self.advance_right();
@@ -2332,15 +3045,18 @@ impl<'a> CachedMaterializedIterator<'a> {
                         None => return Ok(None),
                         Some(l) => {
                             let left_tuple = l?;
-                            let (prefix, idx) = build_mat_range_iter(
-                                &self.materialized,
-                                &self.left_join_indices,
-                                &left_tuple,
-                            );
+                            if self.merge {
+                                self.merge_advance(&left_tuple);
+                            } else {
+                                let (prefix, idx) = build_mat_range_iter(
+                                    &self.materialized,
+                                    &self.left_join_indices,
+                                    &left_tuple,
+                                );
+                                self.right_idx = idx;
+                                self.prefix = prefix;
+                            }
                             self.left_cache = left_tuple;
-
-                            self.right_idx = idx;
-                            self.prefix = prefix;
                         }
                     }
                 }
@@ -2373,11 +3089,74 @@ impl<'a> Iterator for CachedMaterializedIterator<'a> {
     }
 }
 
+struct HashJoinIterator<'a> {
+    /// The right tuples, keyed by their join columns
+    table: HashMap<Tuple, Vec<Tuple>>,
+    eliminate_indices: BTreeSet<usize>,
+    left_join_indices: Vec<usize>,
+    left: TupleIter<'a>,
+    left_cache: Tuple,
+    /// The join columns of the current left tuple
+    prefix: Tuple,
+    right_idx: usize,
+}
+
+impl<'a> HashJoinIterator<'a> {
+    fn probe(&mut self, left_tuple: Tuple) {
+        let prefix = self
+            .left_join_indices
+            .iter()
+            .map(|i| left_tuple[*i].clone())
+            .collect_vec();
+        self.left_cache = left_tuple;
+        self.prefix = prefix;
+        self.right_idx = 0;
+    }
+    fn next_inner(&mut self) -> Result<Option<Tuple>> {
+        loop {
+            if let Some(bucket) = self.table.get(&self.prefix) {
+                if let Some(data) = bucket.get(self.right_idx) {
+                    self.right_idx += 1;
+                    let mut ret = self.left_cache.clone();
+                    ret.extend_from_slice(data);
+                    let tuple = eliminate_from_tuple(ret, &self.eliminate_indices);
+                    return Ok(Some(tuple));
+                }
+            }
+            match self.left.next() {
+                None => return Ok(None),
+                Some(l) => self.probe(l?),
+            }
+        }
+    }
+}
+
+impl<'a> Iterator for HashJoinIterator<'a> {
+    type Item = Result<Tuple>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        swap_option_result(self.next_inner())
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use serde_json::json;
+
     use crate::data::value::DataValue;
     use crate::DbInstance;
 
+    fn explained_ops(db: &DbInstance, script: &str) -> Vec<serde_json::Value> {
+        db.run_default(&format!("::explain {{ {script} }}"))
+            .unwrap()
+            .into_json()["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row.as_array().unwrap()[4].clone())
+            .collect()
+    }
+
     #[test]
     fn test_mat_join() {
         let db = DbInstance::default();
@@ -2395,4 +3174,36 @@ mod tests {
             vec![vec![DataValue::from(1)], vec![DataValue::from(2)]]
         )
     }
+
+    #[test]
+    fn test_hash_join() {
+        let db = DbInstance::default();
+        let script = r#"
+        a[x, y] <- [[1, 'a'], [2, 'b'], [3, 'a'], [4, 'c']]
+        b[z, y] <- [[10, 'a'], [20, 'b'], [30, 'b'], [40, 'd']]
+        ?[x, z] := a[x, y], b[z, y]
+        "#;
+        let res = db.run_default(script).unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            json!([[1, 10], [2, 20], [2, 30], [3, 10]])
+        );
+        assert!(explained_ops(&db, script).contains(&json!("mem_hash_join")));
+    }
+
+    #[test]
+    fn test_merge_join() {
+        let db = DbInstance::default();
+        let script = r#"
+        a[y, x] <- [['a', 1], ['a', 3], ['b', 2], ['c', 4]]
+        b[z, y] <- [[10, 'a'], [20, 'b'], [30, 'b'], [40, 'd']]
+        ?[x, z] := a[y, x], b[z, y]
+        "#;
+        let res = db.run_default(script).unwrap();
+        assert_eq!(
+            res.into_json()["rows"],
+            json!([[1, 10], [2, 20], [2, 30], [3, 10]])
+        );
+        assert!(explained_ops(&db, script).contains(&json!("mem_merge_join")));
+    }
 }
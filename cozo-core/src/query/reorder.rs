@@ -96,6 +96,22 @@ impl NormalFormInlineRule {
                         pending.push(NormalFormAtom::LshSearch(s));
                     }
                 }
+                NormalFormAtom::SpatialSearch(s) => {
+                    if seen_variables.contains(&s.query) {
+                        seen_variables.extend(s.all_bindings().cloned());
+                        round_1_collected.push(NormalFormAtom::SpatialSearch(s));
+                    } else {
+                        pending.push(NormalFormAtom::SpatialSearch(s));
+                    }
+                }
+                NormalFormAtom::JsonIndexSearch(s) => {
+                    if seen_variables.contains(&s.query) {
+                        seen_variables.extend(s.all_bindings().cloned());
+                        round_1_collected.push(NormalFormAtom::JsonIndexSearch(s));
+                    } else {
+                        pending.push(NormalFormAtom::JsonIndexSearch(s));
+                    }
+                }
             }
         }
 
@@ -136,6 +152,14 @@ impl NormalFormInlineRule {
                     seen_variables.extend(s.all_bindings().cloned());
                     collected.push(NormalFormAtom::LshSearch(s));
                 }
+                NormalFormAtom::SpatialSearch(s) => {
+                    seen_variables.extend(s.all_bindings().cloned());
+                    collected.push(NormalFormAtom::SpatialSearch(s));
+                }
+                NormalFormAtom::JsonIndexSearch(s) => {
+                    seen_variables.extend(s.all_bindings().cloned());
+                    collected.push(NormalFormAtom::JsonIndexSearch(s));
+                }
             }
             for atom in last_pending.iter() {
                 match atom {
@@ -178,6 +202,22 @@ impl NormalFormInlineRule {
                             pending.push(NormalFormAtom::LshSearch(s.clone()));
                         }
                     }
+                    NormalFormAtom::SpatialSearch(s) => {
+                        if seen_variables.contains(&s.query) {
+                            seen_variables.extend(s.all_bindings().cloned());
+                            collected.push(NormalFormAtom::SpatialSearch(s.clone()));
+                        } else {
+                            pending.push(NormalFormAtom::SpatialSearch(s.clone()));
+                        }
+                    }
+                    NormalFormAtom::JsonIndexSearch(s) => {
+                        if seen_variables.contains(&s.query) {
+                            seen_variables.extend(s.all_bindings().cloned());
+                            collected.push(NormalFormAtom::JsonIndexSearch(s.clone()));
+                        } else {
+                            pending.push(NormalFormAtom::JsonIndexSearch(s.clone()));
+                        }
+                    }
                     NormalFormAtom::Predicate(p) => {
                         if p.bindings()?.is_subset(&seen_variables) {
                             collected.push(NormalFormAtom::Predicate(p.clone()));
@@ -229,6 +269,12 @@ impl NormalFormInlineRule {
                     NormalFormAtom::LshSearch(s) => {
                         bail!(UnboundVariable(s.span))
                     }
+                    NormalFormAtom::SpatialSearch(s) => {
+                        bail!(UnboundVariable(s.span))
+                    }
+                    NormalFormAtom::JsonIndexSearch(s) => {
+                        bail!(UnboundVariable(s.span))
+                    }
                 }
             }
         }
@@ -15,6 +15,7 @@ use miette::Result;
 use crate::data::program::SortDir;
 use crate::data::symb::Symbol;
 use crate::data::tuple::Tuple;
+use crate::data::value::DataValue;
 use crate::runtime::temp_store::EpochStore;
 use crate::runtime::transact::SessionTx;
 
@@ -32,21 +33,29 @@ impl<'a> SessionTx<'a> {
             .collect_vec();
 
         let mut all_data: Vec<_> = original.all_iter().map(|v| v.into_tuple()).collect_vec();
-        all_data.sort_by(|a, b| {
-            for (idx, dir) in &idx_sorters {
-                match a[*idx].cmp(&b[*idx]) {
-                    Ordering::Equal => {}
-                    o => {
-                        return match dir {
-                            SortDir::Asc => o,
-                            SortDir::Dsc => o.reverse(),
-                        }
-                    }
-                }
-            }
-            Ordering::Equal
-        });
+        all_data.sort_by(|a, b| compare_by_sorters(a, b, &idx_sorters));
 
         Ok(all_data)
     }
 }
+
+/// Compares two rows by the values at the indices of `sorters`, each in its own direction.
+/// Being stable, `sort_by` with this keeps rows that tie on all sorters in their original order.
+pub(crate) fn compare_by_sorters(
+    a: &[DataValue],
+    b: &[DataValue],
+    sorters: &[(usize, SortDir)],
+) -> Ordering {
+    for (idx, dir) in sorters {
+        match a[*idx].cmp(&b[*idx]) {
+            Ordering::Equal => {}
+            o => {
+                return match dir {
+                    SortDir::Asc => o,
+                    SortDir::Dsc => o.reverse(),
+                }
+            }
+        }
+    }
+    Ordering::Equal
+}
@@ -120,6 +120,9 @@ impl<'a> SessionTx<'a> {
         } else {
             self.get_relation(&meta.name, false)?
         };
+        if op != RelationOp::Ensure && op != RelationOp::EnsureNot {
+            self.bump_write_stamp(&relation_store)?;
+        }
         if let Some((old_put, old_retract)) = replaced_old_triggers {
             relation_store.put_triggers = old_put;
             relation_store.rm_triggers = old_retract;
@@ -249,6 +252,8 @@ impl<'a> SessionTx<'a> {
         let has_hnsw_indices = !relation_store.hnsw_indices.is_empty();
         let has_fts_indices = !relation_store.fts_indices.is_empty();
         let has_lsh_indices = !relation_store.lsh_indices.is_empty();
+        let has_spatial_indices = !relation_store.spatial_indices.is_empty();
+        let has_json_indices = !relation_store.json_indices.is_empty();
         let mut new_tuples: Vec<DataValue> = vec![];
         let mut old_tuples: Vec<DataValue> = vec![];
 
@@ -272,6 +277,8 @@ impl<'a> SessionTx<'a> {
         let hnsw_filters = Self::make_hnsw_filters(relation_store)?;
         let fts_lsh_processors = self.make_fts_lsh_processors(relation_store)?;
         let lsh_perms = self.make_lsh_hash_perms(relation_store);
+        let spatial_extractors = Self::make_spatial_extractors(relation_store)?;
+        let json_extractors = Self::make_json_extractors(relation_store)?;
 
         for tuple in res_iter {
             let extracted: Vec<DataValue> = key_extractors
@@ -304,10 +311,13 @@ impl<'a> SessionTx<'a> {
                 || has_hnsw_indices
                 || has_fts_indices
                 || has_lsh_indices
+                || has_spatial_indices
+                || has_json_indices
             {
                 if let Some(existing) = self.store_tx.get(&key, false)? {
                     let mut tup = extracted[0..relation_store.metadata.keys.len()].to_vec();
                     extend_tuple_from_v(&mut tup, &existing);
+                    self.del_in_json(relation_store, &mut stack, &json_extractors, &tup)?;
                     if has_indices && extracted != tup {
                         self.update_in_index(relation_store, &extracted, &tup)?;
                         self.del_in_fts(relation_store, &mut stack, &fts_lsh_processors, &tup)?;
@@ -338,6 +348,8 @@ impl<'a> SessionTx<'a> {
                     &extracted,
                     &lsh_perms,
                 )?;
+                self.put_in_spatial(relation_store, &mut stack, &spatial_extractors, &extracted)?;
+                self.put_in_json(relation_store, &mut stack, &json_extractors, &extracted)?;
 
                 if need_to_collect {
                     new_tuples.push(DataValue::List(extracted));
@@ -428,6 +440,62 @@ impl<'a> SessionTx<'a> {
         Ok(())
     }
 
+    fn put_in_spatial(
+        &mut self,
+        rel_handle: &RelationHandle,
+        stack: &mut Vec<DataValue>,
+        extractors: &BTreeMap<SmartString<LazyCompact>, Vec<Bytecode>>,
+        new_kv: &[DataValue],
+    ) -> Result<()> {
+        for (k, (idx_handle, inv_idx_handle, manifest)) in rel_handle.spatial_indices.iter() {
+            self.put_spatial_index_item(
+                new_kv,
+                extractors.get(k).unwrap(),
+                stack,
+                rel_handle,
+                idx_handle,
+                inv_idx_handle,
+                manifest,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn del_in_spatial(&mut self, rel_handle: &RelationHandle, old_kv: &[DataValue]) -> Result<()> {
+        for (idx_handle, inv_idx_handle, _) in rel_handle.spatial_indices.values() {
+            self.del_spatial_index_item(old_kv, idx_handle, inv_idx_handle)?;
+        }
+        Ok(())
+    }
+
+    fn put_in_json(
+        &mut self,
+        rel_handle: &RelationHandle,
+        stack: &mut Vec<DataValue>,
+        extractors: &BTreeMap<SmartString<LazyCompact>, Vec<Bytecode>>,
+        new_kv: &[DataValue],
+    ) -> Result<()> {
+        for (k, (idx_handle, _)) in rel_handle.json_indices.iter() {
+            let extractor = extractors.get(k).unwrap();
+            self.put_json_index_item(new_kv, extractor, stack, rel_handle, idx_handle)?;
+        }
+        Ok(())
+    }
+
+    fn del_in_json(
+        &mut self,
+        rel_handle: &RelationHandle,
+        stack: &mut Vec<DataValue>,
+        extractors: &BTreeMap<SmartString<LazyCompact>, Vec<Bytecode>>,
+        old_kv: &[DataValue],
+    ) -> Result<()> {
+        for (k, (idx_handle, _)) in rel_handle.json_indices.iter() {
+            let extractor = extractors.get(k).unwrap();
+            self.del_json_index_item(old_kv, extractor, stack, rel_handle, idx_handle)?;
+        }
+        Ok(())
+    }
+
     fn update_in_hnsw(
         &mut self,
         relation_store: &RelationHandle,
@@ -498,6 +566,26 @@ impl<'a> SessionTx<'a> {
         Ok(processors)
     }
 
+    fn make_spatial_extractors(
+        relation_store: &RelationHandle,
+    ) -> Result<BTreeMap<SmartString<LazyCompact>, Vec<Bytecode>>> {
+        let mut extractors = BTreeMap::new();
+        for (name, (_, _, manifest)) in relation_store.spatial_indices.iter() {
+            extractors.insert(name.clone(), manifest.compile_extractor(relation_store)?);
+        }
+        Ok(extractors)
+    }
+
+    fn make_json_extractors(
+        relation_store: &RelationHandle,
+    ) -> Result<BTreeMap<SmartString<LazyCompact>, Vec<Bytecode>>> {
+        let mut extractors = BTreeMap::new();
+        for (name, (_, manifest)) in relation_store.json_indices.iter() {
+            extractors.insert(name.clone(), manifest.compile_extractor(relation_store)?);
+        }
+        Ok(extractors)
+    }
+
     fn make_hnsw_filters(
         relation_store: &RelationHandle,
     ) -> Result<BTreeMap<SmartString<LazyCompact>, Vec<Bytecode>>> {
@@ -559,6 +647,8 @@ impl<'a> SessionTx<'a> {
         let has_hnsw_indices = !relation_store.hnsw_indices.is_empty();
         let has_fts_indices = !relation_store.fts_indices.is_empty();
         let has_lsh_indices = !relation_store.lsh_indices.is_empty();
+        let has_spatial_indices = !relation_store.spatial_indices.is_empty();
+        let has_json_indices = !relation_store.json_indices.is_empty();
         let mut new_tuples: Vec<DataValue> = vec![];
         let mut old_tuples: Vec<DataValue> = vec![];
 
@@ -573,6 +663,8 @@ impl<'a> SessionTx<'a> {
         let hnsw_filters = Self::make_hnsw_filters(relation_store)?;
         let fts_lsh_processors = self.make_fts_lsh_processors(relation_store)?;
         let lsh_perms = self.make_lsh_hash_perms(relation_store);
+        let spatial_extractors = Self::make_spatial_extractors(relation_store)?;
+        let json_extractors = Self::make_json_extractors(relation_store)?;
 
         for tuple in res_iter {
             let mut new_kv: Vec<DataValue> = key_extractors
@@ -618,9 +710,12 @@ impl<'a> SessionTx<'a> {
                 || has_hnsw_indices
                 || has_fts_indices
                 || has_lsh_indices
+                || has_spatial_indices
+                || has_json_indices
             {
                 self.del_in_fts(relation_store, &mut stack, &fts_lsh_processors, &old_kv)?;
                 self.del_in_lsh(relation_store, &old_kv)?;
+                self.del_in_json(relation_store, &mut stack, &json_extractors, &old_kv)?;
                 self.update_in_index(relation_store, &new_kv, &old_kv)?;
 
                 if need_to_collect {
@@ -636,6 +731,8 @@ impl<'a> SessionTx<'a> {
                     &new_kv,
                     &lsh_perms,
                 )?;
+                self.put_in_spatial(relation_store, &mut stack, &spatial_extractors, &new_kv)?;
+                self.put_in_json(relation_store, &mut stack, &json_extractors, &new_kv)?;
 
                 if need_to_collect {
                     new_tuples.push(DataValue::List(new_kv));
@@ -953,7 +1050,10 @@ impl<'a> SessionTx<'a> {
         let has_hnsw_indices = !relation_store.hnsw_indices.is_empty();
         let has_fts_indices = !relation_store.fts_indices.is_empty();
         let has_lsh_indices = !relation_store.lsh_indices.is_empty();
+        let has_spatial_indices = !relation_store.spatial_indices.is_empty();
+        let has_json_indices = !relation_store.json_indices.is_empty();
         let fts_processors = self.make_fts_lsh_processors(relation_store)?;
+        let json_extractors = Self::make_json_extractors(relation_store)?;
         let mut new_tuples: Vec<DataValue> = vec![];
         let mut old_tuples: Vec<DataValue> = vec![];
         let mut stack = vec![];
@@ -978,12 +1078,21 @@ impl<'a> SessionTx<'a> {
                     });
                 }
             }
-            if need_to_collect || has_indices || has_hnsw_indices || has_fts_indices || has_lsh_indices {
+            if need_to_collect
+                || has_indices
+                || has_hnsw_indices
+                || has_fts_indices
+                || has_lsh_indices
+                || has_spatial_indices
+                || has_json_indices
+            {
                 if let Some(existing) = self.store_tx.get(&key, false)? {
                     let mut tup = extracted.clone();
                     extend_tuple_from_v(&mut tup, &existing);
                     self.del_in_fts(relation_store, &mut stack, &fts_processors, &tup)?;
                     self.del_in_lsh(relation_store, &tup)?;
+                    self.del_in_spatial(relation_store, &tup)?;
+                    self.del_in_json(relation_store, &mut stack, &json_extractors, &tup)?;
                     if has_indices {
                         for (idx_rel, extractor) in relation_store.indices.values() {
                             let idx_tup = extractor.iter().map(|i| tup[*i].clone()).collect_vec();
@@ -1223,6 +1332,7 @@ fn make_const_rule(
                 arity: bindings_arity,
                 span: Default::default(),
                 fixed_impl: Arc::new(Box::new(Constant)),
+                named_outputs: Default::default(),
             },
         },
     );
@@ -32,7 +32,9 @@ impl NormalFormAtom {
             | NormalFormAtom::Unification(_)
             | NormalFormAtom::HnswSearch(_)
             | NormalFormAtom::FtsSearch(_)
-            | NormalFormAtom::LshSearch(_) => Default::default(),
+            | NormalFormAtom::LshSearch(_)
+            | NormalFormAtom::SpatialSearch(_)
+            | NormalFormAtom::JsonIndexSearch(_) => Default::default(),
             NormalFormAtom::Rule(r) => BTreeMap::from([(&r.name, false)]),
             NormalFormAtom::NegatedRule(r) => BTreeMap::from([(&r.name, true)]),
         }
@@ -141,7 +143,9 @@ fn convert_normal_form_program_to_graph(
                         FixedRuleArg::InMem { name, .. } => {
                             ret.insert(name, true);
                         }
-                        FixedRuleArg::Stored { .. } | FixedRuleArg::NamedStored { .. } => {}
+                        FixedRuleArg::Stored { .. }
+                        | FixedRuleArg::NamedStored { .. }
+                        | FixedRuleArg::Graph { .. } => {}
                     }
                 }
                 (k, ret)
@@ -37,20 +37,24 @@ use crate::data::json::JsonValue;
 use crate::data::program::{InputProgram, QueryAssertion, RelationOp, ReturnMutation};
 use crate::data::relation::ColumnDef;
 use crate::data::tuple::{Tuple, TupleT};
-use crate::data::value::{DataValue, ValidityTs, LARGEST_UTF_CHAR};
+use crate::data::value::{DataValue, JsonData, ValidityTs, LARGEST_UTF_CHAR};
 use crate::fixed_rule::DEFAULT_FIXED_RULES;
 use crate::fts::TokenizerCache;
 use crate::parse::sys::SysOp;
 use crate::parse::{parse_expressions, parse_script, CozoScript, SourceSpan};
 use crate::query::compile::{CompiledProgram, CompiledRule, CompiledRuleSet};
 use crate::query::ra::{
-    FilteredRA, FtsSearchRA, HnswSearchRA, InnerJoin, LshSearchRA, NegJoin, RelAlgebra, ReorderRA,
-    StoredRA, StoredWithValidityRA, TempStoreRA, UnificationRA,
+    FilteredRA, FtsSearchRA, HnswSearchRA, InnerJoin, JsonIndexSearchRA, LshSearchRA, NegJoin,
+    RelAlgebra, ReorderRA, SpatialSearchRA, StoredRA, StoredWithValidityRA, TempStoreRA,
+    TrieJoinRA, TrieSource, UnificationRA,
 };
 #[allow(unused_imports)]
 use crate::runtime::callback::{
     CallbackCollector, CallbackDeclaration, CallbackOp, EventCallbackRegistry,
 };
+use crate::runtime::graph_projection::{
+    GraphProjection, GraphProjectionNotFound, GraphProjections,
+};
 use crate::runtime::relation::{
     extend_tuple_from_v, AccessLevel, InsufficientAccessLevel, RelationHandle, RelationId,
 };
@@ -102,6 +106,7 @@ pub struct Db<S> {
     pub(crate) running_queries: Arc<Mutex<BTreeMap<u64, RunningQueryHandle>>>,
     pub(crate) fixed_rules: Arc<ShardedLock<BTreeMap<String, Arc<Box<dyn FixedRule>>>>>,
     pub(crate) tokenizers: Arc<TokenizerCache>,
+    pub(crate) graph_projections: Arc<GraphProjections>,
     #[cfg(not(target_arch = "wasm32"))]
     callback_count: Arc<AtomicU32>,
     #[cfg(not(target_arch = "wasm32"))]
@@ -251,6 +256,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             running_queries: Default::default(),
             fixed_rules: Arc::new(ShardedLock::new(DEFAULT_FIXED_RULES.clone())),
             tokenizers: Arc::new(Default::default()),
+            graph_projections: Default::default(),
             #[cfg(not(target_arch = "wasm32"))]
             callback_count: Default::default(),
             // callback_receiver: Arc::new(receiver),
@@ -495,6 +501,7 @@ impl<'s, S: Storage<'s>> Db<S> {
                     handle.access_level
                 ));
             }
+            tx.bump_write_stamp(&handle)?;
 
             let header2idx: BTreeMap<_, _> = in_data
                 .headers
@@ -635,7 +642,13 @@ impl<'s, S: Storage<'s>> Db<S> {
             let iter = s_tx.store_tx.total_scan();
             self.db.batch_put(iter)?;
             s_tx.commit_tx()?;
-            Ok(())
+
+            // the stamps come from the backup, so they may coincide with what projections saw
+            let mut tx = self.transact_write()?;
+            for handle in tx.all_relations()? {
+                tx.bump_write_stamp(&handle)?;
+            }
+            tx.commit_tx()
         }
         #[cfg(not(feature = "storage-sqlite"))]
         bail!("backup requires the 'storage-sqlite' feature to be enabled")
@@ -690,6 +703,7 @@ impl<'s, S: Storage<'s>> Db<S> {
                         dst_handle.access_level
                     ));
                 }
+                dst_tx.bump_write_stamp(&dst_handle)?;
 
                 let src_lower = Tuple::default().encode_as_key(src_handle.id);
                 let src_upper = Tuple::default().encode_as_key(src_handle.id.next());
@@ -832,6 +846,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             relation_store_id: self.relation_store_id.clone(),
             temp_store_id: Default::default(),
             tokenizers: self.tokenizers.clone(),
+            graph_projections: self.graph_projections.clone(),
         };
         Ok(ret)
     }
@@ -842,6 +857,7 @@ impl<'s, S: Storage<'s>> Db<S> {
             relation_store_id: self.relation_store_id.clone(),
             temp_store_id: Default::default(),
             tokenizers: self.tokenizers.clone(),
+            graph_projections: self.graph_projections.clone(),
         };
         Ok(ret)
     }
@@ -1123,6 +1139,48 @@ impl<'s, S: Storage<'s>> Db<S> {
                                             .map(|f| f.to_string())
                                             .collect_vec()),
                                     ),
+                                    RelAlgebra::SpatialSearch(SpatialSearchRA {
+                                        spatial_search,
+                                        ..
+                                    }) => (
+                                        "spatial_index",
+                                        json!(format!(":{}", spatial_search.query.name)),
+                                        json!(spatial_search.query.name),
+                                        json!(spatial_search
+                                            .filter
+                                            .iter()
+                                            .map(|f| f.to_string())
+                                            .collect_vec()),
+                                    ),
+                                    RelAlgebra::JsonIndexSearch(JsonIndexSearchRA {
+                                        json_search,
+                                        ..
+                                    }) => (
+                                        "json_index",
+                                        json!(format!(":{}", json_search.query.name)),
+                                        json!(json_search.query.name),
+                                        json!(json_search
+                                            .filter
+                                            .iter()
+                                            .map(|f| f.to_string())
+                                            .collect_vec()),
+                                    ),
+                                    RelAlgebra::TrieJoin(TrieJoinRA {
+                                        bindings, atoms, ..
+                                    }) => (
+                                        "leapfrog_trie_join",
+                                        json!(atoms
+                                            .iter()
+                                            .map(|atom| match &atom.source {
+                                                TrieSource::TempStore(key) => key.to_string(),
+                                                TrieSource::Stored(handle) => {
+                                                    format!(":{}", handle.name)
+                                                }
+                                            })
+                                            .collect_vec()),
+                                        json!(bindings.iter().map(|b| b.to_string()).collect_vec()),
+                                        json!(null),
+                                    ),
                                 };
                                 ret_for_relation.push(json!({
                                     STRATUM: stratum,
@@ -1193,10 +1251,49 @@ impl<'s, S: Storage<'s>> Db<S> {
             SysOp::ListFixedRules => {
                 let rules = self.fixed_rules.read().unwrap();
                 Ok(NamedRows::new(
-                    vec!["rule".to_string()],
+                    vec![
+                        "rule".to_string(),
+                        "inputs".to_string(),
+                        "options".to_string(),
+                    ],
                     rules
-                        .keys()
-                        .map(|k| vec![DataValue::from(k as &str)])
+                        .iter()
+                        .map(|(k, rule)| {
+                            let mut row = vec![DataValue::from(k as &str)];
+                            match rule.schema() {
+                                None => row.extend([DataValue::Null, DataValue::Null]),
+                                Some(schema) => {
+                                    let inputs = schema
+                                        .inputs
+                                        .iter()
+                                        .map(|input| {
+                                            json!({
+                                                "name": input.name,
+                                                "min_arity": input.min_arity,
+                                                "optional": input.optional,
+                                                "doc": input.doc,
+                                            })
+                                        })
+                                        .collect_vec();
+                                    let options = schema
+                                        .options
+                                        .iter()
+                                        .map(|opt| {
+                                            json!({
+                                                "name": opt.name,
+                                                "type": opt.typ.name(),
+                                                "default": opt.default.clone().map(JsonValue::from),
+                                                "required": opt.required,
+                                                "doc": opt.doc,
+                                            })
+                                        })
+                                        .collect_vec();
+                                    row.push(DataValue::Json(JsonData(inputs.into())));
+                                    row.push(DataValue::Json(JsonData(options.into())));
+                                }
+                            }
+                            row
+                        })
                         .collect_vec(),
                 ))
             }
@@ -1310,6 +1407,46 @@ impl<'s, S: Storage<'s>> Db<S> {
                     vec![vec![DataValue::from(OK_STR)]],
                 ))
             }
+            SysOp::CreateSpatialIndex(config) => {
+                if read_only {
+                    bail!("Cannot create spatial index in read-only mode");
+                }
+                if skip_locking {
+                    tx.create_spatial_index(config)?;
+                } else {
+                    let lock = self
+                        .obtain_relation_locks(iter::once(&config.base_relation))
+                        .pop()
+                        .unwrap();
+                    let _guard = lock.write().unwrap();
+                    tx.create_spatial_index(config)?;
+                }
+
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::CreateJsonIndex(config) => {
+                if read_only {
+                    bail!("Cannot create JSON index in read-only mode");
+                }
+                if skip_locking {
+                    tx.create_json_index(config)?;
+                } else {
+                    let lock = self
+                        .obtain_relation_locks(iter::once(&config.base_relation))
+                        .pop()
+                        .unwrap();
+                    let _guard = lock.write().unwrap();
+                    tx.create_json_index(config)?;
+                }
+
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
             SysOp::RemoveIndex(rel_name, idx_name) => {
                 if read_only {
                     bail!("Cannot remove index in read-only mode");
@@ -1333,6 +1470,121 @@ impl<'s, S: Storage<'s>> Db<S> {
                     vec![vec![DataValue::from(OK_STR)]],
                 ))
             }
+            SysOp::CreateGraphProjection(name, rel_name, columns) => {
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("Graph projection '{0}' already exists")]
+                #[diagnostic(code(eval::graph_projection_exists))]
+                struct GraphProjectionExists(String, #[label] SourceSpan);
+
+                #[derive(Debug, Error, Diagnostic)]
+                #[error("Cannot project {0} columns from relation '{1}' of arity {2}")]
+                #[diagnostic(code(eval::graph_projection_bad_arity))]
+                struct GraphProjectionArityError(usize, String, usize, #[label] SourceSpan);
+
+                ensure!(
+                    !self
+                        .graph_projections
+                        .read()
+                        .unwrap()
+                        .contains_key(&name.name),
+                    GraphProjectionExists(name.name.to_string(), name.span)
+                );
+                if rel_name.is_temp_store_name() {
+                    bail!(
+                        "Cannot create graph projection from the temp relation '{}'",
+                        rel_name.name
+                    );
+                }
+                let handle = tx.get_relation(rel_name, false)?;
+                if handle.access_level < AccessLevel::ReadOnly {
+                    bail!(InsufficientAccessLevel(
+                        handle.name.to_string(),
+                        "graph projection".to_string(),
+                        handle.access_level
+                    ));
+                }
+                let arity = handle.arity();
+                ensure!(
+                    columns.len() <= arity,
+                    GraphProjectionArityError(
+                        columns.len(),
+                        rel_name.name.to_string(),
+                        arity,
+                        rel_name.span
+                    )
+                );
+                let projection = GraphProjection::new(
+                    tx,
+                    name.name.clone(),
+                    rel_name.name.clone(),
+                    columns.iter().map(|col| col.name.clone()).collect_vec(),
+                )?;
+                match self
+                    .graph_projections
+                    .write()
+                    .unwrap()
+                    .entry(name.name.clone())
+                {
+                    Entry::Vacant(e) => {
+                        e.insert(Arc::new(projection));
+                    }
+                    Entry::Occupied(_) => {
+                        bail!(GraphProjectionExists(name.name.to_string(), name.span))
+                    }
+                }
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::RemoveGraphProjection(name) => {
+                self.graph_projections
+                    .write()
+                    .unwrap()
+                    .remove(&name.name)
+                    .ok_or_else(|| GraphProjectionNotFound(name.name.to_string()))?;
+                Ok(NamedRows::new(
+                    vec![STATUS_STR.to_string()],
+                    vec![vec![DataValue::from(OK_STR)]],
+                ))
+            }
+            SysOp::ListGraphProjections => {
+                let projections = self
+                    .graph_projections
+                    .read()
+                    .unwrap()
+                    .values()
+                    .cloned()
+                    .collect_vec();
+                let mut rows = vec![];
+                for projection in projections {
+                    rows.push(vec![
+                        DataValue::from(&projection.name as &str),
+                        DataValue::from(&projection.relation as &str),
+                        DataValue::List(
+                            projection
+                                .columns
+                                .iter()
+                                .map(|col| DataValue::from(col as &str))
+                                .collect_vec(),
+                        ),
+                        // the base relation may have been removed since
+                        match projection.rows(tx) {
+                            Ok(rows) => DataValue::from(rows.len() as i64),
+                            Err(_) => DataValue::Null,
+                        },
+                    ]);
+                }
+                Ok(NamedRows::new(
+                    vec![
+                        "name".to_string(),
+                        "relation".to_string(),
+                        "columns".to_string(),
+                        "rows".to_string(),
+                    ],
+                    rows,
+                ))
+            }
             SysOp::ListColumns(rs) => self.list_columns(tx, rs),
             SysOp::ListIndices(rs) => self.list_indices(tx, rs),
             SysOp::RenameRelation(rename_pairs) => {
@@ -1417,6 +1669,15 @@ impl<'s, S: Storage<'s>> Db<S> {
         }
     }
     fn run_sys_op(&'s self, op: SysOp, read_only: bool) -> Result<NamedRows> {
+        // writers only stamp relations that are already projected, so a projection waits out
+        // the writes in flight before its transaction starts, and they show in its snapshot
+        let projection_lock = match &op {
+            SysOp::CreateGraphProjection(_, rel_name, _) => {
+                self.obtain_relation_locks(iter::once(&rel_name.name)).pop()
+            }
+            _ => None,
+        };
+        let _projection_guard = projection_lock.as_ref().map(|lock| lock.write().unwrap());
         let mut tx = if read_only {
             self.transact()?
         } else {
@@ -1736,6 +1997,27 @@ impl<'s, S: Storage<'s>> Db<S> {
                 }),
             ]);
         }
+        for (name, (rel, inv_rel, manifest)) in &handle.spatial_indices {
+            rows.push(vec![
+                json!(name),
+                json!("spatial"),
+                json!([rel.name, inv_rel.name]),
+                json!({
+                    "extractor": manifest.extractor,
+                    "max_entries": manifest.max_entries,
+                }),
+            ]);
+        }
+        for (name, (rel, manifest)) in &handle.json_indices {
+            rows.push(vec![
+                json!(name),
+                json!("json"),
+                json!([rel.name]),
+                json!({
+                    "extractor": manifest.extractor,
+                }),
+            ]);
+        }
         let rows = rows
             .into_iter()
             .map(|row| row.into_iter().map(DataValue::from).collect_vec())
@@ -0,0 +1,250 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crossbeam::sync::ShardedLock;
+use miette::{ensure, miette, Diagnostic, Result};
+use smartstring::{LazyCompact, SmartString};
+use thiserror::Error;
+
+use crate::data::tuple::{Tuple, TupleT};
+use crate::data::value::DataValue;
+use crate::runtime::relation::{RelationHandle, RelationId};
+use crate::runtime::transact::SessionTx;
+
+pub(crate) type GraphProjections =
+    ShardedLock<BTreeMap<SmartString<LazyCompact>, Arc<GraphProjection>>>;
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("Graph projection '{0}' not found")]
+#[diagnostic(code(eval::graph_projection_not_found))]
+pub(crate) struct GraphProjectionNotFound(pub(crate) String);
+
+/// A named in-memory copy of the leading columns of a stored relation, kept across queries
+/// so that graph algorithms do not need to rescan and re-intern the relation on every call.
+///
+/// Every write to the rows of a stored relation that is projected, imports and restores
+/// included, gives it a new write stamp in the same transaction. The projection remembers the
+/// relation ID and stamp it was read at, and reads the relation again under the current
+/// transaction if they differ. Relations without projections are written as usual.
+pub(crate) struct GraphProjection {
+    pub(crate) name: SmartString<LazyCompact>,
+    pub(crate) relation: SmartString<LazyCompact>,
+    pub(crate) columns: Vec<SmartString<LazyCompact>>,
+    state: Mutex<ProjectionState>,
+}
+
+struct ProjectionState {
+    /// The relation ID and write stamp the rows were read at
+    read_at: (RelationId, u64),
+    /// Projected tuples keyed by the keys of the rows they come from
+    rows: Arc<BTreeMap<Tuple, Tuple>>,
+    #[cfg(feature = "graph-algo")]
+    edges: Option<Arc<ProjectedEdges>>,
+}
+
+/// The projection interned as an edge list, in the form expected by the CSR graph builder.
+#[cfg(feature = "graph-algo")]
+pub(crate) struct ProjectedEdges {
+    pub(crate) indices: Vec<DataValue>,
+    pub(crate) inv_indices: BTreeMap<DataValue, u32>,
+    pub(crate) edges: Vec<(u32, u32)>,
+    /// The third column as weights, `1.0` for every edge if the projection has only two columns
+    pub(crate) weights: Vec<f32>,
+    /// The first weight that is not a finite number
+    pub(crate) bad_weight: Option<DataValue>,
+    /// The first weight that is negative
+    pub(crate) negative_weight: Option<DataValue>,
+}
+
+fn write_stamp_key(id: RelationId) -> Vec<u8> {
+    let tuple = vec![
+        DataValue::Null,
+        DataValue::from("WRITE_STAMP"),
+        DataValue::from(id.0 as i64),
+    ];
+    tuple.encode_as_key(RelationId::SYSTEM)
+}
+
+impl SessionTx<'_> {
+    pub(crate) fn get_graph_projection(&self, name: &str) -> Result<Arc<GraphProjection>> {
+        Ok(self
+            .graph_projections
+            .read()
+            .unwrap()
+            .get(name)
+            .ok_or_else(|| GraphProjectionNotFound(name.to_string()))?
+            .clone())
+    }
+    /// Gives the relation a new write stamp if it is projected, to be called whenever its rows
+    /// are written while holding the relation lock. Stamps are random instead of counted,
+    /// so that the stamp of a transaction that is rolled back is not handed out again to
+    /// different rows.
+    pub(crate) fn bump_write_stamp(&mut self, handle: &RelationHandle) -> Result<()> {
+        let is_projected = self
+            .graph_projections
+            .read()
+            .unwrap()
+            .values()
+            .any(|projection| projection.relation == handle.name);
+        if !is_projected {
+            return Ok(());
+        }
+        let stamp: u64 = rand::random();
+        self.store_tx
+            .put(&write_stamp_key(handle.id), &stamp.to_be_bytes())
+    }
+    pub(crate) fn clear_write_stamp(&mut self, id: RelationId) -> Result<()> {
+        self.store_tx.del(&write_stamp_key(id))
+    }
+    fn write_stamp(&self, id: RelationId) -> Result<u64> {
+        Ok(match self.store_tx.get(&write_stamp_key(id), false)? {
+            None => 0,
+            Some(bytes) => u64::from_be_bytes(
+                bytes
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| miette!("corrupt write stamp for relation {}", id.0))?,
+            ),
+        })
+    }
+}
+
+impl GraphProjection {
+    pub(crate) fn new(
+        tx: &SessionTx<'_>,
+        name: SmartString<LazyCompact>,
+        relation: SmartString<LazyCompact>,
+        columns: Vec<SmartString<LazyCompact>>,
+    ) -> Result<Self> {
+        let handle = tx.get_relation(&relation, false)?;
+        let state = ProjectionState::read(tx, &handle, columns.len())?;
+        Ok(Self {
+            name,
+            relation,
+            columns,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub(crate) fn arity(&self) -> usize {
+        self.columns.len()
+    }
+
+    /// The projected tuples, as seen by the transaction.
+    pub(crate) fn rows(&self, tx: &SessionTx<'_>) -> Result<Arc<BTreeMap<Tuple, Tuple>>> {
+        let state = self.sync(tx)?;
+        Ok(state.rows.clone())
+    }
+
+    /// The projection as an interned edge list, as seen by the transaction.
+    /// Returns `None` if the projection has fewer than two columns.
+    #[cfg(feature = "graph-algo")]
+    pub(crate) fn edges(&self, tx: &SessionTx<'_>) -> Result<Option<Arc<ProjectedEdges>>> {
+        if self.arity() < 2 {
+            return Ok(None);
+        }
+        let mut state = self.sync(tx)?;
+        if state.edges.is_none() {
+            let edges = ProjectedEdges::new(&state.rows);
+            state.edges = Some(Arc::new(edges));
+        }
+        Ok(state.edges.clone())
+    }
+
+    fn sync(&self, tx: &SessionTx<'_>) -> Result<MutexGuard<'_, ProjectionState>> {
+        let handle = tx.get_relation(&self.relation, false)?;
+        let read_at = (handle.id, tx.write_stamp(handle.id)?);
+        let mut state = self.state.lock().unwrap();
+        if state.read_at != read_at {
+            *state = ProjectionState::read(tx, &handle, self.arity())?;
+        }
+        Ok(state)
+    }
+}
+
+impl ProjectionState {
+    fn read(tx: &SessionTx<'_>, handle: &RelationHandle, arity: usize) -> Result<Self> {
+        #[derive(Debug, Error, Diagnostic)]
+        #[error("Relation '{0}' no longer has the {1} columns of the graph projection")]
+        #[diagnostic(code(eval::graph_projection_bad_arity))]
+        struct ProjectionArityMismatch(String, usize);
+
+        ensure!(
+            handle.arity() >= arity,
+            ProjectionArityMismatch(handle.name.to_string(), arity)
+        );
+        // the stamp is read first, so that a write landing during the scan is caught next time
+        let read_at = (handle.id, tx.write_stamp(handle.id)?);
+        let key_len = handle.metadata.keys.len();
+        let mut rows = BTreeMap::new();
+        for tuple in handle.scan_all(tx) {
+            let mut tuple = tuple?;
+            let key = tuple[..key_len].to_vec();
+            tuple.truncate(arity);
+            rows.insert(key, tuple);
+        }
+        Ok(Self {
+            read_at,
+            rows: Arc::new(rows),
+            #[cfg(feature = "graph-algo")]
+            edges: None,
+        })
+    }
+}
+
+#[cfg(feature = "graph-algo")]
+impl ProjectedEdges {
+    fn new(rows: &BTreeMap<Tuple, Tuple>) -> Self {
+        let mut ret = ProjectedEdges {
+            indices: vec![],
+            inv_indices: Default::default(),
+            edges: Vec::with_capacity(rows.len()),
+            weights: Vec::with_capacity(rows.len()),
+            bad_weight: None,
+            negative_weight: None,
+        };
+        for tuple in rows.values() {
+            let from_idx = ret.intern(&tuple[0]);
+            let to_idx = ret.intern(&tuple[1]);
+            ret.edges.push((from_idx, to_idx));
+            let weight = match tuple.get(2) {
+                None => 1.,
+                Some(d) => match d.get_float() {
+                    Some(f) if f.is_finite() => {
+                        if f < 0. && ret.negative_weight.is_none() {
+                            ret.negative_weight = Some(d.clone());
+                        }
+                        f
+                    }
+                    _ => {
+                        if ret.bad_weight.is_none() {
+                            ret.bad_weight = Some(d.clone());
+                        }
+                        0.
+                    }
+                },
+            };
+            ret.weights.push(weight as f32);
+        }
+        ret
+    }
+
+    fn intern(&mut self, node: &DataValue) -> u32 {
+        if let Some(idx) = self.inv_indices.get(node) {
+            *idx
+        } else {
+            let idx = self.indices.len() as u32;
+            self.inv_indices.insert(node.clone(), idx);
+            self.indices.push(node.clone());
+            idx
+        }
+    }
+}
@@ -0,0 +1,321 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! An inverted index over the keys and values of JSON documents.
+//!
+//! Every document is flattened into `(path, scalar)` pairs, and the index relation holds a row
+//! `[path, value, ..keys of the base relation]` for each pair. A containment query is answered
+//! by intersecting the rows found for every pair of the query document, and the candidates are
+//! then checked against the full document.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use itertools::Itertools;
+use miette::{miette, IntoDiagnostic, Result};
+use pest::Parser;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::{eval_bytecode, eval_bytecode_pred, Bytecode};
+use crate::data::functions::{OP_EQ, OP_GET, OP_JSON_CONTAINS};
+use crate::data::json::JsonValue;
+use crate::data::json_path::{json_contains, json_index_entries};
+use crate::data::tuple::Tuple;
+use crate::parse::expr::build_expr;
+use crate::parse::{CozoScriptParser, Rule};
+use crate::runtime::relation::RelationHandle;
+use crate::runtime::transact::SessionTx;
+use crate::{DataValue, Expr, JsonData, SourceSpan, Symbol};
+
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub(crate) struct JsonIndexManifest {
+    pub(crate) base_relation: SmartString<LazyCompact>,
+    pub(crate) index_name: SmartString<LazyCompact>,
+    pub(crate) extractor: String,
+}
+
+impl JsonIndexManifest {
+    fn extractor_expr(&self) -> Result<Expr> {
+        let parsed = CozoScriptParser::parse(Rule::expr, &self.extractor)
+            .into_diagnostic()?
+            .next()
+            .unwrap();
+        build_expr(parsed, &Default::default())
+    }
+    pub(crate) fn compile_extractor(&self, base_handle: &RelationHandle) -> Result<Vec<Bytecode>> {
+        let mut code_expr = self.extractor_expr()?;
+        let binding_map = base_handle.raw_binding_map();
+        code_expr.fill_binding_indices(&binding_map)?;
+        code_expr.compile()
+    }
+    /// If `pred` can only hold for rows whose indexed document contains some pattern, returns
+    /// that pattern. `args` are the variables bound to the columns of the base relation.
+    ///
+    /// Recognized are `json_contains(doc, pattern)` with a constant object or array pattern,
+    /// and `get(doc, path) == value` with a constant path of keys and a constant scalar value.
+    pub(crate) fn implied_pattern(
+        &self,
+        pred: &Expr,
+        base_handle: &RelationHandle,
+        args: &[Symbol],
+    ) -> Result<Option<DataValue>> {
+        let extractor = self.extractor_expr()?;
+        let columns: BTreeMap<&str, &Symbol> = base_handle
+            .metadata
+            .keys
+            .iter()
+            .chain(base_handle.metadata.non_keys.iter())
+            .map(|col| &col.name as &str)
+            .zip(args)
+            .collect();
+        let is_doc = |expr: &Expr| computes_extractor(expr, &extractor, &columns);
+
+        let (op, op_args) = match pred {
+            Expr::Apply { op, args, .. } if args.len() == 2 => (*op, args),
+            _ => return Ok(None),
+        };
+        if *op == OP_JSON_CONTAINS {
+            if !is_doc(&op_args[0]) {
+                return Ok(None);
+            }
+            return Ok(match const_value(&op_args[1]).map(JsonValue::from) {
+                Some(pattern @ (JsonValue::Object(_) | JsonValue::Array(_))) => {
+                    Some(DataValue::Json(JsonData(pattern)))
+                }
+                _ => None,
+            });
+        }
+        if *op != OP_EQ {
+            return Ok(None);
+        }
+        for (getter, val) in [(&op_args[0], &op_args[1]), (&op_args[1], &op_args[0])] {
+            let get_args = match getter {
+                Expr::Apply {
+                    op, args: get_args, ..
+                } if **op == OP_GET && get_args.len() == 2 && is_doc(&get_args[0]) => get_args,
+                _ => continue,
+            };
+            let val = match const_value(val) {
+                Some(
+                    val @ (DataValue::Null
+                    | DataValue::Bool(_)
+                    | DataValue::Num(_)
+                    | DataValue::Str(_)),
+                ) => val,
+                _ => continue,
+            };
+            let path = match const_value(&get_args[1]) {
+                Some(DataValue::Str(key)) => vec![key],
+                Some(DataValue::List(keys)) if !keys.is_empty() => match keys
+                    .iter()
+                    .map(|k| k.get_str().map(SmartString::from))
+                    .collect::<Option<Vec<_>>>()
+                {
+                    Some(path) => path,
+                    None => continue,
+                },
+                _ => continue,
+            };
+            let mut pattern = JsonValue::from(val);
+            for key in path.into_iter().rev() {
+                let mut obj = serde_json::Map::new();
+                obj.insert(key.to_string(), pattern);
+                pattern = JsonValue::Object(obj);
+            }
+            return Ok(Some(DataValue::Json(JsonData(pattern))));
+        }
+        Ok(None)
+    }
+}
+
+/// The value of `expr` if it does not depend on any variable, such as the list `['a', 'b']`,
+/// which is parsed as a call to `list`.
+fn const_value(expr: &Expr) -> Option<DataValue> {
+    match expr {
+        Expr::Const { val, .. } => Some(val.clone()),
+        Expr::Apply { .. } if expr.bindings().ok()?.is_empty() => expr.clone().eval_to_const().ok(),
+        _ => None,
+    }
+}
+
+/// Whether `expr` computes the same as the extractor, with the column names of the extractor
+/// standing for the variables in `columns`.
+fn computes_extractor(expr: &Expr, extractor: &Expr, columns: &BTreeMap<&str, &Symbol>) -> bool {
+    match (expr, extractor) {
+        (Expr::Binding { var, .. }, Expr::Binding { var: col, .. }) => {
+            columns.get(&col.name as &str) == Some(&var)
+        }
+        (Expr::Const { val, .. }, Expr::Const { val: e_val, .. }) => val == e_val,
+        (
+            Expr::Apply { op, args, .. },
+            Expr::Apply {
+                op: e_op,
+                args: e_args,
+                ..
+            },
+        ) => {
+            op == e_op
+                && args.len() == e_args.len()
+                && args
+                    .iter()
+                    .zip(e_args.iter())
+                    .all(|(a, e)| computes_extractor(a, e, columns))
+        }
+        (
+            Expr::Cond { clauses, .. },
+            Expr::Cond {
+                clauses: e_clauses, ..
+            },
+        ) => {
+            clauses.len() == e_clauses.len()
+                && clauses.iter().zip(e_clauses).all(|((c, v), (e_c, e_v))| {
+                    computes_extractor(c, e_c, columns) && computes_extractor(v, e_v, columns)
+                })
+        }
+        _ => false,
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct JsonIndexSearch {
+    pub(crate) base_handle: RelationHandle,
+    pub(crate) idx_handle: RelationHandle,
+    pub(crate) manifest: JsonIndexManifest,
+    pub(crate) bindings: Vec<Symbol>,
+    pub(crate) query: Symbol,
+    pub(crate) k: Option<usize>,
+    pub(crate) filter: Option<Expr>,
+    pub(crate) span: SourceSpan,
+}
+
+impl JsonIndexSearch {
+    pub(crate) fn all_bindings(&self) -> impl Iterator<Item = &Symbol> {
+        self.bindings.iter()
+    }
+}
+
+impl<'a> SessionTx<'a> {
+    fn json_index_keys(
+        tuple: &[DataValue],
+        extractor: &[Bytecode],
+        stack: &mut Vec<DataValue>,
+        rel_handle: &RelationHandle,
+    ) -> Result<Vec<Tuple>> {
+        let doc = match eval_bytecode(extractor, tuple, stack)? {
+            DataValue::Null => return Ok(vec![]),
+            v => JsonValue::from(v),
+        };
+        let key_part = &tuple[..rel_handle.metadata.keys.len()];
+        Ok(json_index_entries(&doc)
+            .into_iter()
+            .map(|(path, value)| {
+                let mut key = Vec::with_capacity(2 + key_part.len());
+                key.push(DataValue::Str(path));
+                key.push(value);
+                key.extend_from_slice(key_part);
+                key
+            })
+            .collect())
+    }
+    pub(crate) fn put_json_index_item(
+        &mut self,
+        tuple: &[DataValue],
+        extractor: &[Bytecode],
+        stack: &mut Vec<DataValue>,
+        rel_handle: &RelationHandle,
+        idx_handle: &RelationHandle,
+    ) -> Result<()> {
+        for key in Self::json_index_keys(tuple, extractor, stack, rel_handle)? {
+            let key_bytes = idx_handle.encode_key_for_store(&key, Default::default())?;
+            self.store_tx.put(&key_bytes, &[])?;
+        }
+        Ok(())
+    }
+    pub(crate) fn del_json_index_item(
+        &mut self,
+        tuple: &[DataValue],
+        extractor: &[Bytecode],
+        stack: &mut Vec<DataValue>,
+        rel_handle: &RelationHandle,
+        idx_handle: &RelationHandle,
+    ) -> Result<()> {
+        for key in Self::json_index_keys(tuple, extractor, stack, rel_handle)? {
+            let key_bytes = idx_handle.encode_key_for_store(&key, Default::default())?;
+            self.store_tx.del(&key_bytes)?;
+        }
+        Ok(())
+    }
+    pub(crate) fn json_index_search(
+        &self,
+        q: &DataValue,
+        config: &JsonIndexSearch,
+        extractor: &[Bytecode],
+        stack: &mut Vec<DataValue>,
+        filter_code: &Option<(Vec<Bytecode>, SourceSpan)>,
+    ) -> Result<Vec<Tuple>> {
+        let pattern = JsonValue::from(q.clone());
+        let pairs = json_index_entries(&pattern);
+
+        let candidates: Vec<Tuple> = if pairs.is_empty() {
+            // nothing to look up, e.g. `{}` or `{"a": []}`: every document is a candidate
+            config
+                .base_handle
+                .scan_all(self)
+                .map_ok(|t| t[..config.base_handle.metadata.keys.len()].to_vec())
+                .collect::<Result<_>>()?
+        } else {
+            let mut found: Option<BTreeSet<Tuple>> = None;
+            for (path, value) in pairs {
+                let prefix = vec![DataValue::Str(path), value];
+                let mut keys = BTreeSet::new();
+                for ks in config.idx_handle.scan_prefix(self, &prefix) {
+                    let ks = ks?;
+                    let key_part = ks[2..].to_vec();
+                    if let Some(prev) = &found {
+                        if !prev.contains(&key_part) {
+                            continue;
+                        }
+                    }
+                    keys.insert(key_part);
+                }
+                let done = keys.is_empty();
+                found = Some(keys);
+                if done {
+                    break;
+                }
+            }
+            found.unwrap_or_default().into_iter().collect()
+        };
+
+        let mut ret = vec![];
+        for key in candidates {
+            let orig_tuple = config
+                .base_handle
+                .get(self, &key)?
+                .ok_or_else(|| miette!("Tuple not found in base JSON relation"))?;
+            let doc = match eval_bytecode(extractor, &orig_tuple, stack)? {
+                DataValue::Null => continue,
+                v => JsonValue::from(v),
+            };
+            if !json_contains(&doc, &pattern) {
+                continue;
+            }
+            if let Some((filter_code, span)) = filter_code {
+                if !eval_bytecode_pred(filter_code, &orig_tuple, stack, *span)? {
+                    continue;
+                }
+            }
+            ret.push(orig_tuple);
+            if let Some(k) = config.k {
+                if ret.len() >= k {
+                    break;
+                }
+            }
+        }
+        Ok(ret)
+    }
+}
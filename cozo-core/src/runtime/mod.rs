@@ -8,11 +8,14 @@
 
 pub(crate) mod callback;
 pub(crate) mod db;
+pub(crate) mod graph_projection;
 pub(crate) mod imperative;
+pub(crate) mod json_index;
 pub(crate) mod relation;
 pub(crate) mod temp_store;
 pub(crate) mod transact;
 pub(crate) mod hnsw;
 pub(crate) mod minhash_lsh;
+pub(crate) mod spatial;
 #[cfg(test)]
 mod tests;
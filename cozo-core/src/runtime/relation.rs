@@ -23,14 +23,18 @@ use crate::data::memcmp::MemCmpEncoder;
 use crate::data::relation::{ColType, ColumnDef, NullableColType, StoredRelationMetadata};
 use crate::data::symb::Symbol;
 use crate::data::tuple::{decode_tuple_from_key, Tuple, TupleT, ENCODED_KEY_MIN_LEN};
-use crate::data::value::{DataValue, ValidityTs};
+use crate::data::value::{DataValue, ValidityTs, LARGEST_UTF_CHAR};
 use crate::fts::FtsIndexManifest;
 use crate::parse::expr::build_expr;
-use crate::parse::sys::{FtsIndexConfig, HnswIndexConfig, MinHashLshConfig};
+use crate::parse::sys::{
+    FtsIndexConfig, HnswIndexConfig, JsonIndexConfig, MinHashLshConfig, SpatialIndexConfig,
+};
 use crate::parse::{CozoScriptParser, Rule, SourceSpan};
 use crate::query::compile::IndexPositionUse;
 use crate::runtime::hnsw::HnswIndexManifest;
+use crate::runtime::json_index::JsonIndexManifest;
 use crate::runtime::minhash_lsh::{HashPermutations, LshParams, MinHashLshIndexManifest, Weights};
+use crate::runtime::spatial::SpatialIndexManifest;
 use crate::runtime::transact::SessionTx;
 use crate::utils::TempCollector;
 use crate::{NamedRows, StoreTx};
@@ -90,6 +94,12 @@ pub(crate) struct RelationHandle {
         (RelationHandle, RelationHandle, MinHashLshIndexManifest),
     >,
     pub(crate) description: SmartString<LazyCompact>,
+    #[serde(default)]
+    pub(crate) spatial_indices:
+        BTreeMap<SmartString<LazyCompact>, (RelationHandle, RelationHandle, SpatialIndexManifest)>,
+    #[serde(default)]
+    pub(crate) json_indices:
+        BTreeMap<SmartString<LazyCompact>, (RelationHandle, JsonIndexManifest)>,
 }
 
 impl RelationHandle {
@@ -98,12 +108,16 @@ impl RelationHandle {
             || self.hnsw_indices.contains_key(index_name)
             || self.fts_indices.contains_key(index_name)
             || self.lsh_indices.contains_key(index_name)
+            || self.spatial_indices.contains_key(index_name)
+            || self.json_indices.contains_key(index_name)
     }
     pub(crate) fn has_no_index(&self) -> bool {
         self.indices.is_empty()
             && self.hnsw_indices.is_empty()
             && self.fts_indices.is_empty()
             && self.lsh_indices.is_empty()
+            && self.spatial_indices.is_empty()
+            && self.json_indices.is_empty()
     }
 }
 
@@ -620,6 +634,8 @@ impl<'a> SessionTx<'a> {
             fts_indices: Default::default(),
             lsh_indices: Default::default(),
             description: Default::default(),
+            spatial_indices: Default::default(),
+            json_indices: Default::default(),
         };
 
         let name_key = vec![DataValue::Str(meta.name.clone())].encode_as_key(RelationId::SYSTEM);
@@ -662,6 +678,20 @@ impl<'a> SessionTx<'a> {
         let metadata = RelationHandle::decode(&found)?;
         Ok(metadata)
     }
+    pub(crate) fn all_relations(&self) -> Result<Vec<RelationHandle>> {
+        let lower = vec![DataValue::from("")].encode_as_key(RelationId::SYSTEM);
+        let upper =
+            vec![DataValue::from(String::from(LARGEST_UTF_CHAR))].encode_as_key(RelationId::SYSTEM);
+        let mut ret = vec![];
+        for kv_res in self.store_tx.range_scan(&lower, &upper) {
+            let (k_slice, v_slice) = kv_res?;
+            if upper <= k_slice {
+                break;
+            }
+            ret.push(RelationHandle::decode(&v_slice)?);
+        }
+        Ok(ret)
+    }
     pub(crate) fn describe_relation(&mut self, name: &str, description: &str) -> Result<()> {
         let mut meta = self.get_relation(name, true)?;
 
@@ -716,6 +746,7 @@ impl<'a> SessionTx<'a> {
             self.temp_store_tx.del(&encoded)?;
         } else {
             self.store_tx.del(&encoded)?;
+            self.clear_write_stamp(store.id)?;
         }
         let lower_bound = Tuple::default().encode_as_key(store.id);
         let upper_bound = Tuple::default().encode_as_key(store.id.next());
@@ -866,6 +897,204 @@ impl<'a> SessionTx<'a> {
         Ok(())
     }
 
+    pub(crate) fn create_spatial_index(&mut self, config: &SpatialIndexConfig) -> Result<()> {
+        // Get relation handle
+        let mut rel_handle = self.get_relation(&config.base_relation, true)?;
+
+        // Check if index already exists
+        if rel_handle.has_index(&config.index_name) {
+            bail!(IndexAlreadyExists(
+                config.index_name.to_string(),
+                config.index_name.to_string()
+            ));
+        }
+
+        let idx_keys = vec![ColumnDef {
+            name: SmartString::from("node"),
+            typing: NullableColType {
+                coltype: ColType::Int,
+                nullable: false,
+            },
+            default_gen: None,
+        }];
+        let idx_vals = vec![
+            ColumnDef {
+                name: SmartString::from("is_leaf"),
+                typing: NullableColType {
+                    coltype: ColType::Bool,
+                    nullable: false,
+                },
+                default_gen: None,
+            },
+            ColumnDef {
+                name: SmartString::from("entries"),
+                typing: NullableColType {
+                    coltype: ColType::List {
+                        eltype: Box::new(NullableColType {
+                            coltype: ColType::Any,
+                            nullable: false,
+                        }),
+                        len: None,
+                    },
+                    nullable: false,
+                },
+                default_gen: None,
+            },
+        ];
+
+        let inv_idx_keys = rel_handle.metadata.keys.clone();
+        let inv_idx_vals = vec![ColumnDef {
+            name: SmartString::from("bbox"),
+            typing: NullableColType {
+                coltype: ColType::List {
+                    eltype: Box::new(NullableColType {
+                        coltype: ColType::Float,
+                        nullable: false,
+                    }),
+                    len: Some(4),
+                },
+                nullable: false,
+            },
+            default_gen: None,
+        }];
+
+        let idx_handle = self.write_idx_relation(
+            &config.base_relation,
+            &config.index_name,
+            idx_keys,
+            idx_vals,
+        )?;
+
+        let inv_idx_handle = self.write_idx_relation(
+            &config.base_relation,
+            &format!("{}:inv", config.index_name),
+            inv_idx_keys,
+            inv_idx_vals,
+        )?;
+
+        // add index to relation
+        let manifest = SpatialIndexManifest {
+            base_relation: config.base_relation.clone(),
+            index_name: config.index_name.clone(),
+            extractor: config.extractor.clone(),
+            max_entries: config.max_entries,
+        };
+
+        // populate index
+        self.spatial_init(&idx_handle)?;
+        let extractor = manifest.compile_extractor(&rel_handle)?;
+        let mut stack = vec![];
+
+        let mut existing = TempCollector::default();
+        for tuple in rel_handle.scan_all(self) {
+            existing.push(tuple?);
+        }
+        for tuple in existing.into_iter() {
+            self.put_spatial_index_item(
+                &tuple,
+                &extractor,
+                &mut stack,
+                &rel_handle,
+                &idx_handle,
+                &inv_idx_handle,
+                &manifest,
+            )?;
+        }
+
+        rel_handle.spatial_indices.insert(
+            manifest.index_name.clone(),
+            (idx_handle, inv_idx_handle, manifest),
+        );
+
+        // update relation metadata
+        let new_encoded =
+            vec![DataValue::from(&rel_handle.name as &str)].encode_as_key(RelationId::SYSTEM);
+        let mut meta_val = vec![];
+        rel_handle
+            .serialize(&mut Serializer::new(&mut meta_val))
+            .unwrap();
+        self.store_tx.put(&new_encoded, &meta_val)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn create_json_index(&mut self, config: &JsonIndexConfig) -> Result<()> {
+        // Get relation handle
+        let mut rel_handle = self.get_relation(&config.base_relation, true)?;
+
+        // Check if index already exists
+        if rel_handle.has_index(&config.index_name) {
+            bail!(IndexAlreadyExists(
+                config.index_name.to_string(),
+                config.index_name.to_string()
+            ));
+        }
+
+        let mut idx_keys = vec![
+            ColumnDef {
+                name: SmartString::from("path"),
+                typing: NullableColType {
+                    coltype: ColType::String,
+                    nullable: false,
+                },
+                default_gen: None,
+            },
+            ColumnDef {
+                name: SmartString::from("value"),
+                typing: NullableColType {
+                    coltype: ColType::Any,
+                    nullable: true,
+                },
+                default_gen: None,
+            },
+        ];
+
+        for k in rel_handle.metadata.keys.iter() {
+            idx_keys.push(ColumnDef {
+                name: format!("src_{}", k.name).into(),
+                typing: k.typing.clone(),
+                default_gen: None,
+            });
+        }
+
+        let idx_handle =
+            self.write_idx_relation(&config.base_relation, &config.index_name, idx_keys, vec![])?;
+
+        // add index to relation
+        let manifest = JsonIndexManifest {
+            base_relation: config.base_relation.clone(),
+            index_name: config.index_name.clone(),
+            extractor: config.extractor.clone(),
+        };
+
+        // populate index
+        let extractor = manifest.compile_extractor(&rel_handle)?;
+        let mut stack = vec![];
+
+        let mut existing = TempCollector::default();
+        for tuple in rel_handle.scan_all(self) {
+            existing.push(tuple?);
+        }
+        for tuple in existing.into_iter() {
+            self.put_json_index_item(&tuple, &extractor, &mut stack, &rel_handle, &idx_handle)?;
+        }
+
+        rel_handle
+            .json_indices
+            .insert(manifest.index_name.clone(), (idx_handle, manifest));
+
+        // update relation metadata
+        let new_encoded =
+            vec![DataValue::from(&rel_handle.name as &str)].encode_as_key(RelationId::SYSTEM);
+        let mut meta_val = vec![];
+        rel_handle
+            .serialize(&mut Serializer::new(&mut meta_val))
+            .unwrap();
+        self.store_tx.put(&new_encoded, &meta_val)?;
+
+        Ok(())
+    }
+
     pub(crate) fn create_fts_index(&mut self, config: &FtsIndexConfig) -> Result<()> {
         // Get relation handle
         let mut rel_handle = self.get_relation(&config.base_relation, true)?;
@@ -1375,6 +1604,7 @@ impl<'a> SessionTx<'a> {
         let mut rel = self.get_relation(rel_name, true)?;
         let is_lsh = rel.lsh_indices.contains_key(&idx_name.name);
         let is_fts = rel.fts_indices.contains_key(&idx_name.name);
+        let is_spatial = rel.spatial_indices.contains_key(&idx_name.name);
         if is_lsh || is_fts {
             self.tokenizers.named_cache.write().unwrap().clear();
             self.tokenizers.hashed_cache.write().unwrap().clear();
@@ -1382,6 +1612,8 @@ impl<'a> SessionTx<'a> {
         if rel.indices.remove(&idx_name.name).is_none()
             && rel.hnsw_indices.remove(&idx_name.name).is_none()
             && rel.lsh_indices.remove(&idx_name.name).is_none()
+            && rel.spatial_indices.remove(&idx_name.name).is_none()
+            && rel.json_indices.remove(&idx_name.name).is_none()
         {
             #[derive(Debug, Error, Diagnostic)]
             #[error("index {0} for relation {1} not found")]
@@ -1393,7 +1625,7 @@ impl<'a> SessionTx<'a> {
 
         let mut to_clean =
             self.destroy_relation(&format!("{}:{}", rel_name.name, idx_name.name))?;
-        if is_lsh {
+        if is_lsh || is_spatial {
             to_clean.extend(
                 self.destroy_relation(&format!("{}:{}:inv", rel_name.name, idx_name.name))?,
             );
@@ -0,0 +1,496 @@
+/*
+ * Copyright 2023, The Cozo Project Authors.
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0.
+ * If a copy of the MPL was not distributed with this file,
+ * You can obtain one at https://mozilla.org/MPL/2.0/.
+ */
+
+//! A persistent R-tree over bounding boxes of geometries.
+//!
+//! Every node of the tree is a row `[node_id] => [is_leaf, entries]` of the index relation,
+//! where each entry is `[min_x, min_y, max_x, max_y, payload]`. The payload is the ID of a
+//! child node for inner nodes and the list of keys of the base relation for leaves. The row
+//! with ID 0 holds the next free node ID, and the root always has ID 1. An inverse relation
+//! maps the keys of the base relation to the bounding box stored in the tree, so that entries
+//! can be located again for removal.
+
+use itertools::Itertools;
+use miette::{bail, miette, IntoDiagnostic, Result};
+use pest::Parser;
+use smartstring::{LazyCompact, SmartString};
+
+use crate::data::expr::{eval_bytecode, eval_bytecode_pred, Bytecode};
+use crate::data::geo::{BBox, Geometry};
+use crate::data::tuple::Tuple;
+use crate::parse::expr::build_expr;
+use crate::parse::{CozoScriptParser, Rule};
+use crate::runtime::relation::RelationHandle;
+use crate::runtime::transact::SessionTx;
+use crate::{DataValue, Expr, SourceSpan, Symbol};
+
+const META_NODE: i64 = 0;
+const ROOT_NODE: i64 = 1;
+
+#[derive(Debug, Clone, PartialEq, serde_derive::Serialize, serde_derive::Deserialize)]
+pub(crate) struct SpatialIndexManifest {
+    pub(crate) base_relation: SmartString<LazyCompact>,
+    pub(crate) index_name: SmartString<LazyCompact>,
+    pub(crate) extractor: String,
+    pub(crate) max_entries: usize,
+}
+
+impl SpatialIndexManifest {
+    pub(crate) fn compile_extractor(&self, base_handle: &RelationHandle) -> Result<Vec<Bytecode>> {
+        let parsed = CozoScriptParser::parse(Rule::expr, &self.extractor)
+            .into_diagnostic()?
+            .next()
+            .unwrap();
+        let mut code_expr = build_expr(parsed, &Default::default())?;
+        let binding_map = base_handle.raw_binding_map();
+        code_expr.fill_binding_indices(&binding_map)?;
+        code_expr.compile()
+    }
+    fn min_entries(&self) -> usize {
+        (self.max_entries * 2 / 5).max(1)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SpatialSearch {
+    pub(crate) base_handle: RelationHandle,
+    pub(crate) idx_handle: RelationHandle,
+    pub(crate) manifest: SpatialIndexManifest,
+    pub(crate) bindings: Vec<Symbol>,
+    pub(crate) query: Symbol,
+    pub(crate) radius: f64,
+    pub(crate) k: Option<usize>,
+    pub(crate) bind_distance: Option<Symbol>,
+    pub(crate) filter: Option<Expr>,
+    pub(crate) span: SourceSpan,
+}
+
+impl SpatialSearch {
+    pub(crate) fn all_bindings(&self) -> impl Iterator<Item = &Symbol> {
+        self.bindings.iter().chain(self.bind_distance.iter())
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RTreeNode {
+    is_leaf: bool,
+    entries: Vec<(BBox, DataValue)>,
+}
+
+impl RTreeNode {
+    fn bbox(&self) -> BBox {
+        self.entries
+            .iter()
+            .fold(BBox::empty(), |acc, (b, _)| acc.union(b))
+    }
+    fn child_id(&self, i: usize) -> i64 {
+        self.entries[i].1.get_int().unwrap()
+    }
+}
+
+fn choose_subtree(entries: &[(BBox, DataValue)], bbox: &BBox) -> usize {
+    let mut best = 0;
+    let mut best_cost = (f64::INFINITY, f64::INFINITY);
+    for (i, (b, _)) in entries.iter().enumerate() {
+        let cost = (b.enlargement(bbox), b.area());
+        if cost < best_cost {
+            best = i;
+            best_cost = cost;
+        }
+    }
+    best
+}
+
+/// Guttman's quadratic split.
+fn quadratic_split(
+    mut entries: Vec<(BBox, DataValue)>,
+    min_entries: usize,
+) -> (Vec<(BBox, DataValue)>, Vec<(BBox, DataValue)>) {
+    let mut seeds = (0, 1);
+    let mut worst = f64::NEG_INFINITY;
+    for i in 0..entries.len() {
+        for j in i + 1..entries.len() {
+            let (a, b) = (&entries[i].0, &entries[j].0);
+            let waste = a.union(b).area() - a.area() - b.area();
+            if waste > worst {
+                worst = waste;
+                seeds = (i, j);
+            }
+        }
+    }
+    let second = entries.swap_remove(seeds.1);
+    let first = entries.swap_remove(seeds.0);
+    let mut left_box = first.0;
+    let mut right_box = second.0;
+    let mut left = vec![first];
+    let mut right = vec![second];
+    while !entries.is_empty() {
+        if left.len() + entries.len() <= min_entries {
+            left.append(&mut entries);
+            break;
+        }
+        if right.len() + entries.len() <= min_entries {
+            right.append(&mut entries);
+            break;
+        }
+        let (pos, _) = entries
+            .iter()
+            .map(|(b, _)| (left_box.enlargement(b) - right_box.enlargement(b)).abs())
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+        let entry = entries.swap_remove(pos);
+        let to_left = (left_box.enlargement(&entry.0), left_box.area(), left.len())
+            <= (
+                right_box.enlargement(&entry.0),
+                right_box.area(),
+                right.len(),
+            );
+        if to_left {
+            left_box = left_box.union(&entry.0);
+            left.push(entry);
+        } else {
+            right_box = right_box.union(&entry.0);
+            right.push(entry);
+        }
+    }
+    (left, right)
+}
+
+impl<'a> SessionTx<'a> {
+    fn spatial_load_node(&self, idx_handle: &RelationHandle, id: i64) -> Result<RTreeNode> {
+        let found = idx_handle
+            .get_val_only(self, &[DataValue::from(id)])?
+            .ok_or_else(|| miette!("Node {} missing from spatial index {}", id, idx_handle.name))?;
+        let is_leaf = found[0].get_bool().unwrap();
+        let entries = found[1]
+            .get_slice()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                let entry = entry.get_slice().unwrap();
+                let bbox = BBox::from_value(&DataValue::List(entry[..4].to_vec())).unwrap();
+                (bbox, entry[4].clone())
+            })
+            .collect_vec();
+        Ok(RTreeNode { is_leaf, entries })
+    }
+    fn spatial_save_node(
+        &mut self,
+        idx_handle: &RelationHandle,
+        id: i64,
+        node: &RTreeNode,
+    ) -> Result<()> {
+        let entries = node
+            .entries
+            .iter()
+            .map(|(b, payload)| {
+                DataValue::List(vec![
+                    DataValue::from(b.min_x),
+                    DataValue::from(b.min_y),
+                    DataValue::from(b.max_x),
+                    DataValue::from(b.max_y),
+                    payload.clone(),
+                ])
+            })
+            .collect_vec();
+        let key = idx_handle.encode_key_for_store(&[DataValue::from(id)], Default::default())?;
+        let val = idx_handle.encode_val_only_for_store(
+            &[DataValue::from(node.is_leaf), DataValue::List(entries)],
+            Default::default(),
+        )?;
+        self.store_tx.put(&key, &val)
+    }
+    fn spatial_del_node(&mut self, idx_handle: &RelationHandle, id: i64) -> Result<()> {
+        let key = idx_handle.encode_key_for_store(&[DataValue::from(id)], Default::default())?;
+        self.store_tx.del(&key)
+    }
+    fn spatial_alloc_node(&mut self, idx_handle: &RelationHandle) -> Result<i64> {
+        let meta = self.spatial_load_node(idx_handle, META_NODE)?;
+        let next_id = meta.entries[0].1.get_int().unwrap();
+        self.spatial_save_meta(idx_handle, next_id + 1)?;
+        Ok(next_id)
+    }
+    fn spatial_save_meta(&mut self, idx_handle: &RelationHandle, next_id: i64) -> Result<()> {
+        let meta = RTreeNode {
+            is_leaf: false,
+            entries: vec![(BBox::empty(), DataValue::from(next_id))],
+        };
+        self.spatial_save_node(idx_handle, META_NODE, &meta)
+    }
+    pub(crate) fn spatial_init(&mut self, idx_handle: &RelationHandle) -> Result<()> {
+        self.spatial_save_meta(idx_handle, ROOT_NODE + 1)?;
+        let root = RTreeNode {
+            is_leaf: true,
+            entries: vec![],
+        };
+        self.spatial_save_node(idx_handle, ROOT_NODE, &root)
+    }
+    fn spatial_insert(
+        &mut self,
+        idx_handle: &RelationHandle,
+        manifest: &SpatialIndexManifest,
+        bbox: BBox,
+        payload: DataValue,
+    ) -> Result<()> {
+        let mut path = vec![];
+        let mut cur_id = ROOT_NODE;
+        let mut cur = self.spatial_load_node(idx_handle, cur_id)?;
+        while !cur.is_leaf {
+            let chosen = choose_subtree(&cur.entries, &bbox);
+            let child_id = cur.child_id(chosen);
+            path.push((cur_id, cur, chosen));
+            cur_id = child_id;
+            cur = self.spatial_load_node(idx_handle, cur_id)?;
+        }
+        cur.entries.push((bbox, payload));
+
+        loop {
+            let mut sibling = None;
+            if cur.entries.len() > manifest.max_entries {
+                let is_leaf = cur.is_leaf;
+                let (left, right) = quadratic_split(cur.entries, manifest.min_entries());
+                let left = RTreeNode {
+                    is_leaf,
+                    entries: left,
+                };
+                let right = RTreeNode {
+                    is_leaf,
+                    entries: right,
+                };
+                if cur_id == ROOT_NODE {
+                    let left_id = self.spatial_alloc_node(idx_handle)?;
+                    let right_id = self.spatial_alloc_node(idx_handle)?;
+                    self.spatial_save_node(idx_handle, left_id, &left)?;
+                    self.spatial_save_node(idx_handle, right_id, &right)?;
+                    cur = RTreeNode {
+                        is_leaf: false,
+                        entries: vec![
+                            (left.bbox(), DataValue::from(left_id)),
+                            (right.bbox(), DataValue::from(right_id)),
+                        ],
+                    };
+                } else {
+                    let right_id = self.spatial_alloc_node(idx_handle)?;
+                    self.spatial_save_node(idx_handle, right_id, &right)?;
+                    sibling = Some((right.bbox(), right_id));
+                    cur = left;
+                }
+            }
+            self.spatial_save_node(idx_handle, cur_id, &cur)?;
+            let cur_bbox = cur.bbox();
+            match path.pop() {
+                None => break,
+                Some((parent_id, mut parent, pos)) => {
+                    if sibling.is_none() && parent.entries[pos].0 == cur_bbox {
+                        break;
+                    }
+                    parent.entries[pos].0 = cur_bbox;
+                    if let Some((sibling_bbox, sibling_id)) = sibling {
+                        parent
+                            .entries
+                            .push((sibling_bbox, DataValue::from(sibling_id)));
+                    }
+                    cur_id = parent_id;
+                    cur = parent;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn spatial_find_leaf(
+        &self,
+        idx_handle: &RelationHandle,
+        id: i64,
+        bbox: &BBox,
+        payload: &DataValue,
+        path: &mut Vec<(i64, RTreeNode, usize)>,
+    ) -> Result<bool> {
+        let node = self.spatial_load_node(idx_handle, id)?;
+        if node.is_leaf {
+            return Ok(
+                match node
+                    .entries
+                    .iter()
+                    .position(|(b, p)| b.contains(bbox) && p == payload)
+                {
+                    Some(i) => {
+                        path.push((id, node, i));
+                        true
+                    }
+                    None => false,
+                },
+            );
+        }
+        for i in 0..node.entries.len() {
+            if !node.entries[i].0.contains(bbox) {
+                continue;
+            }
+            let child_id = node.child_id(i);
+            path.push((id, node.clone(), i));
+            if self.spatial_find_leaf(idx_handle, child_id, bbox, payload, path)? {
+                return Ok(true);
+            }
+            path.pop();
+        }
+        Ok(false)
+    }
+    /// Underfull nodes are kept as they are, only empty nodes are removed.
+    fn spatial_remove(
+        &mut self,
+        idx_handle: &RelationHandle,
+        bbox: BBox,
+        payload: &DataValue,
+    ) -> Result<()> {
+        let mut path = vec![];
+        if !self.spatial_find_leaf(idx_handle, ROOT_NODE, &bbox, payload, &mut path)? {
+            return Ok(());
+        }
+        let (mut cur_id, mut cur, pos) = path.pop().unwrap();
+        cur.entries.remove(pos);
+        loop {
+            match path.pop() {
+                None => {
+                    while !cur.is_leaf && cur.entries.len() == 1 {
+                        let child_id = cur.child_id(0);
+                        cur = self.spatial_load_node(idx_handle, child_id)?;
+                        self.spatial_del_node(idx_handle, child_id)?;
+                    }
+                    if cur.entries.is_empty() {
+                        cur.is_leaf = true;
+                    }
+                    self.spatial_save_node(idx_handle, ROOT_NODE, &cur)?;
+                    break;
+                }
+                Some((parent_id, mut parent, pos)) => {
+                    if cur.entries.is_empty() {
+                        self.spatial_del_node(idx_handle, cur_id)?;
+                        parent.entries.remove(pos);
+                    } else {
+                        self.spatial_save_node(idx_handle, cur_id, &cur)?;
+                        parent.entries[pos].0 = cur.bbox();
+                    }
+                    cur_id = parent_id;
+                    cur = parent;
+                }
+            }
+        }
+        Ok(())
+    }
+    pub(crate) fn del_spatial_index_item(
+        &mut self,
+        tuple: &[DataValue],
+        idx_handle: &RelationHandle,
+        inv_idx_handle: &RelationHandle,
+    ) -> Result<()> {
+        let key_part = &tuple[..inv_idx_handle.metadata.keys.len()];
+        if let Some(found) = inv_idx_handle.get_val_only(self, key_part)? {
+            let bbox = BBox::from_value(&found[0]).unwrap();
+            self.spatial_remove(idx_handle, bbox, &DataValue::List(key_part.to_vec()))?;
+            let inv_key = inv_idx_handle.encode_key_for_store(key_part, Default::default())?;
+            self.store_tx.del(&inv_key)?;
+        }
+        Ok(())
+    }
+    pub(crate) fn put_spatial_index_item(
+        &mut self,
+        tuple: &[DataValue],
+        extractor: &[Bytecode],
+        stack: &mut Vec<DataValue>,
+        rel_handle: &RelationHandle,
+        idx_handle: &RelationHandle,
+        inv_idx_handle: &RelationHandle,
+        manifest: &SpatialIndexManifest,
+    ) -> Result<()> {
+        self.del_spatial_index_item(tuple, idx_handle, inv_idx_handle)?;
+        let to_index = eval_bytecode(extractor, tuple, stack)?;
+        if to_index == DataValue::Null {
+            return Ok(());
+        }
+        let bbox = Geometry::from_value(&to_index)?.bbox();
+        if bbox.is_empty() {
+            return Ok(());
+        }
+        let key_part = &tuple[..rel_handle.metadata.keys.len()];
+        self.spatial_insert(
+            idx_handle,
+            manifest,
+            bbox,
+            DataValue::List(key_part.to_vec()),
+        )?;
+        let inv_key = inv_idx_handle.encode_key_for_store(key_part, Default::default())?;
+        let inv_val =
+            inv_idx_handle.encode_val_only_for_store(&[bbox.to_value()], Default::default())?;
+        self.store_tx.put(&inv_key, &inv_val)?;
+        Ok(())
+    }
+    pub(crate) fn spatial_search(
+        &self,
+        q: &DataValue,
+        config: &SpatialSearch,
+        extractor: &[Bytecode],
+        stack: &mut Vec<DataValue>,
+        filter_code: &Option<(Vec<Bytecode>, SourceSpan)>,
+    ) -> Result<Vec<Tuple>> {
+        if *q == DataValue::Null {
+            return Ok(vec![]);
+        }
+        let query = Geometry::from_value(q)?;
+        let window = query.bbox().expand(config.radius);
+        if window.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut candidates = vec![];
+        let mut to_visit = vec![ROOT_NODE];
+        while let Some(id) = to_visit.pop() {
+            let node = self.spatial_load_node(&config.idx_handle, id)?;
+            for (b, payload) in node.entries {
+                if !b.intersects(&window) {
+                    continue;
+                }
+                if node.is_leaf {
+                    candidates.push(payload);
+                } else {
+                    to_visit.push(payload.get_int().unwrap());
+                }
+            }
+        }
+
+        let mut found = vec![];
+        for keys in candidates {
+            let keys = match keys {
+                DataValue::List(l) => l,
+                _ => bail!("Corrupt entry in spatial index {}", config.idx_handle.name),
+            };
+            let mut orig_tuple = config
+                .base_handle
+                .get(self, &keys)?
+                .ok_or_else(|| miette!("Tuple not found in base spatial relation"))?;
+            let geometry = eval_bytecode(extractor, &orig_tuple, stack)?;
+            let distance = query.distance(&Geometry::from_value(&geometry)?);
+            if distance > config.radius {
+                continue;
+            }
+            if config.bind_distance.is_some() {
+                orig_tuple.push(DataValue::from(distance));
+            }
+            if let Some((filter_code, span)) = filter_code {
+                if !eval_bytecode_pred(filter_code, &orig_tuple, stack, *span)? {
+                    continue;
+                }
+            }
+            found.push((distance, orig_tuple));
+        }
+        if let Some(k) = config.k {
+            found.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+            found.truncate(k);
+        }
+        Ok(found.into_iter().map(|(_, tuple)| tuple).collect_vec())
+    }
+}
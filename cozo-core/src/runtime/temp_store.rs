@@ -10,6 +10,7 @@ use std::cmp::Ordering;
 use std::collections::btree_map::Entry;
 use std::collections::BTreeMap;
 use std::collections::Bound::Included;
+use std::iter;
 use std::mem;
 use std::ops::Bound::Excluded;
 
@@ -62,6 +63,14 @@ impl RegularTempStore {
     pub(crate) fn put_with_skip(&mut self, tuple: Tuple) {
         self.inner.insert(tuple, true);
     }
+    /// Moves all tuples of `other` into the store
+    pub(crate) fn extend(&mut self, other: Self) {
+        if self.inner.is_empty() {
+            self.inner = other.inner;
+        } else {
+            self.inner.extend(other.inner);
+        }
+    }
     // returns true if prev is guaranteed to be the same as self after this function call,
     // false if we are not sure.
     pub(crate) fn merge_in(&mut self, prev: &mut Self, mut new: Self) -> bool {
@@ -243,6 +252,21 @@ impl TempStore {
             TempStore::MeetAggr(m) => m.inner.is_empty(),
         }
     }
+    fn len(&self) -> usize {
+        match self {
+            TempStore::Normal(n) => n.inner.len(),
+            TempStore::MeetAggr(m) => m.inner.len(),
+        }
+    }
+}
+
+/// A key range `[lower, upper)` of the delta of an [EpochStore], unbounded above if `upper` is `None`.
+/// The partitions returned by [EpochStore::delta_partitions] cover the delta without overlapping,
+/// so that the delta can be joined with one partition at a time.
+#[derive(Clone, Debug)]
+pub(crate) struct DeltaPartition {
+    lower: Tuple,
+    upper: Option<Tuple>,
 }
 
 #[derive(Debug)]
@@ -305,12 +329,27 @@ impl EpochStore {
         lower: &Tuple,
         upper: &Tuple,
         upper_inclusive: bool,
+        partition: Option<&DeltaPartition>,
     ) -> impl Iterator<Item = TupleInIter<'_>> {
-        if self.use_total_for_delta {
+        // narrow the range down to its intersection with the partition
+        let (lower, upper, upper_inclusive) = match partition {
+            None => (lower, upper, upper_inclusive),
+            Some(partition) => {
+                let lower = lower.max(&partition.lower);
+                match &partition.upper {
+                    Some(p_upper) if p_upper <= upper => (lower, p_upper, false),
+                    _ => (lower, upper, upper_inclusive),
+                }
+            }
+        };
+        if lower > upper {
+            return Left(iter::empty());
+        }
+        Right(if self.use_total_for_delta {
             self.total.range_iter(lower, upper, upper_inclusive)
         } else {
             self.delta.range_iter(lower, upper, upper_inclusive)
-        }
+        })
     }
     pub(crate) fn prefix_iter(&self, prefix: &Tuple) -> impl Iterator<Item = TupleInIter<'_>> {
         let mut upper = prefix.to_vec();
@@ -320,16 +359,47 @@ impl EpochStore {
     pub(crate) fn delta_prefix_iter(
         &self,
         prefix: &Tuple,
+        partition: Option<&DeltaPartition>,
     ) -> impl Iterator<Item = TupleInIter<'_>> {
         let mut upper = prefix.to_vec();
         upper.push(DataValue::Bot);
-        self.delta_range_iter(prefix, &upper, true)
+        self.delta_range_iter(prefix, &upper, true, partition)
     }
     pub(crate) fn all_iter(&self) -> impl Iterator<Item = TupleInIter<'_>> {
         self.prefix_iter(&vec![])
     }
-    pub(crate) fn delta_all_iter(&self) -> impl Iterator<Item = TupleInIter<'_>> {
-        self.delta_prefix_iter(&vec![])
+    pub(crate) fn delta_all_iter(
+        &self,
+        partition: Option<&DeltaPartition>,
+    ) -> impl Iterator<Item = TupleInIter<'_>> {
+        self.delta_prefix_iter(&vec![], partition)
+    }
+    pub(crate) fn delta_len(&self) -> usize {
+        if self.use_total_for_delta {
+            self.total.len()
+        } else {
+            self.delta.len()
+        }
+    }
+    /// Splits the delta into `n` partitions of roughly equal sizes, or fewer if it is too small
+    pub(crate) fn delta_partitions(&self, n: usize) -> Vec<DeltaPartition> {
+        let step = (self.delta_len() / n.max(1)).max(1);
+        let mut partitions = vec![];
+        let mut lower = vec![];
+        for bound in self
+            .delta_all_iter(None)
+            .step_by(step)
+            .skip(1)
+            .take(n.saturating_sub(1))
+        {
+            let upper = bound.into_tuple();
+            partitions.push(DeltaPartition {
+                lower: mem::replace(&mut lower, upper.clone()),
+                upper: Some(upper),
+            });
+        }
+        partitions.push(DeltaPartition { lower, upper: None });
+        partitions
     }
     pub(crate) fn early_returned_iter(&self) -> impl Iterator<Item = TupleInIter<'_>> {
         self.all_iter().filter(|t| !t.should_skip())
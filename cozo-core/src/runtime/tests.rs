@@ -7,7 +7,7 @@
  *
  */
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::time::Duration;
 
 use itertools::Itertools;
@@ -17,13 +17,16 @@ use smartstring::{LazyCompact, SmartString};
 
 use crate::data::expr::Expr;
 use crate::data::symb::Symbol;
-use crate::data::value::DataValue;
+use crate::data::value::{DataValue, JsonData};
 use crate::fixed_rule::FixedRulePayload;
 use crate::fts::{TokenizerCache, TokenizerConfig};
 use crate::parse::SourceSpan;
 use crate::runtime::callback::CallbackOp;
 use crate::runtime::db::Poison;
-use crate::{DbInstance, FixedRule, RegularTempStore, ScriptMutability};
+use crate::{
+    DbInstance, FixedRule, HostFixedRuleError, NamedRows, PagedFixedRuleRequest, RegularTempStore,
+    ScriptMutability, SimpleFixedRule,
+};
 
 #[test]
 fn test_limit_offset() {
@@ -576,6 +579,361 @@ fn test_custom_rules() {
     assert_eq!(res.into_json()["rows"], json!([[1000], [2600]]));
 }
 
+#[test]
+fn test_fixed_rule_named_outputs() {
+    let db = DbInstance::default();
+    let prog = r#"
+        edges[] <- [['a', 'b'], ['b', 'c'], ['c', 'a'], ['d', 'e'], ['e', 'f'], ['f', 'd'], ['c', 'd']]
+        communities[labels, node], levels: stats[level, n, modularity] <~
+            CommunityDetectionLouvain(edges[], undirected: true)
+        counts[level, count_unique(c)] := communities[labels, _],
+                                          level in int_range(length(labels)),
+                                          c = get(labels, level)
+    "#;
+    let from_stats = db
+        .run_default(&format!("{prog} ?[level, n] := stats[level, n, _]"))
+        .unwrap();
+    let from_labels = db
+        .run_default(&format!("{prog} ?[level, n] := counts[level, n]"))
+        .unwrap();
+    assert!(!from_stats.rows.is_empty());
+    assert_eq!(from_stats.rows, from_labels.rows);
+    let res = db
+        .run_default(&format!("{prog} ?[m] := stats[0, _, m]"))
+        .unwrap();
+    assert!(res.rows[0][0].get_float().unwrap() > 0.);
+
+    // unknown outputs are rejected
+    assert!(db
+        .run_default(
+            "e[] <- [['a', 'b']] ?[labels, node], nothing: x[a] <~ CommunityDetectionLouvain(e[])"
+        )
+        .is_err());
+}
+
+#[test]
+fn test_paged_custom_rules() {
+    let db = DbInstance::default();
+    let (rule, calls) = SimpleFixedRule::rule_with_paged_channel(1, 2);
+    db.register_fixed_rule("PagedSum".to_string(), rule)
+        .unwrap();
+    std::thread::spawn(move || {
+        for call in calls {
+            loop {
+                let (reply, page) = crossbeam::channel::bounded(1);
+                call.requests
+                    .send(PagedFixedRuleRequest::NextInputPage(0, reply))
+                    .unwrap();
+                let page = page.recv().unwrap().unwrap();
+                if page.rows.is_empty() {
+                    break;
+                }
+                let sum: i64 = page.rows.iter().map(|row| row[0].get_int().unwrap()).sum();
+                call.requests
+                    .send(PagedFixedRuleRequest::PutOutputPage(NamedRows::new(
+                        vec!["sum".to_string()],
+                        vec![vec![DataValue::from(sum)]],
+                    )))
+                    .unwrap();
+            }
+            call.requests
+                .send(PagedFixedRuleRequest::Finish(Ok(())))
+                .unwrap();
+        }
+    });
+    let res = db
+        .run_default(
+            r#"
+        rel[] <- [[1], [2], [3], [4], [5]]
+        ?[s] <~ PagedSum(rel[])
+    "#,
+        )
+        .unwrap();
+    // pages of two rows: [1, 2], [3, 4] and [5]
+    assert_eq!(res.into_json()["rows"], json!([[3], [5], [7]]));
+
+    let (rule, calls) = SimpleFixedRule::rule_with_paged_channel(1, 2);
+    db.register_fixed_rule("Stalled".to_string(), rule).unwrap();
+    let (finished_sender, finished_receiver) = crossbeam::channel::unbounded();
+    std::thread::spawn(move || {
+        for call in calls {
+            // never makes a request, so only the timeout of the query can end it
+            std::thread::sleep(Duration::from_millis(500));
+            finished_sender
+                .send(call.requests.send(PagedFixedRuleRequest::Finish(Ok(()))))
+                .unwrap();
+        }
+    });
+    let err = db
+        .run_default("rel[] <- [[1]] ?[s] <~ Stalled(rel[]) :timeout 0.1")
+        .unwrap_err();
+    assert!(err.to_string().contains("killed"));
+    // the query stopped listening before the application finished
+    assert!(finished_receiver.recv().unwrap().is_err());
+}
+
+#[test]
+fn test_fixed_rule_schemas() {
+    let db = DbInstance::default();
+    let edges = "e[a, b] <- [[1, 2], [2, 3], [3, 1]]";
+    assert!(db
+        .run_default(&format!("{edges} ?[n, r] <~ PageRank(e[], iteration: 5)"))
+        .is_err());
+    assert!(db
+        .run_default(&format!("{edges} ?[n, r] <~ PageRank(e[], theta: 'high')"))
+        .is_err());
+    assert!(db
+        .run_default(&format!("{edges} ?[n, r] <~ PageRank(e[], e[])"))
+        .is_err());
+    assert!(db
+        .run_default("e[a] <- [[1]] ?[n, r] <~ PageRank(e[])")
+        .is_err());
+    assert!(db.run_default("?[a, b] <~ BarabasiAlbert(n: 10)").is_err());
+    db.run_default(&format!("{edges} ?[n, r] <~ PageRank(e[], iterations: 5)"))
+        .unwrap();
+
+    let res = db.run_default("::fixed_rules").unwrap();
+    assert_eq!(res.headers, ["rule", "inputs", "options"]);
+    let page_rank = res
+        .rows
+        .iter()
+        .find(|row| row[0] == DataValue::from("PageRank"))
+        .unwrap();
+    let options = match &page_rank[2] {
+        DataValue::Json(JsonData(options)) => options.clone(),
+        v => panic!("{v:?}"),
+    };
+    assert_eq!(options[0]["name"], json!("undirected"));
+    assert_eq!(options[1]["name"], json!("theta"));
+    assert_eq!(options[1]["type"], json!("UnitInterval"));
+    assert_eq!(options[1]["default"], json!(0.85));
+    assert_eq!(options[1]["required"], json!(false));
+}
+
+#[test]
+fn test_host_custom_rules() {
+    let db = DbInstance::default();
+    let (rule, calls) =
+        SimpleFixedRule::rule_with_host_channel(1, Some(Duration::from_millis(200)));
+    db.register_fixed_rule("Host".to_string(), rule).unwrap();
+    let (cancelled_sender, cancelled_receiver) = crossbeam::channel::unbounded();
+    let (dropped_sender, dropped_receiver) = crossbeam::channel::unbounded();
+    std::thread::spawn(move || {
+        let mut pending = vec![];
+        for call in calls {
+            match call.options["mode"].get_str().unwrap() {
+                "echo" => {
+                    let value = call.options["value"].clone();
+                    pending.push((call, value));
+                    // answer in the reverse order once both concurrent invocations have arrived
+                    if pending.len() == 2 {
+                        for (call, value) in pending.drain(..).rev() {
+                            call.reply
+                                .send(Ok(NamedRows::new(vec![], vec![vec![value]])))
+                                .unwrap();
+                        }
+                    }
+                }
+                "drop" => {
+                    // the host keeps watching the invocation, but drops the reply
+                    dropped_sender.send(call.poison).unwrap();
+                }
+                "fail" => {
+                    call.reply
+                        .send(Err(HostFixedRuleError {
+                            message: "bad input".to_string(),
+                            stack: Some("at host_rule (host.js:1:1)".to_string()),
+                        }
+                        .into()))
+                        .unwrap();
+                }
+                _ => {
+                    // never answered, so the query has to give up on it
+                    let cancelled_sender = cancelled_sender.clone();
+                    std::thread::spawn(move || {
+                        // the whole call is moved here, as dropping its reply would end
+                        // the invocation right away
+                        let call = call;
+                        while call.poison.check().is_ok() {
+                            std::thread::sleep(Duration::from_millis(10));
+                        }
+                        cancelled_sender.send(()).unwrap();
+                    });
+                }
+            }
+        }
+    });
+
+    let handles = [1, 2].map(|i| {
+        let db = db.clone();
+        std::thread::spawn(move || {
+            db.run_default(&format!("?[x] <~ Host(mode: 'echo', value: {i})"))
+                .unwrap()
+                .rows
+        })
+    });
+    for (i, handle) in handles.into_iter().enumerate() {
+        assert_eq!(
+            handle.join().unwrap(),
+            vec![vec![DataValue::from(i as i64 + 1)]]
+        );
+    }
+
+    let err = db.run_default("?[x] <~ Host(mode: 'fail')").unwrap_err();
+    assert_eq!(err.to_string(), "bad input");
+    assert_eq!(
+        err.help().unwrap().to_string(),
+        "at host_rule (host.js:1:1)"
+    );
+
+    let err = db.run_default("?[x] <~ Host(mode: 'drop')").unwrap_err();
+    assert!(err.to_string().contains("dropped the invocation"));
+    assert!(dropped_receiver.recv().unwrap().check().is_err());
+
+    let err = db.run_default("?[x] <~ Host(mode: 'hang')").unwrap_err();
+    assert!(err.to_string().contains("did not answer"));
+    cancelled_receiver
+        .recv_timeout(Duration::from_secs(1))
+        .unwrap();
+
+    let (rule, calls) = SimpleFixedRule::rule_with_host_channel(1, None);
+    db.register_fixed_rule("Untimed".to_string(), rule).unwrap();
+    let _calls = calls;
+    assert!(db.run_default("?[x] <~ Untimed() :timeout 0.1").is_err());
+}
+
+#[test]
+fn test_partitioned_delta() {
+    let db = DbInstance::default();
+    // the deltas are large enough to be split into partitions joined in parallel
+    let res = db
+        .run_default(
+            r#"
+        e[a, b] := a in int_range(15000), b = a + 5000
+        reach[a, b] := e[a, b]
+        reach[a, c] := reach[a, b], e[b, c]
+        ?[count(a), max(c)] := reach[a, c]
+        "#,
+        )
+        .unwrap();
+    assert_eq!(
+        res.rows,
+        vec![vec![DataValue::from(30000), DataValue::from(19999)]]
+    );
+
+    let res = db
+        .run_default(
+            r#"
+        e[a, b] := a in int_range(15000), b = a + 5000
+        reach[a, b] := e[a, b], a < 5000
+        reach[a, c] := reach[a, b], e[b, c]
+        ?[a, c] := reach[a, c], c >= 15000
+        "#,
+        )
+        .unwrap();
+    assert_eq!(res.rows.len(), 5000);
+    assert!(res
+        .rows
+        .iter()
+        .all(|row| row[1].get_int().unwrap() == row[0].get_int().unwrap() + 15000));
+}
+
+#[test]
+fn test_trie_join() {
+    let db = DbInstance::default();
+    db.run_default(
+        r#"
+        ?[fr, to] := fr in int_range(30), to in int_range(30), fr < to, (fr * to) % 7 != 3
+        :create edge {fr, to}
+        "#,
+    )
+    .unwrap();
+    let edges = (0..30)
+        .flat_map(|a| (a + 1..30).map(move |b| (a, b)))
+        .filter(|(a, b)| (a * b) % 7 != 3)
+        .collect::<BTreeSet<(i64, i64)>>();
+    let triangles = edges
+        .iter()
+        .flat_map(|(a, b)| {
+            edges
+                .range((*b, 0)..(*b + 1, 0))
+                .filter(|(_, c)| edges.contains(&(*a, *c)))
+                .map(|(_, c)| json!([a, b, c]))
+                .collect_vec()
+        })
+        .collect_vec();
+    assert!(!triangles.is_empty());
+
+    let ops = |script: &str| {
+        db.run_default(&format!("::explain {{ {script} }}"))
+            .unwrap()
+            .into_json()["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row.as_array().unwrap()[4].clone())
+            .collect_vec()
+    };
+
+    // the relation is used as a trie directly
+    let script = "?[a, b, c] := *edge[a, b], *edge[b, c], *edge[a, c]";
+    assert!(ops(script).contains(&json!("leapfrog_trie_join")));
+    let res = db.run_default(script).unwrap();
+    assert_eq!(res.into_json()["rows"], json!(triangles));
+
+    // `r` has its columns the other way round, so it is sorted in memory
+    let script = r#"
+        e[x, y] := *edge[x, y]
+        r[y, x] := *edge[x, y]
+        ?[a, b, c] := e[a, b], e[b, c], r[c, a]
+    "#;
+    assert!(ops(script).contains(&json!("leapfrog_trie_join")));
+    let res = db.run_default(script).unwrap();
+    assert_eq!(res.into_json()["rows"], json!(triangles));
+
+    // with a bound variable, the atoms are joined one at a time as before
+    let script = "?[b, c] := a = 1, *edge[a, b], *edge[b, c], *edge[a, c]";
+    assert!(!ops(script).contains(&json!("leapfrog_trie_join")));
+    let res = db.run_default(script).unwrap();
+    let expected = triangles
+        .iter()
+        .filter(|t| t[0] == json!(1))
+        .map(|t| json!([t[1], t[2]]))
+        .collect_vec();
+    assert_eq!(res.into_json()["rows"], json!(expected));
+
+    // in recursive rules, only the delta of the recursive atom is scanned
+    let res = db
+        .run_default(
+            r#"
+        p[a, b] := *edge[a, b], a = 2, b < 6
+        p[a, c] := p[a, b], *edge[b, c], *edge[a, c]
+        ?[b] := p[a, b]
+        "#,
+        )
+        .unwrap();
+    let mut reached: BTreeSet<i64> = edges
+        .iter()
+        .filter(|(a, b)| *a == 2 && *b < 6)
+        .map(|(_, b)| *b)
+        .collect();
+    loop {
+        let next: BTreeSet<i64> = edges
+            .iter()
+            .filter(|(b, c)| reached.contains(b) && edges.contains(&(2, *c)))
+            .map(|(_, c)| *c)
+            .collect();
+        if next.is_subset(&reached) {
+            break;
+        }
+        reached.extend(next);
+    }
+    assert_eq!(
+        res.into_json()["rows"],
+        json!(reached.into_iter().map(|b| [b]).collect_vec())
+    );
+}
+
 #[test]
 fn test_index_short() {
     let db = DbInstance::default();
@@ -1429,6 +1787,225 @@ fn puts() {
     .unwrap();
 }
 
+#[test]
+fn decimal_column() {
+    let db = DbInstance::default();
+    db.run_default(":create ledger {id: Int => amount: Decimal(2)}")
+        .unwrap();
+    db.run_default(
+        r"
+        ?[id, amount] <- [[1, '0.10'], [2, 0.2], [3, 3], [4, '-1.005']]
+        :put ledger {id => amount}
+    ",
+    )
+    .unwrap();
+    let res = db
+        .run_default("?[id, amount] := *ledger{id, amount} :order amount")
+        .unwrap()
+        .into_json();
+    assert_eq!(
+        res["rows"],
+        json!([[4, "-1.01"], [1, "0.10"], [2, "0.20"], [3, "3.00"]])
+    );
+    let res = db
+        .run_default("?[sum(amount)] := *ledger{amount}")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["2.29"]]));
+    assert!(db
+        .run_default("?[id, amount] <- [[5, 'x']] :put ledger {id => amount}")
+        .is_err());
+
+    // numbers and decimals are ordered together by value
+    let res = db
+        .run_default("?[x] := x in [1, to_decimal('0.5'), 2] :order x")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([["0.5"], [1], [2]]));
+    db.run_default("?[k] := k in [1, to_decimal('0.5'), 2.5, to_decimal('-3')] :create mixed {k}")
+        .unwrap();
+    let res = db.run_default("?[k] := *mixed{k}").unwrap().into_json();
+    assert_eq!(res["rows"], json!([["-3"], ["0.5"], [1], [2.5]]));
+}
+
+#[test]
+fn spatial_index() {
+    let db = DbInstance::default();
+    db.run_default(":create depots {id: Int => x: Float, y: Float}")
+        .unwrap();
+    db.run_default(
+        r"
+        ?[id, x, y] := id in int_range(50), x = to_float(id % 10), y = floor(id / 10)
+        :put depots {id => x, y}
+    ",
+    )
+    .unwrap();
+    db.run_default("::spatial create depots:geo {extractor: st_point(x, y), max_entries: 4}")
+        .unwrap();
+    db.run_default(
+        r"
+        ?[id, x, y] := id in int_range(50, 100), x = to_float(id % 10), y = floor(id / 10)
+        :put depots {id => x, y}
+    ",
+    )
+    .unwrap();
+
+    let near = r"?[id] := ~depots:geo{id | query: st_point(2.0, 3.0), radius: 1.0} :order id";
+    let res = db.run_default(near).unwrap().into_json();
+    assert_eq!(res["rows"], json!([[22], [31], [32], [33], [42]]));
+
+    let res = db
+        .run_default(
+            r#"
+        ?[id] := ~depots:geo{id |
+            query: '{"type": "Polygon", "coordinates": [[[0, 0], [1.5, 0], [1.5, 1.5], [0, 1.5], [0, 0]]]}'
+        }
+        :order id
+    "#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[0], [1], [10], [11]]));
+
+    let res = db
+        .run_default(
+            r"
+        ?[id, d] := ~depots:geo{id | query: [0.1, 0.2], radius: 100, k: 2, bind_distance: d}
+        :order d
+    ",
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"][0][0], json!(0));
+    assert_eq!(res["rows"][1][0], json!(10));
+
+    db.run_default("?[id] <- [[32]] :rm depots {id}").unwrap();
+    db.run_default("?[id, x, y] <- [[31, 50.0, 50.0]] :put depots {id => x, y}")
+        .unwrap();
+    let res = db.run_default(near).unwrap().into_json();
+    assert_eq!(res["rows"], json!([[22], [33], [42]]));
+
+    db.run_default("?[id] := id in int_range(90) :rm depots {id}")
+        .unwrap();
+    let res = db
+        .run_default(r"?[id] := ~depots:geo{id | query: st_point(0.0, 9.0), radius: 2.0} :order id")
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[90], [91], [92]]));
+
+    let res = db.run_default("::indices depots").unwrap().into_json();
+    assert_eq!(res["rows"][0][1], json!("spatial"));
+    db.run_default("::spatial drop depots:geo").unwrap();
+    assert!(db.run_default(near).is_err());
+}
+
+#[test]
+fn json_index() {
+    let db = DbInstance::default();
+    db.run_default(":create docs {id: Int => payload: Json}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[id, payload] <- [
+            [1, parse_json('{"kind": "order", "items": [{"sku": "A1", "qty": 2}, {"sku": "B2", "qty": 1}]}')],
+            [2, parse_json('{"kind": "order", "items": [{"sku": "B2", "qty": 3}]}')],
+        ]
+        :put docs {id => payload}
+    "#,
+    )
+    .unwrap();
+    db.run_default("::json create docs:payload {extractor: payload}")
+        .unwrap();
+    db.run_default(
+        r#"
+        ?[id, payload] <- [
+            [3, parse_json('{"kind": "refund", "items": [{"sku": "A1", "qty": 1.0}]}')],
+            [4, parse_json('{"kind": "order", "tags": ["urgent"]}')],
+        ]
+        :put docs {id => payload}
+    "#,
+    )
+    .unwrap();
+
+    let by_sku = r#"?[id] := ~docs:payload{id | query: {"items": [{"sku": "A1"}]}} :order id"#;
+    let res = db.run_default(by_sku).unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1], [3]]));
+
+    let res = db
+        .run_default(
+            r#"?[id] := ~docs:payload{id | query: {"kind": "order", "items": [{"sku": "B2"}]}} :order id"#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1], [2]]));
+
+    let res = db
+        .run_default(
+            r#"?[id] := ~docs:payload{id | query: {"items": [{"sku": "A1", "qty": 1}]}} :order id"#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[3]]));
+
+    let res = db
+        .run_default(
+            r#"?[id] := ~docs:payload{id | query: {"kind": "order"}, filter: id > 1} :order id"#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[2], [4]]));
+
+    let res = db
+        .run_default(
+            r#"?[id, skus] := *docs{id, payload}, skus = json_query(payload, '$.items[*].sku'), id < 3 :order id"#,
+        )
+        .unwrap()
+        .into_json();
+    assert_eq!(res["rows"], json!([[1, ["A1", "B2"]], [2, ["B2"]]]));
+
+    // scans of the relation filtered by JSON predicates are answered from the index
+    let ops = |script: &str| {
+        db.run_default(&format!("::explain {{ {script} }}"))
+            .unwrap()
+            .into_json()["rows"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|row| row.as_array().unwrap()[4].clone())
+            .collect_vec()
+    };
+    let script =
+        r#"?[id] := *docs{id, payload}, json_contains(payload, {"items": [{"sku": "A1"}]})"#;
+    assert!(ops(script).contains(&json!("json_index")));
+    let res = db.run_default(script).unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1], [3]]));
+    let script = r#"?[id] := *docs{id, payload}, 'order' == get(payload, ['kind'])"#;
+    assert!(ops(script).contains(&json!("json_index")));
+    let res = db.run_default(script).unwrap().into_json();
+    assert_eq!(res["rows"], json!([[1], [2], [4]]));
+    // looking up by key is better than searching the index
+    let script = r#"?[payload] := *docs{id: 3, payload}, get(payload, 'kind') == 'refund'"#;
+    assert!(!ops(script).contains(&json!("json_index")));
+    let res = db.run_default(script).unwrap().into_json();
+    assert_eq!(res["rows"].as_array().unwrap().len(), 1);
+
+    db.run_default(
+        r#"
+        ?[id, payload] <- [[1, parse_json('{"kind": "order", "items": [{"sku": "C3"}]}')]]
+        :put docs {id => payload}
+    "#,
+    )
+    .unwrap();
+    db.run_default("?[id] <- [[3]] :rm docs {id}").unwrap();
+    let res = db.run_default(by_sku).unwrap().into_json();
+    assert_eq!(res["rows"], json!([]));
+
+    let res = db.run_default("::indices docs").unwrap().into_json();
+    assert_eq!(res["rows"][0][1], json!("json"));
+    db.run_default("::json drop docs:payload").unwrap();
+    assert!(db.run_default(by_sku).is_err());
+}
+
 #[test]
 fn short_hand() {
     let db = DbInstance::default();
@@ -1532,3 +2109,97 @@ fn hnsw_index() {
     "#).unwrap();
     println!("{}", res.into_json()["rows"][0][4]);
 }
+
+#[test]
+fn graph_projection() {
+    let db = DbInstance::default();
+    db.run_default(":create edges {fr: String, to: String => w: Float}")
+        .unwrap();
+    db.run_default(r"?[fr, to, w] <- [['a', 'b', 1], ['b', 'c', 2]] :put edges {fr, to => w}")
+        .unwrap();
+    db.run_default("::graph create g from *edges[fr, to, w]")
+        .unwrap();
+    assert!(db
+        .run_default("::graph create g from *edges[fr, to]")
+        .is_err());
+
+    let cost_to_c = || {
+        let res = db
+            .run_default(
+                r"
+                start[] <- [['a']]
+                end[] <- [['c']]
+                ?[s, e, cost, path] <~ ShortestPathDijkstra(@g[], start[], end[])
+                ",
+            )
+            .unwrap();
+        res.rows[0][2].get_float().unwrap()
+    };
+    assert_eq!(cost_to_c(), 3.);
+
+    db.run_default(r"?[fr, to, w] <- [['a', 'c', 1]] :put edges {fr, to => w}")
+        .unwrap();
+    assert_eq!(cost_to_c(), 1.);
+    db.run_default(r"?[fr, to] <- [['a', 'c']] :rm edges {fr, to}")
+        .unwrap();
+    assert_eq!(cost_to_c(), 3.);
+
+    db.run_default(r"?[fr, to, w] <- [['a', 'b', 5], ['b', 'c', 5]] :replace edges {fr, to => w}")
+        .unwrap();
+    assert_eq!(cost_to_c(), 10.);
+
+    db.import_relations(BTreeMap::from([(
+        "edges".to_string(),
+        NamedRows::new(
+            vec!["fr".to_string(), "to".to_string(), "w".to_string()],
+            vec![vec![
+                DataValue::from("a"),
+                DataValue::from("c"),
+                DataValue::from(4.),
+            ]],
+        ),
+    )]))
+    .unwrap();
+    assert_eq!(cost_to_c(), 4.);
+
+    // writes are seen by later queries in the same transaction
+    let res = db
+        .run_default(
+            r"
+            {?[fr, to, w] <- [['a', 'c', 2]] :put edges {fr, to => w}}
+            {
+                start[] <- [['a']]
+                end[] <- [['c']]
+                ?[s, e, cost, path] <~ ShortestPathDijkstra(@g[], start[], end[])
+            }
+            ",
+        )
+        .unwrap();
+    assert_eq!(res.rows[0][2].get_float().unwrap(), 2.);
+
+    // and are forgotten when the transaction is rolled back
+    assert!(db
+        .run_default(
+            r"
+            {?[fr, to, w] <- [['a', 'c', 1]] :put edges {fr, to => w}}
+            {
+                start[] <- [['a']]
+                end[] <- [['c']]
+                ?[s, e, cost, path] <~ ShortestPathDijkstra(@g[], start[], end[])
+                :assert none
+            }
+            ",
+        )
+        .is_err());
+    assert_eq!(cost_to_c(), 2.);
+
+    let res = db.run_default("::graphs").unwrap();
+    assert_eq!(
+        res.into_json()["rows"],
+        json!([["g", "edges", ["fr", "to", "w"], 3]])
+    );
+
+    db.run_default("::graph drop g").unwrap();
+    assert!(db.run_default("?[n, r] <~ PageRank(@g[])").is_err());
+    assert!(db.run_default("::graph drop g").is_err());
+}
@@ -17,6 +17,7 @@ use crate::data::value::DataValue;
 use crate::fts::TokenizerCache;
 use crate::{CallbackOp, NamedRows};
 use crate::runtime::callback::CallbackCollector;
+use crate::runtime::graph_projection::GraphProjections;
 use crate::runtime::relation::RelationId;
 use crate::storage::temp::TempTx;
 use crate::storage::StoreTx;
@@ -27,6 +28,7 @@ pub struct SessionTx<'a> {
     pub(crate) relation_store_id: Arc<AtomicU64>,
     pub(crate) temp_store_id: AtomicU32,
     pub(crate) tokenizers: Arc<TokenizerCache>,
+    pub(crate) graph_projections: Arc<GraphProjections>,
 }
 
 pub const CURRENT_STORAGE_VERSION: [u8; 1] = [0x00];
@@ -133,14 +133,18 @@ class CozoDb {
         return native.unregister_callback(this.db_id, cb_id)
     }
 
-    registerNamedRule(name, arity, cb) {
+    registerNamedRule(name, arity, cb, timeout = -1) {
         return native.register_named_rule(this.db_id, name, arity, async (ret_id, inputs, options) => {
+            const isCancelled = () => native.named_rule_invocation_cancelled(ret_id);
             let ret = undefined;
             try {
-                ret = await cb(inputs, options);
+                ret = await cb(inputs, options, isCancelled);
             } catch (e) {
-                console.error(e);
-                native.respond_to_named_rule_invocation(ret_id, '' + e);
+                if (e instanceof Error) {
+                    native.respond_to_named_rule_invocation(ret_id, e.message, e.stack);
+                } else {
+                    native.respond_to_named_rule_invocation(ret_id, '' + e);
+                }
                 return;
             }
             try {
@@ -148,7 +152,7 @@ class CozoDb {
             } catch (e) {
                 console.error(e);
             }
-        })
+        }, timeout)
     }
 
     unregisterNamedRule(name) {
@@ -9,8 +9,9 @@ use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
 
-use crossbeam::channel::Sender;
+use crossbeam::channel::{RecvTimeoutError, Sender};
 use lazy_static::lazy_static;
 use miette::{miette, Result};
 use neon::prelude::*;
@@ -191,6 +192,7 @@ fn value2js<'a>(cx: &mut impl Context<'a>, val: &DataValue) -> JsResult<'a, JsVa
             target_l.as_value(cx)
         }
         DataValue::Json(JsonData(j)) => json2js(cx, j)?,
+        DataValue::Decimal(d) => cx.string(d.to_string()).as_value(cx),
     })
 }
 
@@ -271,7 +273,7 @@ struct Handles {
     nxt_db_id: AtomicU32,
     dbs: Mutex<BTreeMap<u32, DbInstance>>,
     cb_idx: AtomicU32,
-    current_cbs: Mutex<BTreeMap<u32, Sender<Result<NamedRows>>>>,
+    current_cbs: Mutex<BTreeMap<u32, (Sender<Result<NamedRows>>, Poison)>>,
     nxt_tx_id: AtomicU32,
     txs: Mutex<BTreeMap<u32, Arc<MultiTransaction>>>,
 }
@@ -280,6 +282,9 @@ lazy_static! {
     static ref HANDLES: Handles = Handles::default();
 }
 
+/// How often invocations of host fixed rules that queries have given up on are cleaned up
+const GIVEN_UP_SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
 fn open_db(mut cx: FunctionContext) -> JsResult<JsNumber> {
     let engine = cx.argument::<JsString>(0)?.value(&mut cx);
     let path = cx.argument::<JsString>(1)?.value(&mut cx);
@@ -695,49 +700,77 @@ fn register_named_rule(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let name = cx.argument::<JsString>(1)?.value(&mut cx);
     let arity = cx.argument::<JsNumber>(2)?.value(&mut cx) as usize;
     let callback = Arc::new(cx.argument::<JsFunction>(3)?.root(&mut cx));
+    let timeout = cx.argument::<JsNumber>(4)?.value(&mut cx);
+    let timeout = if timeout < 0. {
+        None
+    } else {
+        Some(Duration::from_secs_f64(timeout))
+    };
     let channel = cx.channel();
-    let (rule_impl, recv) = SimpleFixedRule::rule_with_channel(arity);
+    let (rule_impl, recv) = SimpleFixedRule::rule_with_host_channel(arity, timeout);
     if let Err(err) = db.register_fixed_rule(name, rule_impl) {
         let msg = cx.string(err.to_string());
         return cx.throw(msg);
     }
-    thread::spawn(move || {
-        for (inputs, options, sender) in recv {
-            let id = HANDLES.cb_idx.fetch_add(1, Ordering::AcqRel);
-            {
-                HANDLES.current_cbs.lock().unwrap().insert(id, sender);
+    thread::spawn(move || loop {
+        let received = recv.recv_timeout(GIVEN_UP_SWEEP_INTERVAL);
+        // invocations that queries have given up on are never answered
+        HANDLES
+            .current_cbs
+            .lock()
+            .unwrap()
+            .retain(|_, (_, poison)| poison.check().is_ok());
+        let call = match received {
+            Ok(call) => call,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let id = HANDLES.cb_idx.fetch_add(1, Ordering::AcqRel);
+        {
+            HANDLES
+                .current_cbs
+                .lock()
+                .unwrap()
+                .insert(id, (call.reply, call.poison));
+        }
+        let (inputs, options) = (call.inputs, call.options);
+        let cb = callback.clone();
+        channel.send(move |mut cx| {
+            let callback = cb.to_inner(&mut cx);
+            let inputs_js = cx.empty_array();
+            for (i, input) in inputs.into_iter().enumerate() {
+                let input_js = rows2js(&mut cx, &input.rows)?;
+                inputs_js.set(&mut cx, i as u32, input_js)?;
             }
-            let cb = callback.clone();
-            channel.send(move |mut cx| {
-                let callback = cb.to_inner(&mut cx);
-                let inputs_js = cx.empty_array();
-                for (i, input) in inputs.into_iter().enumerate() {
-                    let input_js = rows2js(&mut cx, &input.rows)?;
-                    inputs_js.set(&mut cx, i as u32, input_js)?;
-                }
-                let inputs_js = inputs_js.as_value(&mut cx);
-                let options_js = params2js(&mut cx, &options)?.as_value(&mut cx);
-                let this = cx.undefined();
-                let ret_id = cx.number(id).as_value(&mut cx);
-                callback.call(&mut cx, this, vec![ret_id, inputs_js, options_js])?;
+            let inputs_js = inputs_js.as_value(&mut cx);
+            let options_js = params2js(&mut cx, &options)?.as_value(&mut cx);
+            let this = cx.undefined();
+            let ret_id = cx.number(id).as_value(&mut cx);
+            callback.call(&mut cx, this, vec![ret_id, inputs_js, options_js])?;
 
-                Ok(())
-            });
-        }
+            Ok(())
+        });
     });
 
     Ok(cx.undefined())
 }
 
+fn named_rule_invocation_cancelled(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let ret_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
+    let cancelled = match HANDLES.current_cbs.lock().unwrap().get(&ret_id) {
+        None => true,
+        Some((_, poison)) => poison.check().is_err(),
+    };
+    Ok(cx.boolean(cancelled))
+}
+
 fn respond_to_named_rule_invocation(mut cx: FunctionContext) -> JsResult<JsUndefined> {
     let ret_id = cx.argument::<JsNumber>(0)?.value(&mut cx) as u32;
     let sender = {
         match HANDLES.current_cbs.lock().unwrap().remove(&ret_id) {
-            None => {
-                let msg = cx.string("fixed rule invocation sender should only be used once");
-                return cx.throw(msg);
-            }
-            Some(s) => s,
+            // the query gave up on the invocation, or it has been answered already
+            None => return Ok(cx.undefined()),
+            Some((s, _)) => s,
         }
     };
 
@@ -746,9 +779,21 @@ fn respond_to_named_rule_invocation(mut cx: FunctionContext) -> JsResult<JsUndef
         err
     };
 
+    // sending fails when the query has given up on the invocation, which is not an error here
     let payload = cx.argument::<JsValue>(1)?;
     if let Ok(msg) = payload.downcast::<JsString, _>(&mut cx) {
-        let _ = sender.send(Err(miette!(msg.value(&mut cx))));
+        let stack = match cx.argument_opt(2) {
+            Some(stack) => stack
+                .downcast::<JsString, _>(&mut cx)
+                .ok()
+                .map(|stack| stack.value(&mut cx)),
+            None => None,
+        };
+        let _ = sender.send(Err(HostFixedRuleError {
+            message: msg.value(&mut cx),
+            stack,
+        }
+        .into()));
         return Ok(cx.undefined());
     }
 
@@ -756,11 +801,7 @@ fn respond_to_named_rule_invocation(mut cx: FunctionContext) -> JsResult<JsUndef
     let mut rows = vec![];
     js2rows(&mut cx, data, &mut rows).map_err(send_err)?;
     let nr = NamedRows::new(vec![], rows);
-    if let Err(err) = sender.send(Ok(nr)) {
-        let msg = err.to_string();
-        let msg = cx.string(msg);
-        return cx.throw(msg);
-    }
+    let _ = sender.send(Ok(nr));
     Ok(cx.undefined())
 }
 
@@ -794,6 +835,10 @@ fn main(mut cx: ModuleContext) -> NeonResult<()> {
         "respond_to_named_rule_invocation",
         respond_to_named_rule_invocation,
     )?;
+    cx.export_function(
+        "named_rule_invocation_cancelled",
+        named_rule_invocation_cancelled,
+    )?;
     cx.export_function("unregister_named_rule", unregister_named_rule)?;
     cx.export_function("abort_tx", abort_tx)?;
     cx.export_function("commit_tx", commit_tx)?;
@@ -8,6 +8,7 @@
 
 use std::collections::{BTreeMap, BTreeSet};
 use std::thread;
+use std::time::Duration;
 
 use miette::{IntoDiagnostic, Report, Result};
 use pyo3::exceptions::PyException;
@@ -30,6 +31,14 @@ fn report2py(r: Report) -> PyErr {
     PyException::new_err(r.to_string())
 }
 
+fn py_err_to_host(err: PyErr, py: Python<'_>) -> Report {
+    HostFixedRuleError {
+        message: err.to_string(),
+        stack: err.traceback(py).and_then(|tb| tb.format().ok()),
+    }
+    .into()
+}
+
 fn py_to_named_rows(ob: &PyAny) -> PyResult<NamedRows> {
     let d = ob.downcast::<PyDict>()?;
     let rows = d
@@ -174,6 +183,7 @@ fn value_to_py(val: DataValue, py: Python<'_>) -> PyObject {
             }
         },
         DataValue::Json(JsonData(j)) => json_to_py(j, py),
+        DataValue::Decimal(d) => d.to_string().into_py(py),
     }
 }
 
@@ -209,6 +219,20 @@ struct CozoDbMulTx {
     tx: MultiTransaction,
 }
 
+/// Passed to fixed rule callbacks registered with `pass_cancellation=True`,
+/// so that long-running callbacks can stop once the query has given up on them.
+#[pyclass]
+struct FixedRuleCancellation {
+    poison: Poison,
+}
+
+#[pymethods]
+impl FixedRuleCancellation {
+    pub fn is_cancelled(&self) -> bool {
+        self.poison.check().is_err()
+    }
+}
+
 const DB_CLOSED_MSG: &str = r##"{"ok":false,"message":"database closed"}"##;
 
 #[pymethods]
@@ -277,27 +301,55 @@ impl CozoDbPy {
             Err(PyException::new_err(DB_CLOSED_MSG))
         }
     }
+    #[pyo3(signature = (name, arity, callback, timeout = None, pass_cancellation = false))]
     pub fn register_fixed_rule(
         &self,
         name: String,
         arity: usize,
         callback: &PyAny,
+        timeout: Option<f64>,
+        pass_cancellation: bool,
     ) -> PyResult<()> {
         if let Some(db) = &self.db {
             let cb: Py<PyAny> = callback.into();
-            let rule_impl = SimpleFixedRule::new(arity, move |inputs, options| -> Result<_> {
-                Python::with_gil(|py| -> Result<NamedRows> {
-                    let py_inputs = PyList::new(
-                        py,
-                        inputs.into_iter().map(|nr| rows_to_py_rows(nr.rows, py)),
-                    );
-                    let py_opts = options_to_py(options, py).into_diagnostic()?;
-                    let args = PyTuple::new(py, vec![PyObject::from(py_inputs), py_opts]);
-                    let res = cb.as_ref(py).call1(args).into_diagnostic()?;
-                    Ok(NamedRows::new(vec![], py_to_rows(res).into_diagnostic()?))
-                })
+            let (rule_impl, calls) = SimpleFixedRule::rule_with_host_channel(
+                arity,
+                timeout.map(Duration::from_secs_f64),
+            );
+            db.register_fixed_rule(name, rule_impl).map_err(report2py)?;
+            thread::spawn(move || {
+                for call in calls {
+                    let cb = Python::with_gil(|py| cb.clone_ref(py));
+                    // each invocation gets its own thread, so that a slow one does not hold up others
+                    thread::spawn(move || {
+                        let res = Python::with_gil(|py| -> Result<NamedRows> {
+                            let py_inputs = PyList::new(
+                                py,
+                                call.inputs
+                                    .into_iter()
+                                    .map(|nr| rows_to_py_rows(nr.rows, py)),
+                            );
+                            let py_opts = options_to_py(call.options, py).into_diagnostic()?;
+                            let mut args = vec![PyObject::from(py_inputs), py_opts];
+                            if pass_cancellation {
+                                let cancellation = FixedRuleCancellation {
+                                    poison: call.poison.clone(),
+                                };
+                                args.push(Py::new(py, cancellation).into_diagnostic()?.into_py(py));
+                            }
+                            let args = PyTuple::new(py, args);
+                            let res = cb
+                                .as_ref(py)
+                                .call1(args)
+                                .map_err(|err| py_err_to_host(err, py))?;
+                            Ok(NamedRows::new(vec![], py_to_rows(res).into_diagnostic()?))
+                        });
+                        // the query may have given up on the invocation already
+                        let _ = call.reply.send(res);
+                    });
+                }
             });
-            db.register_fixed_rule(name, rule_impl).map_err(report2py)
+            Ok(())
         } else {
             Err(PyException::new_err(DB_CLOSED_MSG))
         }
@@ -460,6 +512,7 @@ fn variables(py: Python<'_>, query: &str, params: &PyDict) -> PyResult<BTreeSet<
 fn cozo_embedded(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
     m.add_class::<CozoDbPy>()?;
     m.add_class::<CozoDbMulTx>()?;
+    m.add_class::<FixedRuleCancellation>()?;
     m.add_function(wrap_pyfunction!(eval_expressions, m)?)?;
     m.add_function(wrap_pyfunction!(variables, m)?)?;
     Ok(())